@@ -0,0 +1,52 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use miso::codec_core::CodecCore;
+use miso::Codec;
+
+/// 1000 sequences of 50 tokens drawn from a few thousand IDs, with plenty of
+/// overlap between sequences.
+fn sequences() -> Vec<Vec<i32>> {
+    (0..1000u32)
+        .map(|i| {
+            (0..50u32)
+                .map(|j| ((i % 37) * 50 + j.wrapping_mul(2654435761) % 64) as i32)
+                .collect()
+        })
+        .collect()
+}
+
+/// One gzip stream per sequence vs one gzip stream for the whole batch.
+fn gzip_batch(c: &mut Criterion) {
+    let codec = Codec::new();
+    let seqs = sequences();
+    let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+
+    let individual: usize = refs
+        .iter()
+        .map(|seq| CodecCore::encode_token_ids(seq, true).unwrap().len())
+        .sum();
+    let shared = codec
+        .encode_compressed_batch_shared(&refs, true)
+        .unwrap()
+        .len();
+    println!("1000x50 gzip sizes: individual {individual} bytes, shared {shared} bytes");
+
+    let mut group = c.benchmark_group("gzip_1000x50");
+    group.bench_function("individual", |b| {
+        b.iter(|| {
+            refs.iter()
+                .map(|seq| CodecCore::encode_token_ids(black_box(seq), true).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("shared", |b| {
+        b.iter(|| {
+            codec
+                .encode_compressed_batch_shared(black_box(&refs), true)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, gzip_batch);
+criterion_main!(benches);
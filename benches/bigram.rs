@@ -0,0 +1,72 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use miso::codec_core::CodecCore;
+use miso::freq_map::FreqMap;
+use miso::Codec;
+
+/// 10K tokens from a Zipf-like 50K BPE vocabulary, with some repeated
+/// phrases as in real text.
+fn bpe_sequence() -> Vec<i32> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut ids = Vec::with_capacity(10_000);
+    while ids.len() < 10_000 {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+        if u < 0.1 && ids.len() > 64 {
+            // Repeat a recent 8-token phrase.
+            let start = ids.len() - 64 + (state % 56) as usize;
+            ids.extend_from_within(start..start + 8);
+        } else {
+            ids.push((50_257.0f64.powf(u * u) as i32).min(50_256));
+        }
+    }
+    ids.truncate(10_000);
+    ids
+}
+
+/// Bigram-dictionary coding vs the standard payload, with the dictionary
+/// trained on the stream itself; sizes are printed once up front.
+fn delta_dict(c: &mut Criterion) {
+    let codec = Codec::new();
+    let ids = bpe_sequence();
+    let dict = FreqMap::from_bigrams(&ids);
+    let standard = CodecCore::encode_token_ids(&ids, false).unwrap();
+    let delta = codec.encode_delta_dict(&ids, &dict, false).unwrap();
+    println!(
+        "bigram: {} codes, {} bytes vs {} standard",
+        dict.len(),
+        delta.len(),
+        standard.len()
+    );
+
+    let mut group = c.benchmark_group("bigram_bpe_10k");
+    group.throughput(Throughput::Elements(ids.len() as u64));
+    group.bench_function("train", |b| {
+        b.iter(|| FreqMap::from_bigrams(black_box(&ids)))
+    });
+    group.bench_function("standard_encode", |b| {
+        b.iter(|| CodecCore::encode_token_ids(black_box(&ids), false).unwrap())
+    });
+    group.bench_function("delta_dict_encode", |b| {
+        b.iter(|| {
+            codec
+                .encode_delta_dict(black_box(&ids), &dict, false)
+                .unwrap()
+        })
+    });
+    group.bench_function("standard_decode", |b| {
+        b.iter(|| CodecCore::decode_token_ids(black_box(&standard), false).unwrap())
+    });
+    group.bench_function("delta_dict_decode", |b| {
+        b.iter(|| {
+            codec
+                .decode_delta_dict(black_box(&delta), &dict, false)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, delta_dict);
+criterion_main!(benches);
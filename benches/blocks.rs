@@ -0,0 +1,40 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use miso::Codec;
+
+/// 4M tokens from a 32K vocabulary, cut into 4K-token blocks.
+fn payload(codec: &Codec, gzip: bool) -> Vec<u8> {
+    let ids: Vec<i32> = (0..4_000_000u32)
+        .map(|i| (i.wrapping_mul(2654435761) % 32_000) as i32)
+        .collect();
+    codec.encode_blocks(&ids, 4096, gzip).unwrap()
+}
+
+/// Sequential vs Rayon block decode; the gap should grow with the number of
+/// cores Rayon can use.
+fn block_decode(c: &mut Criterion) {
+    let codec = Codec::new();
+
+    let mut group = c.benchmark_group("block_decode_4m");
+    group.throughput(Throughput::Elements(4_000_000));
+    for gzip in [false, true] {
+        let payload = payload(&codec, gzip);
+        let suffix = if gzip { "_gzip" } else { "" };
+        group.bench_function(format!("sequential{suffix}"), |b| {
+            b.iter(|| codec.decode_blocks(black_box(&payload), gzip).unwrap())
+        });
+        group.bench_function(
+            format!("parallel_{}_threads{suffix}", rayon::current_num_threads()),
+            |b| {
+                b.iter(|| {
+                    codec
+                        .decode_block_parallel(black_box(&payload), gzip)
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, block_decode);
+criterion_main!(benches);
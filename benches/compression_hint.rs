@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use miso::compression_hint::CompressionHint;
+use miso::Codec;
+
+/// 10K tokens from a Zipf-like 50K BPE vocabulary, with some repeated
+/// phrases as in real text.
+fn bpe_sequence() -> Vec<i32> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut ids = Vec::with_capacity(10_000);
+    while ids.len() < 10_000 {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+        if u < 0.1 && ids.len() > 64 {
+            // Repeat a recent 8-token phrase.
+            let start = ids.len() - 64 + (state % 56) as usize;
+            ids.extend_from_within(start..start + 8);
+        } else {
+            ids.push((50_257.0f64.powf(u * u) as i32).min(50_256));
+        }
+    }
+    ids.truncate(10_000);
+    ids
+}
+
+/// Encode time per hint; compressed sizes are printed once up front.
+fn hints(c: &mut Criterion) {
+    let codec = Codec::new();
+    let ids = bpe_sequence();
+    let hints = [
+        ("speed", CompressionHint::SpeedFocused),
+        ("balanced", CompressionHint::Balanced),
+        ("size", CompressionHint::SizeFocused),
+    ];
+    for (name, hint) in hints {
+        let size = codec.encode_with_hint(&ids, hint).unwrap().len();
+        println!("compression_hint/{name}: {size} bytes");
+    }
+
+    let mut group = c.benchmark_group("compression_hint_10k");
+    group.throughput(Throughput::Elements(ids.len() as u64));
+    for (name, hint) in hints {
+        group.bench_function(format!("{name}_encode"), |b| {
+            b.iter(|| codec.encode_with_hint(black_box(&ids), hint).unwrap())
+        });
+    }
+    for (name, hint) in hints {
+        let payload = codec.encode_with_hint(&ids, hint).unwrap();
+        group.bench_function(format!("{name}_decode"), |b| {
+            b.iter(|| codec.decode_with_hint(black_box(&payload)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, hints);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use miso::codec_core::CodecCore;
+use miso::Codec;
+
+/// Checked vs validate-then-unchecked vs fully unchecked decode, on 100K tokens.
+fn decode(c: &mut Criterion) {
+    let codec = Codec::new();
+    let ids: Vec<i32> = (0..100_000).map(|i| (i * 7919) % 30_000).collect();
+    let payload = CodecCore::encode_token_ids(&ids, false).unwrap();
+
+    let mut group = c.benchmark_group("decode_100k");
+    group.bench_function("checked", |b| {
+        b.iter(|| CodecCore::decode_token_ids(black_box(&payload), false).unwrap())
+    });
+    group.bench_function("fast", |b| {
+        b.iter(|| {
+            codec
+                .decode_token_ids_fast(black_box(&payload), false)
+                .unwrap()
+        })
+    });
+    group.bench_function("unchecked", |b| {
+        // SAFETY: the payload was produced by `encode_token_ids` above.
+        b.iter(|| unsafe { codec.decode_token_ids_unchecked(black_box(&payload), false) })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
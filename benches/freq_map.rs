@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use miso::freq_map::FreqMap;
+
+/// Deterministic pseudo-random token IDs in `0..vocab`.
+fn tokens(n: u32, vocab: u32) -> Vec<i32> {
+    (0..n)
+        .map(|i| (i.wrapping_mul(2654435761) % vocab) as i32)
+        .collect()
+}
+
+/// HashMap vs flat-array lookup: 1M queries against a 32K-token vocabulary.
+fn lookup(c: &mut Criterion) {
+    let vocab = 32_768;
+    let fm = FreqMap::from_token_ids(&tokens(200_000, vocab));
+    let table = fm.to_lookup_table(vocab as usize - 1).unwrap();
+    let queries = tokens(1_000_000, vocab);
+
+    let mut group = c.benchmark_group("lookup_1m_queries_32k_vocab");
+    group.bench_function("hash_map", |b| {
+        b.iter(|| {
+            let mut acc = 0i64;
+            for &q in &queries {
+                acc += fm.map_token(black_box(q)).unwrap_or(-1) as i64;
+            }
+            acc
+        })
+    });
+    group.bench_function("array", |b| {
+        b.iter(|| {
+            let mut acc = 0i64;
+            for &q in &queries {
+                acc += table[black_box(q) as usize] as i64;
+            }
+            acc
+        })
+    });
+    group.finish();
+}
+
+/// Binary vs JSON serialization of a 50K-token vocabulary.
+fn serialization(c: &mut Criterion) {
+    let fm = FreqMap::from_token_ids(&tokens(1_000_000, 50_000));
+    let binary = fm.serialize_binary();
+    let json = fm.to_json();
+    println!(
+        "50k-token FreqMap: binary {} bytes, JSON {} bytes",
+        binary.len(),
+        json.len()
+    );
+
+    let mut group = c.benchmark_group("serialize_50k_vocab");
+    group.bench_function("binary_serialize", |b| {
+        b.iter(|| black_box(&fm).serialize_binary())
+    });
+    group.bench_function("json_serialize", |b| b.iter(|| black_box(&fm).to_json()));
+    group.bench_function("binary_deserialize", |b| {
+        b.iter(|| FreqMap::deserialize_binary(black_box(&binary)).unwrap())
+    });
+    group.bench_function("json_deserialize", |b| {
+        b.iter(|| FreqMap::from_json(black_box(&json)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lookup, serialization);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use miso::header::{Header, FORMAT_VERSION};
+
+/// Membership lookups: linear scan of `tokens` vs the cached set vs binary
+/// search over the cached sorted order, at three vocabulary sizes.
+fn membership(c: &mut Criterion) {
+    let mut group = c.benchmark_group("header_membership");
+    for vocab in [100i32, 10_000, 100_000] {
+        // Spread-out token IDs in a scrambled (frequency-like) order.
+        let tokens: Vec<i32> = (0..vocab)
+            .map(|i| i.wrapping_mul(7919) % (vocab * 4))
+            .collect();
+        let header = Header::new(FORMAT_VERSION, 0, tokens);
+        let queries: Vec<i32> = (0..1000).map(|i| i * (vocab * 4 / 1000).max(1)).collect();
+
+        // Warm the caches so only lookups are measured.
+        header.contains_token(0);
+        header.find_mapped_id_binary(0);
+
+        group.bench_with_input(BenchmarkId::new("linear", vocab), &queries, |b, qs| {
+            b.iter(|| {
+                qs.iter()
+                    .filter(|q| header.tokens.contains(black_box(q)))
+                    .count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("hash_set", vocab), &queries, |b, qs| {
+            b.iter(|| {
+                qs.iter()
+                    .filter(|&&q| header.contains_token(black_box(q)))
+                    .count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("binary", vocab), &queries, |b, qs| {
+            b.iter(|| {
+                qs.iter()
+                    .filter_map(|&q| header.find_mapped_id_binary(black_box(q)))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, membership);
+criterion_main!(benches);
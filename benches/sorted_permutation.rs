@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use miso::codec_core::CodecCore;
+use miso::Codec;
+
+/// BPE-like 10K-token sequence: a skewed draw from a 50K vocabulary, where a
+/// few hundred common subwords make up most of the text.
+fn bpe_like_tokens() -> Vec<i32> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    (0..10_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Cubing a uniform sample skews it towards small IDs.
+            let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+            (u * u * u * 50_257.0) as i32
+        })
+        .collect()
+}
+
+fn sorted_permutation(c: &mut Criterion) {
+    let codec = Codec::new();
+    let ids = bpe_like_tokens();
+
+    for gzip in [false, true] {
+        let standard = CodecCore::encode_token_ids(&ids, gzip).unwrap().len();
+        let sorted = codec
+            .encode_sorted_with_permutation(&ids, gzip)
+            .unwrap()
+            .len();
+        println!("10k BPE-like tokens, gzip={gzip}: standard {standard} bytes, sorted+permutation {sorted} bytes");
+    }
+
+    let mut group = c.benchmark_group("sorted_permutation_10k");
+    group.bench_function("standard_encode", |b| {
+        b.iter(|| CodecCore::encode_token_ids(black_box(&ids), true).unwrap())
+    });
+    group.bench_function("sorted_encode", |b| {
+        b.iter(|| {
+            codec
+                .encode_sorted_with_permutation(black_box(&ids), true)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, sorted_permutation);
+criterion_main!(benches);
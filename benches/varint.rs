@@ -0,0 +1,275 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use miso::freq_map::FreqMap;
+use miso::{arithmetic, elias, huffman, prefix_free, rice, varint, vbyte, zigzag};
+
+/// Zipf-like stream of 1M values: value `k` has weight `1 / (k + 1)` over a
+/// 32K vocabulary, so the large majority of values are below 128.
+fn zipf_values() -> Vec<u32> {
+    let vocab = 32_000u32;
+    let weights: Vec<f64> = (0..vocab).map(|k| 1.0 / f64::from(k + 1)).collect();
+    let total: f64 = weights.iter().sum();
+
+    let mut cdf = Vec::with_capacity(weights.len());
+    let mut acc = 0.0;
+    for w in &weights {
+        acc += w / total;
+        cdf.push(acc);
+    }
+
+    // Deterministic uniform samples from a 64-bit LCG.
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    (0..1_000_000)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+            cdf.partition_point(|&c| c < u).min(cdf.len() - 1) as u32
+        })
+        .collect()
+}
+
+fn swar(c: &mut Criterion) {
+    let values = zipf_values();
+    let encoded = varint::encode(&values);
+
+    let mut group = c.benchmark_group("varint_zipf_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("encode", |b| b.iter(|| varint::encode(black_box(&values))));
+    group.bench_function("encode_batch_swar", |b| {
+        b.iter(|| varint::encode_batch_swar(black_box(&values)))
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| varint::decode(black_box(&encoded)).unwrap())
+    });
+    group.bench_function("decode_batch_swar", |b| {
+        b.iter(|| varint::decode_batch_swar(black_box(&encoded)).unwrap())
+    });
+    group.finish();
+}
+
+/// LEB128 vs Group VarInt on the same 1M-value stream.
+fn vbyte(c: &mut Criterion) {
+    let values = zipf_values();
+    let leb128 = varint::encode(&values);
+    let grouped = vbyte::encode_vbyte(&values);
+
+    let mut group = c.benchmark_group("vbyte_zipf_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("leb128_encode", |b| {
+        b.iter(|| varint::encode(black_box(&values)))
+    });
+    group.bench_function("vbyte_encode", |b| {
+        b.iter(|| vbyte::encode_vbyte(black_box(&values)))
+    });
+    group.bench_function("leb128_decode", |b| {
+        b.iter(|| varint::decode(black_box(&leb128)).unwrap())
+    });
+    group.bench_function("vbyte_decode", |b| {
+        b.iter(|| vbyte::decode_vbyte(black_box(&grouped)).unwrap())
+    });
+    group.finish();
+}
+
+/// One-pass zigzag + LEB128 for i64s vs zigzagging into a `Vec<u64>` first.
+fn zigzag_i64(c: &mut Criterion) {
+    // Signed deltas around the Zipf values, as timestamps or offsets would be.
+    let values: Vec<i64> = zipf_values()
+        .windows(2)
+        .map(|w| i64::from(w[1]) - i64::from(w[0]))
+        .collect();
+    let encoded = varint::encode_zigzag_i64(&values);
+
+    let mut group = c.benchmark_group("zigzag_i64_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("two_step_encode", |b| {
+        b.iter(|| {
+            let zigzagged: Vec<u64> = black_box(&values)
+                .iter()
+                .map(|&v| zigzag::encode_i64(v))
+                .collect();
+            varint::encode_u64(&zigzagged)
+        })
+    });
+    group.bench_function("encode_zigzag_i64", |b| {
+        b.iter(|| varint::encode_zigzag_i64(black_box(&values)))
+    });
+    group.bench_function("two_step_decode", |b| {
+        b.iter(|| {
+            let zigzagged = varint::decode_u64(black_box(&encoded)).unwrap();
+            zigzagged
+                .into_iter()
+                .map(zigzag::decode_i64)
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("decode_zigzag_i64", |b| {
+        b.iter(|| varint::decode_zigzag_i64(black_box(&encoded)).unwrap())
+    });
+    group.finish();
+}
+
+/// 1M samples of a geometric distribution with p = 0.1 (mean 9), the shape
+/// frequency remapping tends to produce.
+fn geometric_values() -> Vec<u32> {
+    let ln_q = (1.0f64 - 0.1).ln();
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    (0..1_000_000)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let u = ((state >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+            (u.ln() / ln_q).floor() as u32
+        })
+        .collect()
+}
+
+/// LEB128 vs Rice coding on geometric values. Rice needs about 5.4 bits per
+/// value here against LEB128's 8.
+fn rice(c: &mut Criterion) {
+    let values = geometric_values();
+    let m = rice::optimal_rice_parameter(&values);
+    let leb128 = varint::encode(&values);
+    let riced = rice::encode_rice(&values, m);
+
+    let mut group = c.benchmark_group("rice_geometric_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("leb128_encode", |b| {
+        b.iter(|| varint::encode(black_box(&values)))
+    });
+    group.bench_function("rice_encode", |b| {
+        b.iter(|| rice::encode_rice(black_box(&values), m))
+    });
+    group.bench_function("leb128_decode", |b| {
+        b.iter(|| varint::decode(black_box(&leb128)).unwrap())
+    });
+    group.bench_function("rice_decode", |b| {
+        b.iter(|| rice::decode_rice(black_box(&riced), m, values.len()).unwrap())
+    });
+    group.finish();
+}
+
+/// LEB128 vs Elias gamma on the Zipf stream (shifted up by one, since Elias
+/// gamma needs positive values).
+fn elias_gamma(c: &mut Criterion) {
+    let values: Vec<u32> = zipf_values().into_iter().map(|v| v + 1).collect();
+    let leb128 = varint::encode(&values);
+    let gamma = elias::encode_elias_gamma(&values);
+
+    let mut group = c.benchmark_group("elias_gamma_zipf_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("leb128_encode", |b| {
+        b.iter(|| varint::encode(black_box(&values)))
+    });
+    group.bench_function("elias_gamma_encode", |b| {
+        b.iter(|| elias::encode_elias_gamma(black_box(&values)))
+    });
+    group.bench_function("leb128_decode", |b| {
+        b.iter(|| varint::decode(black_box(&leb128)).unwrap())
+    });
+    group.bench_function("elias_gamma_decode", |b| {
+        b.iter(|| elias::decode_elias_gamma(black_box(&gamma), values.len()).unwrap())
+    });
+    group.finish();
+}
+
+/// LEB128 vs the Fibonacci code on the Zipf stream.
+fn fibonacci(c: &mut Criterion) {
+    let values = zipf_values();
+    let leb128 = varint::encode(&values);
+    let coded = prefix_free::encode_prefix_free(&values);
+
+    let mut group = c.benchmark_group("fibonacci_zipf_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("leb128_encode", |b| {
+        b.iter(|| varint::encode(black_box(&values)))
+    });
+    group.bench_function("fibonacci_encode", |b| {
+        b.iter(|| prefix_free::encode_prefix_free(black_box(&values)))
+    });
+    group.bench_function("leb128_decode", |b| {
+        b.iter(|| varint::decode(black_box(&leb128)).unwrap())
+    });
+    group.bench_function("fibonacci_decode", |b| {
+        b.iter(|| prefix_free::decode_prefix_free(black_box(&coded), values.len()).unwrap())
+    });
+    group.finish();
+}
+
+/// LEB128 vs static Huffman coding on the Zipf stream, with the table built
+/// from the stream's own counts.
+fn huffman(c: &mut Criterion) {
+    let values = zipf_values();
+    let ids: Vec<i32> = values.iter().map(|&v| v as i32).collect();
+    let freq = FreqMap::from_token_ids(&ids);
+    let mapped: Vec<u32> = ids
+        .iter()
+        .map(|&t| freq.map_token(t).unwrap() as u32)
+        .collect();
+    let table = huffman::build_huffman_table(&freq);
+    let leb128 = varint::encode(&mapped);
+    let coded = huffman::encode_huffman(&mapped, &table);
+
+    let mut group = c.benchmark_group("huffman_zipf_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("build_table", |b| {
+        b.iter(|| huffman::build_huffman_table(black_box(&freq)))
+    });
+    group.bench_function("leb128_encode", |b| {
+        b.iter(|| varint::encode(black_box(&mapped)))
+    });
+    group.bench_function("huffman_encode", |b| {
+        b.iter(|| huffman::encode_huffman(black_box(&mapped), &table))
+    });
+    group.bench_function("leb128_decode", |b| {
+        b.iter(|| varint::decode(black_box(&leb128)).unwrap())
+    });
+    group.bench_function("huffman_decode", |b| {
+        b.iter(|| huffman::decode_huffman(black_box(&coded), mapped.len(), &table).unwrap())
+    });
+    group.finish();
+}
+
+/// Static Huffman vs arithmetic coding on the Zipf stream, both modelled on
+/// the stream's own counts.
+fn arithmetic(c: &mut Criterion) {
+    let values = zipf_values();
+    let ids: Vec<i32> = values.iter().map(|&v| v as i32).collect();
+    let freq = FreqMap::from_token_ids(&ids);
+    let mapped: Vec<u32> = ids
+        .iter()
+        .map(|&t| freq.map_token(t).unwrap() as u32)
+        .collect();
+    let table = huffman::build_huffman_table(&freq);
+    let probabilities = freq.to_probability_table();
+    let coded = arithmetic::encode_arithmetic(&mapped, &probabilities);
+
+    let mut group = c.benchmark_group("arithmetic_zipf_1m");
+    group.throughput(Throughput::Elements(values.len() as u64));
+    group.bench_function("huffman_encode", |b| {
+        b.iter(|| huffman::encode_huffman(black_box(&mapped), &table))
+    });
+    group.bench_function("arithmetic_encode", |b| {
+        b.iter(|| arithmetic::encode_arithmetic(black_box(&mapped), &probabilities))
+    });
+    group.bench_function("arithmetic_decode", |b| {
+        b.iter(|| {
+            arithmetic::decode_arithmetic(black_box(&coded), mapped.len(), &probabilities).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    swar,
+    vbyte,
+    zigzag_i64,
+    rice,
+    elias_gamma,
+    fibonacci,
+    huffman,
+    arithmetic
+);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use miso::zigzag;
+
+/// Scalar loop with `collect` vs the slice helpers, on 1M signed values.
+fn slices(c: &mut Criterion) {
+    let values: Vec<i32> = (0..1_000_000i32)
+        .map(|i| i.wrapping_mul(-1640531535))
+        .collect();
+    let encoded = zigzag::encode_slice(&values);
+
+    let mut group = c.benchmark_group("zigzag_1m");
+    group.bench_function("encode_loop", |b| {
+        b.iter(|| {
+            black_box(&values)
+                .iter()
+                .map(|&v| zigzag::encode(v))
+                .collect::<Vec<u32>>()
+        })
+    });
+    group.bench_function("encode_slice", |b| {
+        b.iter(|| zigzag::encode_slice(black_box(&values)))
+    });
+    let mut buf = Vec::with_capacity(values.len());
+    group.bench_function("encode_slice_inplace", |b| {
+        b.iter(|| zigzag::encode_slice_inplace(black_box(&values), &mut buf))
+    });
+    group.bench_function("decode_loop", |b| {
+        b.iter(|| {
+            black_box(&encoded)
+                .iter()
+                .map(|&v| zigzag::decode(v))
+                .collect::<Vec<i32>>()
+        })
+    });
+    group.bench_function("decode_slice", |b| {
+        b.iter(|| zigzag::decode_slice(black_box(&encoded)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, slices);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+fn main() {
+    // Protobuf types are only generated when the `prost` feature is enabled.
+    #[cfg(feature = "prost")]
+    {
+        println!("cargo:rerun-if-changed=proto/miso.proto");
+        // Use the vendored protoc so no system install is needed.
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+        std::env::set_var("PROTOC", protoc);
+        prost_build::compile_protos(&["proto/miso.proto"], &["proto"])
+            .expect("compile proto/miso.proto");
+    }
+}
@@ -0,0 +1,304 @@
+// src/arithmetic.rs
+use anyhow::{bail, Result};
+
+/// Sum of the integer frequencies probabilities are quantized to.
+const FREQ_TOTAL: u64 = 1 << 24;
+
+/// Coder state width in bits.
+const PRECISION: u32 = 32;
+const WHOLE: u64 = 1 << PRECISION;
+const HALF: u64 = WHOLE / 2;
+const QUARTER: u64 = WHOLE / 4;
+
+/// How far probabilities may sum away from 1.0.
+pub const PROBABILITY_TOLERANCE: f64 = 1e-6;
+
+/// Arithmetic code `symbols` (indices into `probabilities`).
+///
+/// The coder is the classic integer one (Witten, Neal and Cleary) on a
+/// 32-bit state in 64-bit arithmetic. Probabilities are first quantized to
+/// integer frequencies summing to 2^24, every symbol getting at least 1, so
+/// encoder and decoder derive the exact same model from the same `f64`s.
+/// Bits are packed most significant bit first and the final byte is
+/// zero-padded; the symbol count must be stored alongside.
+///
+/// # Panics
+///
+/// If `probabilities` is invalid (see `decode_arithmetic`) or a symbol is out
+/// of its range.
+pub fn encode_arithmetic(symbols: &[u32], probabilities: &[f64]) -> Vec<u8> {
+    let cumulative = match cumulative_frequencies(probabilities) {
+        Ok(cumulative) => cumulative,
+        Err(err) => panic!("{err}"),
+    };
+
+    let mut out = BitSink::default();
+    let (mut low, mut high) = (0u64, WHOLE - 1);
+    let mut pending = 0u64;
+    for &symbol in symbols {
+        let s = symbol as usize;
+        assert!(s + 1 < cumulative.len(), "symbol {symbol} out of range");
+        let range = high - low + 1;
+        high = low + range * cumulative[s + 1] / FREQ_TOTAL - 1;
+        low += range * cumulative[s] / FREQ_TOTAL;
+
+        loop {
+            if high < HALF {
+                out.push_with_pending(false, &mut pending);
+            } else if low >= HALF {
+                out.push_with_pending(true, &mut pending);
+                low -= HALF;
+                high -= HALF;
+            } else if low >= QUARTER && high < HALF + QUARTER {
+                pending += 1;
+                low -= QUARTER;
+                high -= QUARTER;
+            } else {
+                break;
+            }
+            low *= 2;
+            high = high * 2 + 1;
+        }
+    }
+
+    // Two more bits pin down a value inside [low, high] whatever follows.
+    pending += 1;
+    out.push_with_pending(low >= QUARTER, &mut pending);
+    out.finish()
+}
+
+/// Decode `count` symbols written by `encode_arithmetic` with the same
+/// probabilities.
+///
+/// Errors if `probabilities` is empty, has more than 2^24 entries, holds a
+/// negative or non-finite value, or doesn't sum to 1.0 within
+/// `PROBABILITY_TOLERANCE`; also errors if decoding runs past the end of
+/// `bytes`.
+pub fn decode_arithmetic(bytes: &[u8], count: usize, probabilities: &[f64]) -> Result<Vec<u32>> {
+    let cumulative = cumulative_frequencies(probabilities)?;
+    let mut input = BitSource { bytes, pos: 0 };
+
+    let (mut low, mut high) = (0u64, WHOLE - 1);
+    let mut value = 0u64;
+    for _ in 0..PRECISION {
+        value = value * 2 + input.next()?;
+    }
+
+    // A single certain symbol costs nothing, so `count` can't be bounded by
+    // the input size; only cap the up-front allocation.
+    let mut out = Vec::with_capacity(count.min(bytes.len() * 8 + 64));
+    for _ in 0..count {
+        let range = high - low + 1;
+        let scaled = ((value - low + 1) * FREQ_TOTAL - 1) / range;
+        // The last symbol whose cumulative frequency is <= scaled.
+        let symbol = cumulative.partition_point(|&c| c <= scaled) - 1;
+        out.push(symbol as u32);
+
+        high = low + range * cumulative[symbol + 1] / FREQ_TOTAL - 1;
+        low += range * cumulative[symbol] / FREQ_TOTAL;
+        loop {
+            if high < HALF {
+            } else if low >= HALF {
+                low -= HALF;
+                high -= HALF;
+                value -= HALF;
+            } else if low >= QUARTER && high < HALF + QUARTER {
+                low -= QUARTER;
+                high -= QUARTER;
+                value -= QUARTER;
+            } else {
+                break;
+            }
+            low *= 2;
+            high = high * 2 + 1;
+            value = value * 2 + input.next()?;
+        }
+    }
+    Ok(out)
+}
+
+/// Quantized cumulative frequencies: entry `i` is the total frequency of
+/// symbols `0..i`, the last one is `FREQ_TOTAL`.
+fn cumulative_frequencies(probabilities: &[f64]) -> Result<Vec<u64>> {
+    if probabilities.is_empty() || probabilities.len() as u64 > FREQ_TOTAL {
+        bail!(
+            "arithmetic coding needs 1 to {} symbols, got {}",
+            FREQ_TOTAL,
+            probabilities.len()
+        );
+    }
+    if probabilities.iter().any(|p| !p.is_finite() || *p < 0.0) {
+        bail!("probabilities must be finite and non-negative");
+    }
+    let sum: f64 = probabilities.iter().sum();
+    if (sum - 1.0).abs() > PROBABILITY_TOLERANCE {
+        bail!("probabilities sum to {}, not 1", sum);
+    }
+
+    let mut freqs: Vec<u64> = probabilities
+        .iter()
+        .map(|p| ((p * FREQ_TOTAL as f64).round() as u64).max(1))
+        .collect();
+
+    // Settle the rounding error on the most frequent symbols, which it
+    // distorts least. Ties go to the lower index, deterministically.
+    let mut by_freq: Vec<usize> = (0..freqs.len()).collect();
+    by_freq.sort_by(|&a, &b| freqs[b].cmp(&freqs[a]).then(a.cmp(&b)));
+    let total: u64 = freqs.iter().sum();
+    if total < FREQ_TOTAL {
+        freqs[by_freq[0]] += FREQ_TOTAL - total;
+    } else {
+        let mut excess = total - FREQ_TOTAL;
+        for &i in &by_freq {
+            let take = excess.min(freqs[i] - 1);
+            freqs[i] -= take;
+            excess -= take;
+            if excess == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut cumulative = Vec::with_capacity(freqs.len() + 1);
+    let mut acc = 0;
+    cumulative.push(0);
+    for f in freqs {
+        acc += f;
+        cumulative.push(acc);
+    }
+    Ok(cumulative)
+}
+
+/// MSB-first bit output.
+#[derive(Default)]
+struct BitSink {
+    out: Vec<u8>,
+    acc: u8,
+    pending: u32,
+}
+
+impl BitSink {
+    fn push(&mut self, bit: bool) {
+        self.acc = (self.acc << 1) | bit as u8;
+        self.pending += 1;
+        if self.pending == 8 {
+            self.out.push(self.acc);
+            self.acc = 0;
+            self.pending = 0;
+        }
+    }
+
+    /// `bit`, then `pending` copies of its opposite.
+    fn push_with_pending(&mut self, bit: bool, pending: &mut u64) {
+        self.push(bit);
+        for _ in 0..*pending {
+            self.push(!bit);
+        }
+        *pending = 0;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.pending > 0 {
+            self.out.push(self.acc << (8 - self.pending));
+        }
+        self.out
+    }
+}
+
+/// MSB-first bit input, reading zeros for up to `PRECISION` bits past the
+/// end (the encoder doesn't write the trailing zeros the decoder looks
+/// ahead at).
+struct BitSource<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitSource<'_> {
+    fn next(&mut self) -> Result<u64> {
+        let bit = match self.bytes.get(self.pos / 8) {
+            Some(byte) => (byte >> (7 - self.pos % 8)) & 1,
+            None if self.pos < self.bytes.len() * 8 + PRECISION as usize => 0,
+            None => bail!("arithmetic-coded stream ended early"),
+        };
+        self.pos += 1;
+        Ok(u64::from(bit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn skewed_sources_approach_their_entropy() {
+        // 99% zeros: 0.08 bits per symbol.
+        let probabilities = [0.99, 0.01];
+        let symbols: Vec<u32> = (0..100_000).map(|i| u32::from(i % 100 == 7)).collect();
+        let enc = encode_arithmetic(&symbols, &probabilities);
+        let entropy_bytes = 100_000.0 * 0.0808 / 8.0;
+        assert!((enc.len() as f64) < entropy_bytes * 1.02, "{}", enc.len());
+        assert_eq!(
+            decode_arithmetic(&enc, symbols.len(), &probabilities).unwrap(),
+            symbols
+        );
+    }
+
+    #[test]
+    fn small_inputs() {
+        let probabilities = [0.5, 0.25, 0.25];
+        let enc = encode_arithmetic(&[], &probabilities);
+        assert!(decode_arithmetic(&enc, 0, &probabilities)
+            .unwrap()
+            .is_empty());
+        for symbols in [[0], [1], [2]] {
+            let enc = encode_arithmetic(&symbols, &probabilities);
+            assert_eq!(decode_arithmetic(&enc, 1, &probabilities).unwrap(), symbols);
+        }
+
+        // A certain symbol takes no bits beyond the final two.
+        let enc = encode_arithmetic(&[0; 1000], &[1.0]);
+        assert_eq!(enc.len(), 1);
+        assert_eq!(decode_arithmetic(&enc, 1000, &[1.0]).unwrap(), [0; 1000]);
+    }
+
+    #[test]
+    fn zero_probabilities_still_code() {
+        let probabilities = [0.7, 0.0, 0.3];
+        let symbols = [0, 1, 2, 1, 0];
+        let enc = encode_arithmetic(&symbols, &probabilities);
+        assert_eq!(decode_arithmetic(&enc, 5, &probabilities).unwrap(), symbols);
+    }
+
+    #[test]
+    fn invalid_probabilities_are_rejected() {
+        for probabilities in [&[][..], &[0.5, 0.4], &[1.5, -0.5], &[f64::NAN, 1.0]] {
+            assert!(decode_arithmetic(&[0], 1, probabilities).is_err());
+        }
+        assert!(decode_arithmetic(&[0], 1, &[0.5, 0.5 + 1e-9]).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn out_of_range_symbols_panic() {
+        encode_arithmetic(&[2], &[0.5, 0.5]);
+    }
+
+    #[test]
+    fn truncated_streams_are_rejected() {
+        let probabilities = [0.5, 0.5];
+        let symbols: Vec<u32> = (0..200).map(|i| (i * 7 % 3 == 0) as u32).collect();
+        let enc = encode_arithmetic(&symbols, &probabilities);
+        assert!(decode_arithmetic(&enc[..enc.len() - 8], symbols.len(), &probabilities).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(symbols in proptest::collection::vec(0u32..6, 0..512)) {
+            let probabilities = [0.4, 0.2, 0.15, 0.1, 0.1, 0.05];
+            let enc = encode_arithmetic(&symbols, &probabilities);
+            prop_assert_eq!(decode_arithmetic(&enc, symbols.len(), &probabilities).unwrap(), symbols);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::Codec;
+
+/// Async wrappers that run encode/decode on tokio's blocking thread pool, so
+/// CPU-bound work never stalls the runtime's worker threads.
+///
+/// The closures own their inputs; encoding also takes a clone of the codec so
+/// the payload follows its config, like `Codec::encode`.
+impl Codec {
+    pub async fn encode_token_ids_async(&self, token_ids: Vec<i32>, gzip: bool) -> Result<Vec<u8>> {
+        let codec = self.clone();
+        run_blocking(move || codec.encode(&token_ids, gzip)).await
+    }
+
+    pub async fn decode_token_ids_async(&self, payload: Vec<u8>, gzip: bool) -> Result<Vec<i32>> {
+        run_blocking(move || CodecCore::decode_token_ids(&payload, gzip)).await
+    }
+}
+
+async fn run_blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| CodecError::Internal(format!("blocking task failed: {err}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CodecConfig, VarintMode};
+    use crate::header::FLAG_VBYTE_ENCODING;
+
+    #[test]
+    fn codec_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Codec>();
+    }
+
+    #[tokio::test]
+    async fn async_round_trip() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..10_000).map(|i| (i * 7919) % 1000).collect();
+
+        for gzip in [false, true] {
+            let payload = codec
+                .encode_token_ids_async(ids.clone(), gzip)
+                .await
+                .unwrap();
+            assert_eq!(payload, CodecCore::encode_token_ids(&ids, gzip).unwrap());
+            let decoded = codec.decode_token_ids_async(payload, gzip).await.unwrap();
+            assert_eq!(decoded, ids);
+        }
+    }
+
+    #[tokio::test]
+    async fn async_encode_follows_the_config() {
+        let codec = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::VByte,
+            ..CodecConfig::default()
+        });
+        let ids = vec![4, 4, 9, 1];
+        let payload = codec.encode_token_ids_async(ids.clone(), false).await.unwrap();
+        assert_eq!(payload, codec.encode(&ids, false).unwrap());
+        assert_eq!(payload[1], FLAG_VBYTE_ENCODING);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn many_concurrent_encodes() {
+        let handles: Vec<_> = (0..1000)
+            .map(|i| {
+                tokio::spawn(async move {
+                    let ids: Vec<i32> = (0..64).map(|j| (i + j) % 50).collect();
+                    let payload = Codec::new()
+                        .encode_token_ids_async(ids.clone(), true)
+                        .await?;
+                    Ok::<_, CodecError>((ids, payload))
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let (ids, payload) = handle.await.unwrap().unwrap();
+            assert_eq!(CodecCore::decode_token_ids(&payload, true).unwrap(), ids);
+        }
+    }
+
+    #[tokio::test]
+    async fn async_decode_reports_errors() {
+        let codec = Codec::new();
+        assert!(matches!(
+            codec.decode_token_ids_async(vec![9, 9, 9], false).await,
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_ATTENTION_MASK;
+use crate::Codec;
+
+/// Token IDs plus their attention mask.
+///
+/// The mask is packed one bit per token, least significant bit first, into
+/// the `FLAG_ATTENTION_MASK` extension; its length is implied by the token
+/// count. Unused bits of the last byte are zero.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` if the mask length differs
+    /// from the token count or the mask holds anything but 0 and 1.
+    pub fn encode_tokens_and_mask(
+        &self,
+        token_ids: &[i32],
+        attention_mask: &[i32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if attention_mask.len() != token_ids.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mask = attention_mask
+            .iter()
+            .map(|&m| match m {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(CodecError::InvalidPayload),
+            })
+            .collect::<Result<Vec<bool>>>()?;
+        let bits = pack_bits(&mask);
+
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_ATTENTION_MASK, &bits)], gzip)
+    }
+
+    pub fn decode_tokens_and_mask(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<i32>)> {
+        let (tokens, bits) = CodecCore::decode_with_extension(payload, FLAG_ATTENTION_MASK, gzip)?;
+        let bits = bits.ok_or(CodecError::InvalidPayload)?;
+        let mask = unpack_bits(bits, tokens.len())?
+            .into_iter()
+            .map(i32::from)
+            .collect();
+        Ok((tokens, mask))
+    }
+}
+
+/// One bit per value, least significant bit first; unused bits of the last
+/// byte are zero.
+pub(crate) fn pack_bits(values: &[bool]) -> Vec<u8> {
+    let mut bits = vec![0u8; values.len().div_ceil(8)];
+    for (i, _) in values.iter().enumerate().filter(|(_, &v)| v) {
+        bits[i / 8] |= 1 << (i % 8);
+    }
+    bits
+}
+
+/// Undo `pack_bits` for `n` values. Errors with `CodecError::InvalidPayload`
+/// on a length mismatch or set padding bits.
+pub(crate) fn unpack_bits(bits: &[u8], n: usize) -> Result<Vec<bool>> {
+    if bits.len() != n.div_ceil(8) {
+        return Err(CodecError::InvalidPayload);
+    }
+    let used_in_last = n % 8;
+    if used_in_last != 0 && bits[bits.len() - 1] >> used_in_last != 0 {
+        return Err(CodecError::InvalidPayload);
+    }
+
+    Ok((0..n).map(|i| bits[i / 8] >> (i % 8) & 1 == 1).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 118 real tokens followed by 10 padding tokens.
+    fn padded_128() -> (Vec<i32>, Vec<i32>) {
+        let mut ids: Vec<i32> = (0..118).map(|i| (i * 7919) % 3000 + 100).collect();
+        ids.extend([0; 10]);
+        let mut mask = vec![1; 118];
+        mask.extend([0; 10]);
+        (ids, mask)
+    }
+
+    #[test]
+    fn tokens_and_mask_round_trip() {
+        let codec = Codec::new();
+        let (ids, mask) = padded_128();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_tokens_and_mask(&ids, &mask, gzip).unwrap();
+            assert_eq!(
+                codec.decode_tokens_and_mask(&payload, gzip).unwrap(),
+                (ids.clone(), mask.clone())
+            );
+
+            // Plain decoders skip the mask.
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn mask_costs_a_bit_per_token() {
+        let codec = Codec::new();
+        let (ids, mask) = padded_128();
+        let with_mask = codec.encode_tokens_and_mask(&ids, &mask, false).unwrap();
+        let without = CodecCore::encode_token_ids(&ids, false).unwrap();
+        // 16 mask bytes plus section framing, tag and extension count.
+        assert!(with_mask.len() - without.len() <= 16 + 6);
+    }
+
+    #[test]
+    fn invalid_masks_are_rejected() {
+        let codec = Codec::new();
+        for mask in [vec![1, 1], vec![1, 2, 0], vec![1, -1, 0]] {
+            assert!(matches!(
+                codec.encode_tokens_and_mask(&[5, 6, 7], &mask, false),
+                Err(CodecError::InvalidPayload)
+            ));
+        }
+
+        let plain = CodecCore::encode_token_ids(&[5, 6, 7], false).unwrap();
+        assert!(codec.decode_tokens_and_mask(&plain, false).is_err());
+    }
+
+    #[test]
+    fn empty_round_trip() {
+        let codec = Codec::new();
+        let payload = codec.encode_tokens_and_mask(&[], &[], false).unwrap();
+        assert_eq!(
+            codec.decode_tokens_and_mask(&payload, false).unwrap(),
+            (vec![], vec![])
+        );
+    }
+}
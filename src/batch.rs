@@ -0,0 +1,171 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::codec_core::CodecCore;
+use crate::errors::Result;
+use crate::Codec;
+
+/// Block-encoded payloads with fewer tokens than this are decoded on the
+/// calling thread, where dispatching them would cost more than it saves.
+pub const PARALLELISM_THRESHOLD: usize = 65536;
+
+/// Batch encode/decode spread over Rayon's thread pool.
+///
+/// Every element is independent, so this is a straight parallel map; results
+/// keep the input order and the first failure (in input order) is returned.
+/// Encoding goes through `Codec::encode`, so it honours the codec's config.
+impl Codec {
+    pub fn encode_batch_parallel(
+        &self,
+        sequences: Vec<Vec<i32>>,
+        gzip: bool,
+    ) -> Result<Vec<Vec<u8>>> {
+        sequences
+            .into_par_iter()
+            .map(|seq| self.encode(&seq, gzip))
+            .collect()
+    }
+
+    pub fn decode_batch_parallel(
+        &self,
+        payloads: Vec<Vec<u8>>,
+        gzip: bool,
+    ) -> Result<Vec<Vec<i32>>> {
+        payloads
+            .into_par_iter()
+            .map(|payload| CodecCore::decode_token_ids(&payload, gzip))
+            .collect()
+    }
+
+    /// `decode_blocks` with the blocks of one payload decoded in parallel and
+    /// concatenated in order.
+    pub fn decode_block_parallel(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let blocks = Self::block_index(payload)?;
+        let total: usize = blocks.iter().map(|(tokens, _)| tokens).sum();
+        if total < PARALLELISM_THRESHOLD {
+            return self.decode_blocks(payload, gzip);
+        }
+
+        let decoded: Vec<Vec<i32>> = blocks
+            .into_par_iter()
+            .map(|block| Self::decode_block(block, gzip))
+            .collect::<Result<_>>()?;
+        Ok(decoded.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::config::{CodecConfig, VarintMode};
+
+    fn batch(count: u32, len: u32) -> Vec<Vec<i32>> {
+        (0..count)
+            .map(|i| {
+                (0..len)
+                    .map(|j| ((j.wrapping_mul(2654435761) ^ i) % 5000) as i32)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let codec = Codec::new();
+        let seqs = batch(128, 512);
+
+        for gzip in [false, true] {
+            let sequential: Vec<Vec<u8>> = seqs
+                .iter()
+                .map(|seq| CodecCore::encode_token_ids(seq, gzip).unwrap())
+                .collect();
+            let parallel = codec.encode_batch_parallel(seqs.clone(), gzip).unwrap();
+            assert_eq!(parallel, sequential);
+
+            assert_eq!(codec.decode_batch_parallel(parallel, gzip).unwrap(), seqs);
+        }
+    }
+
+    #[test]
+    fn parallel_encode_follows_the_config() {
+        let codec = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::VByte,
+            ..CodecConfig::default()
+        });
+        let seqs = batch(8, 64);
+        let payloads = codec.encode_batch_parallel(seqs.clone(), false).unwrap();
+        for (seq, payload) in seqs.iter().zip(&payloads) {
+            assert_eq!(*payload, codec.encode(seq, false).unwrap());
+        }
+        assert_eq!(codec.decode_batch_parallel(payloads, false).unwrap(), seqs);
+    }
+
+    #[test]
+    fn parallel_block_decode_matches_sequential() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) % 5000) as i32)
+            .collect();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_blocks(&ids, 256, gzip).unwrap();
+            let sequential = codec.decode_blocks(&payload, gzip).unwrap();
+            assert_eq!(sequential, ids);
+            assert_eq!(
+                codec.decode_block_parallel(&payload, gzip).unwrap(),
+                sequential
+            );
+        }
+    }
+
+    #[test]
+    fn small_block_payloads_take_the_sequential_path() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..1000).collect();
+        let payload = codec.encode_blocks(&ids, 256, false).unwrap();
+        assert_eq!(codec.decode_block_parallel(&payload, false).unwrap(), ids);
+
+        let mut bad = codec
+            .encode_blocks(&vec![7; PARALLELISM_THRESHOLD], 256, false)
+            .unwrap();
+        let last = bad.len() - 1;
+        bad[last] ^= 0xFF;
+        assert!(codec.decode_block_parallel(&bad, false).is_err());
+    }
+
+    #[test]
+    fn decode_batch_reports_bad_payloads() {
+        let codec = Codec::new();
+        let mut payloads = codec.encode_batch_parallel(batch(4, 8), false).unwrap();
+        payloads[2] = vec![0xFF];
+
+        assert!(codec.decode_batch_parallel(payloads, false).is_err());
+    }
+
+    #[test]
+    fn parallel_is_faster_on_large_batches() {
+        // Nothing to win on a single-core machine.
+        if rayon::current_num_threads() < 2 {
+            return;
+        }
+
+        let codec = Codec::new();
+        let seqs = batch(256, 4096);
+
+        let start = Instant::now();
+        for seq in &seqs {
+            CodecCore::encode_token_ids(seq, true).unwrap();
+        }
+        let sequential = start.elapsed();
+
+        let start = Instant::now();
+        codec.encode_batch_parallel(seqs, true).unwrap();
+        let parallel = start.elapsed();
+
+        assert!(
+            parallel < sequential,
+            "parallel {parallel:?} vs sequential {sequential:?}"
+        );
+    }
+}
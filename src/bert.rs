@@ -0,0 +1,192 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_BERT_INPUTS};
+use crate::{varint, Codec};
+
+/// BERT-style `(input_ids, segment_ids, position_ids)` triples in one payload.
+///
+/// Only `input_ids` go through the usual `FreqMap` pipeline. Segment IDs are
+/// 0 or 1, so they are packed one bit each; position IDs are always `0..n`,
+/// so only their count is stored.
+///
+/// Layout (header carries the `FLAG_BERT_INPUTS` mode and the `input_ids`
+/// token table; gzip covers the body only):
+///   [header][varint n][ceil(n / 8) bytes of segment bits][varint position count]
+///   [standard varint body of input_ids]
+///
+/// Segment bits are packed least significant bit first.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` if the three slices differ in
+    /// length, a segment ID is not 0 or 1, or the positions are not `0..n`.
+    pub fn encode_bert_inputs(
+        &self,
+        input_ids: &[i32],
+        segment_ids: &[i32],
+        position_ids: &[i32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let n = input_ids.len();
+        if segment_ids.len() != n || position_ids.len() != n {
+            return Err(CodecError::InvalidPayload);
+        }
+        if position_ids.iter().zip(0..).any(|(&p, i)| p != i) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut segments = vec![0u8; n.div_ceil(8)];
+        for (i, &segment) in segment_ids.iter().enumerate() {
+            match segment {
+                0 => {}
+                1 => segments[i / 8] |= 1 << (i % 8),
+                _ => return Err(CodecError::InvalidPayload),
+            }
+        }
+
+        let freq = FreqMap::from_token_ids(input_ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_BERT_INPUTS;
+
+        let count = varint::encode(&[to_u32(n)?]);
+        let mut body = count.clone();
+        body.extend_from_slice(&segments);
+        body.extend_from_slice(&count);
+        body.extend_from_slice(&CodecCore::encode_body(input_ids, &freq)?);
+
+        let mut out = header.encode();
+        out.extend_from_slice(&CodecCore::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    pub fn decode_bert_inputs(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<i32>, Vec<i32>)> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_BERT_INPUTS {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let mut rest: &[u8] = &body;
+
+        let n = read_varint(&mut rest)? as usize;
+        let segment_len = n.div_ceil(8);
+        if rest.len() < segment_len {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (segments, mut rest) = rest.split_at(segment_len);
+        let segment_ids = (0..n)
+            .map(|i| i32::from(segments[i / 8] >> (i % 8) & 1))
+            .collect();
+
+        let positions = read_varint(&mut rest)? as usize;
+        let input_ids = CodecCore::decode_body(rest, &parts.header)?;
+        if positions != n || input_ids.len() != n {
+            return Err(CodecError::InvalidPayload);
+        }
+        let position_ids = (0..n as i32).collect();
+
+        Ok((input_ids, segment_ids, position_ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 512-token `[CLS] A [SEP] B [SEP] [PAD]...` input.
+    fn bert_512() -> (Vec<i32>, Vec<i32>, Vec<i32>) {
+        const CLS: i32 = 101;
+        const SEP: i32 = 102;
+        const PAD: i32 = 0;
+
+        let mut input_ids = vec![CLS];
+        input_ids.extend((0..200).map(|i| 1000 + (i * 37) % 3000));
+        input_ids.push(SEP);
+        let first_len = input_ids.len();
+        input_ids.extend((0..150).map(|i| 1000 + (i * 53) % 3000));
+        input_ids.push(SEP);
+        let used = input_ids.len();
+        input_ids.resize(512, PAD);
+
+        let segment_ids = (0..512)
+            .map(|i| i32::from(i >= first_len && i < used))
+            .collect();
+        let position_ids = (0..512).collect();
+        (input_ids, segment_ids, position_ids)
+    }
+
+    #[test]
+    fn bert_512_round_trip() {
+        let codec = Codec::new();
+        let (input_ids, segment_ids, position_ids) = bert_512();
+
+        for gzip in [false, true] {
+            let payload = codec
+                .encode_bert_inputs(&input_ids, &segment_ids, &position_ids, gzip)
+                .unwrap();
+            let decoded = codec.decode_bert_inputs(&payload, gzip).unwrap();
+            assert_eq!(
+                decoded,
+                (input_ids.clone(), segment_ids.clone(), position_ids.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn segments_and_positions_are_nearly_free() {
+        let codec = Codec::new();
+        let (input_ids, segment_ids, position_ids) = bert_512();
+
+        let triple = codec
+            .encode_bert_inputs(&input_ids, &segment_ids, &position_ids, false)
+            .unwrap();
+        let inputs_only = CodecCore::encode_token_ids(&input_ids, false).unwrap();
+        // 64 bytes of segment bits plus two 2-byte counts.
+        assert_eq!(triple.len(), inputs_only.len() + 64 + 4);
+    }
+
+    #[test]
+    fn empty_input_round_trips() {
+        let codec = Codec::new();
+        let payload = codec.encode_bert_inputs(&[], &[], &[], false).unwrap();
+        assert_eq!(
+            codec.decode_bert_inputs(&payload, false).unwrap(),
+            (vec![], vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn unsupported_inputs_are_rejected() {
+        let codec = Codec::new();
+        let ids = [5, 6, 7];
+        assert!(codec
+            .encode_bert_inputs(&ids, &[0, 1], &[0, 1, 2], false)
+            .is_err());
+        assert!(codec
+            .encode_bert_inputs(&ids, &[0, 2, 0], &[0, 1, 2], false)
+            .is_err());
+        assert!(codec
+            .encode_bert_inputs(&ids, &[0, 0, 0], &[0, 2, 1], false)
+            .is_err());
+        assert!(codec
+            .encode_bert_inputs(&ids, &[0, 0, 0], &[1, 2, 3], false)
+            .is_err());
+    }
+
+    #[test]
+    fn other_payloads_are_rejected() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(codec.decode_bert_inputs(&plain, false).is_err());
+
+        let payload = codec
+            .encode_bert_inputs(&[1, 2, 3], &[0, 0, 1], &[0, 1, 2], false)
+            .unwrap();
+        assert!(CodecCore::decode_token_ids(&payload, false).is_err());
+        assert!(codec
+            .decode_bert_inputs(&payload[..payload.len() - 1], false)
+            .is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_DELTA_DICT};
+use crate::{varint, Codec};
+
+/// Bigrams seen fewer times than this when training are left out of a
+/// `BigramDictionary`.
+pub const BIGRAM_MIN_COUNT: usize = 2;
+
+/// Most codes a `BigramDictionary` holds.
+pub const BIGRAM_MAX_CODES: usize = 1 << 16;
+
+/// Pre-trained codes for frequent `(previous token, token)` transitions.
+///
+/// Codes are dense, most frequent bigram first (ties by ascending pair), so
+/// the common transitions get the shortest varints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigramDictionary {
+    /// code (as index) -> bigram
+    pairs: Vec<(i32, i32)>,
+    /// bigram -> code
+    codes: HashMap<(i32, i32), u32>,
+}
+
+impl BigramDictionary {
+    /// Keep the `BIGRAM_MAX_CODES` most frequent bigrams of `counts` that
+    /// reach `BIGRAM_MIN_COUNT`.
+    pub fn from_counts(counts: HashMap<(i32, i32), usize>) -> Self {
+        let mut ranked: Vec<((i32, i32), usize)> = counts
+            .into_iter()
+            .filter(|&(_, count)| count >= BIGRAM_MIN_COUNT)
+            .collect();
+        ranked.sort_by(|(pa, ca), (pb, cb)| cb.cmp(ca).then(pa.cmp(pb)));
+        ranked.truncate(BIGRAM_MAX_CODES);
+
+        let pairs: Vec<(i32, i32)> = ranked.into_iter().map(|(pair, _)| pair).collect();
+        let codes = pairs
+            .iter()
+            .enumerate()
+            .map(|(code, &pair)| (pair, code as u32))
+            .collect();
+        Self { pairs, codes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// The code for `curr` following `prev`, if the dictionary has one.
+    pub fn code(&self, prev: i32, curr: i32) -> Option<u32> {
+        self.codes.get(&(prev, curr)).copied()
+    }
+
+    /// The bigram `code` stands for.
+    pub fn pair(&self, code: u32) -> Option<(i32, i32)> {
+        self.pairs.get(code as usize).copied()
+    }
+}
+
+impl FreqMap {
+    /// Train a `BigramDictionary` on the consecutive pairs of `ids`.
+    pub fn from_bigrams(ids: &[i32]) -> BigramDictionary {
+        let mut counts = HashMap::new();
+        for pair in ids.windows(2) {
+            *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+        }
+        BigramDictionary::from_counts(counts)
+    }
+}
+
+/// Payloads coding each token by its transition from the previous one: a
+/// `BigramDictionary` code when the dictionary has the bigram, otherwise an
+/// escape and the token's mapped ID. The payload records the dictionary
+/// entries it uses, so decoding with the wrong dictionary is caught.
+///
+/// Layout (`FLAG_DELTA_DICT` mode):
+///   [header, standard token table]
+///   [varint entry count][per entry: varint prev mapped ID,
+///    varint curr mapped ID, varint code], sorted by (prev, curr)
+///   [per token: varint 0 then varint mapped ID (escape), or varint code + 1]
+///
+/// Everything after the header is gzipped together when `gzip` is set. The
+/// first token is always escaped.
+impl Codec {
+    pub fn encode_delta_dict(
+        &self,
+        token_ids: &[i32],
+        dict: &BigramDictionary,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_DELTA_DICT;
+        let mapped = CodecCore::map_ids(token_ids, &freq)?;
+
+        let mut values = Vec::with_capacity(token_ids.len() * 2);
+        let mut used = Vec::new();
+        for (i, &token) in token_ids.iter().enumerate() {
+            match i
+                .checked_sub(1)
+                .and_then(|p| dict.code(token_ids[p], token))
+            {
+                Some(code) => {
+                    values.push(code + 1);
+                    used.push((mapped[i - 1] as u32, mapped[i] as u32, code));
+                }
+                None => values.extend([0, mapped[i] as u32]),
+            }
+        }
+        used.sort_unstable();
+        used.dedup();
+
+        let mut body = varint::encode(&[to_u32(used.len())?]);
+        for (prev, curr, code) in used {
+            body.extend_from_slice(&varint::encode(&[prev, curr, code]));
+        }
+        body.extend_from_slice(&varint::encode(&values));
+
+        let mut out = header.encode();
+        out.extend_from_slice(&CodecCore::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    /// Errors with `CodecError::InvalidPayload` if the payload uses a code
+    /// `dict` doesn't assign to the same bigram.
+    pub fn decode_delta_dict(
+        &self,
+        payload: &[u8],
+        dict: &BigramDictionary,
+        gzip: bool,
+    ) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_DELTA_DICT {
+            return Err(CodecError::InvalidPayload);
+        }
+        let tokens = &parts.header.tokens;
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let mut rest = &body[..];
+
+        let token = |mapped: u32| {
+            tokens
+                .get(mapped as usize)
+                .copied()
+                .ok_or(CodecError::InvalidPayload)
+        };
+        let entries = read_varint(&mut rest)?;
+        let mut used = HashMap::new();
+        for _ in 0..entries {
+            let prev = token(read_varint(&mut rest)?)?;
+            let curr = token(read_varint(&mut rest)?)?;
+            let code = read_varint(&mut rest)?;
+            if dict.pair(code) != Some((prev, curr)) {
+                return Err(CodecError::InvalidPayload);
+            }
+            used.insert(code, (prev, curr));
+        }
+
+        let mut out = Vec::with_capacity(rest.len());
+        while !rest.is_empty() {
+            let value = read_varint(&mut rest)?;
+            let next = match value.checked_sub(1) {
+                None => token(read_varint(&mut rest)?)?,
+                Some(code) => {
+                    let &(prev, curr) = used.get(&code).ok_or(CodecError::InvalidPayload)?;
+                    if out.last() != Some(&prev) {
+                        return Err(CodecError::InvalidPayload);
+                    }
+                    curr
+                }
+            };
+            out.push(next);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stream made of a few recurring phrases, as in templated text.
+    fn phrases() -> Vec<i32> {
+        let phrases: [&[i32]; 4] = [
+            &[464, 2068, 7586, 21831],
+            &[18045, 625, 262, 16931, 3290],
+            &[13, 198],
+            &[40, 1101, 257, 3303, 13],
+        ];
+        (0..400u32)
+            .flat_map(|i| {
+                phrases[(i.wrapping_mul(2654435761) >> 7) as usize % 4]
+                    .iter()
+                    .copied()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dictionary_ranks_frequent_bigrams_first() {
+        let dict = FreqMap::from_bigrams(&[1, 2, 1, 2, 1, 2, 3, 4, 3, 4, 5, 6]);
+        // (1, 2) occurs 3 times; (2, 1), (3, 4) twice; the rest once.
+        assert_eq!(dict.len(), 3);
+        assert_eq!(dict.pair(0), Some((1, 2)));
+        assert_eq!(dict.code(2, 1), Some(1));
+        assert_eq!(dict.code(3, 4), Some(2));
+        assert_eq!(dict.code(5, 6), None);
+        assert_eq!(dict.pair(3), None);
+        assert!(FreqMap::from_bigrams(&[7]).is_empty());
+    }
+
+    #[test]
+    fn delta_dict_round_trips() {
+        let codec = Codec::new();
+        let ids = phrases();
+        let dict = FreqMap::from_bigrams(&ids);
+        let empty = FreqMap::from_bigrams(&[]);
+
+        for ids in [ids, vec![], vec![5], vec![1, 2, 3, 1]] {
+            for dict in [&dict, &empty] {
+                for gzip in [false, true] {
+                    let payload = codec.encode_delta_dict(&ids, dict, gzip).unwrap();
+                    assert_eq!(payload[1], FLAG_DELTA_DICT);
+                    assert_eq!(codec.decode_delta_dict(&payload, dict, gzip).unwrap(), ids);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn known_bigrams_take_one_byte() {
+        let codec = Codec::new();
+        let ids = phrases();
+        let trained = codec
+            .encode_delta_dict(&ids, &FreqMap::from_bigrams(&ids), false)
+            .unwrap();
+        let escaped = codec
+            .encode_delta_dict(&ids, &FreqMap::from_bigrams(&[]), false)
+            .unwrap();
+        // Every transition of this stream recurs, so all but the first token
+        // become one-byte codes instead of two-byte escapes.
+        let header_len = Header::decode_prefix(&trained).unwrap().0.body_offset();
+        assert_eq!(escaped.len(), header_len + 1 + 2 * ids.len());
+        assert!(
+            trained.len() < header_len + 100 + ids.len(),
+            "{}",
+            trained.len()
+        );
+    }
+
+    #[test]
+    fn wrong_dictionary_is_rejected() {
+        let codec = Codec::new();
+        let ids = phrases();
+        let payload = codec
+            .encode_delta_dict(&ids, &FreqMap::from_bigrams(&ids), false)
+            .unwrap();
+
+        let other = FreqMap::from_bigrams(&[9, 8, 9, 8, 9, 8]);
+        assert!(codec.decode_delta_dict(&payload, &other, false).is_err());
+
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+        assert!(codec
+            .decode_delta_dict(&plain, &FreqMap::from_bigrams(&ids), false)
+            .is_err());
+    }
+}
@@ -0,0 +1,84 @@
+// src/bits.rs
+use anyhow::{bail, Result};
+
+/// Bit stream writer for the bit-oriented coders (`rice`, `elias`). Bits are
+/// packed least significant bit first; the final byte is zero-padded.
+pub(crate) struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    pending: u32,
+}
+
+impl BitWriter {
+    pub(crate) fn with_capacity(values: usize) -> Self {
+        Self {
+            out: Vec::with_capacity(values),
+            acc: 0,
+            pending: 0,
+        }
+    }
+
+    /// Append the low `n` bits of `bits` (`n <= 33`).
+    #[inline]
+    pub(crate) fn push(&mut self, bits: u64, n: u32) {
+        self.acc |= bits << self.pending;
+        self.pending += n;
+        while self.pending >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.pending -= 8;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.pending > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// Reader for `BitWriter` output.
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Position in bits.
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// True once every bit up to the last byte's padding has been read.
+    pub(crate) fn at_end(&self) -> bool {
+        self.pos.div_ceil(8) == self.bytes.len()
+    }
+
+    /// The next 57 or more bits (fewer near the end, zero-filled past it).
+    #[inline]
+    pub(crate) fn peek(&self) -> u64 {
+        let start = self.pos / 8;
+        let mut window = [0u8; 8];
+        let avail = self.bytes.len().saturating_sub(start).min(8);
+        window[..avail].copy_from_slice(&self.bytes[start..start + avail]);
+        u64::from_le_bytes(window) >> (self.pos % 8)
+    }
+
+    #[inline]
+    pub(crate) fn skip(&mut self, n: u32) -> Result<()> {
+        if self.pos + n as usize > self.bytes.len() * 8 {
+            bail!("bit stream ended early");
+        }
+        self.pos += n as usize;
+        Ok(())
+    }
+
+    /// Read `n <= 32` bits.
+    #[inline]
+    pub(crate) fn read(&mut self, n: u32) -> Result<u32> {
+        let bits = self.peek() & ((1u64 << n) - 1);
+        self.skip(n)?;
+        Ok(bits as u32)
+    }
+}
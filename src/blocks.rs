@@ -0,0 +1,152 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::{varint, Codec};
+
+/// One entry of a block index: the block's token count and its bytes.
+pub(crate) type Block<'a> = (usize, &'a [u8]);
+
+/// Block-encoded payloads: a long sequence cut into fixed-size blocks, each
+/// a self-contained standard payload with its own header (and own gzip
+/// stream), so blocks can be decoded independently of each other.
+///
+/// Layout:
+///   varint  : number of blocks N
+///   N pairs : (varint token count, varint byte length) of each block
+///   N blocks: standard payloads, in order
+impl Codec {
+    pub fn encode_blocks(
+        &self,
+        token_ids: &[i32],
+        block_tokens: usize,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if block_tokens == 0 {
+            return Err(CodecError::Internal(
+                "block size must be at least one token".to_string(),
+            ));
+        }
+
+        let blocks = token_ids
+            .chunks(block_tokens)
+            .map(|chunk| self.encode(chunk, gzip))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut index = vec![to_u32(blocks.len())?];
+        for (chunk, block) in token_ids.chunks(block_tokens).zip(&blocks) {
+            index.push(to_u32(chunk.len())?);
+            index.push(to_u32(block.len())?);
+        }
+
+        let mut out = varint::encode(&index);
+        for block in &blocks {
+            out.extend_from_slice(block);
+        }
+        Ok(out)
+    }
+
+    /// Decode every block in turn. See `decode_block_parallel` (feature
+    /// `parallel`) for the multi-threaded version.
+    pub fn decode_blocks(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let blocks = Self::block_index(payload)?;
+        let mut out = Vec::with_capacity(blocks.iter().map(|(tokens, _)| tokens).sum());
+        for block in blocks {
+            out.extend(Self::decode_block(block, gzip)?);
+        }
+        Ok(out)
+    }
+
+    /// Parse the index of a block-encoded payload, checking that the block
+    /// lengths account for exactly the bytes that follow it.
+    pub(crate) fn block_index(payload: &[u8]) -> Result<Vec<Block<'_>>> {
+        let mut rest = payload;
+        let count = read_varint(&mut rest)? as usize;
+
+        // Every index entry takes at least two bytes, which bounds the allocation.
+        let mut sizes = Vec::with_capacity(count.min(rest.len() / 2));
+        for _ in 0..count {
+            let tokens = read_varint(&mut rest)? as usize;
+            let bytes = read_varint(&mut rest)? as usize;
+            sizes.push((tokens, bytes));
+        }
+
+        let mut blocks = Vec::with_capacity(count);
+        for (tokens, bytes) in sizes {
+            if bytes > rest.len() {
+                return Err(CodecError::InvalidPayload);
+            }
+            let (block, tail) = rest.split_at(bytes);
+            blocks.push((tokens, block));
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(blocks)
+    }
+
+    /// Decode one block, checking it against its index entry.
+    pub(crate) fn decode_block((tokens, bytes): Block<'_>, gzip: bool) -> Result<Vec<i32>> {
+        let decoded = CodecCore::decode_token_ids(bytes, gzip)?;
+        if decoded.len() != tokens {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_round_trip() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..1000).map(|i| (i * 7919) % 613).collect();
+
+        for gzip in [false, true] {
+            for block_tokens in [1, 256, 999, 1000, 5000] {
+                let payload = codec.encode_blocks(&ids, block_tokens, gzip).unwrap();
+                assert_eq!(codec.decode_blocks(&payload, gzip).unwrap(), ids);
+            }
+        }
+        let empty = codec.encode_blocks(&[], 256, false).unwrap();
+        assert_eq!(empty, [0]);
+        assert!(codec.decode_blocks(&empty, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn blocks_are_self_contained() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..600).collect();
+        let payload = codec.encode_blocks(&ids, 256, false).unwrap();
+
+        let blocks = Codec::block_index(&payload).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(
+            Codec::decode_block(blocks[2], false).unwrap(),
+            ids[512..].to_vec()
+        );
+    }
+
+    #[test]
+    fn malformed_index_is_rejected() {
+        let codec = Codec::new();
+        let payload = codec.encode_blocks(&[1, 2, 3, 4], 2, false).unwrap();
+
+        assert!(codec
+            .decode_blocks(&payload[..payload.len() - 1], false)
+            .is_err());
+        assert!(codec
+            .decode_blocks(&[payload.as_slice(), &[0]].concat(), false)
+            .is_err());
+        assert!(codec.encode_blocks(&[1], 0, false).is_err());
+
+        // First block claims 3 tokens but holds 2.
+        let mut wrong_count = payload.clone();
+        wrong_count[1] = 3;
+        assert!(matches!(
+            codec.decode_blocks(&wrong_count, false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{FLAG_BLOOM_FILTER, FLAG_EXTENSIONS, FORMAT_VERSION};
+use crate::{varint, Codec};
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// Salt mixed into the second hash so that it is independent of the first.
+const SECOND_HASH_SALT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Bloom filter over token IDs.
+///
+/// Probe positions come from two 64-bit FNV-1a hashes of the token's LE
+/// bytes, the second one salted, combined by double hashing: probe `i` is
+/// bit `(h1 + i * h2) % num_bits`.
+///
+/// Serialized as:
+///   [varint probe count][filter bits, LSB first]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// An empty filter sized for `items` insertions at `false_positive_rate`:
+    /// `m = -n ln p / ln² 2` bits (rounded up to whole bytes) and
+    /// `k = m / n ln 2` probes.
+    ///
+    /// Panics unless `false_positive_rate` is in `(0, 1)`.
+    pub fn with_rate(items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be in (0, 1), got {false_positive_rate}"
+        );
+        let ln2 = std::f64::consts::LN_2;
+        let n = items.max(1) as f64;
+        let num_bits = (-n * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(8.0);
+        let num_bytes = (num_bits / 8.0).ceil() as usize;
+        let num_hashes = ((num_bytes * 8) as f64 / n * ln2).round().max(1.0) as u32;
+        Self {
+            bits: vec![0; num_bytes],
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, token: i32) {
+        for bit in self.probes(token) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// False for tokens never inserted, except for false positives.
+    pub fn contains(&self, token: i32) -> bool {
+        self.probes(token)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = varint::encode(&[self.num_hashes]);
+        out.extend_from_slice(&self.bits);
+        out
+    }
+
+    /// Errors with `CodecError::InvalidPayload` unless `bytes` holds at least
+    /// one probe and one byte of bits.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (num_hashes, used) =
+            varint::decode_one(bytes).map_err(|_| CodecError::InvalidPayload)?;
+        let bits = &bytes[used..];
+        if num_hashes == 0 || bits.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(Self {
+            bits: bits.to_vec(),
+            num_hashes,
+        })
+    }
+
+    fn probes(&self, token: i32) -> impl Iterator<Item = usize> {
+        let bytes = token.to_le_bytes();
+        let h1 = fnv1a(FNV_OFFSET_BASIS, &bytes);
+        let h2 = fnv1a(FNV_OFFSET_BASIS ^ SECOND_HASH_SALT, &bytes) | 1;
+        let num_bits = self.num_bits() as u64;
+        (0..u64::from(self.num_hashes))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+}
+
+fn fnv1a(basis: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(basis, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Payloads that answer "might this contain token X?" without decoding: a
+/// standard payload with a Bloom filter over its distinct tokens in the
+/// `FLAG_BLOOM_FILTER` extension.
+impl Codec {
+    /// Errors with `CodecError::Internal` unless `false_positive_rate` is in
+    /// `(0, 1)`.
+    pub fn encode_with_bloom(
+        &self,
+        token_ids: &[i32],
+        false_positive_rate: f64,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(CodecError::Internal(format!(
+                "false positive rate must be in (0, 1), got {false_positive_rate}"
+            )));
+        }
+        let freq = FreqMap::from_token_ids(token_ids);
+        let tokens = freq.ordered_tokens();
+        let mut filter = BloomFilter::with_rate(tokens.len(), false_positive_rate);
+        for &token in tokens {
+            filter.insert(token);
+        }
+        CodecCore::encode_with_extensions(
+            token_ids,
+            &[(FLAG_BLOOM_FILTER, &filter.to_bytes())],
+            gzip,
+        )
+    }
+
+    /// Whether the payload may contain `token`: never false for a token it
+    /// holds, and true for others at about the rate it was encoded with.
+    ///
+    /// Only the version and flag bytes and the extension area at the end are
+    /// read; the token table and body are skipped. Errors with
+    /// `CodecError::InvalidPayload` if the payload has no Bloom filter.
+    pub fn query_bloom(&self, payload: &[u8], token: i32) -> Result<bool> {
+        let filter = bloom_extension(payload)?;
+        Ok(BloomFilter::from_bytes(filter)?.contains(token))
+    }
+}
+
+/// The `FLAG_BLOOM_FILTER` extension bytes, found by walking the extension
+/// area back from the end of the payload.
+fn bloom_extension(payload: &[u8]) -> Result<&[u8]> {
+    match payload {
+        [FORMAT_VERSION, flags, ..] if flags & FLAG_EXTENSIONS != 0 => {}
+        _ => return Err(CodecError::InvalidPayload),
+    }
+    let (&count, mut rest) = payload.split_last().ok_or(CodecError::InvalidPayload)?;
+    for _ in 0..count {
+        let (before, framed) = CodecCore::pop_section(rest)?;
+        let (&tag, bytes) = framed.split_last().ok_or(CodecError::InvalidPayload)?;
+        if tag == FLAG_BLOOM_FILTER {
+            return Ok(bytes);
+        }
+        rest = before;
+    }
+    Err(CodecError::InvalidPayload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2000 distinct tokens spread over a 50K vocabulary.
+    fn ids() -> Vec<i32> {
+        (0..2000).map(|i| (i * 7919) % 50_000).collect()
+    }
+
+    #[test]
+    fn bloom_payloads_round_trip() {
+        let codec = Codec::new();
+        for ids in [ids(), vec![], vec![1]] {
+            for gzip in [false, true] {
+                let payload = codec.encode_with_bloom(&ids, 0.01, gzip).unwrap();
+                // Plain decoders skip the extension.
+                assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn no_false_negatives() {
+        let codec = Codec::new();
+        let ids = ids();
+        for rate in [0.5, 0.01, 0.0001] {
+            let payload = codec.encode_with_bloom(&ids, rate, true).unwrap();
+            assert!(ids.iter().all(|&t| codec.query_bloom(&payload, t).unwrap()));
+        }
+    }
+
+    #[test]
+    fn false_positive_rate_is_close_to_the_target() {
+        let codec = Codec::new();
+        let ids = ids();
+        for rate in [0.1, 0.01, 0.001] {
+            let payload = codec.encode_with_bloom(&ids, rate, false).unwrap();
+            // Tokens outside the 50K vocabulary were never inserted.
+            let trials = 200_000;
+            let hits = (0..trials)
+                .filter(|i| codec.query_bloom(&payload, 100_000 + i).unwrap())
+                .count();
+            let measured = hits as f64 / trials as f64;
+            assert!(
+                (rate / 2.0..=2.0 * rate).contains(&measured),
+                "{measured} for {rate}"
+            );
+        }
+    }
+
+    #[test]
+    fn filter_size_follows_the_rate() {
+        let loose = BloomFilter::with_rate(1000, 0.1);
+        let tight = BloomFilter::with_rate(1000, 0.001);
+        // About 4.8 and 14.4 bits per item.
+        assert_eq!(loose.num_bits(), 4800);
+        assert_eq!(loose.num_hashes(), 3);
+        assert!(tight.num_bits() > 3 * loose.num_bits() - 100);
+        assert_eq!(tight.num_hashes(), 10);
+        assert_eq!(BloomFilter::from_bytes(&tight.to_bytes()).unwrap(), tight);
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        let codec = Codec::new();
+        for rate in [0.0, 1.0, -0.5, f64::NAN] {
+            assert!(codec.encode_with_bloom(&[1, 2], rate, false).is_err());
+        }
+
+        let plain = CodecCore::encode_token_ids(&[1, 2], false).unwrap();
+        assert!(codec.query_bloom(&plain, 1).is_err());
+        let other = CodecCore::encode_with_extensions(&[1, 2], &[(0x77, b"x")], false).unwrap();
+        assert!(codec.query_bloom(&other, 1).is_err());
+        assert!(codec.query_bloom(&[], 1).is_err());
+        assert!(BloomFilter::from_bytes(&[0, 1]).is_err());
+        assert!(BloomFilter::from_bytes(&[3]).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::hint::black_box;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::freq_map::FreqMap;
+use crate::Codec;
+
+/// Tokens per calibration run.
+const CALIBRATION_TOKENS: usize = 1 << 16;
+
+/// Calibration runs per measurement; the fastest one counts.
+const CALIBRATION_RUNS: usize = 3;
+
+static COSTS: OnceLock<(u64, u64)> = OnceLock::new();
+
+/// Cost model for `FreqMap::from_token_ids`, for callers deciding whether a
+/// per-payload map is worth building:
+///
+///   n_tokens * TOKEN_COST_NS + n_unique * UNIQUE_COST_NS
+///
+/// The per-token cost covers counting, the per-unique-token cost covers the
+/// sort and the lookup tables. Both are measured on this machine by
+/// `calibrate_costs`, once per process on first use.
+impl Codec {
+    /// Estimated nanoseconds to build a `FreqMap` for `n_tokens` tokens with
+    /// about `n_unique_estimate` distinct ones.
+    pub fn estimate_freq_map_build_ns(n_tokens: usize, n_unique_estimate: usize) -> u64 {
+        let (token_cost, unique_cost) = *COSTS.get_or_init(Self::calibrate_costs);
+        (n_tokens as u64)
+            .saturating_mul(token_cost)
+            .saturating_add((n_unique_estimate as u64).saturating_mul(unique_cost))
+    }
+
+    /// Time `FreqMap` builds and return `(TOKEN_COST_NS, UNIQUE_COST_NS)`,
+    /// each at least 1.
+    ///
+    /// A run over one repeated token gives the per-token cost; a run over
+    /// distinct tokens gives the per-token plus the per-unique cost.
+    pub fn calibrate_costs() -> (u64, u64) {
+        let repeated = vec![7; CALIBRATION_TOKENS];
+        let distinct: Vec<i32> = (0..CALIBRATION_TOKENS as i32)
+            .map(|i| i.wrapping_mul(7919))
+            .collect();
+
+        let per_token = |elapsed: Duration| elapsed.as_nanos() as f64 / CALIBRATION_TOKENS as f64;
+        let token_cost = per_token(fastest_build(&repeated));
+        let unique_cost = per_token(fastest_build(&distinct)) - token_cost;
+        (
+            (token_cost.round() as u64).max(1),
+            (unique_cost.round() as u64).max(1),
+        )
+    }
+}
+
+fn fastest_build(token_ids: &[i32]) -> Duration {
+    (0..CALIBRATION_RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(FreqMap::from_token_ids(black_box(token_ids)));
+            start.elapsed()
+        })
+        .min()
+        .expect("at least one calibration run")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_is_within_5x_of_actual() {
+        let ids: Vec<i32> = (0..200_000).map(|i| (i * 7919) % 20_000).collect();
+        let estimate = Codec::estimate_freq_map_build_ns(ids.len(), 20_000);
+        let actual = fastest_build(&ids).as_nanos() as u64;
+        assert!(
+            estimate <= actual * 5 && actual <= estimate * 5,
+            "estimated {estimate} ns, took {actual} ns"
+        );
+    }
+
+    #[test]
+    fn estimates_grow_with_the_input() {
+        let small = Codec::estimate_freq_map_build_ns(1000, 100);
+        assert!(Codec::estimate_freq_map_build_ns(10_000, 100) > small);
+        assert!(Codec::estimate_freq_map_build_ns(1000, 1000) > small);
+        assert_eq!(Codec::estimate_freq_map_build_ns(0, 0), 0);
+        assert_eq!(
+            Codec::estimate_freq_map_build_ns(usize::MAX, usize::MAX),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn calibrated_costs_are_positive() {
+        let (token_cost, unique_cost) = Codec::calibrate_costs();
+        assert!(token_cost >= 1 && unique_cost >= 1);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::{varint, zigzag, Codec};
+
+/// `(input_ids, labels)`, one entry per sequence in each.
+pub(crate) type CausalLmBatch = (Vec<Vec<i32>>, Vec<Vec<i32>>);
+
+/// Causal-LM training batches: `(input_ids, labels)` pairs where the labels
+/// are the inputs shifted left by one with `pad_id` appended.
+///
+/// Only the inputs are stored; labels are rebuilt on decode.
+///
+/// Layout:
+///   varint  : zigzagged `pad_id`
+///   varint  : number of sequences N
+///   N varint: length of each sequence
+///   payload : standard payload of all N sequences concatenated
+impl Codec {
+    pub fn encode_causal_lm_batch(
+        &self,
+        sequences: &[Vec<i32>],
+        pad_id: i32,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut prefix = vec![zigzag::encode(pad_id), to_u32(sequences.len())?];
+        for seq in sequences {
+            prefix.push(to_u32(seq.len())?);
+        }
+
+        let mut out = varint::encode(&prefix);
+        out.extend_from_slice(&CodecCore::encode_token_ids(&sequences.concat(), gzip)?);
+        Ok(out)
+    }
+
+    pub fn decode_causal_lm_batch(&self, payload: &[u8], gzip: bool) -> Result<CausalLmBatch> {
+        let mut rest = payload;
+        let pad_id = zigzag::decode(read_varint(&mut rest)?);
+        let count = read_varint(&mut rest)? as usize;
+
+        // Every length takes at least one byte, which bounds the allocation.
+        let mut lengths = Vec::with_capacity(count.min(rest.len()));
+        for _ in 0..count {
+            lengths.push(read_varint(&mut rest)? as usize);
+        }
+
+        let tokens = CodecCore::decode_token_ids(rest, gzip)?;
+        if lengths
+            .iter()
+            .try_fold(0usize, |acc, &len| acc.checked_add(len))
+            != Some(tokens.len())
+        {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut inputs = Vec::with_capacity(count);
+        let mut labels = Vec::with_capacity(count);
+        let mut tokens = &tokens[..];
+        for len in lengths {
+            let (seq, tail) = tokens.split_at(len);
+            let mut shifted = seq.get(1..).unwrap_or_default().to_vec();
+            if !seq.is_empty() {
+                shifted.push(pad_id);
+            }
+            inputs.push(seq.to_vec());
+            labels.push(shifted);
+            tokens = tail;
+        }
+        Ok((inputs, labels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAD: i32 = 50256;
+
+    fn batch() -> Vec<Vec<i32>> {
+        (0..8u32)
+            .map(|s| {
+                (0..256u32)
+                    .map(|i| (i.wrapping_mul(2654435761) ^ s) % 500)
+                    .map(|t| t as i32)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn labels_for(seq: &[i32]) -> Vec<i32> {
+        let mut labels = seq[1..].to_vec();
+        labels.push(PAD);
+        labels
+    }
+
+    #[test]
+    fn labels_are_reconstructed() {
+        let codec = Codec::new();
+        let sequences = batch();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_causal_lm_batch(&sequences, PAD, gzip).unwrap();
+            let (inputs, labels) = codec.decode_causal_lm_batch(&payload, gzip).unwrap();
+            assert_eq!(inputs, sequences);
+            let expected: Vec<Vec<i32>> = sequences.iter().map(|seq| labels_for(seq)).collect();
+            assert_eq!(labels, expected);
+        }
+    }
+
+    #[test]
+    fn much_smaller_than_inputs_and_labels_separately() {
+        let codec = Codec::new();
+        let sequences = batch();
+
+        let combined = codec
+            .encode_causal_lm_batch(&sequences, PAD, false)
+            .unwrap();
+        let separate: usize = sequences
+            .iter()
+            .map(|seq| {
+                CodecCore::encode_token_ids(seq, false).unwrap().len()
+                    + CodecCore::encode_token_ids(&labels_for(seq), false)
+                        .unwrap()
+                        .len()
+            })
+            .sum();
+
+        assert!(
+            combined.len() * 2 < separate,
+            "combined {} vs separate {}",
+            combined.len(),
+            separate
+        );
+    }
+
+    #[test]
+    fn empty_sequences_have_empty_labels() {
+        let codec = Codec::new();
+        let sequences = vec![vec![], vec![7], vec![]];
+        let payload = codec.encode_causal_lm_batch(&sequences, -1, false).unwrap();
+
+        let (inputs, labels) = codec.decode_causal_lm_batch(&payload, false).unwrap();
+        assert_eq!(inputs, sequences);
+        assert_eq!(labels, vec![vec![], vec![-1], vec![]]);
+    }
+
+    #[test]
+    fn length_mismatch_is_rejected() {
+        let codec = Codec::new();
+        let mut payload = codec
+            .encode_causal_lm_batch(&[vec![1, 2, 3]], PAD, false)
+            .unwrap();
+        // The single length byte follows the 3-byte pad and 1-byte count.
+        payload[4] = 4;
+        assert!(matches!(
+            codec.decode_causal_lm_batch(&payload, false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
@@ -1,15 +1,974 @@
-use crate::errors::Result;
+use std::borrow::Cow;
+use std::io::{Read, Write};
 
-  pub struct CodecCore;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 
-  impl CodecCore {
-      pub fn encode_token_ids(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
-          let _ = gzip;
-          unimplemented!()
-      }
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{
+    Header, FLAG_ARITHMETIC, FLAG_DELTA_CODED, FLAG_ELIAS_GAMMA, FLAG_EXTENSIONS, FLAG_HUFFMAN,
+    FLAG_RICE_ENCODED, FLAG_VBYTE_ENCODING, SECTION_FLAGS,
+};
+use crate::{arithmetic, delta, elias, huffman, rice, varint, vbyte, zigzag};
 
-      pub fn decode_token_ids(payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
-          let _ = gzip;
-          unimplemented!()
-      }
-  }
\ No newline at end of file
+/// Largest token ID for which `encode_body` uses a flat lookup table.
+pub const LOOKUP_TABLE_MAX_TOKEN: usize = 65535;
+
+/// CodecCore wires the individual stages into the full payload pipeline.
+///
+/// Encode:
+///   1. Build a per-payload `FreqMap` (frequency-ranked mapped IDs).
+///   2. Remap every token to its mapped ID.
+///   3. Zigzag the mapped IDs and varint-encode them into the body.
+///   4. Optionally gzip the body.
+///   5. Prepend the serialized `Header` so the decoder can undo the remapping.
+///
+/// Payload layout:
+///   [header bytes][body (possibly gzipped)][sections...]
+///
+/// The header is never compressed, so its version and flags can be inspected
+/// without inflating anything. Each flag in `SECTION_FLAGS` that is set in the
+/// header means one extra section was appended after the body (see
+/// `push_section`).
+///
+/// Header bits are scarce, so most optional data goes into *extensions*
+/// instead: tagged sections announced by the single `FLAG_EXTENSIONS` bit and
+/// stored after all other sections (see `push_extensions`). Extensions carry
+/// their own tag, so decoders skip unknown ones without any flag changes.
+///
+/// Decode runs the same steps in reverse.
+pub struct CodecCore;
+
+/// A payload split into its header, (possibly compressed) body and the
+/// sections appended after the body.
+#[derive(Debug)]
+pub struct PayloadParts<'a> {
+    pub header: Header,
+    pub body: &'a [u8],
+    /// `(flag, bytes)` for every section flag set in the header, in append order.
+    pub sections: Vec<(u8, &'a [u8])>,
+    /// `(tag, bytes)` for every extension, in append order.
+    pub extensions: Vec<(u8, &'a [u8])>,
+}
+
+impl<'a> PayloadParts<'a> {
+    /// Bytes of the section recorded under `flag`, if present.
+    pub fn section(&self, flag: u8) -> Option<&'a [u8]> {
+        self.sections
+            .iter()
+            .find(|(f, _)| *f == flag)
+            .map(|(_, bytes)| *bytes)
+    }
+
+    /// Bytes of the extension stored under `tag`, if present.
+    pub fn extension(&self, tag: u8) -> Option<&'a [u8]> {
+        self.extensions
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, bytes)| *bytes)
+    }
+}
+
+impl CodecCore {
+    pub fn encode_token_ids(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        Self::encode_with_flags(ids, 0, gzip)
+    }
+
+    pub fn decode_token_ids(payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        Self::decode_with_header(payload, gzip).map(|(tokens, _)| tokens)
+    }
+
+    /// `decode_token_ids`, also handing back the parsed header (token table in
+    /// mapped-ID order, version, flags).
+    pub fn decode_with_header(payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Header)> {
+        let parts = Self::split_payload(payload)?;
+        let body = Self::decompress(parts.body, gzip)?;
+
+        let tokens = match parts.header.mode() {
+            0 => Self::decode_body(&body, &parts.header)?,
+            FLAG_VBYTE_ENCODING => Self::decode_body_vbyte(&body, &parts.header)?,
+            FLAG_RICE_ENCODED => Self::decode_body_rice(&body, &parts.header)?,
+            FLAG_ELIAS_GAMMA => Self::decode_body_elias_gamma(&body, &parts.header)?,
+            FLAG_HUFFMAN => Self::decode_body_huffman(&body, &parts.header)?,
+            FLAG_ARITHMETIC => Self::decode_body_arithmetic(&body, &parts.header)?,
+            FLAG_DELTA_CODED => Self::decode_body_delta(&body, &parts.header)?,
+            // Other modes lay out the payload differently; they have their own
+            // decoders.
+            _ => return Err(CodecError::InvalidPayload),
+        };
+        Ok((tokens, parts.header))
+    }
+
+    /// Same as `encode_token_ids`, but with `flags` recorded in the header.
+    ///
+    /// Callers that set a section flag must append the matching section with
+    /// `push_section` afterwards.
+    pub fn encode_with_flags(ids: &[i32], flags: u8, gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = flags;
+
+        let body = Self::compress(Self::encode_body(ids, &freq)?, gzip)?;
+
+        let mut out = header.encode();
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    /// Standard payload for `ids` followed by the given `(tag, bytes)`
+    /// extensions, with `FLAG_EXTENSIONS` set in the header.
+    pub fn encode_with_extensions(
+        ids: &[i32],
+        extensions: &[(u8, &[u8])],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut out = Self::encode_with_flags(ids, FLAG_EXTENSIONS, gzip)?;
+        Self::push_extensions(&mut out, extensions)?;
+        Ok(out)
+    }
+
+    /// Decode the tokens of a payload and return the bytes of its extension
+    /// `tag`, if it has one.
+    pub fn decode_with_extension(
+        payload: &[u8],
+        tag: u8,
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Option<&[u8]>)> {
+        let parts = Self::split_payload(payload)?;
+        if parts.header.mode() != 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = Self::decompress(parts.body, gzip)?;
+        let tokens = Self::decode_body(&body, &parts.header)?;
+        Ok((tokens, parts.extension(tag)))
+    }
+
+    /// Parse the header and peel off every section its flags announce.
+    pub fn split_payload(payload: &[u8]) -> Result<PayloadParts<'_>> {
+        let (header, mut rest) =
+            Header::decode_prefix(payload).map_err(|_| CodecError::InvalidPayload)?;
+
+        // Extensions come last, so they are peeled off first.
+        let mut extensions = Vec::new();
+        if header.has_flag(FLAG_EXTENSIONS) {
+            let (&count, before) = rest.split_last().ok_or(CodecError::InvalidPayload)?;
+            rest = before;
+            for _ in 0..count {
+                let (before, framed) = Self::pop_section(rest)?;
+                let (&tag, bytes) = framed.split_last().ok_or(CodecError::InvalidPayload)?;
+                extensions.push((tag, bytes));
+                rest = before;
+            }
+            extensions.reverse();
+        }
+
+        // Sections were appended in `SECTION_FLAGS` order; peel them off the end in reverse.
+        let mut sections = Vec::new();
+        for &flag in SECTION_FLAGS.iter().rev() {
+            if header.has_flag(flag) {
+                let (before, section) = Self::pop_section(rest)?;
+                sections.push((flag, section));
+                rest = before;
+            }
+        }
+        sections.reverse();
+
+        Ok(PayloadParts {
+            header,
+            body: rest,
+            sections,
+            extensions,
+        })
+    }
+
+    /// Append `section` followed by its byte length as a little-endian u32.
+    pub fn push_section(out: &mut Vec<u8>, section: &[u8]) -> Result<()> {
+        let len = u32::try_from(section.len())
+            .map_err(|_| CodecError::Internal("section exceeds u32::MAX bytes".to_string()))?;
+        out.extend_from_slice(section);
+        out.extend_from_slice(&len.to_le_bytes());
+        Ok(())
+    }
+
+    /// Append the extension area: each `(tag, bytes)` as a section whose last
+    /// byte is the tag, then a final byte counting the extensions.
+    ///
+    /// Must be the last thing written, and the header must carry
+    /// `FLAG_EXTENSIONS`.
+    pub fn push_extensions(out: &mut Vec<u8>, extensions: &[(u8, &[u8])]) -> Result<()> {
+        let count = u8::try_from(extensions.len())
+            .map_err(|_| CodecError::Internal("more than 255 extensions".to_string()))?;
+        for &(tag, bytes) in extensions {
+            let mut framed = Vec::with_capacity(bytes.len() + 1);
+            framed.extend_from_slice(bytes);
+            framed.push(tag);
+            Self::push_section(out, &framed)?;
+        }
+        out.push(count);
+        Ok(())
+    }
+
+    /// Split the last section written by `push_section` off the end of `bytes`,
+    /// returning `(preceding bytes, section)`.
+    pub fn pop_section(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+        let split = bytes
+            .len()
+            .checked_sub(4)
+            .ok_or(CodecError::InvalidPayload)?;
+        let len_bytes: [u8; 4] = bytes[split..]
+            .try_into()
+            .expect("slice of length 4 will always convert");
+        let len = u32::from_le_bytes(len_bytes) as usize;
+
+        let start = split.checked_sub(len).ok_or(CodecError::InvalidPayload)?;
+        Ok((&bytes[..start], &bytes[start..split]))
+    }
+
+    /// Remap `ids` through `freq` and produce the zigzag + varint body.
+    ///
+    /// When every token lies in `0..=LOOKUP_TABLE_MAX_TOKEN`, remapping goes
+    /// through a flat lookup table instead of the HashMap.
+    ///
+    /// Every token in `ids` must be present in `freq`.
+    pub fn encode_body(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        Ok(varint::encode(&zigzag::encode_slice(&Self::map_ids(ids, freq)?)))
+    }
+
+    /// `encode_body` with the mapped IDs delta-coded (`delta::encode`)
+    /// instead of zigzagged directly.
+    pub fn encode_body_delta(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        Ok(varint::encode(&delta::encode(&Self::map_ids(ids, freq)?)))
+    }
+
+    /// Standard payload with a delta-coded body, flagged with the
+    /// `FLAG_DELTA_CODED` mode.
+    pub fn encode_token_ids_delta(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_DELTA_CODED;
+
+        let mut out = header.encode();
+        out.extend_from_slice(&Self::compress(Self::encode_body_delta(ids, &freq)?, gzip)?);
+        Ok(out)
+    }
+
+    /// `encode_body` with Group VarInt instead of LEB128.
+    pub fn encode_body_vbyte(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        Ok(vbyte::encode_vbyte(&zigzag::encode_slice(&Self::map_ids(ids, freq)?)))
+    }
+
+    /// Standard payload with a Group VarInt body, flagged with the
+    /// `FLAG_VBYTE_ENCODING` mode.
+    pub fn encode_token_ids_vbyte(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_VBYTE_ENCODING;
+
+        let mut out = header.encode();
+        out.extend_from_slice(&Self::compress(Self::encode_body_vbyte(ids, &freq)?, gzip)?);
+        Ok(out)
+    }
+
+    /// `encode_body` with Rice coding instead of LEB128:
+    ///   [log2 of the Rice parameter u8][varint count][rice::encode_rice output]
+    ///
+    /// Mapped IDs are never negative, so they are Rice coded as they are,
+    /// without zigzag.
+    pub fn encode_body_rice(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        let values: Vec<u32> = Self::map_ids(ids, freq)?
+            .into_iter()
+            .map(|mapped| mapped as u32)
+            .collect();
+        let m = rice::optimal_rice_parameter(&values);
+
+        let mut out = vec![m.ilog2() as u8];
+        out.extend(varint::encode(&[to_u32(values.len())?]));
+        out.extend(rice::encode_rice(&values, m));
+        Ok(out)
+    }
+
+    /// Standard payload with a Rice-coded body, flagged with the
+    /// `FLAG_RICE_ENCODED` mode.
+    pub fn encode_token_ids_rice(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_RICE_ENCODED;
+
+        let mut out = header.encode();
+        out.extend_from_slice(&Self::compress(Self::encode_body_rice(ids, &freq)?, gzip)?);
+        Ok(out)
+    }
+
+    /// `encode_body` with Elias-gamma coding instead of LEB128:
+    ///   [u32 LE count][elias::encode_elias_gamma output]
+    ///
+    /// Elias gamma only codes positive values, so each mapped ID is stored
+    /// plus one.
+    pub fn encode_body_elias_gamma(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        let values: Vec<u32> = Self::map_ids(ids, freq)?
+            .into_iter()
+            .map(|mapped| mapped as u32 + 1)
+            .collect();
+
+        let mut out = to_u32(values.len())?.to_le_bytes().to_vec();
+        out.extend(elias::encode_elias_gamma(&values));
+        Ok(out)
+    }
+
+    /// Standard payload with an Elias-gamma body, flagged with the
+    /// `FLAG_ELIAS_GAMMA` mode.
+    pub fn encode_token_ids_elias_gamma(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_ELIAS_GAMMA;
+
+        let mut out = header.encode();
+        let body = Self::encode_body_elias_gamma(ids, &freq)?;
+        out.extend_from_slice(&Self::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    /// `encode_body` with static Huffman coding instead of LEB128:
+    ///   [u8 code length per token table entry][varint count]
+    ///   [huffman::encode_huffman output]
+    ///
+    /// The code lengths are the serialized `HuffmanTable`, one per mapped ID,
+    /// built from `freq`'s counts.
+    pub fn encode_body_huffman(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        let values: Vec<u32> = Self::map_ids(ids, freq)?
+            .into_iter()
+            .map(|mapped| mapped as u32)
+            .collect();
+        let table = huffman::build_huffman_table(freq);
+
+        let mut out = table.code_lengths().to_vec();
+        out.extend_from_slice(&varint::encode(&[to_u32(values.len())?]));
+        out.extend(huffman::encode_huffman(&values, &table));
+        Ok(out)
+    }
+
+    /// Standard payload with a Huffman-coded body, flagged with the
+    /// `FLAG_HUFFMAN` mode.
+    pub fn encode_token_ids_huffman(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_HUFFMAN;
+
+        let mut out = header.encode();
+        out.extend_from_slice(&Self::compress(Self::encode_body_huffman(ids, &freq)?, gzip)?);
+        Ok(out)
+    }
+
+    /// `encode_body` with arithmetic coding instead of LEB128:
+    ///   [varint count per token table entry]
+    ///   [arithmetic::encode_arithmetic output]
+    ///
+    /// The model is `freq`'s probability table, which the decoder rebuilds
+    /// from the counts; their sum is the value count.
+    pub fn encode_body_arithmetic(ids: &[i32], freq: &FreqMap) -> Result<Vec<u8>> {
+        let values: Vec<u32> = Self::map_ids(ids, freq)?
+            .into_iter()
+            .map(|mapped| mapped as u32)
+            .collect();
+
+        let counts = freq
+            .counts()
+            .iter()
+            .map(|&count| to_u32(count))
+            .collect::<Result<Vec<u32>>>()?;
+        let mut out = varint::encode(&counts);
+        if !values.is_empty() {
+            out.extend(arithmetic::encode_arithmetic(
+                &values,
+                &freq.to_probability_table(),
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Standard payload with an arithmetic-coded body, flagged with the
+    /// `FLAG_ARITHMETIC` mode.
+    pub fn encode_token_ids_arithmetic(ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_ARITHMETIC;
+
+        let mut out = header.encode();
+        let body = Self::encode_body_arithmetic(ids, &freq)?;
+        out.extend_from_slice(&Self::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    /// Mapped ID of every token in `ids`.
+    pub(crate) fn map_ids(ids: &[i32], freq: &FreqMap) -> Result<Vec<i32>> {
+        let table = Self::lookup_table(freq);
+
+        let mut mapped_ids = Vec::with_capacity(ids.len());
+        for &token in ids {
+            let mapped = match &table {
+                Some(table) => usize::try_from(token)
+                    .ok()
+                    .and_then(|idx| table.get(idx).copied())
+                    .filter(|&mapped| mapped >= 0),
+                None => freq.map_token(token),
+            };
+            let mapped = mapped.ok_or_else(|| {
+                CodecError::Internal(format!("token {token} missing from frequency map"))
+            })?;
+            mapped_ids.push(mapped);
+        }
+        Ok(mapped_ids)
+    }
+
+    /// Dense token -> mapped ID table, if the map's tokens are small enough.
+    fn lookup_table(freq: &FreqMap) -> Option<Vec<i32>> {
+        let tokens = freq.ordered_tokens();
+        let min = *tokens.iter().min()?;
+        let max = *tokens.iter().max()?;
+        if min < 0 || max as usize > LOOKUP_TABLE_MAX_TOKEN {
+            return None;
+        }
+        freq.to_lookup_table(max as usize).ok()
+    }
+
+    /// Inverse of `encode_body`: varint-decode, un-zigzag, and map each mapped ID
+    /// back to its original token through `header`.
+    pub fn decode_body(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        let values = varint::decode(body).map_err(|_| CodecError::InvalidPayload)?;
+
+        zigzag::decode_slice(&values)
+            .into_iter()
+            .map(|mapped| Self::unmap(mapped, header))
+            .collect()
+    }
+
+    /// `decode_body`, appending to `output` instead of collecting a new Vec.
+    /// On error, `output` may hold some of the body's tokens.
+    pub fn decode_body_into(body: &[u8], header: &Header, output: &mut Vec<i32>) -> Result<()> {
+        let mut rest = body;
+        while !rest.is_empty() {
+            let value = read_varint(&mut rest)?;
+            output.push(Self::unmap(zigzag::decode(value), header)?);
+        }
+        Ok(())
+    }
+
+    /// Undo `encode_body_delta`.
+    pub fn decode_body_delta(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        let values = varint::decode(body).map_err(|_| CodecError::InvalidPayload)?;
+
+        delta::decode(&values, 0)
+            .into_iter()
+            .map(|mapped| Self::unmap(mapped, header))
+            .collect()
+    }
+
+    /// Undo `encode_body_vbyte`.
+    pub fn decode_body_vbyte(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        let values = vbyte::decode_vbyte(body).map_err(|_| CodecError::InvalidPayload)?;
+
+        zigzag::decode_slice(&values)
+            .into_iter()
+            .map(|mapped| Self::unmap(mapped, header))
+            .collect()
+    }
+
+    /// Undo `encode_body_rice`.
+    pub fn decode_body_rice(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        let (&k, mut rest) = body.split_first().ok_or(CodecError::InvalidPayload)?;
+        if k > 31 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let count = read_varint(&mut rest)? as usize;
+        let values =
+            rice::decode_rice(rest, 1 << k, count).map_err(|_| CodecError::InvalidPayload)?;
+
+        values
+            .into_iter()
+            .map(|mapped| Self::unmap(i32::try_from(mapped).unwrap_or(-1), header))
+            .collect()
+    }
+
+    /// Undo `encode_body_elias_gamma`.
+    pub fn decode_body_elias_gamma(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        if body.len() < 4 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (count, rest) = body.split_at(4);
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        let values =
+            elias::decode_elias_gamma(rest, count).map_err(|_| CodecError::InvalidPayload)?;
+
+        values
+            .into_iter()
+            .map(|v| Self::unmap(i32::try_from(v - 1).unwrap_or(-1), header))
+            .collect()
+    }
+
+    /// Undo `encode_body_arithmetic`.
+    pub fn decode_body_arithmetic(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        let mut rest = body;
+        let mut counts = Vec::with_capacity(header.tokens.len());
+        for _ in 0..header.tokens.len() {
+            counts.push(u64::from(read_varint(&mut rest)?));
+        }
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return if rest.is_empty() {
+                Ok(Vec::new())
+            } else {
+                Err(CodecError::InvalidPayload)
+            };
+        }
+
+        // The same division `FreqMap::to_probability_table` does.
+        let probabilities: Vec<f64> = counts.iter().map(|&c| c as f64 / total as f64).collect();
+        let count = usize::try_from(total).map_err(|_| CodecError::InvalidPayload)?;
+        let values = arithmetic::decode_arithmetic(rest, count, &probabilities)
+            .map_err(|_| CodecError::InvalidPayload)?;
+
+        values
+            .into_iter()
+            .map(|mapped| Self::unmap(mapped as i32, header))
+            .collect()
+    }
+
+    /// Undo `encode_body_huffman`.
+    pub fn decode_body_huffman(body: &[u8], header: &Header) -> Result<Vec<i32>> {
+        if body.len() < header.tokens.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (lengths, mut rest) = body.split_at(header.tokens.len());
+        let table =
+            huffman::HuffmanTable::from_code_lengths(lengths).map_err(|_| CodecError::InvalidPayload)?;
+        let count = read_varint(&mut rest)? as usize;
+        let values =
+            huffman::decode_huffman(rest, count, &table).map_err(|_| CodecError::InvalidPayload)?;
+
+        values
+            .into_iter()
+            .map(|mapped| Self::unmap(mapped as i32, header))
+            .collect()
+    }
+
+    /// Look up the original token for a decoded mapped ID.
+    fn unmap(mapped: i32, header: &Header) -> Result<i32> {
+        usize::try_from(mapped)
+            .ok()
+            .and_then(|idx| header.tokens.get(idx).copied())
+            .ok_or(CodecError::InvalidPayload)
+    }
+
+    /// Gzip `bytes` when `gzip` is set; otherwise hand them back untouched.
+    pub fn compress(bytes: Vec<u8>, gzip: bool) -> Result<Vec<u8>> {
+        if !gzip {
+            return Ok(bytes);
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&bytes)
+            .map_err(|e| CodecError::Internal(e.to_string()))?;
+        encoder
+            .finish()
+            .map_err(|e| CodecError::Internal(e.to_string()))
+    }
+
+    /// Undo `compress`. Borrows the input when no decompression is needed.
+    pub fn decompress(payload: &[u8], gzip: bool) -> Result<Cow<'_, [u8]>> {
+        if !gzip {
+            return Ok(Cow::Borrowed(payload));
+        }
+
+        let mut out = Vec::new();
+        GzDecoder::new(payload)
+            .read_to_end(&mut out)
+            .map_err(|_| CodecError::InvalidPayload)?;
+        Ok(Cow::Owned(out))
+    }
+}
+
+/// Convert a length/offset to the u32 used on the wire.
+pub(crate) fn to_u32(value: usize) -> Result<u32> {
+    u32::try_from(value).map_err(|_| CodecError::Internal(format!("{value} exceeds u32")))
+}
+
+/// Read one varint off the front of `bytes`, advancing the slice past it.
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> Result<u32> {
+    let (value, used) = varint::decode_one(bytes).map_err(|_| CodecError::InvalidPayload)?;
+    *bytes = &bytes[used..];
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{FLAG_EXTENSIONS, FLAG_METADATA_PRESENT, FORMAT_VERSION};
+
+    #[test]
+    fn round_trip_plain_and_gzip() {
+        let ids = [5, 5, 9, -3, 5, 9, 1000, 5];
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            let decoded = CodecCore::decode_token_ids(&payload, gzip).unwrap();
+            assert_eq!(decoded, ids);
+        }
+    }
+
+    /// Encode, decode back, and return the payload's header.
+    fn round_trip_edge_case(ids: Vec<i32>) -> Header {
+        let payload = CodecCore::encode_token_ids(&ids, false).unwrap();
+        assert_eq!(CodecCore::decode_token_ids(&payload, false).unwrap(), ids);
+        Header::decode_prefix(&payload).unwrap().0
+    }
+
+    #[test]
+    fn empty_input_round_trips() {
+        let header = round_trip_edge_case(vec![]);
+        assert_eq!(header.len, 0);
+
+        // Nothing but the header prefix, and gzip copes with the empty body too.
+        let payload = CodecCore::encode_token_ids(&[], false).unwrap();
+        assert_eq!(payload, header.encode());
+        let gzipped = CodecCore::encode_token_ids(&[], true).unwrap();
+        assert!(CodecCore::decode_token_ids(&gzipped, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_element_inputs_round_trip() {
+        for token in [0, i32::MIN, i32::MAX] {
+            let header = round_trip_edge_case(vec![token]);
+            assert_eq!(header.len, 1);
+            assert_eq!(header.tokens, vec![token]);
+        }
+    }
+
+    #[test]
+    fn repeated_single_token_round_trips() {
+        let header = round_trip_edge_case(vec![77, 77]);
+        assert_eq!(header.len, 1);
+    }
+
+    #[test]
+    fn decode_with_header_returns_the_encode_header() {
+        let ids = [8, -2, 8, 30, 8, -2];
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            let (tokens, header) = CodecCore::decode_with_header(&payload, gzip).unwrap();
+            assert_eq!(tokens, ids);
+            assert_eq!(header, Header::from_freq_map(&FreqMap::from_token_ids(&ids)));
+            assert_eq!(header.tokens, [8, -2, 30]);
+        }
+    }
+
+    #[test]
+    fn vbyte_payloads_decode_like_standard_ones() {
+        let ids: Vec<i32> = (0..1000).map(|i| (i * 7919) % 997 - 400).collect();
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids_vbyte(&ids, gzip).unwrap();
+            assert_eq!(payload[1], FLAG_VBYTE_ENCODING);
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn rice_payloads_decode_like_standard_ones() {
+        // Skewed towards a few tokens, as frequency-ranked IDs usually are.
+        let ids: Vec<i32> = (0..1000).map(|i| (i * i) % 37 * (i % 3)).collect();
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids_rice(&ids, gzip).unwrap();
+            assert_eq!(payload[1], FLAG_RICE_ENCODED);
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+
+        let empty = CodecCore::encode_token_ids_rice(&[], false).unwrap();
+        assert!(CodecCore::decode_token_ids(&empty, false).unwrap().is_empty());
+
+        let mut bad_parameter = CodecCore::encode_token_ids_rice(&ids, false).unwrap();
+        let offset = Header::decode_prefix(&bad_parameter).unwrap().0.body_offset();
+        bad_parameter[offset] = 32;
+        assert!(CodecCore::decode_token_ids(&bad_parameter, false).is_err());
+    }
+
+    #[test]
+    fn elias_gamma_payloads_decode_like_standard_ones() {
+        let ids: Vec<i32> = (0..1000).map(|i| (i * i) % 37 * (i % 3)).collect();
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids_elias_gamma(&ids, gzip).unwrap();
+            assert_eq!(payload[1], FLAG_ELIAS_GAMMA);
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+
+        let empty = CodecCore::encode_token_ids_elias_gamma(&[], false).unwrap();
+        assert!(CodecCore::decode_token_ids(&empty, false).unwrap().is_empty());
+
+        let mut truncated = CodecCore::encode_token_ids_elias_gamma(&ids, false).unwrap();
+        truncated.truncate(truncated.len() - 1);
+        assert!(CodecCore::decode_token_ids(&truncated, false).is_err());
+    }
+
+    #[test]
+    fn huffman_payloads_decode_like_standard_ones() {
+        let ids: Vec<i32> = (0..1000).map(|i| (i * i) % 37 * (i % 3) - 5).collect();
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids_huffman(&ids, gzip).unwrap();
+            assert_eq!(payload[1], FLAG_HUFFMAN);
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+
+        for ids in [vec![], vec![42; 10]] {
+            let payload = CodecCore::encode_token_ids_huffman(&ids, false).unwrap();
+            assert_eq!(CodecCore::decode_token_ids(&payload, false).unwrap(), ids);
+        }
+
+        let mut truncated = CodecCore::encode_token_ids_huffman(&ids, false).unwrap();
+        truncated.truncate(truncated.len() - 1);
+        assert!(CodecCore::decode_token_ids(&truncated, false).is_err());
+
+        let mut bad_lengths = CodecCore::encode_token_ids_huffman(&ids, false).unwrap();
+        let offset = Header::decode_prefix(&bad_lengths).unwrap().0.body_offset();
+        bad_lengths[offset] = 33;
+        assert!(CodecCore::decode_token_ids(&bad_lengths, false).is_err());
+    }
+
+    #[test]
+    fn huffman_beats_leb128_on_zipf_tokens() {
+        // Zipf-like draws from a 50K vocabulary: a few hundred tokens carry
+        // most of the mass, and thousands more appear a handful of times.
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let ids: Vec<i32> = (0..100_000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                (50_000.0f64.powf(u * u) as i32).min(49_999)
+            })
+            .collect();
+
+        let leb128 = CodecCore::encode_token_ids(&ids, false).unwrap();
+        let huffman = CodecCore::encode_token_ids_huffman(&ids, false).unwrap();
+        assert!(
+            huffman.len() < leb128.len() * 9 / 10,
+            "{} vs {}",
+            huffman.len(),
+            leb128.len()
+        );
+    }
+
+    #[test]
+    fn arithmetic_payloads_decode_like_standard_ones() {
+        let ids: Vec<i32> = (0..1000).map(|i| (i * i) % 37 * (i % 3) - 5).collect();
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids_arithmetic(&ids, gzip).unwrap();
+            assert_eq!(payload[1], FLAG_ARITHMETIC);
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+
+        for ids in [vec![], vec![42; 10]] {
+            let payload = CodecCore::encode_token_ids_arithmetic(&ids, false).unwrap();
+            assert_eq!(CodecCore::decode_token_ids(&payload, false).unwrap(), ids);
+        }
+
+        let mut truncated = CodecCore::encode_token_ids_arithmetic(&ids, false).unwrap();
+        truncated.truncate(truncated.len() - 8);
+        assert!(CodecCore::decode_token_ids(&truncated, false).is_err());
+    }
+
+    #[test]
+    fn arithmetic_beats_huffman_below_one_bit_per_token() {
+        // 95% one token, the rest spread over five others: about 0.4 bits
+        // of entropy per token, while Huffman can't go below 1 bit.
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let ids: Vec<i32> = (0..20_000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                match (state >> 33) % 100 {
+                    0..=94 => 7,
+                    r => r as i32,
+                }
+            })
+            .collect();
+
+        let leb128 = CodecCore::encode_token_ids(&ids, false).unwrap();
+        let huffman = CodecCore::encode_token_ids_huffman(&ids, false).unwrap();
+        let arithmetic = CodecCore::encode_token_ids_arithmetic(&ids, false).unwrap();
+        assert!(
+            arithmetic.len() < huffman.len() && huffman.len() < leb128.len(),
+            "{} vs {} vs {}",
+            arithmetic.len(),
+            huffman.len(),
+            leb128.len()
+        );
+        assert!(arithmetic.len() < 20_000 / 2 / 8, "{}", arithmetic.len());
+    }
+
+    #[test]
+    fn extensions_round_trip_and_are_skipped() {
+        let ids = [4, 4, 1, 9];
+        let payload =
+            CodecCore::encode_with_extensions(&ids, &[(7, b"seven"), (200, b""), (8, &[1, 2])], true)
+                .unwrap();
+
+        let parts = CodecCore::split_payload(&payload).unwrap();
+        assert_eq!(
+            parts.extensions,
+            vec![(7, &b"seven"[..]), (200, &b""[..]), (8, &[1u8, 2][..])]
+        );
+        assert_eq!(parts.extension(8), Some(&[1u8, 2][..]));
+        assert_eq!(parts.extension(9), None);
+
+        assert_eq!(CodecCore::decode_token_ids(&payload, true).unwrap(), ids);
+        let (tokens, ext) = CodecCore::decode_with_extension(&payload, 7, true).unwrap();
+        assert_eq!(tokens, ids);
+        assert_eq!(ext, Some(&b"seven"[..]));
+    }
+
+    #[test]
+    fn extensions_follow_flagged_sections() {
+        let ids = [1, 2, 3];
+        let mut payload =
+            CodecCore::encode_with_flags(&ids, FLAG_METADATA_PRESENT | FLAG_EXTENSIONS, false)
+                .unwrap();
+        CodecCore::push_section(&mut payload, b"meta").unwrap();
+        CodecCore::push_extensions(&mut payload, &[(1, b"ext")]).unwrap();
+
+        let parts = CodecCore::split_payload(&payload).unwrap();
+        assert_eq!(parts.section(FLAG_METADATA_PRESENT), Some(&b"meta"[..]));
+        assert_eq!(parts.extension(1), Some(&b"ext"[..]));
+        assert_eq!(CodecCore::decode_token_ids(&payload, false).unwrap(), ids);
+    }
+
+    #[test]
+    fn payload_starts_with_header() {
+        // 5 is most frequent -> mapped 0, then 9 -> 1, then 7 -> 2.
+        let ids = [5, 9, 5, 7, 9, 5];
+        let payload = CodecCore::encode_token_ids(&ids, false).unwrap();
+
+        let (header, body) = Header::decode_prefix(&payload).unwrap();
+        assert_eq!(header.tokens, vec![5, 9, 7]);
+
+        // Body: zigzag(mapped) = [0, 2, 0, 4, 2, 0], all single-byte varints.
+        assert_eq!(body, &[0, 2, 0, 4, 2, 0]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_mapped_id() {
+        let header = Header::new(FORMAT_VERSION, 0, vec![42]);
+        let mut payload = header.encode();
+        // zigzag(1) = 2, but only mapped ID 0 exists.
+        payload.push(2);
+
+        assert!(matches!(
+            CodecCore::decode_token_ids(&payload, false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn lookup_table_and_hash_paths_agree() {
+        // Small non-negative IDs take the table path; a negative one forces the
+        // HashMap path. Both must produce the same body for the shared tokens.
+        let small = [4, 4, 9, 65535, 4];
+        let freq = FreqMap::from_token_ids(&small);
+        let body = CodecCore::encode_body(&small, &freq).unwrap();
+        assert_eq!(body, varint::encode(&[0, 0, 2, 4, 0]));
+
+        let mixed = [4, 4, 9, -1, 4];
+        let freq = FreqMap::from_token_ids(&mixed);
+        let body = CodecCore::encode_body(&mixed, &freq).unwrap();
+        assert_eq!(body, varint::encode(&[0, 0, 4, 2, 0]));
+
+        // Tokens missing from the map are rejected on the table path too.
+        let freq = FreqMap::from_token_ids(&[1, 2]);
+        assert!(CodecCore::encode_body(&[3], &freq).is_err());
+    }
+
+    #[test]
+    fn gzip_leaves_header_readable() {
+        let ids: Vec<i32> = (0..500).map(|i| i % 7).collect();
+        let payload = CodecCore::encode_token_ids(&ids, true).unwrap();
+
+        let (header, _) = Header::decode_prefix(&payload).unwrap();
+        assert_eq!(header.len, 7);
+    }
+
+    #[test]
+    fn rejects_garbage_gzip() {
+        let mut payload = Header::from_freq_map(&FreqMap::from_token_ids(&[1])).encode();
+        payload.extend_from_slice(&[1, 2, 3]);
+        assert!(CodecCore::decode_token_ids(&payload, true).is_err());
+    }
+
+    #[test]
+    fn sections_are_skipped_by_plain_decode() {
+        let ids = [3, 1, 4, 1, 5];
+        let mut payload =
+            CodecCore::encode_with_flags(&ids, FLAG_METADATA_PRESENT, false).unwrap();
+        CodecCore::push_section(&mut payload, b"extra").unwrap();
+
+        let parts = CodecCore::split_payload(&payload).unwrap();
+        assert_eq!(parts.section(FLAG_METADATA_PRESENT), Some(&b"extra"[..]));
+        assert_eq!(CodecCore::decode_token_ids(&payload, false).unwrap(), ids);
+    }
+
+    #[test]
+    fn pop_section_rejects_bad_length() {
+        // Claims a 200-byte section but only 2 bytes precede the length.
+        let bytes = [1, 2, 200, 0, 0, 0];
+        assert!(CodecCore::pop_section(&bytes).is_err());
+    }
+
+    #[test]
+    fn i64_ids_round_trip_through_zigzag_and_varint() {
+        // IDs and signed hashes beyond the i32 range.
+        let ids: Vec<i64> = (0..1000i64)
+            .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15u64 as i64) >> (i % 64))
+            .chain([0, -1, i64::MIN, i64::MAX, i32::MIN as i64 - 1])
+            .collect();
+        let zigzagged: Vec<u64> = ids.iter().map(|&id| zigzag::encode_i64(id)).collect();
+        let bytes = varint::encode_u64(&zigzagged);
+        let decoded: Vec<i64> = varint::decode_u64(&bytes)
+            .unwrap()
+            .into_iter()
+            .map(zigzag::decode_i64)
+            .collect();
+        assert_eq!(decoded, ids);
+        assert_eq!(varint::encode_zigzag_i64(&ids), bytes);
+    }
+
+    #[test]
+    fn delta_payloads_round_trip() {
+        let ids: Vec<i32> = (0..500).map(|i| (i * 7919) % 1000 - 300).collect();
+        for ids in [ids, vec![], vec![i32::MIN, i32::MAX]] {
+            for gzip in [false, true] {
+                let payload = CodecCore::encode_token_ids_delta(&ids, gzip).unwrap();
+                assert_eq!(payload[1], FLAG_DELTA_CODED);
+                assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn delta_body_is_smaller_for_neighbouring_mapped_ids() {
+        // Sweeps 0..100, 0..99, 0..98, ... over a 100-token vocabulary: lower
+        // tokens are more frequent, so mapped IDs equal tokens and consecutive
+        // mapped IDs mostly differ by one.
+        let ids: Vec<i32> = (0..100).flat_map(|r| 0..100 - r).take(1000).collect();
+        let freq = FreqMap::from_token_ids(&ids);
+        assert_eq!(freq.ordered_tokens().len(), 100);
+
+        let plain = CodecCore::encode_body(&ids, &freq).unwrap();
+        let delta = CodecCore::encode_body_delta(&ids, &freq).unwrap();
+        assert!(
+            delta.len() * 5 <= plain.len() * 4,
+            "{} vs {}",
+            delta.len(),
+            plain.len()
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+
+/// `"v"` field written by `encode_compact_json`.
+pub const COMPACT_JSON_VERSION: u64 = 1;
+
+/// Token IDs as a small JSON object for REST APIs:
+/// `{"v":1,"d":"<base64>"}`, where `d` is the standard base64 (with padding)
+/// of the non-gzipped standard payload.
+///
+/// # Panics
+///
+/// If `token_ids` is too long for a standard payload (over `u32::MAX`
+/// tokens).
+pub fn encode_compact_json(token_ids: &[i32]) -> String {
+    let payload = CodecCore::encode_token_ids(token_ids, false)
+        .expect("token sequence too long for a standard payload");
+    // Base64 needs no escaping, and writing the object by hand keeps "v"
+    // first.
+    format!(
+        r#"{{"v":{COMPACT_JSON_VERSION},"d":"{}"}}"#,
+        STANDARD.encode(payload)
+    )
+}
+
+/// Reverse of `encode_compact_json`. Errors with `CodecError::InvalidPayload`
+/// on malformed JSON or base64, and with `CodecError::UnknownVersion` for a
+/// newer `v`.
+pub fn decode_compact_json(json: &str) -> Result<Vec<i32>> {
+    let value: Value = serde_json::from_str(json).map_err(|_| CodecError::InvalidPayload)?;
+    decode_compact_json_value(&value)
+}
+
+/// Several sequences as a JSON array of `encode_compact_json` objects.
+pub fn encode_compact_json_array(sequences: &[&[i32]]) -> String {
+    let objects: Vec<String> = sequences
+        .iter()
+        .map(|ids| encode_compact_json(ids))
+        .collect();
+    format!("[{}]", objects.join(","))
+}
+
+/// Reverse of `encode_compact_json_array`.
+pub fn decode_compact_json_array(json: &str) -> Result<Vec<Vec<i32>>> {
+    let value: Value = serde_json::from_str(json).map_err(|_| CodecError::InvalidPayload)?;
+    value
+        .as_array()
+        .ok_or(CodecError::InvalidPayload)?
+        .iter()
+        .map(decode_compact_json_value)
+        .collect()
+}
+
+fn decode_compact_json_value(value: &Value) -> Result<Vec<i32>> {
+    let version = value
+        .get("v")
+        .and_then(Value::as_u64)
+        .ok_or(CodecError::InvalidPayload)?;
+    if version != COMPACT_JSON_VERSION {
+        return Err(CodecError::UnknownVersion(version.min(u8::MAX as u64) as u8));
+    }
+    let data = value
+        .get("d")
+        .and_then(Value::as_str)
+        .ok_or(CodecError::InvalidPayload)?;
+    let payload = STANDARD
+        .decode(data)
+        .map_err(|_| CodecError::InvalidPayload)?;
+    CodecCore::decode_token_ids(&payload, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10K tokens from a Zipf-like 50K BPE vocabulary, with repeated phrases
+    /// as in real text.
+    fn bpe_sequence() -> Vec<i32> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut ids = Vec::with_capacity(10_000);
+        while ids.len() < 10_000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+            if u < 0.1 && ids.len() > 64 {
+                let start = ids.len() - 64 + (state % 56) as usize;
+                ids.extend_from_within(start..start + 8);
+            } else {
+                ids.push((50_257.0f64.powf(u * u) as i32).min(50_256));
+            }
+        }
+        ids.truncate(10_000);
+        ids
+    }
+
+    #[test]
+    fn compact_json_round_trips_and_is_valid_json() {
+        for ids in [vec![], vec![9], bpe_sequence()] {
+            let json = encode_compact_json(&ids);
+            let value: Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(value["v"], 1);
+            assert!(value["d"].is_string());
+            assert_eq!(decode_compact_json(&json).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn known_layout() {
+        // The standard payload for [9] is 01 00 01 00 00 00 09 00 00 00 00.
+        assert_eq!(
+            encode_compact_json(&[9]),
+            r#"{"v":1,"d":"AQABAAAACQAAAAA="}"#
+        );
+    }
+
+    #[test]
+    fn arrays_round_trip() {
+        let long = bpe_sequence();
+        let sequences: [&[i32]; 3] = [&[1, 2, 1], &[], &long];
+        let json = encode_compact_json_array(&sequences);
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 3);
+        assert_eq!(decode_compact_json_array(&json).unwrap(), sequences);
+        assert_eq!(encode_compact_json_array(&[]), "[]");
+    }
+
+    #[test]
+    fn smaller_than_a_json_array_for_bpe_output() {
+        let ids = bpe_sequence();
+        let compact = encode_compact_json(&ids);
+        let plain = serde_json::to_string(&ids).unwrap();
+        assert!(
+            compact.len() < plain.len(),
+            "{} vs {}",
+            compact.len(),
+            plain.len()
+        );
+    }
+
+    #[test]
+    fn malformed_json_is_rejected() {
+        for json in [
+            "",
+            "[1, 2]",
+            r#"{"v":1}"#,
+            r#"{"v":1,"d":"not base64!"}"#,
+            r#"{"v":1,"d":"AQAB"}"#,
+        ] {
+            assert!(decode_compact_json(json).is_err(), "{json}");
+        }
+        assert!(matches!(
+            decode_compact_json(r#"{"v":2,"d":"AQABAAAACQAAAAA="}"#),
+            Err(CodecError::UnknownVersion(2))
+        ));
+        assert!(decode_compact_json_array(r#"{"v":1}"#).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::Codec;
+
+/// Wrapper applied to a payload body after encoding.
+///
+/// The mode is not recorded in the payload; callers pass it to decode, as
+/// they do the `gzip` flag elsewhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompressionMode {
+    /// Body stored as is.
+    #[default]
+    None,
+    /// Gzip, as written by `CodecCore::compress(_, true)`.
+    Gzip,
+    /// LZ4 block with its uncompressed size prepended as a u32 LE.
+    Lz4,
+}
+
+impl CompressionMode {
+    pub fn compress(self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(bytes),
+            Self::Gzip => CodecCore::compress(bytes, true),
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(&bytes)),
+        }
+    }
+
+    /// Undo `compress`. Borrows the input when no decompression is needed.
+    pub fn decompress(self, bytes: &[u8]) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Self::None => Ok(Cow::Borrowed(bytes)),
+            Self::Gzip => CodecCore::decompress(bytes, true),
+            Self::Lz4 => lz4_flex::decompress_size_prepended(bytes)
+                .map(Cow::Owned)
+                .map_err(|_| CodecError::InvalidPayload),
+        }
+    }
+}
+
+impl Codec {
+    /// Re-wrap the body of `payload` from `from` to `to` compression.
+    ///
+    /// Token IDs are never decoded: the header, the uncompressed body bytes
+    /// and any sections after the body are carried over unchanged.
+    pub fn transcode_compression(
+        &self,
+        payload: &[u8],
+        from: CompressionMode,
+        to: CompressionMode,
+    ) -> Result<Vec<u8>> {
+        let parts = CodecCore::split_payload(payload)?;
+        let body_start = parts.header.body_offset();
+        let body_end = body_start + parts.body.len();
+
+        let body = to.compress(from.decompress(parts.body)?.into_owned())?;
+
+        let mut out = Vec::with_capacity(payload.len() - parts.body.len() + body.len());
+        out.extend_from_slice(&payload[..body_start]);
+        out.extend_from_slice(&body);
+        out.extend_from_slice(&payload[body_end..]);
+        Ok(out)
+    }
+
+    /// Standard payload for `token_ids` with an LZ4-compressed body.
+    pub fn encode_lz4(&self, token_ids: &[i32]) -> Result<Vec<u8>> {
+        let payload = self.encode(token_ids, false)?;
+        self.transcode_compression(&payload, CompressionMode::None, CompressionMode::Lz4)
+    }
+
+    /// Decode an `encode_lz4` payload.
+    pub fn decode_lz4(&self, payload: &[u8]) -> Result<Vec<i32>> {
+        let plain =
+            self.transcode_compression(payload, CompressionMode::Lz4, CompressionMode::None)?;
+        Ok(CodecCore::decode_with_header(&plain, false)?.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::FLAG_OFFSETS_PRESENT;
+
+    const MODES: [CompressionMode; 3] = [
+        CompressionMode::None,
+        CompressionMode::Gzip,
+        CompressionMode::Lz4,
+    ];
+
+    fn ids() -> Vec<i32> {
+        (0..4000).map(|i| (i * 7919) % 331 - 40).collect()
+    }
+
+    #[test]
+    fn gzip_to_lz4() {
+        let codec = Codec::new();
+        let gzip_payload = CodecCore::encode_token_ids(&ids(), true).unwrap();
+
+        let lz4_payload = codec
+            .transcode_compression(&gzip_payload, CompressionMode::Gzip, CompressionMode::Lz4)
+            .unwrap();
+        assert_eq!(codec.decode_lz4(&lz4_payload).unwrap(), ids());
+        assert_eq!(lz4_payload, codec.encode_lz4(&ids()).unwrap());
+    }
+
+    #[test]
+    fn every_mode_pair_preserves_tokens() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&ids(), false).unwrap();
+
+        for from in MODES {
+            let payload = codec
+                .transcode_compression(&plain, CompressionMode::None, from)
+                .unwrap();
+            for to in MODES {
+                let out = codec.transcode_compression(&payload, from, to).unwrap();
+                let back = codec
+                    .transcode_compression(&out, to, CompressionMode::None)
+                    .unwrap();
+                assert_eq!(back, plain, "{from:?} -> {to:?}");
+                assert_eq!(CodecCore::decode_token_ids(&back, false).unwrap(), ids());
+            }
+        }
+    }
+
+    #[test]
+    fn extensions_are_carried_over() {
+        let codec = Codec::new();
+        let offsets = [1u8, 2, 3];
+        let payload =
+            CodecCore::encode_with_extensions(&ids(), &[(FLAG_OFFSETS_PRESENT, &offsets)], true)
+                .unwrap();
+
+        let lz4 = codec
+            .transcode_compression(&payload, CompressionMode::Gzip, CompressionMode::Lz4)
+            .unwrap();
+        let plain = codec
+            .transcode_compression(&lz4, CompressionMode::Lz4, CompressionMode::None)
+            .unwrap();
+        let (tokens, ext) =
+            CodecCore::decode_with_extension(&plain, FLAG_OFFSETS_PRESENT, false).unwrap();
+        assert_eq!(tokens, ids());
+        assert_eq!(ext, Some(&offsets[..]));
+    }
+
+    #[test]
+    fn wrong_source_mode_is_rejected() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&ids(), false).unwrap();
+        for from in [CompressionMode::Gzip, CompressionMode::Lz4] {
+            assert!(matches!(
+                codec.transcode_compression(&plain, from, CompressionMode::None),
+                Err(CodecError::InvalidPayload)
+            ));
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::str::FromStr;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_HINT_BALANCED, FLAG_HINT_SIZE, FLAG_HINT_SPEED};
+use crate::{varint, zigzag, Codec};
+
+/// Zstd level for `CompressionHint::Balanced`.
+const BALANCED_LEVEL: i32 = 3;
+
+/// Zstd level for `CompressionHint::SizeFocused`.
+const SIZE_LEVEL: i32 = 15;
+
+/// What a caller cares about most when the codec picks a body compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionHint {
+    /// Standard body, LZ4 compressed.
+    SpeedFocused,
+    /// Standard body, zstd level 3.
+    Balanced,
+    /// Delta-coded mapped IDs, zstd level 15.
+    SizeFocused,
+}
+
+impl CompressionHint {
+    /// Mode code recording the hint; its low 2 bits are the hint (1 to 3).
+    fn mode(self) -> u8 {
+        match self {
+            Self::SpeedFocused => FLAG_HINT_SPEED,
+            Self::Balanced => FLAG_HINT_BALANCED,
+            Self::SizeFocused => FLAG_HINT_SIZE,
+        }
+    }
+
+    fn from_mode(mode: u8) -> Option<Self> {
+        match mode {
+            FLAG_HINT_SPEED => Some(Self::SpeedFocused),
+            FLAG_HINT_BALANCED => Some(Self::Balanced),
+            FLAG_HINT_SIZE => Some(Self::SizeFocused),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for CompressionHint {
+    type Err = CodecError;
+
+    /// Parses `"speed"`, `"balanced"` or `"size"`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "speed" => Ok(Self::SpeedFocused),
+            "balanced" => Ok(Self::Balanced),
+            "size" => Ok(Self::SizeFocused),
+            other => Err(CodecError::Internal(format!(
+                "unknown compression hint {other:?}"
+            ))),
+        }
+    }
+}
+
+/// Payloads compressed according to a `CompressionHint`, recorded as the mode
+/// so that `decode_with_hint` needs nothing but the payload:
+///   [header, mode = hint][compressed body]
+///
+/// The size-focused body stores each mapped ID as the zigzagged difference
+/// from the previous one (starting from 0) before compression.
+impl Codec {
+    pub fn encode_with_hint(&self, token_ids: &[i32], hint: CompressionHint) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = hint.mode();
+
+        let body = match hint {
+            CompressionHint::SpeedFocused => {
+                lz4_flex::compress_prepend_size(&CodecCore::encode_body(token_ids, &freq)?)
+            }
+            CompressionHint::Balanced => zstd::encode_all(
+                CodecCore::encode_body(token_ids, &freq)?.as_slice(),
+                BALANCED_LEVEL,
+            )?,
+            CompressionHint::SizeFocused => {
+                let mut prev = 0i32;
+                let deltas: Vec<u32> = CodecCore::map_ids(token_ids, &freq)?
+                    .into_iter()
+                    .map(|mapped| {
+                        let delta = mapped.wrapping_sub(prev);
+                        prev = mapped;
+                        zigzag::encode(delta)
+                    })
+                    .collect();
+                zstd::encode_all(varint::encode(&deltas).as_slice(), SIZE_LEVEL)?
+            }
+        };
+
+        let mut out = header.encode();
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    pub fn decode_with_hint(&self, payload: &[u8]) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        let hint =
+            CompressionHint::from_mode(parts.header.mode()).ok_or(CodecError::InvalidPayload)?;
+
+        match hint {
+            CompressionHint::SpeedFocused => {
+                let body = lz4_flex::decompress_size_prepended(parts.body)
+                    .map_err(|_| CodecError::InvalidPayload)?;
+                CodecCore::decode_body(&body, &parts.header)
+            }
+            CompressionHint::Balanced => {
+                let body = zstd::decode_all(parts.body).map_err(|_| CodecError::InvalidPayload)?;
+                CodecCore::decode_body(&body, &parts.header)
+            }
+            CompressionHint::SizeFocused => {
+                let body = zstd::decode_all(parts.body).map_err(|_| CodecError::InvalidPayload)?;
+                let values = varint::decode(&body).map_err(|_| CodecError::InvalidPayload)?;
+
+                let mut mapped = 0i32;
+                values
+                    .into_iter()
+                    .map(|v| {
+                        mapped = mapped.wrapping_add(zigzag::decode(v));
+                        usize::try_from(mapped)
+                            .ok()
+                            .and_then(|idx| parts.header.tokens.get(idx).copied())
+                            .ok_or(CodecError::InvalidPayload)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HINTS: [CompressionHint; 3] = [
+        CompressionHint::SpeedFocused,
+        CompressionHint::Balanced,
+        CompressionHint::SizeFocused,
+    ];
+
+    fn ids() -> Vec<i32> {
+        (0..10_000)
+            .map(|i| (i * i) % 4999 / ((i % 7) + 1))
+            .collect()
+    }
+
+    #[test]
+    fn every_hint_round_trips() {
+        let codec = Codec::new();
+        for ids in [ids(), vec![], vec![i32::MIN, i32::MAX, 0]] {
+            for hint in HINTS {
+                let payload = codec.encode_with_hint(&ids, hint).unwrap();
+                assert_eq!(payload[1], hint.mode());
+                assert_eq!(codec.decode_with_hint(&payload).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn hint_occupies_the_low_two_mode_bits() {
+        let modes: Vec<u8> = HINTS.iter().map(|h| h.mode() & 0b11).collect();
+        assert_eq!(modes, [1, 2, 3]);
+        assert_eq!(
+            HINTS.map(|h| h.mode() & !0b11),
+            [FLAG_HINT_SPEED & !0b11; 3]
+        );
+    }
+
+    fn size(ids: &[i32], hint: CompressionHint) -> usize {
+        Codec::new().encode_with_hint(ids, hint).unwrap().len()
+    }
+
+    #[test]
+    fn zstd_beats_lz4() {
+        let ids = ids();
+        assert!(size(&ids, CompressionHint::Balanced) < size(&ids, CompressionHint::SpeedFocused));
+    }
+
+    #[test]
+    fn delta_coding_pays_off_on_slowly_changing_ids() {
+        // Every token appears equally often, so mapped IDs follow token order.
+        let ids: Vec<i32> = (0..10_000)
+            .map(|i| (i * 7 + (i * 31) % 5) % 20_000)
+            .collect();
+        assert!(size(&ids, CompressionHint::SizeFocused) < size(&ids, CompressionHint::Balanced));
+    }
+
+    #[test]
+    fn other_payloads_are_rejected() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(matches!(
+            codec.decode_with_hint(&plain),
+            Err(CodecError::InvalidPayload)
+        ));
+
+        let mut corrupt = codec
+            .encode_with_hint(&[1, 2, 3], CompressionHint::Balanced)
+            .unwrap();
+        corrupt.truncate(corrupt.len() - 2);
+        assert!(codec.decode_with_hint(&corrupt).is_err());
+    }
+
+    #[test]
+    fn hints_parse() {
+        assert_eq!(
+            "speed".parse::<CompressionHint>().unwrap(),
+            CompressionHint::SpeedFocused
+        );
+        assert_eq!(
+            "balanced".parse::<CompressionHint>().unwrap(),
+            CompressionHint::Balanced
+        );
+        assert_eq!(
+            "size".parse::<CompressionHint>().unwrap(),
+            CompressionHint::SizeFocused
+        );
+        assert!("fast".parse::<CompressionHint>().is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::custom_varint::VarintEncoder;
+use crate::errors::CodecError;
+
+/// How token bodies are turned into bytes.
+#[derive(Clone, Default)]
+pub enum VarintMode {
+    /// LEB128 (`varint`), the standard layout.
+    #[default]
+    Leb128,
+    /// Group VarInt (`vbyte`), recorded as the `FLAG_VBYTE_ENCODING` mode.
+    VByte,
+    /// Rice coding (`rice`), recorded as the `FLAG_RICE_ENCODED` mode.
+    Rice,
+    /// Elias-gamma coding (`elias`), recorded as the `FLAG_ELIAS_GAMMA` mode.
+    EliasGamma,
+    /// Static Huffman coding (`huffman`), recorded as the `FLAG_HUFFMAN` mode.
+    Huffman,
+    /// Arithmetic coding (`arithmetic`), recorded as the `FLAG_ARITHMETIC`
+    /// mode.
+    Arithmetic,
+    /// A caller-supplied encoder (`custom_varint`), recorded as the
+    /// `FLAG_CUSTOM_VARINT` mode. Such payloads only decode with
+    /// `Codec::decode_with_custom_varint` and the same encoder.
+    ///
+    /// This is the config's only slot for a custom encoder: a separate
+    /// `varint_encoder` field would duplicate it and could disagree with the
+    /// mode. The `Arc` keeps `VarintMode` and `CodecConfig` cloneable.
+    Custom(Arc<dyn VarintEncoder + Send + Sync>),
+}
+
+impl fmt::Debug for VarintMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Leb128 => f.write_str("Leb128"),
+            Self::VByte => f.write_str("VByte"),
+            Self::Rice => f.write_str("Rice"),
+            Self::EliasGamma => f.write_str("EliasGamma"),
+            Self::Huffman => f.write_str("Huffman"),
+            Self::Arithmetic => f.write_str("Arithmetic"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Custom encoders compare by identity: two modes are equal only if they
+/// share the same encoder instance.
+impl PartialEq for VarintMode {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for VarintMode {}
+
+impl FromStr for VarintMode {
+    type Err = CodecError;
+
+    /// Parses `"leb128"`, `"vbyte"`, `"rice"`, `"elias_gamma"`, `"huffman"` or
+    /// `"arithmetic"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "leb128" => Ok(Self::Leb128),
+            "vbyte" => Ok(Self::VByte),
+            "rice" => Ok(Self::Rice),
+            "elias_gamma" => Ok(Self::EliasGamma),
+            "huffman" => Ok(Self::Huffman),
+            "arithmetic" => Ok(Self::Arithmetic),
+            other => Err(CodecError::Internal(format!(
+                "unknown varint mode {other:?}"
+            ))),
+        }
+    }
+}
+
+/// Settings that change how a `Codec` encodes.
+///
+/// Apart from `VarintMode::Custom`, payloads never depend on the config to
+/// be decoded: anything a decoder needs is recorded in the payload itself.
+/// Custom-encoded payloads need the same encoder to be read back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodecConfig {
+    /// Token substituted for tokens that a caller-provided `FreqMap` does not
+    /// contain (see `Codec::encode_with_freq_map`). `None` makes such tokens
+    /// an error.
+    pub unk_token: Option<i32>,
+    /// Body encoding used by `Codec::encode`.
+    pub varint_mode: VarintMode,
+    /// Delta-code mapped IDs before LEB128 in `Codec::encode` (the
+    /// `FLAG_DELTA_CODED` mode). Only valid with `VarintMode::Leb128`.
+    pub use_delta: bool,
+    /// Filler written after the payload by `Codec::encode_padded`.
+    pub pad_byte: u8,
+    /// Probability mass of each position's logits kept by
+    /// `Codec::encode_with_logits`, in `(0, 1]`. `None` means
+    /// `logits::DEFAULT_TOP_P`.
+    pub logits_top_p: Option<f32>,
+    /// Pre-trained dictionary used by `Codec::encode_token_ids_zstd_dict`
+    /// (see `Codec::with_zstd_dict`).
+    #[cfg(feature = "zstd")]
+    pub zstd_dict: Option<Arc<[u8]>>,
+}
+
+impl CodecConfig {
+    /// Whether `Codec::encode` writes the standard LEB128 body, the only one
+    /// that can be produced chunk by chunk.
+    pub(crate) fn is_standard_body(&self) -> bool {
+        self.varint_mode == VarintMode::Leb128 && !self.use_delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec_core::CodecCore;
+    use crate::header::{
+        FLAG_ARITHMETIC, FLAG_DELTA_CODED, FLAG_ELIAS_GAMMA, FLAG_HUFFMAN, FLAG_RICE_ENCODED, FLAG_VBYTE_ENCODING,
+    };
+    use crate::Codec;
+
+    #[test]
+    fn varint_mode_selects_the_body_encoding() {
+        let ids = [4, 4, 9, 1];
+        let leb128 = Codec::new().encode(&ids, false).unwrap();
+        assert_eq!(leb128, CodecCore::encode_token_ids(&ids, false).unwrap());
+
+        let vbyte = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::VByte,
+            ..CodecConfig::default()
+        })
+        .encode(&ids, false)
+        .unwrap();
+        assert_eq!(vbyte[1], FLAG_VBYTE_ENCODING);
+        assert_eq!(CodecCore::decode_token_ids(&vbyte, false).unwrap(), ids);
+
+        let rice = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::Rice,
+            ..CodecConfig::default()
+        })
+        .encode(&ids, false)
+        .unwrap();
+        assert_eq!(rice[1], FLAG_RICE_ENCODED);
+        assert_eq!(CodecCore::decode_token_ids(&rice, false).unwrap(), ids);
+
+        let elias = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::EliasGamma,
+            ..CodecConfig::default()
+        })
+        .encode(&ids, false)
+        .unwrap();
+        assert_eq!(elias[1], FLAG_ELIAS_GAMMA);
+        assert_eq!(CodecCore::decode_token_ids(&elias, false).unwrap(), ids);
+
+        let huffman = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::Huffman,
+            ..CodecConfig::default()
+        })
+        .encode(&ids, false)
+        .unwrap();
+        assert_eq!(huffman[1], FLAG_HUFFMAN);
+        assert_eq!(CodecCore::decode_token_ids(&huffman, false).unwrap(), ids);
+
+        let arithmetic = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::Arithmetic,
+            ..CodecConfig::default()
+        })
+        .encode(&ids, false)
+        .unwrap();
+        assert_eq!(arithmetic[1], FLAG_ARITHMETIC);
+        assert_eq!(CodecCore::decode_token_ids(&arithmetic, false).unwrap(), ids);
+    }
+
+    #[test]
+    fn use_delta_selects_delta_coding() {
+        let ids = [4, 4, 9, 1];
+        let delta = Codec::with_config(CodecConfig {
+            use_delta: true,
+            ..CodecConfig::default()
+        });
+        let payload = delta.encode(&ids, true).unwrap();
+        assert_eq!(payload[1], FLAG_DELTA_CODED);
+        assert_eq!(CodecCore::decode_token_ids(&payload, true).unwrap(), ids);
+
+        let rice = Codec::with_config(CodecConfig {
+            use_delta: true,
+            varint_mode: VarintMode::Rice,
+            ..CodecConfig::default()
+        });
+        assert!(rice.encode(&ids, false).is_err());
+    }
+
+    #[test]
+    fn varint_mode_parses() {
+        assert_eq!("leb128".parse::<VarintMode>().unwrap(), VarintMode::Leb128);
+        assert_eq!("vbyte".parse::<VarintMode>().unwrap(), VarintMode::VByte);
+        assert_eq!("rice".parse::<VarintMode>().unwrap(), VarintMode::Rice);
+        assert_eq!(
+            "elias_gamma".parse::<VarintMode>().unwrap(),
+            VarintMode::EliasGamma
+        );
+        assert_eq!(
+            "huffman".parse::<VarintMode>().unwrap(),
+            VarintMode::Huffman
+        );
+        assert_eq!(
+            "arithmetic".parse::<VarintMode>().unwrap(),
+            VarintMode::Arithmetic
+        );
+        assert!("zstd".parse::<VarintMode>().is_err());
+    }
+
+    #[test]
+    fn custom_modes_compare_by_identity() {
+        let encoder: Arc<dyn VarintEncoder + Send + Sync> =
+            Arc::new(crate::custom_varint::FixedU32Encoder);
+        let mode = VarintMode::Custom(encoder.clone());
+        assert_eq!(mode, VarintMode::Custom(encoder));
+        assert_ne!(
+            mode,
+            VarintMode::Custom(Arc::new(crate::custom_varint::FixedU32Encoder))
+        );
+        assert_ne!(mode, VarintMode::Leb128);
+        assert_eq!(format!("{mode:?}"), "Custom(..)");
+    }
+}
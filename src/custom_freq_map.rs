@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::Header;
+use crate::Codec;
+
+/// Encoding against a caller-provided `FreqMap`, e.g. one global vocabulary
+/// shared by every payload instead of a map built per payload.
+///
+/// The output is a standard payload whose header is the provided map's token
+/// table, so it decodes with `decode_token_ids` like any other payload.
+impl Codec {
+    /// Tokens missing from `freq_map` are replaced by `CodecConfig::unk_token`
+    /// when set; otherwise (or when the unk token is missing too) encoding
+    /// fails with `CodecError::UnknownToken`.
+    pub fn encode_with_freq_map(
+        &self,
+        token_ids: &[i32],
+        freq_map: &FreqMap,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let ids = self.replace_unknown(token_ids, freq_map)?;
+
+        let mut out = Header::from_freq_map(freq_map).encode();
+        out.extend_from_slice(&CodecCore::compress(
+            CodecCore::encode_body(&ids, freq_map)?,
+            gzip,
+        )?);
+        Ok(out)
+    }
+
+    /// The map `encode_token_ids` builds for `token_ids`.
+    pub fn build_freq_map(&self, token_ids: &[i32]) -> FreqMap {
+        FreqMap::from_token_ids(token_ids)
+    }
+
+    /// `token_ids` with every token `freq_map` lacks swapped for the unk token.
+    fn replace_unknown<'a>(
+        &self,
+        token_ids: &'a [i32],
+        freq_map: &FreqMap,
+    ) -> Result<Cow<'a, [i32]>> {
+        let known = |token: i32| freq_map.map_token(token).is_some();
+        let Some(first_unknown) = token_ids.iter().copied().find(|&t| !known(t)) else {
+            return Ok(Cow::Borrowed(token_ids));
+        };
+
+        match self.config().unk_token {
+            Some(unk) if known(unk) => Ok(Cow::Owned(
+                token_ids
+                    .iter()
+                    .map(|&t| if known(t) { t } else { unk })
+                    .collect(),
+            )),
+            Some(unk) => Err(CodecError::UnknownToken(unk)),
+            None => Err(CodecError::UnknownToken(first_unknown)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CodecConfig;
+
+    const UNK: i32 = 100;
+
+    fn global_vocab() -> FreqMap {
+        let corpus: Vec<i32> = (0..5000).map(|i| (i * 7919) % 300).chain([UNK]).collect();
+        FreqMap::from_token_ids(&corpus)
+    }
+
+    #[test]
+    fn global_vocab_round_trip() {
+        let codec = Codec::new();
+        let vocab = global_vocab();
+        let ids = [5, 17, 5, 299, 0, 17];
+
+        for gzip in [false, true] {
+            let payload = codec.encode_with_freq_map(&ids, &vocab, gzip).unwrap();
+            let (tokens, header) = CodecCore::decode_with_header(&payload, gzip).unwrap();
+            assert_eq!(tokens, ids);
+            assert_eq!(header, Header::from_freq_map(&vocab));
+        }
+    }
+
+    #[test]
+    fn build_freq_map_matches_encode_token_ids() {
+        let codec = Codec::new();
+        let ids = [9, 9, 4, 1, 4, 9];
+        let freq_map = codec.build_freq_map(&ids);
+        assert_eq!(
+            codec.encode_with_freq_map(&ids, &freq_map, true).unwrap(),
+            CodecCore::encode_token_ids(&ids, true).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_tokens_follow_the_config() {
+        let vocab = global_vocab();
+        let ids = [5, 1234, 17];
+
+        let strict = Codec::new();
+        assert!(matches!(
+            strict.encode_with_freq_map(&ids, &vocab, false),
+            Err(CodecError::UnknownToken(1234))
+        ));
+
+        let lenient = Codec::with_config(CodecConfig {
+            unk_token: Some(UNK),
+            ..CodecConfig::default()
+        });
+        let payload = lenient.encode_with_freq_map(&ids, &vocab, false).unwrap();
+        assert_eq!(
+            CodecCore::decode_token_ids(&payload, false).unwrap(),
+            [5, UNK, 17]
+        );
+
+        let missing_unk = Codec::with_config(CodecConfig {
+            unk_token: Some(-7),
+            ..CodecConfig::default()
+        });
+        assert!(matches!(
+            missing_unk.encode_with_freq_map(&ids, &vocab, false),
+            Err(CodecError::UnknownToken(-7))
+        ));
+    }
+}
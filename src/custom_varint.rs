@@ -0,0 +1,225 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_CUSTOM_VARINT};
+use crate::{varint, zigzag, Codec};
+
+/// A pluggable integer encoding for token bodies.
+///
+/// `encode` receives the zigzagged mapped IDs that LEB128 would otherwise
+/// write, and `decode` must give them back exactly.
+pub trait VarintEncoder {
+    fn encode(&self, values: &[u32]) -> Vec<u8>;
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u32>>;
+}
+
+/// LEB128 (`varint`), the standard body encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Leb128Encoder;
+
+impl VarintEncoder for Leb128Encoder {
+    fn encode(&self, values: &[u32]) -> Vec<u8> {
+        varint::encode(values)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u32>> {
+        varint::decode(bytes).map_err(|_| CodecError::InvalidPayload)
+    }
+}
+
+/// Raw little-endian u32s, 4 bytes per value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FixedU32Encoder;
+
+impl VarintEncoder for FixedU32Encoder {
+    fn encode(&self, values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<u32>> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().expect("chunks are 4 bytes")))
+            .collect())
+    }
+}
+
+/// Payloads whose body is written by a caller-supplied `VarintEncoder`: the
+/// standard header (in the `FLAG_CUSTOM_VARINT` mode) and
+/// `encoder.encode` of the zigzagged mapped IDs, gzipped when `gzip` is set.
+///
+/// Nothing in the payload identifies the encoder, so readers must know which
+/// one was used.
+impl Codec {
+    pub fn encode_with_custom_varint<V: VarintEncoder + ?Sized>(
+        &self,
+        token_ids: &[i32],
+        encoder: &V,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_CUSTOM_VARINT;
+        let mapped = CodecCore::map_ids(token_ids, &freq)?;
+
+        let mut out = header.encode();
+        let body = encoder.encode(&zigzag::encode_slice(&mapped));
+        out.extend_from_slice(&CodecCore::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    pub fn decode_with_custom_varint<V: VarintEncoder + ?Sized>(
+        &self,
+        payload: &[u8],
+        encoder: &V,
+        gzip: bool,
+    ) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_CUSTOM_VARINT {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let tokens = &parts.header.tokens;
+        encoder
+            .decode(&body)?
+            .into_iter()
+            .map(|value| {
+                usize::try_from(zigzag::decode(value))
+                    .ok()
+                    .and_then(|mapped| tokens.get(mapped).copied())
+                    .ok_or(CodecError::InvalidPayload)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::{CodecConfig, VarintMode};
+
+    /// Writes every value twice; decoding keeps one of each pair.
+    struct DoubleEncoder;
+
+    impl VarintEncoder for DoubleEncoder {
+        fn encode(&self, values: &[u32]) -> Vec<u8> {
+            let doubled: Vec<u32> = values.iter().flat_map(|&v| [v, v]).collect();
+            varint::encode(&doubled)
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Vec<u32>> {
+            let doubled = varint::decode(bytes).map_err(|_| CodecError::InvalidPayload)?;
+            if !doubled.len().is_multiple_of(2) || doubled.chunks(2).any(|pair| pair[0] != pair[1])
+            {
+                return Err(CodecError::InvalidPayload);
+            }
+            Ok(doubled.into_iter().step_by(2).collect())
+        }
+    }
+
+    fn ids() -> Vec<i32> {
+        (0..500).map(|i| (i * 7919) % 1000).collect()
+    }
+
+    #[test]
+    fn custom_encoders_round_trip() {
+        let codec = Codec::new();
+        for ids in [ids(), vec![], vec![3]] {
+            for gzip in [false, true] {
+                let encoders: [&dyn VarintEncoder; 3] =
+                    [&Leb128Encoder, &FixedU32Encoder, &DoubleEncoder];
+                for encoder in encoders {
+                    let payload = codec
+                        .encode_with_custom_varint(&ids, encoder, gzip)
+                        .unwrap();
+                    assert_eq!(payload[1], FLAG_CUSTOM_VARINT);
+                    assert_eq!(
+                        codec
+                            .decode_with_custom_varint(&payload, encoder, gzip)
+                            .unwrap(),
+                        ids
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn double_encoder_is_halved_on_decode() {
+        let codec = Codec::new();
+        let ids = ids();
+        let single = codec
+            .encode_with_custom_varint(&ids, &Leb128Encoder, false)
+            .unwrap();
+        let double = codec
+            .encode_with_custom_varint(&ids, &DoubleEncoder, false)
+            .unwrap();
+        let header_len = Header::decode_prefix(&single).unwrap().0.body_offset();
+        assert_eq!(double.len() - header_len, 2 * (single.len() - header_len));
+        assert_eq!(
+            codec
+                .decode_with_custom_varint(&double, &DoubleEncoder, false)
+                .unwrap(),
+            ids
+        );
+        // Reading it back as plain LEB128 yields every token twice.
+        let doubled: Vec<i32> = ids.iter().flat_map(|&t| [t, t]).collect();
+        assert_eq!(
+            codec
+                .decode_with_custom_varint(&double, &Leb128Encoder, false)
+                .unwrap(),
+            doubled
+        );
+    }
+
+    #[test]
+    fn known_encoders_match_their_layouts() {
+        let codec = Codec::new();
+        let ids = ids();
+        let leb128 = codec
+            .encode_with_custom_varint(&ids, &Leb128Encoder, false)
+            .unwrap();
+        let standard = CodecCore::encode_token_ids(&ids, false).unwrap();
+        // Same bytes apart from the mode.
+        assert_eq!(leb128[2..], standard[2..]);
+
+        let fixed = codec
+            .encode_with_custom_varint(&ids, &FixedU32Encoder, false)
+            .unwrap();
+        let header_len = Header::decode_prefix(&fixed).unwrap().0.body_offset();
+        assert_eq!(fixed.len(), header_len + 4 * ids.len());
+        assert!(FixedU32Encoder.decode(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn config_selects_a_custom_encoder() {
+        let encoder = Arc::new(DoubleEncoder);
+        let codec = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::Custom(encoder.clone()),
+            ..CodecConfig::default()
+        });
+        let ids = ids();
+        let payload = codec.encode(&ids, true).unwrap();
+        assert_eq!(payload[1], FLAG_CUSTOM_VARINT);
+        assert_eq!(
+            codec
+                .decode_with_custom_varint(&payload, encoder.as_ref(), true)
+                .unwrap(),
+            ids
+        );
+        // Standard decoders can't read it.
+        assert!(CodecCore::decode_token_ids(&payload, true).is_err());
+        assert!(codec
+            .decode_with_custom_varint(
+                &CodecCore::encode_token_ids(&ids, true).unwrap(),
+                encoder.as_ref(),
+                true
+            )
+            .is_err());
+    }
+}
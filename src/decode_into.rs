@@ -0,0 +1,178 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::Header;
+use crate::Codec;
+
+/// Decoding into a caller-owned `Vec`, so that a serving loop can reuse one
+/// buffer across requests.
+///
+/// Tokens are appended after whatever `output` already holds. Every token
+/// takes at least one body byte, so reserving the (decompressed) body length
+/// up front means `output` grows at most once per call. On error `output` is
+/// truncated back to its original length.
+impl Codec {
+    /// Returns the number of tokens appended. Standard-layout payloads decode
+    /// straight into `output`; the other body encodings `decode_token_ids`
+    /// reads are decoded first and then appended.
+    pub fn decode_token_ids_into(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+        output: &mut Vec<i32>,
+    ) -> Result<usize> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != 0 {
+            let (tokens, _) = CodecCore::decode_with_header(payload, gzip)?;
+            output.extend_from_slice(&tokens);
+            return Ok(tokens.len());
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        append_body(&body, &parts.header, output)
+    }
+
+    /// `decode_token_ids_into` for a payload whose header the caller has
+    /// already parsed (with `Header::decode_prefix`), skipping the token table.
+    ///
+    /// Only standard payloads without sections or extensions are accepted;
+    /// others error with `CodecError::InvalidPayload`.
+    pub fn decode_token_ids_into_with_header(
+        &self,
+        payload: &[u8],
+        header: &Header,
+        gzip: bool,
+        output: &mut Vec<i32>,
+    ) -> Result<usize> {
+        if header.flags != 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = payload
+            .get(header.body_offset()..)
+            .ok_or(CodecError::InvalidPayload)?;
+        let body = CodecCore::decompress(body, gzip)?;
+        append_body(&body, header, output)
+    }
+}
+
+fn append_body(body: &[u8], header: &Header, output: &mut Vec<i32>) -> Result<usize> {
+    let start = output.len();
+    output.reserve(body.len());
+    match CodecCore::decode_body_into(body, header, output) {
+        Ok(()) => Ok(output.len() - start),
+        Err(err) => {
+            output.truncate(start);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payloads(gzip: bool) -> Vec<(Vec<i32>, Vec<u8>)> {
+        (0..1000)
+            .map(|i| {
+                let ids: Vec<i32> = (0..i % 97).map(|j| (j * 7919 + i) % 50_000).collect();
+                let payload = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+                (ids, payload)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_1000_payloads_into_one_buffer() {
+        let codec = Codec::new();
+        for gzip in [false, true] {
+            let mut output = Vec::new();
+            for (ids, payload) in payloads(gzip) {
+                output.clear();
+                let n = codec
+                    .decode_token_ids_into(&payload, gzip, &mut output)
+                    .unwrap();
+                assert_eq!(n, ids.len());
+                assert_eq!(output, ids);
+            }
+        }
+    }
+
+    #[test]
+    fn with_header_matches() {
+        let codec = Codec::new();
+        let mut output = Vec::new();
+        for (ids, payload) in payloads(false) {
+            output.clear();
+            let (header, _) = Header::decode_prefix(&payload).unwrap();
+            let n = codec
+                .decode_token_ids_into_with_header(&payload, &header, false, &mut output)
+                .unwrap();
+            assert_eq!(n, ids.len());
+            assert_eq!(output, ids);
+        }
+    }
+
+    #[test]
+    fn appends_and_grows_at_most_once() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..5000).map(|i| (i * 7919) % 50_000).collect();
+        let payload = CodecCore::encode_token_ids(&ids, true).unwrap();
+
+        let mut output = vec![-1, -2];
+        output.shrink_to_fit();
+        let before = output.capacity();
+        assert_eq!(
+            codec
+                .decode_token_ids_into(&payload, true, &mut output)
+                .unwrap(),
+            5000
+        );
+        assert_eq!(output[..2], [-1, -2]);
+        assert_eq!(output[2..], ids);
+        // One reservation, no doubling afterwards.
+        assert!(output.capacity() > before);
+        let reserved = output.capacity();
+        output.truncate(2);
+        codec
+            .decode_token_ids_into(&payload, true, &mut output)
+            .unwrap();
+        assert_eq!(output.capacity(), reserved);
+    }
+
+    #[test]
+    fn other_body_encodings_are_appended() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..300).map(|i| (i * 31) % 200).collect();
+        let payload = CodecCore::encode_token_ids_huffman(&ids, false).unwrap();
+        let mut output = vec![7];
+        assert_eq!(
+            codec
+                .decode_token_ids_into(&payload, false, &mut output)
+                .unwrap(),
+            300
+        );
+        assert_eq!(output[1..], ids);
+    }
+
+    #[test]
+    fn errors_leave_output_untouched() {
+        let codec = Codec::new();
+        let mut payload = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        // Mapped ID 5 is past the three-entry table.
+        *payload.last_mut().unwrap() = 10;
+        let mut output = vec![9, 9];
+        assert!(codec
+            .decode_token_ids_into(&payload, false, &mut output)
+            .is_err());
+        assert_eq!(output, [9, 9]);
+
+        let (header, _) = Header::decode_prefix(&payload).unwrap();
+        assert!(codec
+            .decode_token_ids_into_with_header(&payload[..4], &header, false, &mut output)
+            .is_err());
+        let extended = CodecCore::encode_with_extensions(&[1], &[(0x77, b"x")], false).unwrap();
+        let (header, _) = Header::decode_prefix(&extended).unwrap();
+        assert!(codec
+            .decode_token_ids_into_with_header(&extended, &header, false, &mut output)
+            .is_err());
+        assert_eq!(output, [9, 9]);
+    }
+}
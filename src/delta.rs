@@ -0,0 +1,70 @@
+// src/delta.rs
+use crate::zigzag;
+
+/// Delta-code `values`, then zigzag the differences: `values[0]` first, then
+/// `values[i] - values[i - 1]` (wrapping), each through `zigzag::encode`.
+///
+/// Streams whose neighbouring values are close, such as mapped IDs after
+/// frequency remapping, turn into mostly small numbers that LEB128 stores
+/// in one byte.
+pub fn encode(values: &[i32]) -> Vec<u32> {
+    let mut prev = 0i32;
+    values
+        .iter()
+        .map(|&v| {
+            let delta = v.wrapping_sub(prev);
+            prev = v;
+            zigzag::encode(delta)
+        })
+        .collect()
+}
+
+/// Reverse of `encode`, with `first` as the value preceding `encoded[0]`:
+/// `decode(&encode(values), 0) == values`. Passing the last value of an
+/// earlier chunk continues a stream that was encoded in one piece.
+pub fn decode(encoded: &[u32], first: i32) -> Vec<i32> {
+    let mut prev = first;
+    encoded
+        .iter()
+        .map(|&z| {
+            prev = prev.wrapping_add(zigzag::decode(z));
+            prev
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        // Deltas 5, -2, 0, 3 zigzag to 10, 3, 0, 6.
+        assert_eq!(encode(&[5, 3, 3, 6]), [10, 3, 0, 6]);
+        assert_eq!(decode(&[10, 3, 0, 6], 0), [5, 3, 3, 6]);
+        assert!(encode(&[]).is_empty());
+    }
+
+    #[test]
+    fn extremes_wrap() {
+        let values = [i32::MIN, i32::MAX, i32::MIN, 0, -1];
+        assert_eq!(decode(&encode(&values), 0), values);
+    }
+
+    #[test]
+    fn chunks_continue_from_first() {
+        let values = [7, 9, 4, 4, 100, -3];
+        let encoded = encode(&values);
+        let head = decode(&encoded[..2], 0);
+        assert_eq!(decode(&encoded[2..], head[1]), values[2..]);
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(xs in proptest::collection::vec(any::<i32>(), 0..512)) {
+            prop_assert_eq!(decode(&encode(&xs), 0), xs);
+        }
+    }
+}
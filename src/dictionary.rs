@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_DICT_REF, FLAG_MODE_MASK, FORMAT_VERSION, PREFIX_LEN};
+
+/// Header deduplication for many payloads sharing one vocabulary ordering.
+///
+/// A header is registered once and then referenced by a 4-byte ID instead of
+/// being repeated in every payload. Dict-ref payloads mirror the standard
+/// header prefix with the token count replaced by the ID:
+///
+///   [version u8][flags u8 = FLAG_DICT_REF][u32 LE dict_id][body]
+///
+/// The decoder must have the same header registered under the same ID.
+#[derive(Debug, Clone, Default)]
+pub struct DictionaryCodec {
+    dictionary: HashMap<u32, Header>,
+}
+
+impl DictionaryCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `header` and return its ID: the CRC32 of its token table, bumped
+    /// past any colliding entry. Registering the same tokens again returns the
+    /// same ID.
+    pub fn register_header(&mut self, header: &Header) -> u32 {
+        let mut id = header.token_table_crc32();
+        loop {
+            match self.dictionary.get(&id) {
+                Some(existing) if existing.tokens == header.tokens => return id,
+                Some(_) => id = id.wrapping_add(1),
+                None => break,
+            }
+        }
+
+        self.dictionary
+            .insert(id, Header::new(FORMAT_VERSION, 0, header.tokens.clone()));
+        id
+    }
+
+    /// The header registered under `dict_id`, if any.
+    pub fn header(&self, dict_id: u32) -> Option<&Header> {
+        self.dictionary.get(&dict_id)
+    }
+
+    /// Encode against the registered header `dict_id`; every token must appear
+    /// in that header (`CodecError::UnknownToken` otherwise).
+    pub fn encode_with_dict_ref(
+        &self,
+        token_ids: &[i32],
+        dict_id: u32,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let header = self
+            .header(dict_id)
+            .ok_or(CodecError::UnknownDictionary(dict_id))?;
+        if let Some(&token) = token_ids.iter().find(|&&t| !header.contains_token(t)) {
+            return Err(CodecError::UnknownToken(token));
+        }
+
+        let freq = FreqMap::from_ordered_tokens(&header.tokens);
+        let body = CodecCore::compress(CodecCore::encode_body(token_ids, &freq)?, gzip)?;
+
+        let mut out = Vec::with_capacity(PREFIX_LEN + body.len());
+        out.push(FORMAT_VERSION);
+        out.push(FLAG_DICT_REF);
+        out.extend_from_slice(&dict_id.to_le_bytes());
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    /// Decode either a dict-ref payload or a standard payload with a header.
+    pub fn decode(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let is_dict_ref = payload.len() >= PREFIX_LEN
+            && payload[0] == FORMAT_VERSION
+            && payload[1] & FLAG_MODE_MASK == FLAG_DICT_REF;
+        if !is_dict_ref {
+            return CodecCore::decode_token_ids(payload, gzip);
+        }
+
+        let id_bytes: [u8; 4] = payload[2..PREFIX_LEN]
+            .try_into()
+            .expect("slice of length 4 will always convert");
+        let dict_id = u32::from_le_bytes(id_bytes);
+        let header = self
+            .header(dict_id)
+            .ok_or(CodecError::UnknownDictionary(dict_id))?;
+
+        let body = CodecCore::decompress(&payload[PREFIX_LEN..], gzip)?;
+        CodecCore::decode_body(&body, header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 sequences drawn from one shared 300-token vocabulary.
+    fn sequences() -> Vec<Vec<i32>> {
+        (0..100u32)
+            .map(|i| {
+                (0..64u32)
+                    .map(|j| ((i * 64 + j).wrapping_mul(2654435761) % 300) as i32 + 50_000)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shared_vocabulary_round_trip() {
+        let seqs = sequences();
+        let all: Vec<i32> = seqs.concat();
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(&all));
+
+        let mut codec = DictionaryCodec::new();
+        let id = codec.register_header(&header);
+        assert_eq!(codec.register_header(&header), id);
+
+        for gzip in [false, true] {
+            let mut referenced = 0;
+            let mut standalone = 0;
+            for seq in &seqs {
+                let payload = codec.encode_with_dict_ref(seq, id, gzip).unwrap();
+                assert_eq!(&codec.decode(&payload, gzip).unwrap(), seq);
+
+                referenced += payload.len();
+                standalone += CodecCore::encode_token_ids(seq, gzip).unwrap().len();
+            }
+            assert!(referenced * 2 < standalone, "{referenced} vs {standalone}");
+        }
+    }
+
+    #[test]
+    fn decode_accepts_standard_payloads() {
+        let codec = DictionaryCodec::new();
+        let payload = CodecCore::encode_token_ids(&[3, 1, 3], true).unwrap();
+        assert_eq!(codec.decode(&payload, true).unwrap(), vec![3, 1, 3]);
+    }
+
+    #[test]
+    fn dict_ref_payloads_need_the_dictionary() {
+        let mut codec = DictionaryCodec::new();
+        let id = codec.register_header(&Header::new(FORMAT_VERSION, 0, vec![7, 8]));
+        let payload = codec.encode_with_dict_ref(&[8, 7, 8], id, false).unwrap();
+
+        assert!(matches!(
+            DictionaryCodec::new().decode(&payload, false),
+            Err(CodecError::UnknownDictionary(got)) if got == id
+        ));
+        assert!(CodecCore::decode_token_ids(&payload, false).is_err());
+
+        assert!(matches!(
+            codec.encode_with_dict_ref(&[9], id, false),
+            Err(CodecError::UnknownToken(9))
+        ));
+        assert!(matches!(
+            codec.encode_with_dict_ref(&[7], id.wrapping_add(1), false),
+            Err(CodecError::UnknownDictionary(_))
+        ));
+    }
+
+    #[test]
+    fn colliding_ids_are_bumped() {
+        let mut codec = DictionaryCodec::new();
+        let first = Header::new(FORMAT_VERSION, 0, vec![1]);
+        let id = codec.register_header(&first);
+
+        // Squat on the ID the next header would get.
+        let second = Header::new(FORMAT_VERSION, 0, vec![2]);
+        let natural = second.token_table_crc32();
+        codec.dictionary.insert(natural, first.clone());
+
+        let bumped = codec.register_header(&second);
+        assert_ne!(bumped, natural);
+        assert_ne!(bumped, id);
+        assert_eq!(codec.header(bumped).unwrap().tokens, vec![2]);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::{FLAG_ECC, FLAG_EXTENSIONS};
+use crate::Codec;
+
+/// Bytes covered by each parity byte.
+pub const ECC_BLOCK_LEN: usize = 64;
+
+/// Error detection for archival payloads: a standard payload whose bytes
+/// (header and body) are split into `ECC_BLOCK_LEN`-byte blocks, each with a
+/// 1-byte XOR parity, stored in the `FLAG_ECC` extension.
+///
+/// A block's parity catches any single flipped bit, and any set of flips
+/// that doesn't hit the same bit position an even number of times. Errors
+/// are only detected, not corrected.
+impl Codec {
+    pub fn encode_with_ecc(&self, token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let mut out = CodecCore::encode_with_flags(token_ids, FLAG_EXTENSIONS, gzip)?;
+        let parity = block_parity(&out);
+        CodecCore::push_extensions(&mut out, &[(FLAG_ECC, &parity)])?;
+        Ok(out)
+    }
+
+    /// The tokens, and whether each block failed its parity check.
+    ///
+    /// Corruption bad enough to break decoding is an error; `check_ecc` still
+    /// finds the blocks at fault.
+    pub fn decode_with_ecc(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<bool>)> {
+        let errors = self.check_ecc(payload)?;
+        let (tokens, _) = CodecCore::decode_with_extension(payload, FLAG_ECC, gzip)?;
+        Ok((tokens, errors))
+    }
+
+    /// Whether each block of an `encode_with_ecc` payload fails its parity
+    /// check, without decoding it.
+    ///
+    /// The parity is found from the end of the payload, so this works even if
+    /// the header is damaged.
+    pub fn check_ecc(&self, payload: &[u8]) -> Result<Vec<bool>> {
+        // The ECC extension is the last one: [..covered][parity][tag][u32 len][count].
+        let (&count, rest) = payload.split_last().ok_or(CodecError::InvalidPayload)?;
+        if count == 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (before, framed) = CodecCore::pop_section(rest)?;
+        let (&tag, parity) = framed.split_last().ok_or(CodecError::InvalidPayload)?;
+        if tag != FLAG_ECC || parity.len() != before.len().div_ceil(ECC_BLOCK_LEN) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        Ok(block_parity(before)
+            .iter()
+            .zip(parity)
+            .map(|(computed, stored)| computed != stored)
+            .collect())
+    }
+}
+
+fn block_parity(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .chunks(ECC_BLOCK_LEN)
+        .map(|block| block.iter().fold(0, |acc, b| acc ^ b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids() -> Vec<i32> {
+        (0..2000).map(|i| (i * 7919) % 3000).collect()
+    }
+
+    #[test]
+    fn ecc_round_trips() {
+        let codec = Codec::new();
+        for ids in [ids(), vec![], vec![1]] {
+            for gzip in [false, true] {
+                let payload = codec.encode_with_ecc(&ids, gzip).unwrap();
+                let (tokens, errors) = codec.decode_with_ecc(&payload, gzip).unwrap();
+                assert_eq!(tokens, ids);
+                assert!(errors.iter().all(|&e| !e));
+                // Plain decoders skip the extension.
+                assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn every_single_bit_flip_is_detected() {
+        let codec = Codec::new();
+        let payload = codec.encode_with_ecc(&ids(), false).unwrap();
+        let blocks = codec.check_ecc(&payload).unwrap().len();
+        let covered = payload.len() - (blocks + 6);
+
+        // One flipped bit in every block at once, at a different position in
+        // each: every block is caught.
+        let mut corrupt = payload.clone();
+        for block in 0..blocks {
+            let byte = (block * ECC_BLOCK_LEN + block * 13 % ECC_BLOCK_LEN).min(covered - 1);
+            corrupt[byte] ^= 1 << (block % 8);
+        }
+        let errors = codec.check_ecc(&corrupt).unwrap();
+        assert_eq!(errors.len(), blocks);
+        assert!(errors.iter().all(|&e| e));
+
+        // Every position of the covered bytes, one at a time: 100% detection,
+        // and only in the right block.
+        let mut detected = 0;
+        for bit in 0..covered * 8 {
+            let mut corrupt = payload.clone();
+            corrupt[bit / 8] ^= 1 << (bit % 8);
+            let errors = codec.check_ecc(&corrupt).unwrap();
+            let block = bit / 8 / ECC_BLOCK_LEN;
+            assert_eq!(errors.iter().filter(|&&e| e).count(), 1);
+            detected += errors[block] as usize;
+        }
+        assert_eq!(detected, covered * 8);
+    }
+
+    #[test]
+    fn flips_outside_the_token_stream_still_decode() {
+        let codec = Codec::new();
+        let ids = ids();
+        let mut payload = codec.encode_with_ecc(&ids, false).unwrap();
+        // The first token table entry, in block 0: decoding succeeds with a
+        // wrong token, and the parity flags it.
+        payload[6] ^= 0x10;
+        let (tokens, errors) = codec.decode_with_ecc(&payload, false).unwrap();
+        assert_ne!(tokens, ids);
+        assert!(errors[0]);
+        assert!(errors[1..].iter().all(|&e| !e));
+    }
+
+    #[test]
+    fn double_flips_in_one_bit_position_are_missed() {
+        let codec = Codec::new();
+        let mut payload = codec.encode_with_ecc(&ids(), false).unwrap();
+        payload[100] ^= 0x04;
+        payload[101] ^= 0x04;
+        assert!(codec.check_ecc(&payload).unwrap().iter().all(|&e| !e));
+    }
+
+    #[test]
+    fn other_payloads_are_rejected() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&ids(), false).unwrap();
+        assert!(codec.check_ecc(&plain).is_err());
+        assert!(codec.decode_with_ecc(&plain, false).is_err());
+        assert!(codec.check_ecc(&[]).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+// src/elias.rs
+use anyhow::{bail, Result};
+
+use crate::bits::{BitReader, BitWriter};
+
+/// Elias-gamma code `values`, which must all be positive.
+///
+/// `n` with `N = floor(log2(n))` is written as `N` zero bits, a one bit (the
+/// leading bit of `n`), then the `N` bits below it as one `N`-bit field. As
+/// in `rice`, bits are packed least significant bit first and the final byte
+/// is zero-padded. The code is self-delimiting per value only, so the count
+/// must be stored alongside.
+///
+/// # Panics
+///
+/// If any value is 0.
+pub fn encode_elias_gamma(values: &[u32]) -> Vec<u8> {
+    let mut writer = BitWriter::with_capacity(values.len());
+    for &v in values {
+        assert!(v > 0, "Elias-gamma coding needs positive values");
+        let n = v.ilog2();
+        // n zeros, then the leading one.
+        writer.push(1 << n, n + 1);
+        writer.push(u64::from(v) & ((1 << n) - 1), n);
+    }
+    writer.finish()
+}
+
+/// Decode `count` values written by `encode_elias_gamma`. Errors on a
+/// truncated stream or trailing bytes.
+pub fn decode_elias_gamma(bytes: &[u8], count: usize) -> Result<Vec<u32>> {
+    // Every value takes at least one bit, which bounds the allocation.
+    if count > bytes.len() * 8 {
+        bail!("elias-gamma stream too short for {} values", count);
+    }
+
+    let mut reader = BitReader::new(bytes);
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let n = reader.peek().trailing_zeros();
+        if n > 31 {
+            bail!("invalid elias-gamma prefix");
+        }
+        reader.skip(n + 1)?;
+        out.push((1 << n) | reader.read(n)?);
+    }
+
+    if !reader.at_end() {
+        bail!("trailing bytes after elias-gamma stream");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn known_layout() {
+        // In stream order: 1 -> 1; 2 -> 0 1 0; 5 -> 0 0 1, then 01 LSB first.
+        assert_eq!(encode_elias_gamma(&[1, 2, 5]), [0b1100_0101, 0]);
+        assert_eq!(decode_elias_gamma(&[0b1100_0101, 0], 3).unwrap(), [1, 2, 5]);
+    }
+
+    #[test]
+    fn extremes_round_trip() {
+        let values = [1, u32::MAX, 1 << 31, 3];
+        let enc = encode_elias_gamma(&values);
+        // 1 + 63 + 63 + 3 bits.
+        assert_eq!(enc.len(), 17);
+        assert_eq!(decode_elias_gamma(&enc, 4).unwrap(), values);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive")]
+    fn zero_is_rejected() {
+        encode_elias_gamma(&[3, 0]);
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        let enc = encode_elias_gamma(&[9, 1, 4, 200]);
+        assert!(decode_elias_gamma(&enc[..enc.len() - 1], 4).is_err());
+        assert!(decode_elias_gamma(&[enc.as_slice(), &[0]].concat(), 4).is_err());
+        assert!(decode_elias_gamma(&[], 1).is_err());
+        assert!(decode_elias_gamma(&[0; 8], 1).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(vals in proptest::collection::vec(1u32.., 0..512)) {
+            let enc = encode_elias_gamma(&vals);
+            prop_assert_eq!(decode_elias_gamma(&enc, vals.len()).unwrap(), vals);
+        }
+    }
+}
@@ -1,11 +1,35 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::PyErr;
 use thiserror::Error;
 
-  #[derive(Debug, Error)]
-  pub enum CodecError {
-      #[error("invalid payload")]
-      InvalidPayload,
-      #[error("internal error: {0}")]
-      Internal(String),
-  }
+#[derive(Debug, Error)]
+pub enum CodecError {
+    #[error("invalid payload")]
+    InvalidPayload,
+    #[error("index {index} out of range for {len} entries")]
+    IndexOutOfRange { index: usize, len: usize },
+    #[error("token {token} outside the supported range 0..={max}")]
+    TokenOutOfRange { token: i32, max: usize },
+    #[error("unknown format version {0}")]
+    UnknownVersion(u8),
+    #[error("expected payload type {expected:#04x}, found {found:#04x}")]
+    UnexpectedPayloadType { expected: u8, found: u8 },
+    #[error("token {0} is not in the header's token table")]
+    UnknownToken(i32),
+    #[error("no header registered under dictionary id {0:#010x}")]
+    UnknownDictionary(u32),
+    #[error("payload of {size} bytes does not fit in {target} bytes")]
+    PayloadTooLarge { size: usize, target: usize },
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("internal error: {0}")]
+    Internal(String),
+}
 
-  pub type Result<T> = std::result::Result<T, CodecError>;
+pub type Result<T> = std::result::Result<T, CodecError>;
+
+impl From<CodecError> for PyErr {
+    fn from(err: CodecError) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
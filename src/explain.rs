@@ -0,0 +1,266 @@
+use std::fmt::{Display, Write};
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::Header;
+use crate::{varint, zigzag, Codec};
+
+/// How many items of each list to print before eliding the rest.
+const PREVIEW: usize = 8;
+
+/// Every intermediate value of decoding a standard payload, one entry per
+/// token.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeTrace {
+    pub tokens: Vec<i32>,
+    pub mapped_ids: Vec<i32>,
+    pub zigzag_values: Vec<u32>,
+    /// Byte offset of each token's varint in the (decompressed) body.
+    pub varint_positions: Vec<usize>,
+}
+
+/// Human-readable, step-by-step traces of the encode and decode pipelines, for
+/// debugging payload sizes and decode failures.
+///
+/// Reports are built with `std::fmt::Write` into a single `String` (writing to a
+/// `String` cannot fail, hence the ignored results). A failing stage is
+/// reported as an `Error:` line instead of aborting the report.
+impl Codec {
+    pub fn explain(&self, token_ids: &[i32], gzip: bool) -> String {
+        let mut out = String::new();
+        if let Err(err) = Self::write_encode_trace(&mut out, token_ids, gzip) {
+            let _ = writeln!(out, "Error: {err}");
+        }
+        out
+    }
+
+    pub fn explain_payload(&self, payload: &[u8], gzip: bool) -> String {
+        let mut out = String::new();
+        if let Err(err) = Self::write_decode_trace(&mut out, payload, gzip) {
+            let _ = writeln!(out, "Error: {err}");
+        }
+        out
+    }
+
+    /// Decode a standard (LEB128, mode 0) payload, keeping every stage.
+    pub fn decode_with_trace(&self, payload: &[u8], gzip: bool) -> Result<DecodeTrace> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+
+        let mut trace = DecodeTrace::default();
+        let mut pos = 0;
+        while pos < body.len() {
+            let (value, len) =
+                varint::decode_one(&body[pos..]).map_err(|_| CodecError::InvalidPayload)?;
+            let mapped = zigzag::decode(value);
+            let token = usize::try_from(mapped)
+                .ok()
+                .and_then(|idx| parts.header.tokens.get(idx).copied())
+                .ok_or(CodecError::InvalidPayload)?;
+
+            trace.tokens.push(token);
+            trace.mapped_ids.push(mapped);
+            trace.zigzag_values.push(value);
+            trace.varint_positions.push(pos);
+            pos += len;
+        }
+        Ok(trace)
+    }
+
+    fn write_encode_trace(out: &mut String, ids: &[i32], gzip: bool) -> Result<()> {
+        let freq = FreqMap::from_token_ids(ids);
+        let header = Header::from_freq_map(&freq);
+
+        let _ = writeln!(out, "Input: {} tokens, {} unique", ids.len(), header.len);
+
+        let _ = write!(out, "FreqMap: ");
+        let pairs = freq
+            .ordered_tokens()
+            .iter()
+            .enumerate()
+            .map(|(mapped, &token)| MappedPair { token, mapped });
+        write_list(out, pairs);
+
+        let header_len = header.encode().len();
+        let _ = writeln!(out, "Header: {header_len} bytes");
+
+        let zigzagged: Vec<u32> = ids
+            .iter()
+            .filter_map(|&token| freq.map_token(token))
+            .map(zigzag::encode)
+            .collect();
+        let _ = write!(out, "Zigzag output: ");
+        write_list(out, zigzagged.iter());
+
+        let body = varint::encode(&zigzagged);
+        let _ = writeln!(out, "Varint bytes: {} bytes", body.len());
+
+        let body_len = if gzip {
+            let compressed = CodecCore::compress(body, true)?.len();
+            let _ = writeln!(out, "Compressed: {compressed} bytes");
+            compressed
+        } else {
+            let _ = writeln!(out, "Compressed: skipped (gzip disabled)");
+            body.len()
+        };
+
+        let _ = writeln!(out, "Total: {} bytes", header_len + body_len);
+        Ok(())
+    }
+
+    fn write_decode_trace(out: &mut String, payload: &[u8], gzip: bool) -> Result<()> {
+        let _ = writeln!(out, "Payload: {} bytes", payload.len());
+
+        let parts = CodecCore::split_payload(payload)?;
+        let header = &parts.header;
+        let _ = writeln!(
+            out,
+            "Header: {} bytes, version {}, flags {:#04x}, {} unique tokens",
+            header.encode().len(),
+            header.version,
+            header.flags,
+            header.len
+        );
+        for (flag, section) in &parts.sections {
+            let _ = writeln!(out, "Section {flag:#04x}: {} bytes", section.len());
+        }
+
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        if gzip {
+            let _ = writeln!(
+                out,
+                "Decompressed: {} -> {} bytes",
+                parts.body.len(),
+                body.len()
+            );
+        }
+        let _ = writeln!(out, "Varint bytes: {} bytes", body.len());
+
+        let values = varint::decode(&body).map_err(|_| CodecError::InvalidPayload)?;
+        let _ = write!(out, "Zigzag values: ");
+        write_list(out, values.iter());
+
+        let mapped: Vec<i32> = values.iter().map(|&v| zigzag::decode(v)).collect();
+        let _ = write!(out, "Mapped IDs: ");
+        write_list(out, mapped.iter());
+
+        let tokens = CodecCore::decode_body(&body, header)?;
+        let _ = write!(out, "Output: {} tokens ", tokens.len());
+        write_list(out, tokens.iter());
+        Ok(())
+    }
+}
+
+/// `token->mapped`, written straight into the output instead of going through
+/// a `String` per pair.
+struct MappedPair {
+    token: i32,
+    mapped: usize,
+}
+
+impl Display for MappedPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}->{}", self.token, self.mapped)
+    }
+}
+
+/// Write `[a, b, ...]` showing at most `PREVIEW` items, then a newline.
+fn write_list<T: Display>(out: &mut String, items: impl Iterator<Item = T>) {
+    let mut items = items.peekable();
+    out.push('[');
+    for (i, item) in items.by_ref().take(PREVIEW).enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let _ = write!(out, "{item}");
+    }
+    if items.peek().is_some() {
+        out.push_str(", ...");
+    }
+    out.push_str("]\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explain_mentions_every_stage() {
+        let codec = Codec::new();
+        let report = codec.explain(&[3, 7, 3, 3, 9], false);
+
+        assert!(report.contains("Input: 5 tokens, 3 unique"));
+        assert!(report.contains("FreqMap: [3->0, 7->1, 9->2]"));
+        assert!(report.contains("Header:"));
+        assert!(report.contains("Zigzag output: [0, 2, 0, 0, 4]"));
+        assert!(report.contains("Varint bytes: 5 bytes"));
+    }
+
+    #[test]
+    fn explain_elides_long_lists_and_reports_compression() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..100).collect();
+        let report = codec.explain(&ids, true);
+
+        assert!(report.contains(", ...]"));
+        assert!(report.contains("Compressed: "));
+        assert!(!report.contains("skipped"));
+    }
+
+    #[test]
+    fn explain_payload_walks_the_decode_path() {
+        let codec = Codec::new();
+        let payload = CodecCore::encode_token_ids(&[3, 7, 3], true).unwrap();
+        let report = codec.explain_payload(&payload, true);
+
+        assert!(report.contains("Header:"));
+        assert!(report.contains("Decompressed:"));
+        assert!(report.contains("Mapped IDs: [0, 1, 0]"));
+        assert!(report.contains("Output: 3 tokens [3, 7, 3]"));
+    }
+
+    #[test]
+    fn decode_trace_lines_up_with_the_header() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..500).map(|i| (i * 7919) % 300 - 20).collect();
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            let header = Header::decode_prefix(&payload).unwrap().0;
+            let trace = codec.decode_with_trace(&payload, gzip).unwrap();
+
+            assert_eq!(trace.tokens, ids);
+            assert_eq!(trace.mapped_ids.len(), ids.len());
+            for i in 0..ids.len() {
+                assert_eq!(trace.tokens[i], header.tokens[trace.mapped_ids[i] as usize]);
+                assert_eq!(trace.zigzag_values[i], zigzag::encode(trace.mapped_ids[i]));
+            }
+            assert_eq!(trace.varint_positions[0], 0);
+            assert!(trace.varint_positions.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        let empty = CodecCore::encode_token_ids(&[], false).unwrap();
+        assert_eq!(codec.decode_with_trace(&empty, false).unwrap(), DecodeTrace::default());
+    }
+
+    #[test]
+    fn decode_trace_rejects_other_layouts() {
+        let codec = Codec::new();
+        let vbyte = CodecCore::encode_token_ids_vbyte(&[1, 2, 3], false).unwrap();
+        assert!(codec.decode_with_trace(&vbyte, false).is_err());
+
+        let mut truncated = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        *truncated.last_mut().unwrap() |= 0x80;
+        assert!(codec.decode_with_trace(&truncated, false).is_err());
+    }
+
+    #[test]
+    fn explain_payload_reports_errors() {
+        let codec = Codec::new();
+        let report = codec.explain_payload(&[9, 9, 9], false);
+        assert!(report.contains("Error: invalid payload"));
+    }
+}
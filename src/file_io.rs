@@ -0,0 +1,261 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::codec_core::CodecCore;
+use crate::errors::Result;
+use crate::freq_map::FreqMap;
+use crate::Codec;
+
+/// File extension `encode_to_mmap` requires for its output.
+pub const MISO_EXTENSION: &str = "miso";
+
+/// Encoding straight to and decoding straight from files.
+///
+/// Files hold exactly the bytes `Codec::encode` returns, nothing more.
+impl Codec {
+    /// Encode and write to `path` atomically: the payload goes to a sibling
+    /// temp file first, which is then renamed over `path`, so readers never see
+    /// a half-written file.
+    pub fn encode_to_file(&self, token_ids: &[i32], path: &Path, gzip: bool) -> Result<()> {
+        let payload = self.encode(token_ids, gzip)?;
+
+        let tmp = temp_path(path);
+        let written = File::create(&tmp).and_then(|mut file| {
+            file.write_all(&payload)?;
+            file.sync_all()
+        });
+        if let Err(err) = written.and_then(|()| fs::rename(&tmp, path)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Decode a file written by `encode_to_file`, reading it through a memory
+    /// map instead of copying it into a buffer first.
+    pub fn decode_from_file(&self, path: &Path, gzip: bool) -> Result<Vec<i32>> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only and dropped before we return; as with any
+        // mmap, the file must not be truncated by another process meanwhile.
+        let map = unsafe { Mmap::map(&file)? };
+        CodecCore::decode_token_ids(&map, gzip)
+    }
+
+    /// Like `encode_to_file`, but sizes the file up front and writes the payload
+    /// through a writable memory map. Not atomic: `path` is written in place.
+    pub fn encode_to_file_mmap(&self, token_ids: &[i32], path: &Path, gzip: bool) -> Result<()> {
+        let payload = self.encode(token_ids, gzip)?;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(payload.len() as u64)?;
+
+        // SAFETY: we own the freshly truncated file and the map does not outlive
+        // this function.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map.copy_from_slice(&payload);
+        map.flush()?;
+        Ok(())
+    }
+
+    /// Encode straight into a memory-mapped `.miso` file, without ever holding
+    /// the payload in a `Vec`. Returns the number of bytes written.
+    ///
+    /// The file is pre-allocated to an upper bound of the payload size, filled
+    /// through the map by `encode_streaming`, then truncated to the bytes
+    /// actually written. Configs with a body encoding other than standard
+    /// LEB128 have no such bound and go through `encode_to_file_mmap` instead.
+    /// Not atomic: `path` is written in place. Paths without the `.miso`
+    /// extension are rejected with an `InvalidInput` I/O error.
+    pub fn encode_to_mmap(&self, token_ids: &[i32], path: &Path, gzip: bool) -> Result<usize> {
+        if path.extension().is_none_or(|ext| ext != MISO_EXTENSION) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("output path must have a .{MISO_EXTENSION} extension"),
+            )
+            .into());
+        }
+        if !self.config.is_standard_body() {
+            self.encode_to_file_mmap(token_ids, path, gzip)?;
+            return Ok(fs::metadata(path)?.len() as usize);
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(max_payload_size(&FreqMap::from_token_ids(token_ids), gzip) as u64)?;
+
+        // SAFETY: we own the freshly truncated file and the map is dropped
+        // before the file is resized again.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        let mut window = &mut map[..];
+        let written = self.encode_streaming(token_ids, gzip, &mut window)?;
+        map.flush()?;
+        drop(map);
+
+        file.set_len(written as u64)?;
+        Ok(written)
+    }
+
+    /// Decode a file written by `encode_to_mmap` through a read-only map.
+    pub fn decode_from_mmap(&self, path: &Path, gzip: bool) -> Result<Vec<i32>> {
+        self.decode_from_file(path, gzip)
+    }
+}
+
+/// Upper bound on the payload size for the map's input: the exact
+/// uncompressed size, plus deflate's worst-case overhead for incompressible
+/// data (5 bytes per 16K stored block) and gzip's framing when `gzip` is set.
+fn max_payload_size(freq: &FreqMap, gzip: bool) -> usize {
+    let body = freq.expected_body_size_estimate();
+    let body = if gzip {
+        body + 5 * (body / 16_383 + 1) + 64
+    } else {
+        body
+    };
+    freq.expected_header_size() + body
+}
+
+/// `path` with `.tmp` appended to its file name, in the same directory so the
+/// final rename never crosses filesystems.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CodecConfig, VarintMode};
+
+    fn sample() -> Vec<i32> {
+        (0..1000).map(|i| (i * 37) % 101 - 50).collect()
+    }
+
+    #[test]
+    fn file_round_trip_matches_in_memory_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.miso");
+        let codec = Codec::new();
+        let ids = sample();
+
+        for gzip in [false, true] {
+            codec.encode_to_file(&ids, &path, gzip).unwrap();
+            assert_eq!(
+                fs::read(&path).unwrap(),
+                CodecCore::encode_token_ids(&ids, gzip).unwrap()
+            );
+            assert_eq!(codec.decode_from_file(&path, gzip).unwrap(), ids);
+        }
+
+        // The temp file is gone after the rename.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn file_writers_follow_the_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.miso");
+        let codec = Codec::with_config(CodecConfig {
+            varint_mode: VarintMode::VByte,
+            ..CodecConfig::default()
+        });
+        let ids = sample();
+        let expected = codec.encode(&ids, false).unwrap();
+
+        codec.encode_to_file(&ids, &path, false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        codec.encode_to_file_mmap(&ids, &path, false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        assert_eq!(codec.encode_to_mmap(&ids, &path, false).unwrap(), expected.len());
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        assert_eq!(codec.decode_from_file(&path, false).unwrap(), ids);
+    }
+
+    #[test]
+    fn mmap_writer_matches_in_memory_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.miso");
+        let codec = Codec::new();
+
+        // Write a large payload first so the truncation is exercised.
+        codec.encode_to_file_mmap(&sample(), &path, false).unwrap();
+
+        let ids = vec![4, 4, 2];
+        codec.encode_to_file_mmap(&ids, &path, true).unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            CodecCore::encode_token_ids(&ids, true).unwrap()
+        );
+        assert_eq!(codec.decode_from_file(&path, true).unwrap(), ids);
+    }
+
+    #[test]
+    fn mmap_output_is_truncated_to_the_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.miso");
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..200_000).map(|i| (i * 7919) % 50_000).collect();
+
+        for gzip in [false, true] {
+            let written = codec.encode_to_mmap(&ids, &path, gzip).unwrap();
+            assert_eq!(fs::metadata(&path).unwrap().len(), written as u64);
+            assert_eq!(
+                fs::read(&path).unwrap(),
+                CodecCore::encode_token_ids(&ids, gzip).unwrap()
+            );
+            assert_eq!(codec.decode_from_mmap(&path, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn mmap_output_fits_incompressible_gzip_bodies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("noise.miso");
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..5000u32)
+            .map(|i| i.wrapping_mul(2654435761) as i32)
+            .collect();
+
+        let written = codec.encode_to_mmap(&ids, &path, true).unwrap();
+        assert_eq!(codec.decode_from_mmap(&path, true).unwrap(), ids);
+        assert_eq!(fs::metadata(&path).unwrap().len(), written as u64);
+    }
+
+    #[test]
+    fn mmap_output_requires_miso_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let codec = Codec::new();
+
+        for name in ["tokens.bin", "tokens"] {
+            let path = dir.path().join(name);
+            assert!(matches!(
+                codec.encode_to_mmap(&[1, 2, 3], &path, false),
+                Err(crate::errors::CodecError::Io(err)) if err.kind() == io::ErrorKind::InvalidInput
+            ));
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let codec = Codec::new();
+
+        assert!(matches!(
+            codec.decode_from_file(&dir.path().join("absent"), false),
+            Err(crate::errors::CodecError::Io(_))
+        ));
+    }
+}
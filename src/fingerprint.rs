@@ -0,0 +1,92 @@
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::codec_core::CodecCore;
+use crate::errors::Result;
+use crate::freq_map::FreqMap;
+use crate::Codec;
+
+/// Stable 64-bit XXH3 hashes for cache keys and deduplication.
+///
+/// Token sequences are hashed as their little-endian i32 bytes, so a
+/// fingerprint does not depend on the platform or on how they are encoded.
+impl Codec {
+    pub fn fingerprint(token_ids: &[i32]) -> u64 {
+        xxh3_64(&i32_bytes(token_ids))
+    }
+
+    /// Hash of the payload bytes themselves.
+    pub fn fingerprint_encoded(payload: &[u8]) -> u64 {
+        xxh3_64(payload)
+    }
+
+    /// Hash of the map's vocabulary order (`ordered_tokens`), which is what
+    /// determines the header.
+    pub fn fingerprint_freq_map(freq_map: &FreqMap) -> u64 {
+        xxh3_64(&i32_bytes(freq_map.ordered_tokens()))
+    }
+
+    /// `encode`, plus the `fingerprint` of `token_ids`.
+    pub fn encode_with_fingerprint(&self, token_ids: &[i32], gzip: bool) -> Result<(Vec<u8>, u64)> {
+        Ok((self.encode(token_ids, gzip)?, Self::fingerprint(token_ids)))
+    }
+
+    /// Whether `payload` decodes to tokens with fingerprint `expected`.
+    pub fn verify_fingerprint(&self, payload: &[u8], expected: u64, gzip: bool) -> Result<bool> {
+        let tokens = CodecCore::decode_token_ids(payload, gzip)?;
+        Ok(Self::fingerprint(&tokens) == expected)
+    }
+}
+
+fn i32_bytes(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn distinct_sequences_have_distinct_fingerprints() {
+        // 10K sequences that differ in length, content or order.
+        let mut seen = HashSet::new();
+        for i in 0..10_000i32 {
+            let mut ids: Vec<i32> = (0..(i % 17)).map(|j| (i * 31 + j) % 1000).collect();
+            ids.push(i);
+            assert!(seen.insert(Codec::fingerprint(&ids)), "collision at {i}");
+        }
+
+        assert_ne!(Codec::fingerprint(&[1, 2]), Codec::fingerprint(&[2, 1]));
+        assert_ne!(Codec::fingerprint(&[]), Codec::fingerprint(&[0]));
+    }
+
+    #[test]
+    fn fingerprints_are_stable() {
+        let ids = [5, 9, 5, -1];
+        assert_eq!(
+            Codec::fingerprint(&ids),
+            xxh3_64(&[5, 0, 0, 0, 9, 0, 0, 0, 5, 0, 0, 0, 255, 255, 255, 255])
+        );
+
+        let freq = FreqMap::from_token_ids(&ids);
+        assert_eq!(
+            Codec::fingerprint_freq_map(&freq),
+            Codec::fingerprint(&[5, -1, 9])
+        );
+    }
+
+    #[test]
+    fn encode_with_fingerprint_verifies() {
+        let codec = Codec::new();
+        let ids = [3, 1, 4, 1, 5, 9, 2, 6];
+        for gzip in [false, true] {
+            let (payload, fp) = codec.encode_with_fingerprint(&ids, gzip).unwrap();
+            assert_eq!(fp, Codec::fingerprint(&ids));
+            assert!(codec.verify_fingerprint(&payload, fp, gzip).unwrap());
+            assert!(!codec.verify_fingerprint(&payload, fp ^ 1, gzip).unwrap());
+            assert_eq!(Codec::fingerprint_encoded(&payload), xxh3_64(&payload));
+        }
+        assert!(codec.verify_fingerprint(&[9, 9], 0, false).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_FIXED_WIDTH};
+use crate::{varint, Codec};
+
+/// Supported widths, in bits per token.
+const WIDTHS: std::ops::RangeInclusive<u8> = 4..=32;
+
+/// Fixed-width payloads: every mapped ID takes exactly `bits_per_token` bits,
+/// so the body size depends only on the token count.
+///
+/// Layout (header flags carry the `FLAG_FIXED_WIDTH` mode, body is never
+/// compressed):
+///   [header][bits_per_token u8][varint token count][packed mapped IDs]
+///
+/// Mapped IDs are packed least significant bit first, and the final byte is
+/// zero-padded. The token count is needed because padding can be wider than a
+/// token.
+impl Codec {
+    pub fn encode_fixed_width(&self, token_ids: &[i32], bits_per_token: u8) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        if !WIDTHS.contains(&bits_per_token)
+            || min_width(freq.ordered_tokens().len()) > bits_per_token
+        {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut header = Header::from_freq_map(&freq);
+        header.flags = FLAG_FIXED_WIDTH;
+        let mut out = header.encode();
+        out.push(bits_per_token);
+        out.extend_from_slice(&varint::encode(&[to_u32(token_ids.len())?]));
+
+        let mut acc: u64 = 0;
+        let mut pending: u8 = 0;
+        for &token in token_ids {
+            let mapped = freq.map_token(token).ok_or(CodecError::InvalidPayload)?;
+            acc |= u64::from(mapped as u32) << pending;
+            pending += bits_per_token;
+            while pending >= 8 {
+                out.push(acc as u8);
+                acc >>= 8;
+                pending -= 8;
+            }
+        }
+        if pending > 0 {
+            out.push(acc as u8);
+        }
+        Ok(out)
+    }
+
+    pub fn decode_fixed_width(&self, payload: &[u8], bits_per_token: u8) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_FIXED_WIDTH {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (&stored_bits, mut rest) =
+            parts.body.split_first().ok_or(CodecError::InvalidPayload)?;
+        if stored_bits != bits_per_token || !WIDTHS.contains(&bits_per_token) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let count = read_varint(&mut rest)? as usize;
+        let packed_len = count
+            .checked_mul(usize::from(bits_per_token))
+            .map(|bits| bits.div_ceil(8))
+            .ok_or(CodecError::InvalidPayload)?;
+        if rest.len() != packed_len {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mask = u64::MAX >> (64 - u32::from(bits_per_token));
+        let mut bytes = rest.iter();
+        let mut acc: u64 = 0;
+        let mut available: u8 = 0;
+        let mut tokens = Vec::with_capacity(count);
+        for _ in 0..count {
+            while available < bits_per_token {
+                // The length check above guarantees enough bytes.
+                let byte = *bytes.next().ok_or(CodecError::InvalidPayload)?;
+                acc |= u64::from(byte) << available;
+                available += 8;
+            }
+            let mapped = (acc & mask) as usize;
+            acc >>= bits_per_token;
+            available -= bits_per_token;
+
+            let token = parts.header.tokens.get(mapped).copied();
+            tokens.push(token.ok_or(CodecError::InvalidPayload)?);
+        }
+        Ok(tokens)
+    }
+}
+
+/// Bits needed to tell `n_unique` mapped IDs apart, i.e. `ceil(log2(n))`.
+fn min_width(n_unique: usize) -> u8 {
+    match n_unique {
+        0 | 1 => 0,
+        n => (usize::BITS - (n - 1).leading_zeros()) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Length of the header plus the bits and count fields for `ids`.
+    fn overhead(ids: &[i32]) -> usize {
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(ids));
+        header.encode().len() + 1 + varint::encoded_len(ids.len() as u32)
+    }
+
+    #[test]
+    fn round_trip_at_each_width() {
+        let codec = Codec::new();
+        for bits in [4u8, 8, 16, 32] {
+            let unique = (1usize << bits.min(10)).min(1000);
+            let ids: Vec<i32> = (0..1001)
+                .map(|i| ((i * 7919) % unique) as i32 - 300)
+                .collect();
+
+            let payload = codec.encode_fixed_width(&ids, bits).unwrap();
+            assert_eq!(payload[1], FLAG_FIXED_WIDTH);
+            assert_eq!(
+                payload.len() - overhead(&ids),
+                (ids.len() * usize::from(bits)).div_ceil(8)
+            );
+            assert_eq!(codec.decode_fixed_width(&payload, bits).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn odd_token_counts_keep_their_length() {
+        let codec = Codec::new();
+        let ids = [3, 1, 4];
+        let payload = codec.encode_fixed_width(&ids, 4).unwrap();
+        assert_eq!(payload.len() - overhead(&ids), 2);
+        assert_eq!(codec.decode_fixed_width(&payload, 4).unwrap(), ids);
+    }
+
+    #[test]
+    fn empty_input_round_trips() {
+        let codec = Codec::new();
+        let payload = codec.encode_fixed_width(&[], 8).unwrap();
+        assert!(codec.decode_fixed_width(&payload, 8).unwrap().is_empty());
+    }
+
+    #[test]
+    fn too_narrow_widths_are_rejected() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..17).collect();
+        assert!(matches!(
+            codec.encode_fixed_width(&ids, 4),
+            Err(CodecError::InvalidPayload)
+        ));
+        assert!(codec.encode_fixed_width(&ids, 5).is_ok());
+        assert!(codec.encode_fixed_width(&[1], 3).is_err());
+        assert!(codec.encode_fixed_width(&[1], 33).is_err());
+    }
+
+    #[test]
+    fn decode_checks_width_and_mode() {
+        let codec = Codec::new();
+        let payload = codec.encode_fixed_width(&[1, 2, 3], 8).unwrap();
+        assert!(codec.decode_fixed_width(&payload, 16).is_err());
+        assert!(codec
+            .decode_fixed_width(&payload[..payload.len() - 1], 8)
+            .is_err());
+
+        // Fixed-width bodies are not varint bodies.
+        assert!(CodecCore::decode_token_ids(&payload, false).is_err());
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(codec.decode_fixed_width(&plain, 8).is_err());
+    }
+
+    #[test]
+    fn min_width_is_ceil_log2() {
+        assert_eq!(min_width(1), 0);
+        assert_eq!(min_width(2), 1);
+        assert_eq!(min_width(16), 4);
+        assert_eq!(min_width(17), 5);
+        assert_eq!(min_width(1 << 20), 20);
+    }
+}
@@ -1,4 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+use crate::errors::{CodecError, Result};
+use crate::header::PREFIX_LEN;
+use crate::{varint, zigzag};
+
+/// Leading bytes of `FreqMap::serialize_binary` output.
+const BINARY_MAGIC: &[u8; 4] = b"FMAP";
+
+/// Newest binary format version `FreqMap::deserialize_binary` understands.
+const BINARY_VERSION: u8 = 1;
 
 /// FreqMap holds a *per-payload* mapping between:
 /// - original token IDs (from the tokenizer), and
@@ -9,8 +19,9 @@ use std::collections::HashMap;
 ///   - We count their frequencies.
 ///   - We sort tokens by frequency (desc), then by token ID (asc).
 ///   - We assign mapped IDs 0, 1, 2, ... in that order.
+///
 /// This makes common tokens map to small integers, which helps later zigzag/varint compression.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FreqMap {
     /// original token ID -> mapped ID (0..N-1)
     token_to_mapped: HashMap<i32, i32>,
@@ -19,6 +30,12 @@ pub struct FreqMap {
     ///
     /// invariant: mapped_to_token[mapped_id] == original_token
     mapped_to_token: Vec<i32>,
+
+    /// mapped ID (as index) -> number of times the token was observed
+    counts: Vec<usize>,
+
+    /// Sum of `counts` (i.e. the length of the input slice).
+    total: usize,
 }
 
 impl FreqMap {
@@ -37,8 +54,54 @@ impl FreqMap {
         }
 
         // 2) Collect into a sortable Vec so ordering is deterministic.
-        let mut entries: Vec<(i32, usize)> = counts.into_iter().collect();
+        Self::from_counts(counts.into_iter().collect())
+    }
+
+    /// Build a map from explicit per-token frequencies, where `frequencies[i]`
+    /// is the count of `tokens[i]`. Mapped IDs are assigned exactly as
+    /// `from_token_ids` would for an input with those counts.
+    ///
+    /// Errors with `CodecError::InvalidPayload` if the slices differ in length
+    /// or a token is listed twice.
+    pub fn from_frequencies(tokens: &[i32], frequencies: &[u64]) -> Result<Self> {
+        if tokens.len() != frequencies.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut entries = Vec::with_capacity(tokens.len());
+        let mut seen = HashSet::with_capacity(tokens.len());
+        for (&token, &count) in tokens.iter().zip(frequencies) {
+            let count = usize::try_from(count).map_err(|_| CodecError::InvalidPayload)?;
+            if !seen.insert(token) {
+                return Err(CodecError::InvalidPayload);
+            }
+            entries.push((token, count));
+        }
+        Ok(Self::from_counts(entries))
+    }
+
+    /// Build a map whose mapped IDs follow `tokens` exactly (`tokens[i]` gets
+    /// mapped ID `i`), e.g. to encode against an existing header's ordering.
+    ///
+    /// Frequencies are unknown, so every token is counted once. `tokens` must
+    /// not contain duplicates.
+    pub fn from_ordered_tokens(tokens: &[i32]) -> Self {
+        let token_to_mapped = tokens
+            .iter()
+            .enumerate()
+            .map(|(mapped, &token)| (token, mapped as i32))
+            .collect();
+
+        Self {
+            token_to_mapped,
+            mapped_to_token: tokens.to_vec(),
+            counts: vec![1; tokens.len()],
+            total: tokens.len(),
+        }
+    }
 
+    /// Steps 3 and 4 of `from_token_ids`, given the (token, count) pairs.
+    fn from_counts(mut entries: Vec<(i32, usize)>) -> Self {
         // 3) Sort:
         //    - primary key: frequency descending (higher count first)
         //    - secondary key: token ID ascending (for stable, deterministic ordering)
@@ -50,15 +113,19 @@ impl FreqMap {
         // 4) Assign mapped IDs and build forward & reverse lookups.
         let mut token_to_mapped = HashMap::with_capacity(entries.len());
         let mut mapped_to_token = Vec::with_capacity(entries.len());
+        let mut counts = Vec::with_capacity(entries.len());
 
-        for (mapped_id, (token, _count)) in entries.into_iter().enumerate() {
+        for (mapped_id, (token, count)) in entries.into_iter().enumerate() {
             token_to_mapped.insert(token, mapped_id as i32);
             mapped_to_token.push(token);
+            counts.push(count);
         }
 
         Self {
             token_to_mapped,
             mapped_to_token,
+            total: counts.iter().sum(),
+            counts,
         }
     }
 
@@ -89,6 +156,721 @@ impl FreqMap {
     pub fn ordered_tokens(&self) -> &[i32] {
         &self.mapped_to_token
     }
+
+    /// Returns observation counts in mapped-ID order (so non-increasing).
+    ///
+    /// That is, counts()[i] == how often the token with mapped ID `i` appeared.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Total number of observations the map was built from.
+    pub fn total_observations(&self) -> usize {
+        self.total
+    }
+
+    /// Exact size of the header built from this map: the fixed prefix plus four
+    /// bytes per token.
+    pub fn expected_header_size(&self) -> usize {
+        PREFIX_LEN + self.mapped_to_token.len() * 4
+    }
+
+    /// Body size if every value took the 5-byte worst case.
+    pub fn expected_body_size_upper_bound(&self) -> usize {
+        self.total * 5
+    }
+
+    /// Uncompressed body size: each token's count times the varint length of
+    /// its zigzagged mapped ID. Exact for the input the map was built from.
+    pub fn expected_body_size_estimate(&self) -> usize {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(mapped, &count)| count * varint::encoded_len(zigzag::encode(mapped as i32)))
+            .sum()
+    }
+
+    /// `expected_header_size() + expected_body_size_estimate()`.
+    pub fn expected_total_size_estimate(&self) -> usize {
+        self.expected_header_size() + self.expected_body_size_estimate()
+    }
+
+    /// Empirical probability of every token: count / total observations.
+    pub fn normalize_to_probability(&self) -> HashMap<i32, f64> {
+        self.mapped_to_token
+            .iter()
+            .zip(&self.counts)
+            .map(|(&token, &count)| (token, count as f64 / self.total as f64))
+            .collect()
+    }
+
+    /// Empirical probability of every mapped ID, in mapped-ID order: the
+    /// model `arithmetic::encode_arithmetic` expects. Empty for an empty map.
+    pub fn to_probability_table(&self) -> Vec<f64> {
+        self.counts
+            .iter()
+            .map(|&count| count as f64 / self.total as f64)
+            .collect()
+    }
+
+    /// Natural log of the token's empirical probability.
+    ///
+    /// Returns None if the token never appeared when we built the map.
+    pub fn log_probability(&self, token: i32) -> Option<f64> {
+        let mapped = self.map_token(token)? as usize;
+        Some((self.counts[mapped] as f64 / self.total as f64).ln())
+    }
+
+    /// The `k` most probable tokens with their probabilities, most probable first.
+    ///
+    /// Mapped-ID order is already frequency-descending, so this is just a prefix.
+    pub fn top_k_by_probability(&self, k: usize) -> Vec<(i32, f64)> {
+        self.mapped_to_token
+            .iter()
+            .zip(&self.counts)
+            .take(k)
+            .map(|(&token, &count)| (token, count as f64 / self.total as f64))
+            .collect()
+    }
+
+    /// Fraction of all observations covered by the `k` most frequent tokens.
+    ///
+    /// `k` past the end covers everything; an empty map covers nothing (0.0).
+    pub fn coverage_at_k(&self, k: usize) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let covered: usize = self.counts.iter().take(k).sum();
+        covered as f64 / self.total as f64
+    }
+
+    /// Smallest `k` whose `coverage_at_k` is at least `target_coverage`.
+    ///
+    /// 0 for targets of 0 or below; the full vocabulary size for targets no
+    /// `k` reaches (above 1.0, or any positive target on an empty map).
+    pub fn min_k_for_coverage(&self, target_coverage: f64) -> usize {
+        if target_coverage <= 0.0 {
+            return 0;
+        }
+        self.coverage_curve()
+            .into_iter()
+            .find(|&(_, coverage)| coverage >= target_coverage)
+            .map_or(self.counts.len(), |(k, _)| k)
+    }
+
+    /// `(k, coverage_at_k(k))` for every `k` in `1..=len`, for plotting.
+    pub fn coverage_curve(&self) -> Vec<(usize, f64)> {
+        let mut covered = 0;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                covered += count;
+                (i + 1, covered as f64 / self.total as f64)
+            })
+            .collect()
+    }
+
+    /// Draw a token according to the empirical distribution.
+    ///
+    /// Panics if the map is empty (there is nothing to sample).
+    #[cfg(feature = "rand")]
+    pub fn sample(&self, rng: &mut impl rand::Rng) -> i32 {
+        assert!(self.total > 0, "cannot sample from an empty FreqMap");
+
+        // Pick an observation uniformly, then find which token owns it.
+        let mut remaining = rng.gen_range(0..self.total);
+        for (&token, &count) in self.mapped_to_token.iter().zip(&self.counts) {
+            if remaining < count {
+                return token;
+            }
+            remaining -= count;
+        }
+        unreachable!("counts sum to total")
+    }
+
+    /// Flatten the forward mapping into a dense array indexed by token ID.
+    ///
+    /// Returns a Vec of length `max_token_id + 1` where `table[token] == mapped_id`
+    /// and `-1` marks tokens absent from this map. A flat array is far more
+    /// cache-friendly than the HashMap when token IDs are small and bounded.
+    ///
+    /// Errors with `CodecError::TokenOutOfRange` if any token is negative or
+    /// larger than `max_token_id`.
+    pub fn to_lookup_table(&self, max_token_id: usize) -> Result<Vec<i32>> {
+        let mut table = vec![-1; max_token_id + 1];
+        for (mapped, &token) in self.mapped_to_token.iter().enumerate() {
+            let slot = usize::try_from(token)
+                .ok()
+                .filter(|&idx| idx <= max_token_id)
+                .ok_or(CodecError::TokenOutOfRange {
+                    token,
+                    max: max_token_id,
+                })?;
+            table[slot] = mapped as i32;
+        }
+        Ok(table)
+    }
+
+    /// Rebuild a map from a table produced by `to_lookup_table`.
+    ///
+    /// Tokens are ordered by their stored mapped ID, then by position (token ID)
+    /// on ties, and re-assigned dense mapped IDs 0..N-1. The original
+    /// frequencies are not part of the table, so every token is counted once.
+    pub fn from_lookup_table(arr: &[i32]) -> Self {
+        // (stored mapped ID, token) pairs for every present slot.
+        let mut entries: Vec<(i32, i32)> = arr
+            .iter()
+            .enumerate()
+            .filter(|&(_, &mapped)| mapped != -1)
+            .map(|(token, &mapped)| (mapped, token as i32))
+            .collect();
+        entries.sort();
+
+        let mut token_to_mapped = HashMap::with_capacity(entries.len());
+        let mut mapped_to_token = Vec::with_capacity(entries.len());
+        for (mapped_id, (_, token)) in entries.into_iter().enumerate() {
+            token_to_mapped.insert(token, mapped_id as i32);
+            mapped_to_token.push(token);
+        }
+
+        let len = mapped_to_token.len();
+        Self {
+            token_to_mapped,
+            mapped_to_token,
+            counts: vec![1; len],
+            total: len,
+        }
+    }
+}
+
+/// Accumulates token counts one token (or batch) at a time and builds the
+/// `FreqMap` in a single sort at the end.
+///
+/// `build` assigns mapped IDs exactly as `FreqMap::from_token_ids` would for
+/// the concatenation of everything added.
+#[derive(Debug, Clone, Default)]
+pub struct FreqMapBuilder {
+    counts: HashMap<i32, usize>,
+}
+
+impl FreqMapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, token: i32) {
+        self.add_weighted(token, 1);
+    }
+
+    pub fn add_batch(&mut self, tokens: &[i32]) {
+        for &token in tokens {
+            self.add(token);
+        }
+    }
+
+    /// Count `token` as if it had been added `count` times.
+    pub fn add_weighted(&mut self, token: i32, count: usize) {
+        *self.counts.entry(token).or_insert(0) += count;
+    }
+
+    /// Number of distinct tokens added so far.
+    pub fn approximate_len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn build(self) -> FreqMap {
+        FreqMap::from_counts(self.counts.into_iter().collect())
+    }
+}
+
+impl FromIterator<i32> for FreqMapBuilder {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        let mut builder = Self::new();
+        for token in iter {
+            builder.add(token);
+        }
+        builder
+    }
+}
+
+impl FreqMap {
+    /// Start an incremental `FreqMapBuilder`.
+    pub fn builder() -> FreqMapBuilder {
+        FreqMapBuilder::new()
+    }
+}
+
+/// Compact binary and JSON forms, for storing or shipping a map without the
+/// token stream it was built from.
+///
+/// Layout:
+///   [b"FMAP"][version u8 = 1][varint len]
+///   [len × i32 LE tokens, in mapped-ID order][len × varint u64 counts]
+///
+/// Tokens keep their mapped-ID order, so a round trip reproduces the map
+/// exactly, including maps whose order is not frequency-derived (e.g. from
+/// `from_ordered_tokens`).
+impl FreqMap {
+    pub fn serialize_binary(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.serialized_size());
+        out.extend_from_slice(BINARY_MAGIC);
+        out.push(BINARY_VERSION);
+        // A map never holds more than u32::MAX distinct i32 tokens.
+        out.extend_from_slice(&varint::encode(&[self.mapped_to_token.len() as u32]));
+        for token in &self.mapped_to_token {
+            out.extend_from_slice(&token.to_le_bytes());
+        }
+        let counts: Vec<u64> = self.counts.iter().map(|&count| count as u64).collect();
+        out.extend_from_slice(&varint::encode_u64(&counts));
+        out
+    }
+
+    /// Exact length of `serialize_binary()` output, computed without
+    /// allocating.
+    pub fn serialized_size(&self) -> usize {
+        let len = self.mapped_to_token.len();
+        let counts: usize = self
+            .counts
+            .iter()
+            .map(|&count| varint::encoded_len_u64(count as u64))
+            .sum();
+        BINARY_MAGIC.len() + 1 + varint::encoded_len(len as u32) + len * 4 + counts
+    }
+
+    /// Parse `serialize_binary` output.
+    ///
+    /// Errors with `CodecError::InvalidPayload` on a wrong magic or malformed
+    /// contents, and `CodecError::UnknownVersion` for versions newer than 1.
+    pub fn deserialize_binary(bytes: &[u8]) -> Result<Self> {
+        let rest = bytes
+            .strip_prefix(BINARY_MAGIC)
+            .ok_or(CodecError::InvalidPayload)?;
+        let (&version, rest) = rest.split_first().ok_or(CodecError::InvalidPayload)?;
+        if version > BINARY_VERSION {
+            return Err(CodecError::UnknownVersion(version));
+        }
+
+        let (len, used) = varint::decode_one(rest).map_err(|_| CodecError::InvalidPayload)?;
+        let len = len as usize;
+        let rest = &rest[used..];
+        let table_len = len.checked_mul(4).ok_or(CodecError::InvalidPayload)?;
+        if rest.len() < table_len {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (table, rest) = rest.split_at(table_len);
+
+        let mapped_to_token: Vec<i32> = table
+            .chunks_exact(4)
+            .map(|chunk| i32::from_le_bytes(chunk.try_into().expect("chunk has 4 bytes")))
+            .collect();
+        // The counts run to the end, so trailing bytes show up as extra values.
+        let counts = varint::decode_u64(rest).map_err(|_| CodecError::InvalidPayload)?;
+        if counts.len() != len {
+            return Err(CodecError::InvalidPayload);
+        }
+        let counts = counts
+            .into_iter()
+            .map(usize::try_from)
+            .collect::<std::result::Result<Vec<usize>, _>>()
+            .map_err(|_| CodecError::InvalidPayload)?;
+
+        Self::from_parts(mapped_to_token, counts)
+    }
+
+    /// JSON form: `{"tokens": [...], "frequencies": [...]}`, both in mapped-ID
+    /// order. Round-trips exactly, like `serialize_binary`.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "tokens": self.mapped_to_token,
+            "frequencies": self.counts,
+        })
+        .to_string()
+    }
+
+    /// Parse `to_json` output. Errors with `CodecError::InvalidPayload` on
+    /// malformed JSON, mismatched array lengths or duplicate tokens.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|_| CodecError::InvalidPayload)?;
+        let field = |name: &str| value.get(name).cloned().ok_or(CodecError::InvalidPayload);
+        let tokens: Vec<i32> =
+            serde_json::from_value(field("tokens")?).map_err(|_| CodecError::InvalidPayload)?;
+        let counts: Vec<usize> = serde_json::from_value(field("frequencies")?)
+            .map_err(|_| CodecError::InvalidPayload)?;
+        if tokens.len() != counts.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Self::from_parts(tokens, counts)
+    }
+
+    /// Rebuild a map from its tokens and counts in mapped-ID order, rejecting
+    /// duplicate tokens and overflowing totals.
+    fn from_parts(mapped_to_token: Vec<i32>, counts: Vec<usize>) -> Result<Self> {
+        let mut token_to_mapped = HashMap::with_capacity(mapped_to_token.len());
+        for (mapped, &token) in mapped_to_token.iter().enumerate() {
+            if token_to_mapped.insert(token, mapped as i32).is_some() {
+                return Err(CodecError::InvalidPayload);
+            }
+        }
+        let total = counts
+            .iter()
+            .try_fold(0usize, |acc, &count| acc.checked_add(count))
+            .ok_or(CodecError::InvalidPayload)?;
+
+        Ok(Self {
+            token_to_mapped,
+            mapped_to_token,
+            counts,
+            total,
+        })
+    }
+}
+
+/// The changes between two `FreqMap`s, for shipping vocabulary updates without
+/// resending the whole map.
+///
+/// Produced by `FreqMap::diff` and consumed by `FreqMap::apply_diff`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FreqMapDiff {
+    /// Tokens that are new, or whose count changed, with their new count.
+    pub added: Vec<(i32, usize)>,
+    /// Tokens that no longer appear.
+    pub removed: Vec<i32>,
+    /// `(token, new mapped ID)` for the tokens that have to be pinned. The
+    /// rest keep the relative order of the old map (minus `removed`, plus new
+    /// tokens appended) and fill the remaining slots.
+    pub reordered: Vec<(i32, i32)>,
+}
+
+impl FreqMapDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.reordered.is_empty()
+    }
+}
+
+impl FreqMap {
+    /// Compute the changes that turn `old` into `new`.
+    pub fn diff(old: &FreqMap, new: &FreqMap) -> FreqMapDiff {
+        let removed: Vec<i32> = old
+            .mapped_to_token
+            .iter()
+            .copied()
+            .filter(|token| !new.token_to_mapped.contains_key(token))
+            .collect();
+
+        let added: Vec<(i32, usize)> = new
+            .mapped_to_token
+            .iter()
+            .zip(&new.counts)
+            .filter(|&(token, &count)| {
+                old.map_token(*token)
+                    .is_none_or(|mapped| old.counts[mapped as usize] != count)
+            })
+            .map(|(&token, &count)| (token, count))
+            .collect();
+
+        // Keep the longest run of tokens whose provisional order survives and
+        // pin only the others, so one token moving doesn't pin all it shifts.
+        let provisional = old.provisional_order(&removed, &added);
+        let positions: Vec<usize> = provisional
+            .iter()
+            .map(|token| new.token_to_mapped[token] as usize)
+            .collect();
+        let kept = longest_increasing_run(&positions);
+        let reordered = provisional
+            .iter()
+            .zip(&positions)
+            .zip(kept)
+            .filter(|(_, kept)| !kept)
+            .map(|((&token, &mapped), _)| (token, mapped as i32))
+            .collect();
+
+        FreqMapDiff {
+            added,
+            removed,
+            reordered,
+        }
+    }
+
+    /// Apply a diff produced by `FreqMap::diff(self, new)`, rebuilding `new`.
+    ///
+    /// # Panics
+    ///
+    /// If the diff reorders a token that is neither in `self` nor in
+    /// `diff.added`, which a diff from `FreqMap::diff(self, _)` never does.
+    pub fn apply_diff(&self, diff: &FreqMapDiff) -> FreqMap {
+        let provisional = self.provisional_order(&diff.removed, &diff.added);
+
+        // Pin the reordered tokens, then fill the free slots in provisional order.
+        let mut slots: Vec<Option<i32>> = vec![None; provisional.len()];
+        for &(token, mapped) in &diff.reordered {
+            if let Some(slot) = slots.get_mut(mapped as usize) {
+                *slot = Some(token);
+            }
+        }
+        let pinned: HashMap<i32, i32> = diff.reordered.iter().copied().collect();
+        let mut rest = provisional
+            .into_iter()
+            .filter(|token| !pinned.contains_key(token));
+        let mapped_to_token: Vec<i32> = slots
+            .into_iter()
+            .filter_map(|slot| slot.or_else(|| rest.next()))
+            .collect();
+
+        let updated: HashMap<i32, usize> = diff.added.iter().copied().collect();
+        let counts: Vec<usize> = mapped_to_token
+            .iter()
+            .map(|token| match updated.get(token) {
+                Some(&count) => count,
+                None => {
+                    let mapped = self
+                        .token_to_mapped
+                        .get(token)
+                        .expect("diff reorders a token this map doesn't have");
+                    self.counts[*mapped as usize]
+                }
+            })
+            .collect();
+
+        let token_to_mapped = mapped_to_token
+            .iter()
+            .enumerate()
+            .map(|(mapped, &token)| (token, mapped as i32))
+            .collect();
+
+        Self {
+            token_to_mapped,
+            mapped_to_token,
+            total: counts.iter().sum(),
+            counts,
+        }
+    }
+
+    /// Our order without `removed`, followed by the tokens of `added` we lack.
+    fn provisional_order(&self, removed: &[i32], added: &[(i32, usize)]) -> Vec<i32> {
+        let removed: HashSet<i32> = removed.iter().copied().collect();
+        let mut order: Vec<i32> = self
+            .mapped_to_token
+            .iter()
+            .copied()
+            .filter(|token| !removed.contains(token))
+            .collect();
+        order.extend(
+            added
+                .iter()
+                .map(|&(token, _)| token)
+                .filter(|token| !self.token_to_mapped.contains_key(token)),
+        );
+        order
+    }
+}
+
+/// Which entries of `values` (distinct) form one longest strictly increasing
+/// subsequence, in O(n log n).
+fn longest_increasing_run(values: &[usize]) -> Vec<bool> {
+    // `tails[k]`: index of the smallest value ending a run of length k + 1.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev = vec![usize::MAX; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&j| values[j] < value);
+        if k > 0 {
+            prev[i] = tails[k - 1];
+        }
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut kept = vec![false; values.len()];
+    let mut cur = tails.last().copied().unwrap_or(usize::MAX);
+    while cur != usize::MAX {
+        kept[cur] = true;
+        cur = prev[cur];
+    }
+    kept
+}
+
+/// Set-level vocabulary comparisons. Only token identity matters; counts are
+/// ignored.
+impl FreqMap {
+    /// `|a ∩ b| / |a ∪ b|`. Two empty maps count as identical (1.0).
+    pub fn jaccard_similarity(a: &FreqMap, b: &FreqMap) -> f64 {
+        let shared = Self::shared_tokens(a, b);
+        let union = a.mapped_to_token.len() + b.mapped_to_token.len() - shared;
+        if union == 0 {
+            return 1.0;
+        }
+        shared as f64 / union as f64
+    }
+
+    /// `|a ∩ b| / min(|a|, |b|)`: 1.0 when one vocabulary contains the other.
+    /// Two empty maps count as identical (1.0); otherwise an empty map
+    /// overlaps nothing (0.0).
+    pub fn overlap_coefficient(a: &FreqMap, b: &FreqMap) -> f64 {
+        let smaller = a.mapped_to_token.len().min(b.mapped_to_token.len());
+        if smaller == 0 {
+            return if a.mapped_to_token.len() == b.mapped_to_token.len() {
+                1.0
+            } else {
+                0.0
+            };
+        }
+        Self::shared_tokens(a, b) as f64 / smaller as f64
+    }
+
+    /// Fraction of the tokens in `b_ids` (counting repeats) that `a` contains,
+    /// i.e. how much of that corpus `a` could encode. 1.0 for an empty
+    /// `b_ids`.
+    pub fn coverage_of_b_by_a(a: &FreqMap, b_ids: &[i32]) -> f64 {
+        if b_ids.is_empty() {
+            return 1.0;
+        }
+        let covered = b_ids
+            .iter()
+            .filter(|token| a.token_to_mapped.contains_key(token))
+            .count();
+        covered as f64 / b_ids.len() as f64
+    }
+
+    /// Number of tokens present in both maps.
+    fn shared_tokens(a: &FreqMap, b: &FreqMap) -> usize {
+        let (small, large) = if a.mapped_to_token.len() <= b.mapped_to_token.len() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        small
+            .mapped_to_token
+            .iter()
+            .filter(|token| large.token_to_mapped.contains_key(token))
+            .count()
+    }
+}
+
+/// Combining maps from several sources.
+impl FreqMap {
+    /// Target total of `normalize_frequencies`.
+    pub const NORMALIZED_TOTAL: usize = 1_000_000;
+
+    /// Sum of both maps' counts, as if their token streams were concatenated.
+    pub fn merge(a: &FreqMap, b: &FreqMap) -> FreqMap {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for map in [a, b] {
+            for (&token, &count) in map.mapped_to_token.iter().zip(&map.counts) {
+                *counts.entry(token).or_insert(0) += count;
+            }
+        }
+        Self::from_counts(counts.into_iter().collect())
+    }
+
+    /// Sum of every map's counts times its weight, each token's total rounded
+    /// to the nearest integer. Tokens whose total rounds to 0 are dropped, so
+    /// a map with weight 0 contributes nothing.
+    ///
+    /// # Panics
+    ///
+    /// If a weight is negative or not finite.
+    pub fn merge_weighted(maps: &[(&FreqMap, f64)]) -> FreqMap {
+        let mut weighted: HashMap<i32, f64> = HashMap::new();
+        for &(map, weight) in maps {
+            assert!(
+                weight.is_finite() && weight >= 0.0,
+                "merge weights must be finite and non-negative, got {weight}"
+            );
+            for (&token, &count) in map.mapped_to_token.iter().zip(&map.counts) {
+                *weighted.entry(token).or_insert(0.0) += count as f64 * weight;
+            }
+        }
+
+        let entries = weighted
+            .into_iter()
+            .map(|(token, count)| (token, count.round() as usize))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        Self::from_counts(entries)
+    }
+
+    /// Same tokens with counts scaled to total exactly `NORMALIZED_TOTAL`, a
+    /// canonical fixed-point form for comparing maps built from corpora of
+    /// different sizes. Rounding uses largest remainders, so counts stay
+    /// within 1 of the exact share and the ordering is rebuilt from them.
+    pub fn normalize_frequencies(&self) -> FreqMap {
+        if self.total == 0 {
+            return self.clone();
+        }
+
+        let target = Self::NORMALIZED_TOTAL as u128;
+        let total = self.total as u128;
+        let mut scaled: Vec<(usize, u128)> = self
+            .counts
+            .iter()
+            .map(|&count| {
+                let exact = count as u128 * target;
+                ((exact / total) as usize, exact % total)
+            })
+            .collect();
+
+        let assigned: usize = scaled.iter().map(|&(count, _)| count).sum();
+        let mut by_remainder: Vec<usize> = (0..scaled.len()).collect();
+        by_remainder.sort_by(|&a, &b| scaled[b].1.cmp(&scaled[a].1).then(a.cmp(&b)));
+        for &i in by_remainder.iter().take(Self::NORMALIZED_TOTAL - assigned) {
+            scaled[i].0 += 1;
+        }
+
+        let entries = self
+            .mapped_to_token
+            .iter()
+            .zip(scaled)
+            .map(|(&token, (count, _))| (token, count))
+            .collect();
+        Self::from_counts(entries)
+    }
+}
+
+/// Interop with pre-trained vocabularies whose token order is fixed.
+impl FreqMap {
+    /// Same tokens and counts, with mapped IDs following `external_order`:
+    /// the map's tokens that appear there come first, in its order, then the
+    /// rest in this map's (frequency) order. Tokens of `external_order` that
+    /// never occurred are skipped, so mapped IDs stay dense.
+    ///
+    /// Errors with `CodecError::InvalidPayload` if `external_order` repeats a
+    /// token.
+    pub fn reorder_by_external_rank(&self, external_order: &[i32]) -> Result<FreqMap> {
+        let mut seen = HashSet::with_capacity(external_order.len());
+        if !external_order.iter().all(|&token| seen.insert(token)) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let ranked = external_order
+            .iter()
+            .copied()
+            .filter(|token| self.token_to_mapped.contains_key(token));
+        let unranked = self
+            .mapped_to_token
+            .iter()
+            .copied()
+            .filter(|token| !seen.contains(token));
+
+        let (tokens, counts) = ranked
+            .chain(unranked)
+            .map(|token| (token, self.counts[self.token_to_mapped[&token] as usize]))
+            .unzip();
+        Self::from_parts(tokens, counts)
+    }
+
+    /// Whether this map's mapped IDs already follow `external_order`: its
+    /// first tokens are `external_order`'s first, one for one, up to the
+    /// shorter of the two.
+    pub fn is_compatible_with_external(&self, external_order: &[i32]) -> bool {
+        self.mapped_to_token
+            .iter()
+            .zip(external_order)
+            .all(|(ours, theirs)| ours == theirs)
+    }
 }
 
 #[cfg(test)]
@@ -132,4 +914,503 @@ mod tests {
         assert_eq!(fm.map_token(20), Some(2));
         assert_eq!(fm.ordered_tokens(), &[5, 10, 20]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn probabilities_sum_to_one() {
+        let ids = [4, 4, 4, 9, 9, 1, 7, 7, 7, 7];
+        let fm = FreqMap::from_token_ids(&ids);
+
+        let probs = fm.normalize_to_probability();
+        let sum: f64 = probs.values().sum();
+        assert!((sum - 1.0).abs() < 1e-12);
+
+        assert_eq!(probs[&7], 0.4);
+        assert_eq!(probs[&1], 0.1);
+        assert_eq!(fm.counts(), &[4, 3, 2, 1]);
+        assert_eq!(fm.total_observations(), 10);
+    }
+
+    #[test]
+    fn probability_table_follows_mapped_ids() {
+        let fm = FreqMap::from_token_ids(&[4, 4, 4, 9, 9, 1, 7, 7, 7, 7]);
+        assert_eq!(fm.to_probability_table(), [0.4, 0.3, 0.2, 0.1]);
+        assert!(FreqMap::from_token_ids(&[]).to_probability_table().is_empty());
+    }
+
+    #[test]
+    fn log_probability_and_top_k() {
+        let ids = [1, 2, 1, 3, 2, 1];
+        let fm = FreqMap::from_token_ids(&ids);
+
+        assert!((fm.log_probability(1).unwrap() - 0.5f64.ln()).abs() < 1e-12);
+        assert_eq!(fm.log_probability(99), None);
+
+        let top = fm.top_k_by_probability(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, 1);
+        assert_eq!(top[1].0, 2);
+        assert!(top[0].1 >= top[1].1);
+
+        // Asking for more than exists returns everything.
+        assert_eq!(fm.top_k_by_probability(10).len(), 3);
+    }
+
+    #[test]
+    fn empty_map_has_no_probabilities() {
+        let fm = FreqMap::from_token_ids(&[]);
+        assert!(fm.normalize_to_probability().is_empty());
+        assert!(fm.top_k_by_probability(3).is_empty());
+        assert_eq!(fm.log_probability(0), None);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn sample_follows_distribution() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        // 9 has probability 0.75, 2 has 0.25.
+        let fm = FreqMap::from_token_ids(&[9, 9, 9, 2]);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let draws = 10_000;
+        let nines = (0..draws).filter(|_| fm.sample(&mut rng) == 9).count();
+        let ratio = nines as f64 / draws as f64;
+        assert!((ratio - 0.75).abs() < 0.03, "ratio {ratio}");
+    }
+
+    #[test]
+    fn lookup_table_round_trip() {
+        let ids = [3, 3, 3, 0, 7, 7, 5];
+        let fm = FreqMap::from_token_ids(&ids);
+
+        let table = fm.to_lookup_table(8).unwrap();
+        assert_eq!(table, vec![2, -1, -1, 0, -1, 3, -1, 1, -1]);
+
+        let rebuilt = FreqMap::from_lookup_table(&table);
+        assert_eq!(rebuilt.ordered_tokens(), fm.ordered_tokens());
+        for &token in &ids {
+            assert_eq!(rebuilt.map_token(token), fm.map_token(token));
+        }
+    }
+
+    #[test]
+    fn lookup_table_rejects_out_of_range_tokens() {
+        let fm = FreqMap::from_token_ids(&[1, 10]);
+        assert!(matches!(
+            fm.to_lookup_table(9),
+            Err(CodecError::TokenOutOfRange { token: 10, max: 9 })
+        ));
+
+        let negative = FreqMap::from_token_ids(&[-1]);
+        assert!(negative.to_lookup_table(100).is_err());
+    }
+
+    #[test]
+    fn from_lookup_table_breaks_ties_by_position() {
+        // Slots 4 and 2 both claim mapped ID 0; the lower token ID goes first.
+        let fm = FreqMap::from_lookup_table(&[-1, 5, 0, -1, 0]);
+        assert_eq!(fm.ordered_tokens(), &[2, 4, 1]);
+    }
+
+    #[test]
+    fn from_frequencies_matches_from_token_ids() {
+        let fm = FreqMap::from_token_ids(&[4, 4, 4, 9, 9, 1, 7, 7, 7, 7]);
+        let rebuilt = FreqMap::from_frequencies(&[1, 9, 7, 4], &[1, 2, 4, 3]).unwrap();
+        assert_eq!(rebuilt, fm);
+
+        assert!(FreqMap::from_frequencies(&[1, 2], &[1]).is_err());
+        assert!(FreqMap::from_frequencies(&[1, 1], &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn size_estimates_match_actual_encoding() {
+        use crate::codec_core::CodecCore;
+        use crate::header::Header;
+
+        let inputs: [Vec<i32>; 3] = [
+            vec![],
+            vec![5, 5, 5, 1],
+            (0..5000).map(|i| (i * 7919) % 3001 - 1500).collect(),
+        ];
+        for ids in &inputs {
+            let fm = FreqMap::from_token_ids(ids);
+            let header = Header::from_freq_map(&fm).encode().len();
+            let payload = CodecCore::encode_token_ids(ids, false).unwrap();
+
+            assert_eq!(fm.expected_header_size(), header);
+            assert_eq!(fm.expected_body_size_estimate(), payload.len() - header);
+            assert_eq!(fm.expected_total_size_estimate(), payload.len());
+            assert!(fm.expected_body_size_upper_bound() >= payload.len() - header);
+        }
+    }
+
+    #[test]
+    fn diff_of_identical_maps_is_empty() {
+        let fm = FreqMap::from_token_ids(&[1, 2, 1, 3, 2, 1]);
+        assert!(FreqMap::diff(&fm, &fm.clone()).is_empty());
+    }
+
+    #[test]
+    fn empty_diff_is_identity() {
+        let fm = FreqMap::from_token_ids(&[8, 8, -2, 40]);
+        assert_eq!(fm.apply_diff(&FreqMapDiff::default()), fm);
+    }
+
+    #[test]
+    fn diff_and_apply_round_trip() {
+        let old_ids: Vec<i32> = (0..500).map(|i| (i * 7) % 97).collect();
+        let mut new_ids = old_ids.clone();
+        new_ids.retain(|&t| t != 11 && t != 42);
+        new_ids.extend([1000, 1000, 1001, 5, 5, 5, 5, 5, 5, 5, 5]);
+
+        let old = FreqMap::from_token_ids(&old_ids);
+        let new = FreqMap::from_token_ids(&new_ids);
+        let diff = FreqMap::diff(&old, &new);
+
+        assert_eq!(diff.removed.len(), 2);
+        assert_eq!(old.apply_diff(&diff), new);
+
+        // A handful of changes is far cheaper than resending every token.
+        let entries = diff.added.len() + diff.removed.len() + diff.reordered.len();
+        assert!(entries < new.ordered_tokens().len() / 2, "{entries} entries");
+    }
+
+    #[test]
+    fn diff_between_unrelated_maps_round_trips() {
+        let old = FreqMap::from_token_ids(&[1, 1, 2, 3]);
+        let new = FreqMap::from_token_ids(&[9, 3, 3, 3, 1, 7]);
+        assert_eq!(old.apply_diff(&FreqMap::diff(&old, &new)), new);
+
+        let empty = FreqMap::from_token_ids(&[]);
+        assert_eq!(old.apply_diff(&FreqMap::diff(&old, &empty)), empty);
+        assert_eq!(empty.apply_diff(&FreqMap::diff(&empty, &old)), old);
+    }
+
+    #[test]
+    fn diff_pins_only_the_tokens_that_moved() {
+        let old_ids: Vec<i32> = (0..100).flat_map(|t| vec![t; 200 - t as usize]).collect();
+        let old = FreqMap::from_token_ids(&old_ids);
+
+        // A new token that becomes the most frequent.
+        let mut with_new = old_ids.clone();
+        with_new.extend([-1; 500]);
+        let new = FreqMap::from_token_ids(&with_new);
+        let diff = FreqMap::diff(&old, &new);
+        assert_eq!((diff.added.len(), diff.reordered.len()), (1, 1), "{diff:?}");
+        assert_eq!(old.apply_diff(&diff), new);
+
+        // An existing token bumped to rank 0.
+        let mut bumped = old_ids.clone();
+        bumped.extend([73; 500]);
+        let new = FreqMap::from_token_ids(&bumped);
+        let diff = FreqMap::diff(&old, &new);
+        assert_eq!((diff.added.len(), diff.reordered.len()), (1, 1), "{diff:?}");
+        assert_eq!(old.apply_diff(&diff), new);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't have")]
+    fn diff_from_another_map_panics() {
+        let base = FreqMap::from_token_ids(&[1, 1, 2]);
+        // Token 99 is neither in `base` nor added.
+        let foreign = FreqMapDiff {
+            reordered: vec![(99, 0)],
+            ..FreqMapDiff::default()
+        };
+        base.apply_diff(&foreign);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let ids: Vec<i32> = (0..5000).map(|i| (i * 7919) % 1301 - 600).collect();
+        for fm in [
+            FreqMap::from_token_ids(&ids),
+            FreqMap::from_token_ids(&[]),
+            FreqMap::from_ordered_tokens(&[9, -4, 100]),
+        ] {
+            let bytes = fm.serialize_binary();
+            assert_eq!(&bytes[..4], b"FMAP");
+            assert_eq!(bytes.len(), fm.serialized_size());
+            assert_eq!(FreqMap::deserialize_binary(&bytes).unwrap(), fm);
+        }
+    }
+
+    #[test]
+    fn binary_layout() {
+        let fm = FreqMap::from_token_ids(&[7, 7, -1]);
+        let bytes = fm.serialize_binary();
+        assert_eq!(
+            bytes,
+            [b'F', b'M', b'A', b'P', 1, 2, 7, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 2, 1]
+        );
+    }
+
+    #[test]
+    fn binary_rejects_bad_input() {
+        let mut bytes = FreqMap::from_token_ids(&[1, 2, 2]).serialize_binary();
+
+        bytes[0] = b'X';
+        assert!(matches!(
+            FreqMap::deserialize_binary(&bytes),
+            Err(CodecError::InvalidPayload)
+        ));
+        bytes[0] = b'F';
+
+        bytes[4] = 2;
+        assert!(matches!(
+            FreqMap::deserialize_binary(&bytes),
+            Err(CodecError::UnknownVersion(2))
+        ));
+        bytes[4] = 1;
+
+        assert!(FreqMap::deserialize_binary(&bytes[..bytes.len() - 1]).is_err());
+        bytes.push(0);
+        assert!(FreqMap::deserialize_binary(&bytes).is_err());
+
+        // The same token twice.
+        let dup = FreqMap::from_ordered_tokens(&[3, 3]).serialize_binary();
+        assert!(FreqMap::deserialize_binary(&dup).is_err());
+    }
+
+    #[test]
+    fn builder_matches_from_token_ids() {
+        let ids: Vec<i32> = (0..3000).map(|i| (i * 7919) % 211 - 100).collect();
+
+        let mut builder = FreqMap::builder();
+        for &token in &ids[..10] {
+            builder.add(token);
+        }
+        builder.add_batch(&ids[10..]);
+        assert_eq!(builder.build(), FreqMap::from_token_ids(&ids));
+
+        let collected: FreqMapBuilder = ids.iter().copied().collect();
+        assert_eq!(collected.build(), FreqMap::from_token_ids(&ids));
+    }
+
+    #[test]
+    fn builder_weights_and_len() {
+        let mut builder = FreqMapBuilder::default();
+        assert_eq!(builder.approximate_len(), 0);
+
+        builder.add_weighted(4, 3);
+        builder.add(9);
+        builder.add(4);
+        assert_eq!(builder.approximate_len(), 2);
+
+        let fm = builder.build();
+        assert_eq!(fm, FreqMap::from_token_ids(&[4, 4, 4, 9, 4]));
+        assert_eq!(fm.counts(), [4, 1]);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let fm = FreqMap::from_token_ids(&[3, 3, -8, 12, 3, 12]);
+        let json = fm.to_json();
+        assert_eq!(json, r#"{"frequencies":[3,2,1],"tokens":[3,12,-8]}"#);
+        assert_eq!(FreqMap::from_json(&json).unwrap(), fm);
+
+        let ordered = FreqMap::from_ordered_tokens(&[5, 1]);
+        assert_eq!(FreqMap::from_json(&ordered.to_json()).unwrap(), ordered);
+    }
+
+    #[test]
+    fn json_rejects_bad_input() {
+        for json in [
+            "not json",
+            r#"{"tokens":[1,2]}"#,
+            r#"{"tokens":[1,2],"frequencies":[1]}"#,
+            r#"{"tokens":[1,1],"frequencies":[1,1]}"#,
+            r#"{"tokens":[1.5],"frequencies":[1]}"#,
+        ] {
+            assert!(FreqMap::from_json(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn coverage_at_the_ends() {
+        let freq = FreqMap::from_token_ids(&[1, 1, 1, 1, 2, 2, 3, 4]);
+        assert_eq!(freq.coverage_at_k(0), 0.0);
+        assert_eq!(freq.coverage_at_k(1), 0.5);
+        assert_eq!(freq.coverage_at_k(2), 0.75);
+        assert_eq!(freq.coverage_at_k(4), 1.0);
+        assert_eq!(freq.coverage_at_k(100), 1.0);
+        assert_eq!(FreqMap::from_token_ids(&[]).coverage_at_k(3), 0.0);
+    }
+
+    #[test]
+    fn coverage_curve_is_non_decreasing() {
+        let ids: Vec<i32> = (0..5000).map(|i| (i * i) % 211).collect();
+        let freq = FreqMap::from_token_ids(&ids);
+        let curve = freq.coverage_curve();
+
+        assert_eq!(curve.len(), freq.ordered_tokens().len());
+        assert!(curve.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(curve.iter().all(|&(k, c)| c == freq.coverage_at_k(k)));
+        assert_eq!(curve.last().unwrap().1, 1.0);
+    }
+
+    #[test]
+    fn min_k_for_coverage_picks_the_smallest_k() {
+        let freq = FreqMap::from_token_ids(&[1, 1, 1, 1, 2, 2, 3, 4]);
+        assert_eq!(freq.min_k_for_coverage(0.0), 0);
+        assert_eq!(freq.min_k_for_coverage(0.5), 1);
+        assert_eq!(freq.min_k_for_coverage(0.51), 2);
+        assert_eq!(freq.min_k_for_coverage(0.9), 4);
+        assert_eq!(freq.min_k_for_coverage(1.0), 4);
+        assert_eq!(freq.min_k_for_coverage(1.5), 4);
+        assert_eq!(FreqMap::from_token_ids(&[]).min_k_for_coverage(0.5), 0);
+    }
+
+    #[test]
+    fn similarity_of_disjoint_vocabularies() {
+        let a = FreqMap::from_token_ids(&[1, 2, 3, 1]);
+        let b = FreqMap::from_token_ids(&[4, 5, 6]);
+        assert_eq!(FreqMap::jaccard_similarity(&a, &b), 0.0);
+        assert_eq!(FreqMap::overlap_coefficient(&a, &b), 0.0);
+        assert_eq!(FreqMap::coverage_of_b_by_a(&a, &[4, 5, 6]), 0.0);
+    }
+
+    #[test]
+    fn similarity_of_identical_vocabularies() {
+        // Same tokens, different counts: only identity matters.
+        let a = FreqMap::from_token_ids(&[1, 2, 3]);
+        let b = FreqMap::from_token_ids(&[3, 3, 2, 1, 1, 1]);
+        assert_eq!(FreqMap::jaccard_similarity(&a, &b), 1.0);
+        assert_eq!(FreqMap::overlap_coefficient(&a, &b), 1.0);
+        assert_eq!(FreqMap::coverage_of_b_by_a(&a, &[3, 3, 2, 1]), 1.0);
+
+        let empty = FreqMap::from_token_ids(&[]);
+        assert_eq!(FreqMap::jaccard_similarity(&empty, &empty), 1.0);
+        assert_eq!(FreqMap::overlap_coefficient(&empty, &empty), 1.0);
+        assert_eq!(FreqMap::overlap_coefficient(&a, &empty), 0.0);
+        assert_eq!(FreqMap::coverage_of_b_by_a(&empty, &[]), 1.0);
+    }
+
+    #[test]
+    fn similarity_of_half_overlapping_vocabularies() {
+        // a = {0..4}, b = {2..6}: 2 shared out of 6 distinct.
+        let a = FreqMap::from_token_ids(&[0, 1, 2, 3]);
+        let b = FreqMap::from_token_ids(&[2, 3, 4, 5]);
+        assert!((FreqMap::jaccard_similarity(&a, &b) - 2.0 / 6.0).abs() < 1e-12);
+        assert_eq!(FreqMap::overlap_coefficient(&a, &b), 0.5);
+        assert_eq!(FreqMap::coverage_of_b_by_a(&a, &[2, 3, 4, 5]), 0.5);
+        // Repeats count towards coverage.
+        assert_eq!(FreqMap::coverage_of_b_by_a(&a, &[2, 2, 2, 9]), 0.75);
+
+        // A subset overlaps fully even though Jaccard does not.
+        let subset = FreqMap::from_token_ids(&[0, 1]);
+        assert_eq!(FreqMap::overlap_coefficient(&a, &subset), 1.0);
+        assert_eq!(FreqMap::jaccard_similarity(&a, &subset), 0.5);
+    }
+
+    #[test]
+    fn merge_weighted_with_unit_weights_is_merge() {
+        let a = FreqMap::from_token_ids(&[1, 1, 2, 3, 3, 3]);
+        let b = FreqMap::from_token_ids(&[3, 4, 4, 2]);
+        let merged = FreqMap::merge(&a, &b);
+        assert_eq!(FreqMap::merge_weighted(&[(&a, 1.0), (&b, 1.0)]), merged);
+        assert_eq!(
+            merged,
+            FreqMap::from_token_ids(&[1, 1, 2, 3, 3, 3, 3, 4, 4, 2])
+        );
+    }
+
+    #[test]
+    fn zero_weight_drops_a_map() {
+        let a = FreqMap::from_token_ids(&[1, 1, 2, 3, 3, 3]);
+        let b = FreqMap::from_token_ids(&[7, 8, 8, 2]);
+        assert_eq!(FreqMap::merge_weighted(&[(&a, 1.0), (&b, 0.0)]), a);
+        assert_eq!(FreqMap::merge_weighted(&[(&a, 0.0), (&b, 1.0)]), b);
+        assert_eq!(FreqMap::merge_weighted(&[]), FreqMap::from_token_ids(&[]));
+    }
+
+    #[test]
+    fn weights_rebalance_sources() {
+        // A large corpus dominated by 1, a small one dominated by 2.
+        let large = FreqMap::from_token_ids(&[vec![1; 900], vec![2; 100]].concat());
+        let small = FreqMap::from_token_ids(&[vec![2; 9], vec![1; 1]].concat());
+
+        let plain = FreqMap::merge(&large, &small);
+        assert_eq!(plain.ordered_tokens(), [1, 2]);
+
+        // 2: 100 * 0.005 + 9 = 9.5, and 1: 900 * 0.005 + 1 = 5.5, rounded.
+        let rebalanced = FreqMap::merge_weighted(&[(&large, 0.005), (&small, 1.0)]);
+        assert_eq!(rebalanced.ordered_tokens(), [2, 1]);
+        assert_eq!(rebalanced.counts(), [10, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "non-negative")]
+    fn negative_weights_panic() {
+        let a = FreqMap::from_token_ids(&[1]);
+        FreqMap::merge_weighted(&[(&a, -1.0)]);
+    }
+
+    #[test]
+    fn normalized_counts_total_one_million() {
+        let fm = FreqMap::from_token_ids(&[1, 1, 1, 2, 2, 3]);
+        let normalized = fm.normalize_frequencies();
+        assert_eq!(normalized.total_observations(), FreqMap::NORMALIZED_TOTAL);
+        assert_eq!(normalized.counts(), [500_000, 333_333, 166_667]);
+        assert_eq!(normalized.ordered_tokens(), fm.ordered_tokens());
+
+        // Thirds don't divide evenly; largest remainders absorb the slack.
+        let thirds = FreqMap::from_token_ids(&[4, 5, 6]).normalize_frequencies();
+        assert_eq!(thirds.counts(), [333_334, 333_333, 333_333]);
+        assert_eq!(thirds.total_observations(), FreqMap::NORMALIZED_TOTAL);
+
+        let empty = FreqMap::from_token_ids(&[]);
+        assert_eq!(empty.normalize_frequencies(), empty);
+    }
+
+    /// GPT-2 style: byte-level tokens first (IDs 0..256 in vocabulary order),
+    /// then merges, with `"<|endoftext|>"` last at 50256.
+    fn gpt2_order() -> Vec<i32> {
+        (0..50_257).collect()
+    }
+
+    #[test]
+    fn reorder_follows_the_external_ranking() {
+        // " the" (262), "," (11), "." (13), "<|endoftext|>" (50256).
+        let ids = [262, 11, 262, 13, 262, 11, 50_256, 99_999];
+        let fm = FreqMap::from_token_ids(&ids);
+        assert_eq!(fm.ordered_tokens(), [262, 11, 13, 50_256, 99_999]);
+
+        let reordered = fm.reorder_by_external_rank(&gpt2_order()).unwrap();
+        // Ranked tokens in vocabulary order, then the out-of-vocabulary one.
+        assert_eq!(reordered.ordered_tokens(), [11, 13, 262, 50_256, 99_999]);
+        assert_eq!(reordered.counts(), [2, 1, 3, 1, 1]);
+        assert_eq!(reordered.total_observations(), ids.len());
+        assert_eq!(reordered.map_token(262), Some(2));
+
+        assert!(reordered.is_compatible_with_external(&[11, 13, 262]));
+        assert!(!fm.is_compatible_with_external(&[11, 13, 262]));
+    }
+
+    #[test]
+    fn unranked_tokens_keep_frequency_order() {
+        let fm = FreqMap::from_token_ids(&[5, 5, 5, 6, 6, 7, 8]);
+        let reordered = fm.reorder_by_external_rank(&[8, 100]).unwrap();
+        assert_eq!(reordered.ordered_tokens(), [8, 5, 6, 7]);
+        assert_eq!(fm.reorder_by_external_rank(&[]).unwrap(), fm);
+    }
+
+    #[test]
+    fn duplicate_external_tokens_are_rejected() {
+        let fm = FreqMap::from_token_ids(&[1, 2]);
+        assert!(matches!(
+            fm.reorder_by_external_rank(&[2, 1, 2]),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn compatibility_covers_the_shorter_prefix() {
+        let fm = FreqMap::from_ordered_tokens(&[3, 1, 2]);
+        assert!(fm.is_compatible_with_external(&[3, 1, 2, 9, 10]));
+        assert!(fm.is_compatible_with_external(&[3]));
+        assert!(fm.is_compatible_with_external(&[]));
+        assert!(!fm.is_compatible_with_external(&[3, 2]));
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::{Header, FLAG_FUSED_HEADER, FORMAT_VERSION};
+use crate::{varint, zigzag, Codec};
+
+/// Fused-header payloads for short sequences, where the token table would
+/// outweigh the body: tokens are numbered in order of first appearance and
+/// each one is defined inline the first time it occurs.
+///
+/// Layout (header carries the `FLAG_FUSED_HEADER` mode and no token table):
+///   [header][one entry per token]
+///
+/// An entry is a varint: 0 is the sentinel for a new token and is followed by
+/// the zigzagged token as a varint; `k > 0` repeats the token first defined
+/// as number `k - 1`. Numbering by first appearance means no table is needed,
+/// and the sentinel costs a single byte.
+impl Codec {
+    pub fn encode_fused(&self, token_ids: &[i32]) -> Result<Vec<u8>> {
+        let mut numbers: HashMap<i32, u32> = HashMap::new();
+        let mut values = Vec::with_capacity(token_ids.len());
+        for &token in token_ids {
+            match numbers.get(&token) {
+                Some(&number) => values.push(number + 1),
+                None => {
+                    numbers.insert(token, to_u32(numbers.len())?);
+                    values.push(0);
+                    values.push(zigzag::encode(token));
+                }
+            }
+        }
+
+        let header = Header::new(FORMAT_VERSION, FLAG_FUSED_HEADER, Vec::new());
+        let mut out = header.encode();
+        out.extend_from_slice(&varint::encode(&values));
+        Ok(out)
+    }
+
+    pub fn decode_fused(&self, payload: &[u8]) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_FUSED_HEADER || !parts.header.tokens.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut rest = parts.body;
+        let mut defined = Vec::new();
+        let mut tokens = Vec::new();
+        while !rest.is_empty() {
+            let token = match read_varint(&mut rest)? {
+                0 => {
+                    let token = zigzag::decode(read_varint(&mut rest)?);
+                    defined.push(token);
+                    token
+                }
+                number => *defined
+                    .get(number as usize - 1)
+                    .ok_or(CodecError::InvalidPayload)?,
+            };
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fused_round_trips() {
+        let codec = Codec::new();
+        for ids in [
+            vec![],
+            vec![7],
+            vec![3, 3, 3],
+            vec![i32::MIN, 0, i32::MAX, 0, i32::MIN],
+            (0..300).map(|i| i % 131 - 60).collect(),
+        ] {
+            let payload = codec.encode_fused(&ids).unwrap();
+            assert_eq!(payload[1], FLAG_FUSED_HEADER);
+            assert_eq!(codec.decode_fused(&payload).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn known_layout() {
+        let payload = Codec::new().encode_fused(&[5, -1, 5]).unwrap();
+        assert_eq!(
+            payload,
+            [
+                FORMAT_VERSION,
+                FLAG_FUSED_HEADER,
+                0,
+                0,
+                0,
+                0,
+                0,
+                10,
+                0,
+                1,
+                1
+            ]
+        );
+    }
+
+    #[test]
+    fn short_sequences_beat_the_standard_format() {
+        let codec = Codec::new();
+        for n in [1, 4, 8, 16] {
+            // BPE-sized IDs, with a repeat in all but the shortest.
+            let ids: Vec<i32> = (0..n).map(|i| 1000 + (i * 7919) % (n - n / 4)).collect();
+            let fused = codec.encode_fused(&ids).unwrap();
+            let standard = CodecCore::encode_token_ids(&ids, false).unwrap();
+            assert!(
+                fused.len() < standard.len(),
+                "{n} tokens: {} vs {}",
+                fused.len(),
+                standard.len()
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        let codec = Codec::new();
+        let header = Header::new(FORMAT_VERSION, FLAG_FUSED_HEADER, Vec::new()).encode();
+
+        // A reference to a token that was never defined.
+        let mut undefined = header.clone();
+        undefined.extend_from_slice(&[0, 4, 2]);
+        assert!(codec.decode_fused(&undefined).is_err());
+
+        // A definition cut short.
+        let mut truncated = header;
+        truncated.push(0);
+        assert!(codec.decode_fused(&truncated).is_err());
+
+        let plain = CodecCore::encode_token_ids(&[1, 2], false).unwrap();
+        assert!(codec.decode_fused(&plain).is_err());
+    }
+}
@@ -1,6 +1,261 @@
+use std::cell::OnceCell;
+use std::collections::HashSet;
+
 use anyhow::{bail, Result};
 use crate::freq_map::FreqMap;
 
+/// Current wire-format version written by `Header::encode`.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Every format version this build can read, oldest first.
+///
+///   0 : `[u32 len][tokens][body]`, no version byte or flags; gzip wrapped the
+///       whole payload.
+///   1 : `[version][flags][u32 len][tokens][body][sections]`; gzip wraps only
+///       the body.
+pub const VERSION_HISTORY: &[u8] = &[0, 1];
+
+/// Header flag: a metadata section (key/value annotations) follows the body.
+pub const FLAG_METADATA_PRESENT: u8 = 0x40;
+
+/// Header flag: a schema section (tensor shape) follows the body.
+pub const FLAG_SCHEMA_PRESENT: u8 = 0x80;
+
+/// Header flag: tagged extension sections follow all other sections (see
+/// `CodecCore::push_extensions`). Individual extensions are identified by the
+/// extension tags below (e.g. `FLAG_OFFSETS_PRESENT`), not by header bits.
+pub const FLAG_EXTENSIONS: u8 = 0x20;
+
+/// Flags whose sections are appended after the body, in append order.
+///
+/// Decoders peel sections off the end of the payload in reverse order, so every
+/// new section flag must be added here to keep plain decoding working.
+pub const SECTION_FLAGS: &[u8] = &[FLAG_METADATA_PRESENT, FLAG_SCHEMA_PRESENT];
+
+/// Extension tag: delta-encoded byte offsets, one per token.
+pub const FLAG_OFFSETS_PRESENT: u8 = 0x01;
+
+/// Extension tag: attention mask, one bit per token.
+pub const FLAG_ATTENTION_MASK: u8 = 0x02;
+
+/// Extension tag: name and version of the model that produced the tokens.
+pub const FLAG_MODEL_INFO: u8 = 0x03;
+
+/// Extension tag: which tokens were retrieved, one bit per token.
+pub const FLAG_RETRIEVED_MASK: u8 = 0x04;
+
+/// Extension tag: f16 relevance score per token.
+pub const FLAG_RELEVANCE_SCORES: u8 = 0x05;
+
+/// Extension tag: f16 position bias (e.g. ALiBi), one value per position.
+pub const FLAG_POSITION_BIAS: u8 = 0x06;
+
+/// Extension tag: varint lengths of the concatenated segments (documents).
+pub const FLAG_SEGMENTS: u8 = 0x07;
+
+/// Extension tag: one XOR parity byte per 64-byte block of the header and
+/// body (`ecc`).
+pub const FLAG_ECC: u8 = 0x08;
+
+/// Extension tag: the top-p part of each token's logits, f16-quantized
+/// (`logits`).
+pub const FLAG_LOGITS: u8 = 0x09;
+
+/// Extension tag: f16 key and value tensors of a transformer KV cache
+/// (`kv_cache`).
+pub const FLAG_KV_CACHE: u8 = 0x0A;
+
+/// Extension tag: a Bloom filter over the distinct tokens (`bloom`).
+pub const FLAG_BLOOM_FILTER: u8 = 0x0B;
+
+/// Extension tag: delta-encoded positions of the masked tokens of an MLM
+/// input (`mlm`).
+pub const FLAG_MASKED_POSITIONS: u8 = 0x0C;
+
+/// The low five flag bits are not independent flags: together they hold a
+/// *mode code* selecting the payload's body (or header) layout. Code 0 is the
+/// standard layout, and modes are mutually exclusive. Mode constants are
+/// written as `FLAG_*` values inside this mask.
+pub const FLAG_MODE_MASK: u8 = 0x1F;
+
+/// Mode: the body is a bit-packed array of mapped IDs, all the same width.
+pub const FLAG_FIXED_WIDTH: u8 = 0x01;
+
+/// Mode: a standard payload whose body is Group VarInt (`vbyte`) instead of
+/// LEB128.
+pub const FLAG_VBYTE_ENCODING: u8 = 0x04;
+
+/// Mode: the token table is replaced by a 4-byte `token_table_crc32` of the
+/// previous header in a `SessionCodec` session.
+pub const FLAG_SAME_HEADER: u8 = 0x05;
+
+/// Mode: the body holds BERT-style input, segment and position IDs.
+pub const FLAG_BERT_INPUTS: u8 = 0x03;
+
+/// Mode: the token table is replaced by a 4-byte dictionary ID referring to a
+/// header registered with a `DictionaryCodec`.
+pub const FLAG_DICT_REF: u8 = 0x02;
+
+/// Mode: the body holds the sorted tokens, delta coded, and the permutation
+/// restoring their order; the token table is empty.
+pub const FLAG_SORTED_PERMUTATION: u8 = 0x06;
+
+/// Mode: a standard payload whose body is Rice coded (`rice`) instead of
+/// LEB128, preceded by one byte holding the Rice parameter's log2.
+pub const FLAG_RICE_ENCODED: u8 = 0x07;
+
+/// Mode: a standard payload whose token table holds u16s instead of i32s.
+pub const FLAG_U16_TOKENS: u8 = 0x08;
+
+/// Modes: a body compressed per a `CompressionHint`, which is held in the
+/// low 2 bits (LZ4, zstd level 3, delta coding plus zstd level 15).
+pub const FLAG_HINT_SPEED: u8 = 0x09;
+pub const FLAG_HINT_BALANCED: u8 = 0x0A;
+pub const FLAG_HINT_SIZE: u8 = 0x0B;
+
+/// Mode: the body is `rle::encode_rle_only` output (no remapping); the token
+/// table is empty.
+pub const FLAG_RLE_ONLY: u8 = 0x0C;
+
+/// Mode: a standard payload whose body is Elias-gamma coded (`elias`)
+/// instead of LEB128, preceded by the value count as a u32 LE.
+pub const FLAG_ELIAS_GAMMA: u8 = 0x0D;
+
+/// Mode: the token table holds only the most frequent tokens, and the body
+/// writes every other token as itself (`sparse_header`).
+pub const FLAG_SPARSE_HEADER: u8 = 0x0E;
+
+/// Mode: a standard payload whose body is static Huffman coded (`huffman`)
+/// instead of LEB128, preceded by the code lengths and the value count.
+pub const FLAG_HUFFMAN: u8 = 0x0F;
+
+/// Mode: a standard payload whose body is arithmetic coded (`arithmetic`)
+/// instead of LEB128, preceded by every table entry's count.
+pub const FLAG_ARITHMETIC: u8 = 0x10;
+
+/// Mode: each token is a pre-trained bigram code or an escaped mapped ID,
+/// after the dictionary entries the payload uses (`bigram`).
+pub const FLAG_DELTA_DICT: u8 = 0x11;
+
+/// Mode: a standard payload followed by a u64 LE timestamp in nanoseconds
+/// (`temporal`).
+pub const FLAG_TIMESTAMP: u8 = 0x12;
+
+/// Mode: a standard payload followed by the 32-byte hash of the text it was
+/// tokenized from (`provenance`).
+pub const FLAG_SOURCE_HASH: u8 = 0x13;
+
+/// Mode: the body is written by a caller-supplied `VarintEncoder`
+/// (`custom_varint`) and can only be read back with the same encoder.
+pub const FLAG_CUSTOM_VARINT: u8 = 0x14;
+
+/// Mode: a standard payload whose mapped IDs are delta-coded (`delta`)
+/// before zigzag and LEB128.
+pub const FLAG_DELTA_CODED: u8 = 0x15;
+
+/// Mode: tokens are defined inline in the body on first appearance (`fused`);
+/// the token table is empty.
+pub const FLAG_FUSED_HEADER: u8 = 0x18;
+
+/// Human-readable name of every (non-mode) flag bit this build understands.
+///
+/// Every new flag bit must be listed here so that compatibility checks can
+/// report it by name.
+pub const FLAG_NAMES: &[(u8, &str)] = &[
+    (FLAG_METADATA_PRESENT, "metadata"),
+    (FLAG_SCHEMA_PRESENT, "schema"),
+    (FLAG_EXTENSIONS, "extensions"),
+];
+
+/// Human-readable name of every mode code this build understands.
+pub const MODE_NAMES: &[(u8, &str)] = &[
+    (FLAG_FIXED_WIDTH, "fixed_width"),
+    (FLAG_DICT_REF, "dict_ref"),
+    (FLAG_BERT_INPUTS, "bert_inputs"),
+    (FLAG_VBYTE_ENCODING, "vbyte"),
+    (FLAG_SAME_HEADER, "same_header"),
+    (FLAG_SORTED_PERMUTATION, "sorted_permutation"),
+    (FLAG_RICE_ENCODED, "rice"),
+    (FLAG_U16_TOKENS, "u16_tokens"),
+    (FLAG_HINT_SPEED, "hint_speed"),
+    (FLAG_HINT_BALANCED, "hint_balanced"),
+    (FLAG_HINT_SIZE, "hint_size"),
+    (FLAG_RLE_ONLY, "rle_only"),
+    (FLAG_ELIAS_GAMMA, "elias_gamma"),
+    (FLAG_SPARSE_HEADER, "sparse_header"),
+    (FLAG_HUFFMAN, "huffman"),
+    (FLAG_ARITHMETIC, "arithmetic"),
+    (FLAG_DELTA_DICT, "delta_dict"),
+    (FLAG_TIMESTAMP, "timestamp"),
+    (FLAG_SOURCE_HASH, "source_hash"),
+    (FLAG_CUSTOM_VARINT, "custom_varint"),
+    (FLAG_DELTA_CODED, "delta"),
+    (FLAG_FUSED_HEADER, "fused_header"),
+];
+
+/// Union of every flag bit in `FLAG_NAMES`.
+pub const KNOWN_FLAGS: u8 = {
+    let mut known = 0;
+    let mut i = 0;
+    while i < FLAG_NAMES.len() {
+        known |= FLAG_NAMES[i].0;
+        i += 1;
+    }
+    known
+};
+
+/// Names of the features encoded in `flags`: flag bits in `FLAG_NAMES` order,
+/// then the mode (if not the standard one).
+///
+/// Bits without a name are reported as `unknown_flag_0x..` and unknown modes as
+/// `unknown_mode_0x..`.
+pub fn flag_names(flags: u8) -> Vec<String> {
+    let mut names: Vec<String> = FLAG_NAMES
+        .iter()
+        .filter(|&&(flag, _)| flags & flag == flag)
+        .map(|&(_, name)| name.to_string())
+        .collect();
+    names.extend(unknown_bit_names(flags));
+
+    let mode = flags & FLAG_MODE_MASK;
+    if mode != 0 {
+        names.push(match mode_name(mode) {
+            Some(name) => name.to_string(),
+            None => format!("unknown_mode_{mode:#04x}"),
+        });
+    }
+    names
+}
+
+/// The subset of `flag_names(flags)` this build cannot decode.
+pub fn unsupported_features(flags: u8) -> Vec<String> {
+    let mut names = unknown_bit_names(flags);
+    let mode = flags & FLAG_MODE_MASK;
+    if mode != 0 && mode_name(mode).is_none() {
+        names.push(format!("unknown_mode_{mode:#04x}"));
+    }
+    names
+}
+
+fn mode_name(mode: u8) -> Option<&'static str> {
+    MODE_NAMES
+        .iter()
+        .find(|&&(code, _)| code == mode)
+        .map(|&(_, name)| name)
+}
+
+fn unknown_bit_names(flags: u8) -> Vec<String> {
+    let unknown = flags & !KNOWN_FLAGS & !FLAG_MODE_MASK;
+    (0..8)
+        .map(|bit| 1u8 << bit)
+        .filter(|bit| unknown & bit != 0)
+        .map(|bit| format!("unknown_flag_{bit:#04x}"))
+        .collect()
+}
+
+/// Fixed bytes before the token list: version (1) + flags (1) + length (4).
+pub(crate) const PREFIX_LEN: usize = 6;
+
 /// Metadata describing how tokens were remapped for this payload.
 ///
 /// The key idea: we only need to store the *ordering* of original tokens
@@ -10,14 +265,38 @@ use crate::freq_map::FreqMap;
 ///   11 -> mapped 0
 ///   42 -> mapped 1
 ///   -5 -> mapped 2
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Membership helpers (`contains_token`, `find_mapped_id_binary`, ...) build
+/// their lookup structures lazily on first use and cache them, so `tokens`
+/// must not be modified after they have been called.
+#[derive(Debug, Clone)]
 pub struct Header {
+    /// Wire-format version (see `FORMAT_VERSION`).
+    pub version: u8,
+    /// Bitset of `FLAG_*` values describing optional payload features.
+    pub flags: u8,
     /// Original tokens ordered by mapped ID (highest frequency first).
     pub tokens: Vec<i32>,
     /// Number of entries in the frequency map (cached for convenience).
     pub len: usize,
+    /// Lazily built set of `tokens`.
+    token_set: OnceCell<HashSet<i32>>,
+    /// Lazily built mapped IDs, ordered by their original token value.
+    mapped_by_token: OnceCell<Vec<i32>>,
+}
+
+/// Headers compare by their wire contents; the lookup caches are ignored.
+impl PartialEq for Header {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.flags == other.flags
+            && self.tokens == other.tokens
+            && self.len == other.len
+    }
 }
 
+impl Eq for Header {}
+
 impl Header {
     /// Build a header from an existing `FreqMap`, capturing the token ordering.
     ///
@@ -25,22 +304,122 @@ impl Header {
     ///   - descending frequency
     ///   - then ascending token ID on ties
     pub fn from_freq_map(freq: &FreqMap) -> Self {
-        let tokens = freq.ordered_tokens().to_vec();
+        Self::new(FORMAT_VERSION, 0, freq.ordered_tokens().to_vec())
+    }
+
+    /// Build a header from its parts; `len` is taken from `tokens`.
+    pub fn new(version: u8, flags: u8, tokens: Vec<i32>) -> Self {
         let len = tokens.len();
-        Self { tokens, len }
+        Self {
+            version,
+            flags,
+            tokens,
+            len,
+            token_set: OnceCell::new(),
+            mapped_by_token: OnceCell::new(),
+        }
+    }
+
+    /// CRC32 of the token table (count plus tokens, as `encode` writes them),
+    /// independent of version and flags. Short references to a header use it
+    /// as an ID.
+    pub fn token_table_crc32(&self) -> u32 {
+        crc32fast::hash(&self.encode()[PREFIX_LEN - 4..])
+    }
+
+    /// Offset of the first byte after this header in a payload, i.e. the
+    /// length of `encode()`.
+    pub fn body_offset(&self) -> usize {
+        PREFIX_LEN + self.tokens.len() * 4
+    }
+
+    /// The original tokens as a set (built on first use, then cached).
+    pub fn tokens_as_set(&self) -> HashSet<i32> {
+        self.token_set().clone()
+    }
+
+    /// The original tokens sorted by value (the permutation is cached).
+    pub fn tokens_as_sorted_vec(&self) -> Vec<i32> {
+        self.mapped_by_token()
+            .iter()
+            .map(|&mapped| self.tokens[mapped as usize])
+            .collect()
+    }
+
+    /// True if `token` appears in this header's mapping.
+    pub fn contains_token(&self, token: i32) -> bool {
+        self.token_set().contains(&token)
+    }
+
+    /// Mapped ID of `token`, found by binary search over the tokens sorted by
+    /// value (instead of a linear scan of `tokens`).
+    pub fn find_mapped_id_binary(&self, token: i32) -> Option<i32> {
+        let order = self.mapped_by_token();
+        let idx = order
+            .binary_search_by_key(&token, |&mapped| self.tokens[mapped as usize])
+            .ok()?;
+        Some(order[idx])
+    }
+
+    fn token_set(&self) -> &HashSet<i32> {
+        self.token_set.get_or_init(|| self.tokens.iter().copied().collect())
+    }
+
+    fn mapped_by_token(&self) -> &[i32] {
+        self.mapped_by_token.get_or_init(|| {
+            let mut order: Vec<i32> = (0..self.tokens.len() as i32).collect();
+            order.sort_by_key(|&mapped| self.tokens[mapped as usize]);
+            order
+        })
+    }
+
+    /// True if every flag bit of `flag` is set in `self.flags` and, when `flag`
+    /// names a mode, the payload uses exactly that mode.
+    pub fn has_flag(&self, flag: u8) -> bool {
+        let bits = flag & !FLAG_MODE_MASK;
+        let mode = flag & FLAG_MODE_MASK;
+        self.flags & bits == bits && (mode == 0 || self.mode() == mode)
+    }
+
+    /// The mode code in the low flag bits (0 for the standard layout).
+    pub fn mode(&self) -> u8 {
+        self.flags & FLAG_MODE_MASK
+    }
+
+    /// True if a decoder speaking `decoder_version` can read this header's version.
+    pub fn is_compatible_with(&self, decoder_version: u8) -> bool {
+        self.version <= decoder_version
+    }
+
+    /// Human-readable names of the features this payload relies on.
+    pub fn required_features(&self) -> Vec<String> {
+        flag_names(self.flags)
+    }
+
+    /// The flags a decoder must support to read this payload.
+    ///
+    /// Every flag currently changes the payload layout (even skippable sections
+    /// must be recognised to be peeled off), so this is all of `flags`.
+    pub fn compatible_decoder_flags(&self) -> u8 {
+        self.flags
     }
 
-    /// Serialize the header into bytes (tokens + length) for inclusion in the payload.
+    /// Serialize the header into bytes for inclusion in the payload.
     ///
     /// Format (little-endian):
-    ///   [0..4)    : u32 length (number of tokens)
-    ///   [4..]     : `len` i32 values (4 bytes each) representing the original tokens
+    ///   [0]       : u8 format version
+    ///   [1]       : u8 flags
+    ///   [2..6)    : u32 length (number of tokens)
+    ///   [6..]     : `len` i32 values (4 bytes each) representing the original tokens
     pub fn encode(&self) -> Vec<u8> {
         // Ensure `len` matches the actual tokens length.
         let len = self.tokens.len() as u32;
 
-        // Allocate capacity: 4 bytes for length + 4 bytes per token.
-        let mut out = Vec::with_capacity(4 + self.tokens.len() * 4);
+        // Allocate capacity: fixed prefix + 4 bytes per token.
+        let mut out = Vec::with_capacity(PREFIX_LEN + self.tokens.len() * 4);
+
+        out.push(self.version);
+        out.push(self.flags);
 
         // Write length as little-endian u32.
         out.extend_from_slice(&len.to_le_bytes());
@@ -56,9 +435,86 @@ impl Header {
     /// Parse a header from bytes, reconstructing the token ordering information.
     ///
     /// Performs basic validation:
-    ///   - At least 4 bytes for the length.
+    ///   - At least 6 bytes for version, flags and length.
+    ///   - A version this build understands.
     ///   - Remaining bytes must be exactly `len * 4`.
     pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (header, rest) = Self::decode_prefix(bytes)?;
+
+        // Remaining bytes must be exactly `len * 4`.
+        if !rest.is_empty() {
+            bail!(
+                "header size mismatch: expected {} bytes for tokens, got {}",
+                header.len * 4,
+                bytes.len() - PREFIX_LEN
+            );
+        }
+
+        Ok(header)
+    }
+
+    /// CRC32 of the whole encoded header (version and flags included).
+    pub fn compute_crc32(&self) -> u32 {
+        crc32fast::hash(&self.encode())
+    }
+
+    pub fn verify_crc32(&self, expected: u32) -> bool {
+        self.compute_crc32() == expected
+    }
+
+    /// `encode()` followed by its CRC32 as a little-endian u32.
+    pub fn encode_with_crc32(&self) -> Vec<u8> {
+        let mut out = self.encode();
+        let crc = crc32fast::hash(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Parse `encode_with_crc32` output, failing if the checksum does not match
+    /// (checked before the header itself is parsed).
+    pub fn decode_with_crc32(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 {
+            bail!("header too short: missing crc32");
+        }
+        let (header_bytes, crc_bytes) = bytes.split_at(bytes.len() - 4);
+        let expected = u32::from_le_bytes(
+            crc_bytes
+                .try_into()
+                .expect("slice of length 4 will always convert"),
+        );
+        let actual = crc32fast::hash(header_bytes);
+        if actual != expected {
+            bail!("header crc32 mismatch: expected {expected:#010x}, got {actual:#010x}");
+        }
+        Self::decode(header_bytes)
+    }
+
+    /// Parse a header from the front of `bytes`, returning it together with the
+    /// bytes that follow it (typically the varint body of a payload).
+    ///
+    /// Same validation as `decode`, except trailing bytes are allowed.
+    pub fn decode_prefix(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        // Need version and flags before the token table.
+        if bytes.len() < 2 {
+            bail!("header too short: missing version or flags");
+        }
+
+        let version = bytes[0];
+        if version != FORMAT_VERSION {
+            bail!("unsupported header version {}", version);
+        }
+
+        let (mut header, rest) = Self::decode_prefix_v0(&bytes[2..])?;
+        header.version = version;
+        header.flags = bytes[1];
+        Ok((header, rest))
+    }
+
+    /// Parse a version-0 header: the original layout with no version or flags
+    /// bytes, just `[u32 length][length i32 tokens]`.
+    ///
+    /// The returned header reports `version == 0` and no flags.
+    pub fn decode_prefix_v0(bytes: &[u8]) -> Result<(Self, &[u8])> {
         // Need at least 4 bytes to read the length.
         if bytes.len() < 4 {
             bail!("header too short: missing length prefix");
@@ -70,15 +526,15 @@ impl Header {
             .expect("slice of length 4 will always convert");
         let len = u32::from_le_bytes(len_bytes) as usize;
 
-        // Remaining bytes must be exactly `len * 4`.
+        // At least `len * 4` bytes of tokens must follow.
         let expected_bytes = len
             .checked_mul(4)
             .ok_or_else(|| anyhow::anyhow!("header length overflow"))?;
         let actual_bytes = bytes.len() - 4;
 
-        if actual_bytes != expected_bytes {
+        if actual_bytes < expected_bytes {
             bail!(
-                "header size mismatch: expected {} bytes for tokens, got {}",
+                "header truncated: expected {} bytes for tokens, got {}",
                 expected_bytes,
                 actual_bytes
             );
@@ -97,7 +553,7 @@ impl Header {
             offset = end;
         }
 
-        Ok(Self { tokens, len })
+        Ok((Self::new(0, 0, tokens), &bytes[offset..]))
     }
 }
 
@@ -108,10 +564,7 @@ mod tests {
     #[test]
     fn header_round_trip_manual_tokens() {
         let tokens = vec![10, 20, -5, 42];
-        let header = Header {
-            tokens: tokens.clone(),
-            len: tokens.len(),
-        };
+        let header = Header::new(FORMAT_VERSION, 0, tokens.clone());
 
         let bytes = header.encode();
         let decoded = Header::decode(&bytes).unwrap();
@@ -122,10 +575,7 @@ mod tests {
 
     #[test]
     fn header_handles_empty() {
-        let header = Header {
-            tokens: Vec::new(),
-            len: 0,
-        };
+        let header = Header::new(FORMAT_VERSION, 0, Vec::new());
 
         let bytes = header.encode();
         let decoded = Header::decode(&bytes).unwrap();
@@ -134,6 +584,64 @@ mod tests {
         assert_eq!(decoded.len, 0);
     }
 
+    #[test]
+    fn crc32_round_trip() {
+        let header = Header::new(FORMAT_VERSION, 0, vec![10, 20, -5, 42]);
+        let bytes = header.encode_with_crc32();
+        assert_eq!(bytes.len(), header.encode().len() + 4);
+        assert!(header.verify_crc32(header.compute_crc32()));
+        assert!(!header.verify_crc32(header.compute_crc32() ^ 1));
+        assert_eq!(Header::decode_with_crc32(&bytes).unwrap(), header);
+        assert!(Header::decode_with_crc32(&bytes[..3]).is_err());
+    }
+
+    #[test]
+    fn crc32_detects_every_byte_mutation() {
+        let header = Header::new(FORMAT_VERSION, FLAG_EXTENSIONS, vec![7, -1, 300]);
+        let original = header.encode_with_crc32();
+        let header_len = header.encode().len();
+
+        for i in 0..original.len() {
+            let mut mutated = original.clone();
+            mutated[i] ^= 0x5A;
+            if i < header_len {
+                assert_ne!(
+                    crc32fast::hash(&mutated[..header_len]),
+                    header.compute_crc32()
+                );
+            }
+            assert!(
+                Header::decode_with_crc32(&mutated).is_err(),
+                "mutating byte {i} went unnoticed"
+            );
+        }
+    }
+
+    #[test]
+    fn body_offset_is_encoded_len() {
+        for tokens in [vec![], vec![7], vec![10, 20, -5, 42]] {
+            let header = Header::new(FORMAT_VERSION, 0, tokens);
+            assert_eq!(header.body_offset(), header.encode().len());
+        }
+    }
+
+    #[test]
+    fn header_decode_prefix_returns_trailing_bytes() {
+        let header = Header::new(FORMAT_VERSION, FLAG_METADATA_PRESENT, vec![7, 3]);
+
+        let mut bytes = header.encode();
+        bytes.extend_from_slice(&[0xAA, 0xBB]);
+
+        let (decoded, rest) = Header::decode_prefix(&bytes).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(rest, &[0xAA, 0xBB]);
+
+        assert!(decoded.has_flag(FLAG_METADATA_PRESENT));
+
+        // Strict decode rejects the same bytes.
+        assert!(Header::decode(&bytes).is_err());
+    }
+
     // Optional: only if you want to hook up FreqMap here.
     // This verifies that `from_freq_map` + encode/decode preserves ordering.
     #[test]
@@ -150,4 +658,115 @@ mod tests {
         assert_eq!(decoded.tokens, header.tokens);
         assert_eq!(decoded.len, header.len);
     }
+
+    #[test]
+    fn header_decode_prefix_v0_reads_legacy_layout() {
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(&[8, 8, -1]));
+        let bytes = header.encode();
+
+        // Version 0 is the same table without the version and flags bytes.
+        let (legacy, rest) = Header::decode_prefix_v0(&bytes[2..]).unwrap();
+        assert_eq!(legacy.version, 0);
+        assert_eq!(legacy.tokens, header.tokens);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn header_rejects_unknown_version() {
+        let mut bytes = Header::from_freq_map(&FreqMap::from_token_ids(&[1])).encode();
+        bytes[0] = FORMAT_VERSION + 1;
+
+        let err = Header::decode(&bytes).unwrap_err().to_string();
+        assert!(err.contains("unsupported header version"));
+    }
+
+    #[test]
+    fn membership_queries() {
+        let header = Header::new(FORMAT_VERSION, 0, vec![40, -3, 7, 1000]);
+
+        assert_eq!(header.tokens_as_sorted_vec(), vec![-3, 7, 40, 1000]);
+        assert_eq!(header.tokens_as_set(), HashSet::from([40, -3, 7, 1000]));
+        assert!(header.contains_token(7));
+        assert!(!header.contains_token(8));
+
+        for (mapped, &token) in header.tokens.iter().enumerate() {
+            assert_eq!(header.find_mapped_id_binary(token), Some(mapped as i32));
+        }
+        assert_eq!(header.find_mapped_id_binary(41), None);
+    }
+
+    #[test]
+    fn caches_do_not_affect_equality() {
+        let warm = Header::new(FORMAT_VERSION, 0, vec![1, 2]);
+        assert!(warm.contains_token(1));
+        assert_eq!(warm, Header::new(FORMAT_VERSION, 0, vec![1, 2]));
+    }
+
+    #[test]
+    fn compatibility_with_decoder_versions() {
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(&[1]));
+        assert!(header.is_compatible_with(FORMAT_VERSION));
+        assert!(header.is_compatible_with(FORMAT_VERSION + 1));
+        assert!(!header.is_compatible_with(0));
+    }
+
+    #[test]
+    fn required_features_for_each_flag_combination() {
+        let mut header = Header::from_freq_map(&FreqMap::from_token_ids(&[1]));
+        assert!(header.required_features().is_empty());
+        assert_eq!(header.compatible_decoder_flags(), 0);
+
+        header.flags = FLAG_METADATA_PRESENT;
+        assert_eq!(header.required_features(), vec!["metadata"]);
+        assert_eq!(header.compatible_decoder_flags(), FLAG_METADATA_PRESENT);
+
+        for &(flag, name) in FLAG_NAMES.iter().chain(MODE_NAMES) {
+            header.flags = flag;
+            assert_eq!(header.required_features(), vec![name]);
+        }
+
+        header.flags = FLAG_METADATA_PRESENT | FLAG_SCHEMA_PRESENT | FLAG_DICT_REF;
+        assert_eq!(
+            header.required_features(),
+            vec!["metadata", "schema", "dict_ref"]
+        );
+
+        // Every non-mode bit plus one (unknown) mode.
+        header.flags = 0xFF;
+        assert_eq!(
+            header.required_features().len(),
+            (!FLAG_MODE_MASK).count_ones() as usize + 1
+        );
+    }
+
+    #[test]
+    fn unknown_flags_are_named_by_bit() {
+        let unknown_bits = (0..8)
+            .map(|bit| 1u8 << bit)
+            .filter(|bit| bit & (KNOWN_FLAGS | FLAG_MODE_MASK) == 0);
+        for bit in unknown_bits {
+            let name = format!("unknown_flag_{bit:#04x}");
+            assert_eq!(flag_names(bit), vec![name.clone()]);
+            assert_eq!(unsupported_features(bit), vec![name]);
+        }
+    }
+
+    #[test]
+    fn modes_are_codes_not_bits() {
+        let mut header = Header::new(FORMAT_VERSION, FLAG_DICT_REF, vec![]);
+        assert_eq!(header.mode(), FLAG_DICT_REF);
+        assert!(header.has_flag(FLAG_DICT_REF));
+
+        // A different mode that happens to share bits is not a match.
+        header.flags = FLAG_DICT_REF | 0x04;
+        assert!(!header.has_flag(FLAG_DICT_REF));
+
+        let unknown = (1..=FLAG_MODE_MASK)
+            .find(|&mode| mode_name(mode).is_none())
+            .unwrap();
+        let name = format!("unknown_mode_{unknown:#04x}");
+        assert_eq!(flag_names(unknown), vec![name.clone()]);
+        assert_eq!(unsupported_features(unknown), vec![name]);
+        assert!(unsupported_features(FLAG_DICT_REF | FLAG_METADATA_PRESENT).is_empty());
+    }
 }
@@ -0,0 +1,247 @@
+// src/histogram.rs
+use crate::bits::{BitReader, BitWriter};
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+
+/// Most buckets a histogram may have, so that a bucket selector fits in one
+/// bit-stream write.
+pub const MAX_BUCKETS: usize = 32;
+
+/// A range of mapped IDs sharing one bit width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bucket {
+    start: u32,
+    max: u32,
+    bits: u32,
+}
+
+/// Bit packing by a fixed histogram of mapped IDs rather than a per-payload
+/// code: frequent tokens (low mapped IDs) get few bits, rare ones more.
+///
+/// Each token is written as a unary bucket selector (`i` one bits and a zero
+/// for bucket `i`; the last bucket leaves out the zero) followed by its
+/// offset from the bucket's first mapped ID in the bucket's bit width. The
+/// output is the bare bit stream, packed least significant bit first; the
+/// token count and frequency map travel out of band.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramCodec {
+    buckets: Vec<Bucket>,
+}
+
+impl HistogramCodec {
+    /// Buckets from `(max_mapped_id, bits_per_token)` pairs: the first covers
+    /// mapped IDs `0..=histogram[0].0`, each next one starts right after the
+    /// previous one's maximum.
+    ///
+    /// # Panics
+    ///
+    /// If the histogram is empty or has more than `MAX_BUCKETS` buckets, if
+    /// the maxima are not strictly increasing, or if a bucket's IDs don't fit
+    /// in its bit width.
+    pub fn from_histogram(histogram: &[(u32, u32)]) -> Self {
+        assert!(
+            (1..=MAX_BUCKETS).contains(&histogram.len()),
+            "a histogram needs 1 to {MAX_BUCKETS} buckets, got {}",
+            histogram.len()
+        );
+
+        let mut buckets = Vec::with_capacity(histogram.len());
+        let mut start = 0u32;
+        for &(max, bits) in histogram {
+            assert!(
+                max >= start && (start > 0 || buckets.is_empty()),
+                "bucket maxima must be strictly increasing, got {max} after {}",
+                start.wrapping_sub(1)
+            );
+            let span = u64::from(max - start) + 1;
+            assert!(
+                bits <= 32 && span <= 1u64 << bits,
+                "{span} mapped IDs from {start} don't fit in {bits} bits"
+            );
+            buckets.push(Bucket { start, max, bits });
+            // Past u32::MAX no further bucket can start; the check above
+            // rejects one.
+            start = max.wrapping_add(1);
+        }
+        Self { buckets }
+    }
+
+    /// Largest mapped ID the histogram covers.
+    pub fn max_mapped_id(&self) -> u32 {
+        self.buckets[self.buckets.len() - 1].max
+    }
+
+    /// Errors with `CodecError::TokenOutOfRange` for a token whose mapped ID
+    /// is past the last bucket.
+    pub fn encode(&self, token_ids: &[i32], freq_map: &FreqMap) -> Result<Vec<u8>> {
+        let last = self.buckets.len() - 1;
+        let mut writer = BitWriter::with_capacity(token_ids.len() * 2);
+        for (&token, mapped) in token_ids
+            .iter()
+            .zip(CodecCore::map_ids(token_ids, freq_map)?)
+        {
+            let mapped = mapped as u32;
+            let index = self.buckets.partition_point(|bucket| bucket.max < mapped);
+            let bucket = self.buckets.get(index).ok_or(CodecError::TokenOutOfRange {
+                token,
+                max: self.max_mapped_id() as usize,
+            })?;
+
+            let ones = (1u64 << index) - 1;
+            let selector_len = if index == last { index } else { index + 1 };
+            writer.push(ones, selector_len as u32);
+            writer.push(u64::from(mapped - bucket.start), bucket.bits);
+        }
+        Ok(writer.finish())
+    }
+
+    /// Reverse of `encode` for `n` tokens.
+    pub fn decode(&self, payload: &[u8], n: usize, freq_map: &FreqMap) -> Result<Vec<i32>> {
+        let last = self.buckets.len() - 1;
+        let mut reader = BitReader::new(payload);
+        // Every token takes at least one bit, unless a single bucket of one
+        // ID needs none; either way this caps the allocation.
+        let mut tokens = Vec::with_capacity(n.min(payload.len() * 8));
+        for _ in 0..n {
+            let index = (reader.peek().trailing_ones() as usize).min(last);
+            let selector_len = if index == last { index } else { index + 1 };
+            let bucket = self.buckets[index];
+
+            reader
+                .skip(selector_len as u32)
+                .map_err(|_| CodecError::InvalidPayload)?;
+            let offset = reader
+                .read(bucket.bits)
+                .map_err(|_| CodecError::InvalidPayload)?;
+            let mapped = bucket
+                .start
+                .checked_add(offset)
+                .filter(|&mapped| mapped <= bucket.max)
+                .ok_or(CodecError::InvalidPayload)?;
+
+            let token = i32::try_from(mapped)
+                .ok()
+                .and_then(|mapped| freq_map.unmap_token(mapped))
+                .ok_or(CodecError::InvalidPayload)?;
+            tokens.push(token);
+        }
+
+        if !payload.is_empty() && !reader.at_end() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4 bits for the 16 most frequent tokens, 8 for the next 240, 16 for
+    /// the rest.
+    fn three_buckets() -> HistogramCodec {
+        HistogramCodec::from_histogram(&[(15, 4), (255, 8), (65_535, 16)])
+    }
+
+    /// 10K tokens from a Zipf-like 50K vocabulary.
+    fn zipf_sequence() -> Vec<i32> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..10_000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                (50_000.0f64.powf(u * u) as i32).min(49_999)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zipf_sequence_round_trips() {
+        let codec = three_buckets();
+        let ids = zipf_sequence();
+        let freq = FreqMap::from_token_ids(&ids);
+        assert!(
+            freq.ordered_tokens().len() > 256,
+            "the sequence should reach the last bucket"
+        );
+
+        let payload = codec.encode(&ids, &freq).unwrap();
+        assert_eq!(codec.decode(&payload, ids.len(), &freq).unwrap(), ids);
+
+        // Frequent tokens dominate, so this beats LEB128's 8 bits or more
+        // per token.
+        let leb128 = CodecCore::encode_body(&ids, &freq).unwrap();
+        assert!(
+            payload.len() < leb128.len(),
+            "{} vs {}",
+            payload.len(),
+            leb128.len()
+        );
+    }
+
+    #[test]
+    fn known_layout() {
+        let codec = three_buckets();
+        // Mapped IDs 0 (token 7) and 1 (token 3).
+        let freq = FreqMap::from_token_ids(&[7, 7, 3]);
+        let payload = codec.encode(&[7, 3], &freq).unwrap();
+        // Selector 0 and offset 0000 in bits 0 to 4, selector 0 and offset
+        // 0001 in bits 5 to 9 (least significant bit first).
+        assert_eq!(payload, [0b0100_0000, 0b0000_0000]);
+        assert_eq!(codec.decode(&payload, 2, &freq).unwrap(), [7, 3]);
+    }
+
+    #[test]
+    fn every_bucket_round_trips() {
+        let codec = three_buckets();
+        // Token t appears 1000 - t times, so it maps to mapped ID t.
+        let ids: Vec<i32> = (0..1000).flat_map(|t| vec![t; 1000 - t as usize]).collect();
+        let freq = FreqMap::from_token_ids(&ids);
+        let sample = [0, 15, 16, 255, 256, 999];
+        let payload = codec.encode(&sample, &freq).unwrap();
+        assert_eq!(codec.decode(&payload, sample.len(), &freq).unwrap(), sample);
+    }
+
+    #[test]
+    fn tokens_past_the_last_bucket_are_rejected() {
+        let codec = HistogramCodec::from_histogram(&[(1, 1)]);
+        let freq = FreqMap::from_token_ids(&[1, 1, 1, 2, 2, 3]);
+        assert!(codec.encode(&[1, 2], &freq).is_ok());
+        assert!(matches!(
+            codec.encode(&[1, 3], &freq),
+            Err(CodecError::TokenOutOfRange { token: 3, max: 1 })
+        ));
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        let codec = three_buckets();
+        let ids = zipf_sequence();
+        let freq = FreqMap::from_token_ids(&ids);
+        let payload = codec.encode(&ids, &freq).unwrap();
+
+        assert!(codec
+            .decode(&payload[..payload.len() - 1], ids.len(), &freq)
+            .is_err());
+        assert!(codec.decode(&payload, ids.len() - 10, &freq).is_err());
+        // Offsets past the map's tokens.
+        assert!(codec
+            .decode(&[0xFF; 6], 2, &FreqMap::from_token_ids(&[1]))
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "strictly increasing")]
+    fn unordered_buckets_panic() {
+        HistogramCodec::from_histogram(&[(15, 4), (15, 8)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "don't fit")]
+    fn narrow_buckets_panic() {
+        HistogramCodec::from_histogram(&[(15, 3)]);
+    }
+}
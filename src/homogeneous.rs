@@ -0,0 +1,177 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::Header;
+use crate::varint;
+use crate::Codec;
+
+/// A batch of sequences from one vocabulary, encoded against a single
+/// `FreqMap` built from the whole batch: one shared header, then one body per
+/// sequence with no header of its own.
+///
+/// Bodies are standard (zigzag + varint) bodies, gzipped one by one when the
+/// batch was built with `gzip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomogeneousBatch {
+    pub header: Header,
+    pub bodies: Vec<Vec<u8>>,
+}
+
+impl HomogeneousBatch {
+    /// Layout:
+    ///   [shared header][varint body length][body]...
+    pub fn encode_to_bytes(&self) -> Vec<u8> {
+        let mut out = self.header.encode();
+        for body in &self.bodies {
+            let len = u32::try_from(body.len()).expect("body lengths are checked when encoding");
+            out.extend_from_slice(&varint::encode(&[len]));
+            out.extend_from_slice(body);
+        }
+        out
+    }
+
+    /// Reverse of `encode_to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (header, mut rest) =
+            Header::decode_prefix(bytes).map_err(|_| CodecError::InvalidPayload)?;
+
+        let mut bodies = Vec::new();
+        while !rest.is_empty() {
+            let len = read_varint(&mut rest)? as usize;
+            if len > rest.len() {
+                return Err(CodecError::InvalidPayload);
+            }
+            let (body, tail) = rest.split_at(len);
+            bodies.push(body.to_vec());
+            rest = tail;
+        }
+        Ok(Self { header, bodies })
+    }
+}
+
+impl Codec {
+    pub fn encode_batch_homogeneous(
+        &self,
+        sequences: &[&[i32]],
+        gzip: bool,
+    ) -> Result<HomogeneousBatch> {
+        let freq = FreqMap::from_token_ids(&sequences.concat());
+
+        let bodies = sequences
+            .iter()
+            .map(|seq| {
+                let body = CodecCore::compress(CodecCore::encode_body(seq, &freq)?, gzip)?;
+                to_u32(body.len())?;
+                Ok(body)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(HomogeneousBatch {
+            header: Header::from_freq_map(&freq),
+            bodies,
+        })
+    }
+
+    /// Decode `HomogeneousBatch::encode_to_bytes` output.
+    pub fn decode_batch_homogeneous(&self, payload: &[u8], gzip: bool) -> Result<Vec<Vec<i32>>> {
+        let batch = HomogeneousBatch::from_bytes(payload)?;
+        batch
+            .bodies
+            .iter()
+            .map(|body| CodecCore::decode_body(&CodecCore::decompress(body, gzip)?, &batch.header))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 sequences of 20 to 120 tokens from one Zipf-like 50K vocabulary.
+    fn batch() -> Vec<Vec<i32>> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..100)
+            .map(|i| {
+                (0..20 + i % 101)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                        (50_000.0f64.powf(u * u) as i32).min(49_999)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn homogeneous_batch_round_trips() {
+        let codec = Codec::new();
+        let seqs = batch();
+        let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+
+        for gzip in [false, true] {
+            let batch = codec.encode_batch_homogeneous(&refs, gzip).unwrap();
+            assert_eq!(batch.bodies.len(), seqs.len());
+            let payload = batch.encode_to_bytes();
+            assert_eq!(HomogeneousBatch::from_bytes(&payload).unwrap(), batch);
+            assert_eq!(
+                codec.decode_batch_homogeneous(&payload, gzip).unwrap(),
+                seqs
+            );
+        }
+    }
+
+    #[test]
+    fn shared_header_beats_independent_payloads() {
+        let codec = Codec::new();
+        let seqs = batch();
+        let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+
+        let homogeneous = codec
+            .encode_batch_homogeneous(&refs, false)
+            .unwrap()
+            .encode_to_bytes();
+        let independent: usize = refs
+            .iter()
+            .map(|seq| CodecCore::encode_token_ids(seq, false).unwrap().len())
+            .sum();
+        assert!(
+            homogeneous.len() < independent,
+            "{} vs {independent}",
+            homogeneous.len()
+        );
+    }
+
+    #[test]
+    fn empty_batch_and_empty_sequences() {
+        let codec = Codec::new();
+        for seqs in [vec![], vec![vec![], vec![3, 3], vec![]]] {
+            let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+            let payload = codec
+                .encode_batch_homogeneous(&refs, false)
+                .unwrap()
+                .encode_to_bytes();
+            assert_eq!(
+                codec.decode_batch_homogeneous(&payload, false).unwrap(),
+                seqs
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_payloads_are_rejected() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_batch_homogeneous(&[&[1, 2, 3], &[3, 2]], false)
+            .unwrap()
+            .encode_to_bytes();
+        assert!(codec
+            .decode_batch_homogeneous(&payload[..payload.len() - 1], false)
+            .is_err());
+        assert!(codec
+            .decode_batch_homogeneous(&payload[..3], false)
+            .is_err());
+    }
+}
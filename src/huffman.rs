@@ -0,0 +1,335 @@
+// src/huffman.rs
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use anyhow::{bail, Result};
+
+use crate::freq_map::FreqMap;
+
+/// Longest code a `HuffmanTable` may assign.
+pub const MAX_CODE_LEN: u8 = 32;
+
+/// Canonical Huffman code over mapped IDs `0..len`.
+///
+/// The code is fully determined by its code lengths (`code_lengths`): codes
+/// are handed out in order of (length, mapped ID), each one the previous plus
+/// one, shifted left whenever the length grows. Storing the lengths is
+/// therefore enough to rebuild the table (`from_code_lengths`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HuffmanTable {
+    /// Code length per mapped ID; 0 for IDs without a code.
+    lengths: Vec<u8>,
+    /// Code per mapped ID, in the low `lengths[id]` bits.
+    codes: Vec<u32>,
+    /// Number of codes of each length.
+    length_counts: [u32; MAX_CODE_LEN as usize + 1],
+    /// First code of each length.
+    first_codes: [u64; MAX_CODE_LEN as usize + 1],
+    /// Index into `sorted` of the first ID of each length.
+    first_indices: [u32; MAX_CODE_LEN as usize + 1],
+    /// Mapped IDs in canonical order.
+    sorted: Vec<u32>,
+}
+
+impl HuffmanTable {
+    /// Rebuild a table from `code_lengths` output. Errors on lengths beyond
+    /// `MAX_CODE_LEN` or lengths that no prefix code can have.
+    pub fn from_code_lengths(lengths: &[u8]) -> Result<Self> {
+        if lengths.iter().any(|&len| len > MAX_CODE_LEN) {
+            bail!("huffman code length above {}", MAX_CODE_LEN);
+        }
+        // Kraft's inequality, in units of 2^-MAX_CODE_LEN.
+        let kraft: u64 = lengths
+            .iter()
+            .filter(|&&len| len > 0)
+            .map(|&len| 1u64 << (MAX_CODE_LEN - len))
+            .sum();
+        if kraft > 1u64 << MAX_CODE_LEN {
+            bail!("huffman code lengths oversubscribe the code space");
+        }
+
+        let mut sorted: Vec<u32> = (0..lengths.len() as u32)
+            .filter(|&id| lengths[id as usize] > 0)
+            .collect();
+        sorted.sort_by_key(|&id| (lengths[id as usize], id));
+
+        let mut length_counts = [0u32; MAX_CODE_LEN as usize + 1];
+        let mut first_codes = [0u64; MAX_CODE_LEN as usize + 1];
+        let mut first_indices = [0u32; MAX_CODE_LEN as usize + 1];
+        let mut codes = vec![0u32; lengths.len()];
+
+        let mut code = 0u64;
+        let mut len = 0u8;
+        for (index, &id) in sorted.iter().enumerate() {
+            let id_len = lengths[id as usize];
+            if id_len != len {
+                code <<= id_len - len;
+                len = id_len;
+                first_codes[len as usize] = code;
+                first_indices[len as usize] = index as u32;
+            }
+            codes[id as usize] = code as u32;
+            length_counts[len as usize] += 1;
+            code += 1;
+        }
+
+        Ok(Self {
+            lengths: lengths.to_vec(),
+            codes,
+            length_counts,
+            first_codes,
+            first_indices,
+            sorted,
+        })
+    }
+
+    /// Code length of every mapped ID, the table's serialized form.
+    pub fn code_lengths(&self) -> &[u8] {
+        &self.lengths
+    }
+
+    /// Encoded size in bits of `values`.
+    pub fn encoded_bits(&self, values: &[u32]) -> u64 {
+        values
+            .iter()
+            .map(|&v| u64::from(self.lengths[v as usize]))
+            .sum()
+    }
+}
+
+/// Huffman table for the mapped IDs of `freq_map`, weighted by their counts
+/// (a count of 0 is treated as 1, so every ID gets a code).
+///
+/// The tree is built with a min-heap, merging the two lightest nodes (the
+/// older one on ties) until one is left. Should a code come out longer than
+/// `MAX_CODE_LEN`, the weights are halved and the tree rebuilt. A single ID
+/// gets a 1-bit code.
+pub fn build_huffman_table(freq_map: &FreqMap) -> HuffmanTable {
+    let mut weights: Vec<u64> = freq_map
+        .counts()
+        .iter()
+        .map(|&count| (count as u64).max(1))
+        .collect();
+
+    loop {
+        let lengths = tree_depths(&weights);
+        if lengths.iter().all(|&len| len <= MAX_CODE_LEN) {
+            return HuffmanTable::from_code_lengths(&lengths)
+                .expect("a Huffman tree gives valid code lengths");
+        }
+        for w in &mut weights {
+            *w = w.div_ceil(2);
+        }
+    }
+}
+
+/// Depth of every leaf in the Huffman tree for `weights`, capped at
+/// `u8::MAX`.
+fn tree_depths(weights: &[u64]) -> Vec<u8> {
+    match weights.len() {
+        0 => return Vec::new(),
+        1 => return vec![1],
+        _ => {}
+    }
+
+    // Leaves are nodes 0..n, merged nodes follow; `parent[node]` links up.
+    let n = weights.len();
+    let mut parent = vec![0usize; 2 * n - 1];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = weights
+        .iter()
+        .enumerate()
+        .map(|(node, &w)| Reverse((w, node)))
+        .collect();
+
+    let mut next = n;
+    while let (Some(Reverse((wa, a))), Some(Reverse((wb, b)))) = (heap.pop(), heap.pop()) {
+        parent[a] = next;
+        parent[b] = next;
+        heap.push(Reverse((wa + wb, next)));
+        next += 1;
+    }
+
+    // Parents always come after their children, so depths fill in from the
+    // root (the last node) down.
+    let root = next - 1;
+    let mut depth = vec![0u8; 2 * n - 1];
+    for node in (0..root).rev() {
+        depth[node] = depth[parent[node]].saturating_add(1);
+    }
+    depth.truncate(n);
+    depth
+}
+
+/// Huffman code `values` (mapped IDs) with `table`:
+///   [u8 pad bit count][codes, most significant bit first, zero-padded]
+///
+/// # Panics
+///
+/// If a value has no code in `table`.
+pub fn encode_huffman(values: &[u32], table: &HuffmanTable) -> Vec<u8> {
+    let bits = table.encoded_bits(values);
+    let mut out = Vec::with_capacity(1 + bits.div_ceil(8) as usize);
+    out.push(((8 - bits % 8) % 8) as u8);
+
+    let mut acc = 0u64;
+    let mut pending = 0u32;
+    for &v in values {
+        let len = u32::from(table.lengths[v as usize]);
+        assert!(len > 0, "mapped ID {v} has no Huffman code");
+        acc = (acc << len) | u64::from(table.codes[v as usize]);
+        pending += len;
+        while pending >= 8 {
+            pending -= 8;
+            out.push((acc >> pending) as u8);
+        }
+        acc &= (1 << pending) - 1;
+    }
+    if pending > 0 {
+        out.push((acc << (8 - pending)) as u8);
+    }
+    out
+}
+
+/// Decode `count` values written by `encode_huffman` with the same table.
+/// Errors on an invalid code, a truncated stream or trailing bits.
+pub fn decode_huffman(bytes: &[u8], count: usize, table: &HuffmanTable) -> Result<Vec<u32>> {
+    let Some((&pad, stream)) = bytes.split_first() else {
+        bail!("huffman stream is missing its padding count");
+    };
+    if pad > 7 || (stream.is_empty() && pad > 0) {
+        bail!("invalid huffman padding count {}", pad);
+    }
+    let total_bits = stream.len() * 8 - pad as usize;
+    // Every value takes at least one bit, which bounds the allocation.
+    if count > total_bits {
+        bail!("huffman stream too short for {} values", count);
+    }
+
+    let mut pos = 0usize;
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut code = 0u64;
+        let mut symbol = None;
+        for len in 1..=MAX_CODE_LEN as usize {
+            if pos == total_bits {
+                bail!("huffman stream ended early");
+            }
+            let bit = (stream[pos / 8] >> (7 - pos % 8)) & 1;
+            code = (code << 1) | u64::from(bit);
+            pos += 1;
+
+            let offset = code.wrapping_sub(table.first_codes[len]);
+            if code >= table.first_codes[len] && offset < u64::from(table.length_counts[len]) {
+                symbol = Some(table.sorted[table.first_indices[len] as usize + offset as usize]);
+                break;
+            }
+        }
+        match symbol {
+            Some(symbol) => out.push(symbol),
+            None => bail!("invalid huffman code"),
+        }
+    }
+
+    if pos != total_bits {
+        bail!("trailing bits after huffman stream");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn canonical_codes() {
+        // Counts 5, 2, 1, 1: lengths 1, 2, 3, 3 and codes 0, 10, 110, 111.
+        let table = build_huffman_table(&FreqMap::from_token_ids(&[7, 7, 7, 7, 7, 8, 8, 9, 6]));
+        assert_eq!(table.code_lengths(), [1, 2, 3, 3]);
+        assert_eq!(table.codes, [0b0, 0b10, 0b110, 0b111]);
+
+        // 0 10 110 111 0, then 6 pad bits.
+        let enc = encode_huffman(&[0, 1, 2, 3, 0], &table);
+        assert_eq!(enc, [6, 0b0101_1011, 0b1000_0000]);
+        assert_eq!(decode_huffman(&enc, 5, &table).unwrap(), [0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn tables_rebuild_from_code_lengths() {
+        let ids: Vec<i32> = (0..5000).map(|i| (i * i) % 97 % (i % 13 + 1)).collect();
+        let table = build_huffman_table(&FreqMap::from_token_ids(&ids));
+        assert_eq!(
+            HuffmanTable::from_code_lengths(table.code_lengths()).unwrap(),
+            table
+        );
+
+        assert!(HuffmanTable::from_code_lengths(&[1, 1, 1]).is_err());
+        assert!(HuffmanTable::from_code_lengths(&[33]).is_err());
+        // Incomplete codes are fine; the unused ones are just invalid.
+        let sparse = HuffmanTable::from_code_lengths(&[2, 0, 2]).unwrap();
+        assert!(decode_huffman(&[6, 0b1100_0000], 1, &sparse).is_err());
+    }
+
+    #[test]
+    fn small_tables() {
+        let empty = build_huffman_table(&FreqMap::from_token_ids(&[]));
+        assert_eq!(encode_huffman(&[], &empty), [0]);
+        assert!(decode_huffman(&[0], 0, &empty).unwrap().is_empty());
+
+        let single = build_huffman_table(&FreqMap::from_token_ids(&[4, 4, 4]));
+        assert_eq!(single.code_lengths(), [1]);
+        let enc = encode_huffman(&[0, 0, 0], &single);
+        assert_eq!(decode_huffman(&enc, 3, &single).unwrap(), [0, 0, 0]);
+    }
+
+    #[test]
+    fn skewed_weights_stay_within_the_length_limit() {
+        // Fibonacci counts give the deepest possible tree, one more level per
+        // token; 40 of them would need 39-bit codes.
+        let mut fib = vec![1u64, 1];
+        while fib.len() < 40 {
+            fib.push(fib[fib.len() - 1] + fib[fib.len() - 2]);
+        }
+        let tokens: Vec<i32> = (0..40).collect();
+        let freq = FreqMap::from_frequencies(&tokens, &fib).unwrap();
+        let table = build_huffman_table(&freq);
+        assert!(table
+            .code_lengths()
+            .iter()
+            .all(|&len| (1..=MAX_CODE_LEN).contains(&len)));
+
+        let values: Vec<u32> = (0..40).collect();
+        let enc = encode_huffman(&values, &table);
+        assert_eq!(decode_huffman(&enc, 40, &table).unwrap(), values);
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        let table = build_huffman_table(&FreqMap::from_token_ids(&[1, 1, 2, 3, 3, 3]));
+        let enc = encode_huffman(&[0, 1, 2, 2, 1, 0], &table);
+        assert!(decode_huffman(&enc[..enc.len() - 1], 6, &table).is_err());
+        assert!(decode_huffman(&enc, 5, &table).is_err());
+        assert!(decode_huffman(&[enc.as_slice(), &[0]].concat(), 6, &table).is_err());
+        assert!(decode_huffman(&[], 0, &table).is_err());
+        assert!(decode_huffman(&[8, 0], 1, &table).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "no Huffman code")]
+    fn values_without_a_code_panic() {
+        let table = HuffmanTable::from_code_lengths(&[1, 0, 1]).unwrap();
+        encode_huffman(&[1], &table);
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(ids in proptest::collection::vec(0i32..300, 0..512)) {
+            let freq = FreqMap::from_token_ids(&ids);
+            let table = build_huffman_table(&freq);
+            let values: Vec<u32> = ids.iter().map(|&t| freq.map_token(t).unwrap() as u32).collect();
+            let enc = encode_huffman(&values, &table);
+            prop_assert_eq!(decode_huffman(&enc, values.len(), &table).unwrap(), values);
+        }
+    }
+}
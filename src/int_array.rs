@@ -0,0 +1,120 @@
+use crate::codec_core::{to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::varint;
+use crate::Codec;
+
+/// General integer arrays (position IDs, label IDs, bias matrices, ...).
+///
+/// These are plain uncompressed `encode_token_ids` payloads; nothing in the
+/// pipeline is specific to token IDs.
+///
+/// Matrix layout:
+///   varint : number of rows R
+///   R times: varint byte length of the row, then the row's standalone payload
+///
+/// Each row gets its own `FreqMap`, so rows with disjoint value ranges do not
+/// pay for each other's headers.
+impl Codec {
+    pub fn encode_int_array(&self, values: &[i32]) -> Result<Vec<u8>> {
+        CodecCore::encode_token_ids(values, false)
+    }
+
+    pub fn decode_int_array(&self, payload: &[u8]) -> Result<Vec<i32>> {
+        CodecCore::decode_token_ids(payload, false)
+    }
+
+    pub fn encode_int_matrix(&self, rows: &[&[i32]]) -> Result<Vec<u8>> {
+        let mut out = varint::encode(&[to_u32(rows.len())?]);
+        for row in rows {
+            let encoded = self.encode_int_array(row)?;
+            out.extend_from_slice(&varint::encode(&[to_u32(encoded.len())?]));
+            out.extend_from_slice(&encoded);
+        }
+        Ok(out)
+    }
+
+    pub fn decode_int_matrix(&self, payload: &[u8]) -> Result<Vec<Vec<i32>>> {
+        let (count, mut pos) =
+            varint::decode_one(payload).map_err(|_| CodecError::InvalidPayload)?;
+
+        // Every row needs at least one length byte, which bounds the allocation.
+        let mut rows = Vec::with_capacity((count as usize).min(payload.len()));
+        for _ in 0..count {
+            let (len, used) =
+                varint::decode_one(&payload[pos..]).map_err(|_| CodecError::InvalidPayload)?;
+            pos += used;
+
+            let end = pos
+                .checked_add(len as usize)
+                .filter(|&end| end <= payload.len())
+                .ok_or(CodecError::InvalidPayload)?;
+            rows.push(self.decode_int_array(&payload[pos..end])?);
+            pos = end;
+        }
+
+        if pos != payload.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(rows: &[Vec<i32>]) {
+        let codec = Codec::new();
+        let refs: Vec<&[i32]> = rows.iter().map(Vec::as_slice).collect();
+        let payload = codec.encode_int_matrix(&refs).unwrap();
+        assert_eq!(codec.decode_int_matrix(&payload).unwrap(), rows);
+    }
+
+    #[test]
+    fn int_array_matches_uncompressed_token_ids() {
+        let codec = Codec::new();
+        let values = [0, 1, 2, 3, -1, 511, 0];
+
+        let payload = codec.encode_int_array(&values).unwrap();
+        assert_eq!(payload, CodecCore::encode_token_ids(&values, false).unwrap());
+        assert_eq!(codec.decode_int_array(&payload).unwrap(), values);
+    }
+
+    #[test]
+    fn identity_matrix() {
+        let rows: Vec<Vec<i32>> = (0..16)
+            .map(|i| (0..16).map(|j| i32::from(i == j)).collect())
+            .collect();
+        round_trip(&rows);
+    }
+
+    #[test]
+    fn zero_matrix() {
+        round_trip(&vec![vec![0; 32]; 8]);
+    }
+
+    #[test]
+    fn random_matrix() {
+        let rows: Vec<Vec<i32>> = (0..10u32)
+            .map(|i| {
+                (0..(i * 13) % 40)
+                    .map(|j| (j ^ i).wrapping_mul(2654435761) as i32)
+                    .collect()
+            })
+            .collect();
+        round_trip(&rows);
+    }
+
+    #[test]
+    fn empty_matrix() {
+        round_trip(&[]);
+    }
+
+    #[test]
+    fn truncated_matrix_is_rejected() {
+        let codec = Codec::new();
+        let payload = codec.encode_int_matrix(&[&[1, 2, 3], &[4, 5]]).unwrap();
+
+        assert!(codec.decode_int_matrix(&payload[..payload.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::varint;
+use crate::Codec;
+
+/// Interleaved multi-stream payloads for K parallel token streams (e.g. audio
+/// codec codebooks), where each position carries one value per stream.
+///
+/// Every stream is encoded independently (own `FreqMap`, header and gzip
+/// stream), then the K encodings are interleaved byte by byte: byte 0 of each
+/// stream, then byte 1 of each stream, and so on. Encodings that run out early
+/// simply drop out of the rotation.
+///
+/// Layout:
+///   K varint: byte length of each stream's encoding
+///   then    : the interleaved bytes
+impl Codec {
+    /// Fails with `CodecError::InvalidPayload` unless all streams have the same
+    /// length.
+    pub fn encode_interleaved(&self, streams: &[&[i32]], gzip: bool) -> Result<Vec<u8>> {
+        if streams
+            .windows(2)
+            .any(|pair| pair[0].len() != pair[1].len())
+        {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let encoded = streams
+            .iter()
+            .map(|stream| CodecCore::encode_token_ids(stream, gzip))
+            .collect::<Result<Vec<_>>>()?;
+        let lengths = encoded
+            .iter()
+            .map(|bytes| to_u32(bytes.len()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let total: usize = encoded.iter().map(Vec::len).sum();
+        let longest = encoded.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut out = varint::encode(&lengths);
+        out.reserve(total);
+        for i in 0..longest {
+            out.extend(encoded.iter().filter_map(|bytes| bytes.get(i)));
+        }
+        Ok(out)
+    }
+
+    /// Inverse of `encode_interleaved`; `k` must be the number of streams that
+    /// were encoded.
+    pub fn decode_interleaved(
+        &self,
+        payload: &[u8],
+        k: usize,
+        gzip: bool,
+    ) -> Result<Vec<Vec<i32>>> {
+        let mut rest = payload;
+        let mut lengths = Vec::with_capacity(k.min(payload.len()));
+        for _ in 0..k {
+            lengths.push(read_varint(&mut rest)? as usize);
+        }
+        if lengths.iter().sum::<usize>() != rest.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut encoded: Vec<Vec<u8>> =
+            lengths.iter().map(|&len| Vec::with_capacity(len)).collect();
+        let mut bytes = rest.iter();
+        let longest = lengths.iter().copied().max().unwrap_or(0);
+        for i in 0..longest {
+            for (stream, &len) in encoded.iter_mut().zip(&lengths) {
+                if i < len {
+                    // The length check above guarantees enough bytes.
+                    stream.push(*bytes.next().ok_or(CodecError::InvalidPayload)?);
+                }
+            }
+        }
+
+        encoded
+            .iter()
+            .map(|bytes| CodecCore::decode_token_ids(bytes, gzip))
+            .collect()
+    }
+}
+
+/// Filler for the shorter sequence of a pair. It never reaches the caller,
+/// since the decoder knows both lengths.
+pub const PAIR_SENTINEL: i32 = -1;
+
+/// Token-interleaved sequence pairs (e.g. cross-encoder inputs), sharing one
+/// `FreqMap`: `a[0], b[0], a[1], b[1], ...`, with the shorter sequence padded
+/// with `PAIR_SENTINEL`.
+///
+/// Layout:
+///   [varint len_a][varint len_b][standard payload of the interleaved stream]
+impl Codec {
+    pub fn encode_pair_interleaved(
+        &self,
+        seq_a: &[i32],
+        seq_b: &[i32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let longest = seq_a.len().max(seq_b.len());
+        let padded = |seq: &[i32], i: usize| seq.get(i).copied().unwrap_or(PAIR_SENTINEL);
+        let stream: Vec<i32> = (0..longest)
+            .flat_map(|i| [padded(seq_a, i), padded(seq_b, i)])
+            .collect();
+
+        let mut out = varint::encode(&[to_u32(seq_a.len())?, to_u32(seq_b.len())?]);
+        out.extend_from_slice(&CodecCore::encode_token_ids(&stream, gzip)?);
+        Ok(out)
+    }
+
+    /// Inverse of `encode_pair_interleaved`. Fails with
+    /// `CodecError::InvalidPayload` unless `len_a` and `len_b` match the
+    /// lengths recorded in the payload.
+    pub fn decode_pair_interleaved(
+        &self,
+        payload: &[u8],
+        len_a: usize,
+        len_b: usize,
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<i32>)> {
+        let mut rest = payload;
+        let stored_a = read_varint(&mut rest)? as usize;
+        let stored_b = read_varint(&mut rest)? as usize;
+        if (stored_a, stored_b) != (len_a, len_b) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let stream = CodecCore::decode_token_ids(rest, gzip)?;
+        if stream.len() != 2 * len_a.max(len_b) {
+            return Err(CodecError::InvalidPayload);
+        }
+        let seq_a = stream.iter().step_by(2).take(len_a).copied().collect();
+        let seq_b = stream.iter().skip(1).step_by(2).take(len_b).copied().collect();
+        Ok((seq_a, seq_b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// K=4 codebook-like streams of 100 tokens with different vocabularies.
+    fn streams() -> Vec<Vec<i32>> {
+        (0..4u32)
+            .map(|k| {
+                (0..100u32)
+                    .map(|i| ((i.wrapping_mul(2654435761) >> k) % (4 << (2 * k))) as i32)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_matches_separate_payloads() {
+        let codec = Codec::new();
+        let streams = streams();
+        let refs: Vec<&[i32]> = streams.iter().map(Vec::as_slice).collect();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_interleaved(&refs, gzip).unwrap();
+            let decoded = codec.decode_interleaved(&payload, 4, gzip).unwrap();
+            assert_eq!(decoded, streams);
+
+            let separate: Vec<Vec<i32>> = refs
+                .iter()
+                .map(|stream| {
+                    let bytes = CodecCore::encode_token_ids(stream, gzip).unwrap();
+                    CodecCore::decode_token_ids(&bytes, gzip).unwrap()
+                })
+                .collect();
+            assert_eq!(decoded, separate);
+        }
+    }
+
+    #[test]
+    fn bytes_are_interleaved() {
+        let codec = Codec::new();
+        let a = CodecCore::encode_token_ids(&[1, 2], false).unwrap();
+        let b = CodecCore::encode_token_ids(&[3, 3], false).unwrap();
+        let payload = codec
+            .encode_interleaved(&[&[1, 2], &[3, 3]], false)
+            .unwrap();
+
+        let body = &payload[2..];
+        assert_eq!(&body[..4], &[a[0], b[0], a[1], b[1]]);
+        // `b` has one token fewer in its header, so `a` finishes alone.
+        assert_eq!(&body[body.len() - 4..], &a[a.len() - 4..]);
+    }
+
+    #[test]
+    fn rejects_unequal_streams_and_wrong_k() {
+        let codec = Codec::new();
+        assert!(codec.encode_interleaved(&[&[1, 2], &[1]], false).is_err());
+
+        let payload = codec
+            .encode_interleaved(&[&[1, 2], &[3, 4]], false)
+            .unwrap();
+        assert!(codec.decode_interleaved(&payload, 3, false).is_err());
+    }
+
+    #[test]
+    fn pairs_round_trip() {
+        let codec = Codec::new();
+        let query: Vec<i32> = (0..32).map(|i| (i * 31) % 500).collect();
+        let doc: Vec<i32> = (0..300).map(|i| (i * 7919) % 500).collect();
+        let pairs: [(&[i32], &[i32]); 5] = [
+            (&query, &query[..]),
+            (&query, &doc),
+            (&doc, &query),
+            (&[], &doc),
+            (&[], &[]),
+        ];
+
+        for (a, b) in pairs {
+            for gzip in [false, true] {
+                let payload = codec.encode_pair_interleaved(a, b, gzip).unwrap();
+                let (got_a, got_b) = codec
+                    .decode_pair_interleaved(&payload, a.len(), b.len(), gzip)
+                    .unwrap();
+                assert_eq!((got_a.as_slice(), got_b.as_slice()), (a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn pairs_are_interleaved_with_a_shared_table() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_pair_interleaved(&[1, 2, 3], &[2], false)
+            .unwrap();
+        assert_eq!(&payload[..2], &[3, 1]);
+        let stream = CodecCore::decode_token_ids(&payload[2..], false).unwrap();
+        assert_eq!(stream, [1, 2, 2, PAIR_SENTINEL, 3, PAIR_SENTINEL]);
+    }
+
+    #[test]
+    fn pair_lengths_must_match() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_pair_interleaved(&[1, 2, 3], &[4], false)
+            .unwrap();
+        assert!(codec.decode_pair_interleaved(&payload, 3, 2, false).is_err());
+        assert!(codec.decode_pair_interleaved(&payload, 1, 3, false).is_err());
+
+        // Recorded lengths that disagree with the stream.
+        let mut forged = payload.clone();
+        forged[0] = 5;
+        assert!(codec.decode_pair_interleaved(&forged, 5, 1, false).is_err());
+    }
+
+    #[test]
+    fn no_streams() {
+        let codec = Codec::new();
+        let payload = codec.encode_interleaved(&[], false).unwrap();
+        assert!(payload.is_empty());
+        assert!(codec
+            .decode_interleaved(&payload, 0, false)
+            .unwrap()
+            .is_empty());
+    }
+}
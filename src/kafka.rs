@@ -0,0 +1,101 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+
+/// Leading bytes of every Kafka-compatible payload.
+pub const KAFKA_MAGIC: &[u8; 4] = b"MISO";
+
+/// Envelope version written after `KAFKA_MAGIC`.
+pub const KAFKA_FORMAT_VERSION: u16 = 1;
+
+/// Envelope length: the magic and the version.
+pub const KAFKA_PREFIX_LEN: usize = KAFKA_MAGIC.len() + 2;
+
+/// Self-identifying payloads for message queues, where consumers in other
+/// languages see raw bytes without a schema registry:
+///   [b"MISO"][u16 LE envelope version][standard payload]
+///
+/// The standard payload is `encode_token_ids` output; FORMAT.md documents
+/// both layers.
+pub fn encode_kafka_compatible(token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+    let payload = CodecCore::encode_token_ids(token_ids, gzip)?;
+    let mut out = Vec::with_capacity(KAFKA_PREFIX_LEN + payload.len());
+    out.extend_from_slice(KAFKA_MAGIC);
+    out.extend_from_slice(&KAFKA_FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
+
+/// Whether `payload` starts with the Kafka envelope's magic bytes.
+pub fn is_kafka_compatible(payload: &[u8]) -> bool {
+    payload.len() >= KAFKA_PREFIX_LEN && payload.starts_with(KAFKA_MAGIC)
+}
+
+/// Errors with `CodecError::InvalidPayload` without the magic bytes, and
+/// with `CodecError::UnknownVersion` for a newer envelope (reported as its
+/// low byte).
+pub fn decode_kafka_compatible(payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+    if !is_kafka_compatible(payload) {
+        return Err(CodecError::InvalidPayload);
+    }
+    let version = u16::from_le_bytes([payload[4], payload[5]]);
+    if version != KAFKA_FORMAT_VERSION {
+        return Err(CodecError::UnknownVersion(version as u8));
+    }
+    CodecCore::decode_token_ids(&payload[KAFKA_PREFIX_LEN..], gzip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kafka_payloads_round_trip() {
+        let ids: Vec<i32> = (0..500).map(|i| (i * 7919) % 3000 - 40).collect();
+        for gzip in [false, true] {
+            let payload = encode_kafka_compatible(&ids, gzip).unwrap();
+            assert!(is_kafka_compatible(&payload));
+            assert_eq!(decode_kafka_compatible(&payload, gzip).unwrap(), ids);
+
+            // The rest is a standard payload.
+            assert_eq!(
+                payload[KAFKA_PREFIX_LEN..],
+                CodecCore::encode_token_ids(&ids, gzip).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn known_layout() {
+        let payload = encode_kafka_compatible(&[9], false).unwrap();
+        assert_eq!(
+            payload,
+            [b'M', b'I', b'S', b'O', 1, 0, 1, 0, 1, 0, 0, 0, 9, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn other_bytes_are_not_kafka_compatible() {
+        assert!(!is_kafka_compatible(&[0; 64]));
+        assert!(!is_kafka_compatible(&[]));
+        assert!(!is_kafka_compatible(b"MISO"));
+        assert!(!is_kafka_compatible(b"MISX\x01\x00"));
+
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(!is_kafka_compatible(&plain));
+        assert!(matches!(
+            decode_kafka_compatible(&plain, false),
+            Err(CodecError::InvalidPayload)
+        ));
+        assert!(decode_kafka_compatible(&[0; 64], false).is_err());
+    }
+
+    #[test]
+    fn newer_envelopes_are_rejected() {
+        let mut payload = encode_kafka_compatible(&[1, 2], false).unwrap();
+        payload[4] = 2;
+        assert!(matches!(
+            decode_kafka_compatible(&payload, false),
+            Err(CodecError::UnknownVersion(2))
+        ));
+    }
+}
@@ -0,0 +1,187 @@
+use half::f16;
+
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_KV_CACHE;
+use crate::{varint, Codec};
+
+/// Tensor bytes are XORed with the previous position's (`KvCache` layout).
+const KV_DELTA: u8 = 0x01;
+
+/// Token IDs plus a transformer KV cache, kept in the `FLAG_KV_CACHE`
+/// extension:
+///   [varint n_heads][varint head_dim][u8 kv flags][varint tensor byte length]
+///   [tensors: keys then values, f16 LE]
+///
+/// Both tensors are `[n_tokens][n_heads][head_dim]`, row-major. With gzip the
+/// tensor bytes are compressed too, after delta coding across the sequence
+/// (each element's bits XORed with the same element one position earlier,
+/// flagged `KV_DELTA`), which turns slowly changing activations into runs of
+/// small values. The length prefix lets readers skip the tensors without
+/// parsing them.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` unless both tensors hold
+    /// `n_tokens * n_heads * head_dim` elements.
+    pub fn encode_kv_cache(
+        &self,
+        token_ids: &[i32],
+        kv_keys: &[f16],
+        kv_values: &[f16],
+        n_heads: usize,
+        head_dim: usize,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let elements = token_ids
+            .len()
+            .checked_mul(n_heads)
+            .and_then(|n| n.checked_mul(head_dim));
+        if elements != Some(kv_keys.len()) || elements != Some(kv_values.len()) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let stride = n_heads * head_dim;
+        let flags = if gzip { KV_DELTA } else { 0 };
+        let mut tensors = Vec::with_capacity(4 * kv_keys.len());
+        for tensor in [kv_keys, kv_values] {
+            for (i, value) in tensor.iter().enumerate() {
+                let mut bits = value.to_bits();
+                if flags & KV_DELTA != 0 && i >= stride {
+                    bits ^= tensor[i - stride].to_bits();
+                }
+                tensors.extend_from_slice(&bits.to_le_bytes());
+            }
+        }
+        let tensors = CodecCore::compress(tensors, gzip)?;
+
+        let mut section = varint::encode(&[to_u32(n_heads)?, to_u32(head_dim)?]);
+        section.push(flags);
+        section.extend_from_slice(&varint::encode(&[to_u32(tensors.len())?]));
+        section.extend_from_slice(&tensors);
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_KV_CACHE, &section)], gzip)
+    }
+
+    /// Returns `(token_ids, kv_keys, kv_values)`.
+    pub fn decode_kv_cache(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<f16>, Vec<f16>)> {
+        let (tokens, section) = CodecCore::decode_with_extension(payload, FLAG_KV_CACHE, gzip)?;
+        let mut rest = section.ok_or(CodecError::InvalidPayload)?;
+
+        let n_heads = read_varint(&mut rest)? as usize;
+        let head_dim = read_varint(&mut rest)? as usize;
+        let (&flags, tail) = rest.split_first().ok_or(CodecError::InvalidPayload)?;
+        rest = tail;
+        let len = read_varint(&mut rest)? as usize;
+        if flags & !KV_DELTA != 0 || len != rest.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let stride = n_heads
+            .checked_mul(head_dim)
+            .ok_or(CodecError::InvalidPayload)?;
+        let elements = tokens
+            .len()
+            .checked_mul(stride)
+            .ok_or(CodecError::InvalidPayload)?;
+        let bytes = CodecCore::decompress(rest, gzip)?;
+        if Some(bytes.len()) != elements.checked_mul(4) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let (keys, values) = bytes.split_at(2 * elements);
+        let tensor = |bytes: &[u8]| {
+            let mut out: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            if flags & KV_DELTA != 0 {
+                for i in stride..out.len() {
+                    out[i] ^= out[i - stride];
+                }
+            }
+            out.into_iter().map(f16::from_bits).collect::<Vec<_>>()
+        };
+        Ok((tokens, tensor(keys), tensor(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N_HEADS: usize = 2;
+    const HEAD_DIM: usize = 4;
+
+    /// Keys and values for 6 tokens that drift slowly along the sequence.
+    fn cache() -> (Vec<i32>, Vec<f16>, Vec<f16>) {
+        let ids = vec![50, 7, 50, 12, 3, 50];
+        let elements = ids.len() * N_HEADS * HEAD_DIM;
+        let keys = (0..elements)
+            .map(|i| f16::from_f32(0.25 + i as f32 / 64.0))
+            .collect();
+        let values = (0..elements)
+            .map(|i| f16::from_f32(-1.0 + (i % 8) as f32 * 0.125))
+            .collect();
+        (ids, keys, values)
+    }
+
+    #[test]
+    fn kv_cache_round_trips_exactly() {
+        let codec = Codec::new();
+        let (ids, keys, values) = cache();
+        for gzip in [false, true] {
+            let payload = codec
+                .encode_kv_cache(&ids, &keys, &values, N_HEADS, HEAD_DIM, gzip)
+                .unwrap();
+            let (tokens, decoded_keys, decoded_values) =
+                codec.decode_kv_cache(&payload, gzip).unwrap();
+            assert_eq!(tokens, ids);
+            assert_eq!(decoded_keys, keys);
+            assert_eq!(decoded_values, values);
+
+            // Plain decoders skip the extension.
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn uncompressed_tensors_are_raw_f16() {
+        let codec = Codec::new();
+        let (ids, keys, values) = cache();
+        let payload = codec
+            .encode_kv_cache(&ids, &keys, &values, N_HEADS, HEAD_DIM, false)
+            .unwrap();
+        let parts = CodecCore::split_payload(&payload).unwrap();
+        let section = parts.extension(FLAG_KV_CACHE).unwrap();
+
+        let tensor_len = 2 * 2 * keys.len();
+        // n_heads, head_dim, flags, then the two-byte varint length.
+        assert_eq!(section[..3], [N_HEADS as u8, HEAD_DIM as u8, 0]);
+        assert_eq!(
+            varint::decode_one(&section[3..]).unwrap(),
+            (tensor_len as u32, 2)
+        );
+        assert_eq!(section[5..7], keys[0].to_bits().to_le_bytes());
+        assert_eq!(section.len(), 5 + tensor_len);
+    }
+
+    #[test]
+    fn mismatched_shapes_are_rejected() {
+        let codec = Codec::new();
+        let (ids, keys, values) = cache();
+        assert!(codec
+            .encode_kv_cache(&ids, &keys[1..], &values, N_HEADS, HEAD_DIM, false)
+            .is_err());
+        assert!(codec
+            .encode_kv_cache(&ids, &keys, &values, N_HEADS, HEAD_DIM + 1, false)
+            .is_err());
+        assert!(codec
+            .encode_kv_cache(&ids, &keys, &values, usize::MAX, 2, false)
+            .is_err());
+
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+        assert!(codec.decode_kv_cache(&plain, false).is_err());
+    }
+}
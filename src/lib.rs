@@ -1,36 +1,795 @@
-mod zigzag;
-mod varint;
-mod freq_map;
-mod codec_core;
-mod errors;
+pub mod zigzag;
+pub mod delta;
+pub mod varint;
+pub mod vbyte;
+pub mod rle;
+mod bits;
+pub mod rice;
+pub mod elias;
+pub mod prefix_free;
+pub mod huffman;
+pub mod arithmetic;
+pub mod quantize;
+pub mod freq_map;
+pub mod header;
+pub mod codec_core;
+pub mod compression;
+pub mod config;
+pub mod errors;
+
+#[cfg(feature = "tokio")]
+mod async_codec;
+mod attention_mask;
+#[cfg(feature = "parallel")]
+mod batch;
+mod bert;
+pub mod bigram;
+mod blocks;
+pub mod bloom;
+mod build_cost;
+mod causal_lm;
+pub mod compact_json;
+#[cfg(feature = "zstd")]
+pub mod compression_hint;
+mod custom_freq_map;
+pub mod custom_varint;
+mod decode_into;
+pub mod dictionary;
+mod ecc;
+pub mod explain;
+mod file_io;
+mod fingerprint;
+mod fixed_width;
+mod fused;
+pub mod histogram;
+pub mod homogeneous;
+mod int_array;
+mod interleave;
+pub mod kafka;
+#[cfg(feature = "half")]
+mod kv_cache;
+pub mod logits;
+mod metadata;
+mod mlm;
+mod model_info;
+pub mod msgpack;
+mod multi_modal;
+pub mod ndjson;
+pub mod npy;
+mod no_alloc;
+mod offsets;
+mod padded;
+mod pair_delta;
+mod position_bias;
+#[cfg(feature = "prost")]
+pub mod proto;
+pub mod provenance;
+mod retrieval;
+#[cfg(feature = "blake3")]
+mod salt;
+mod schema;
+mod segments;
+pub mod session;
+mod shared_batch;
+pub mod shared_payload;
+mod smart;
+mod sorted_permutation;
+mod sos;
+mod sparse_header;
+pub mod stream;
+pub mod temporal;
+pub mod typed;
+mod u16_ids;
+mod unchecked;
+pub mod version;
+mod vq;
+#[cfg(feature = "zstd")]
+pub mod zstd_dict;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
 
 use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes, PyDict};
+
+use crate::codec_core::CodecCore;
+use crate::config::{CodecConfig, VarintMode};
+use crate::freq_map::FreqMap;
+use crate::shared_payload::SharedPayload;
+
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct Codec {
+    config: CodecConfig,
+}
+
+impl Codec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: CodecConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &CodecConfig {
+        &self.config
+    }
+
+    /// Standard payload for `token_ids`, with the body encoding chosen by
+    /// `CodecConfig::varint_mode` and `CodecConfig::use_delta`.
+    pub fn encode(&self, token_ids: &[i32], gzip: bool) -> errors::Result<Vec<u8>> {
+        if self.config.use_delta {
+            return match self.config.varint_mode {
+                VarintMode::Leb128 => CodecCore::encode_token_ids_delta(token_ids, gzip),
+                _ => Err(errors::CodecError::Internal(
+                    "use_delta needs the leb128 varint mode".to_string(),
+                )),
+            };
+        }
+        match &self.config.varint_mode {
+            VarintMode::Leb128 => CodecCore::encode_token_ids(token_ids, gzip),
+            VarintMode::VByte => CodecCore::encode_token_ids_vbyte(token_ids, gzip),
+            VarintMode::Rice => CodecCore::encode_token_ids_rice(token_ids, gzip),
+            VarintMode::EliasGamma => CodecCore::encode_token_ids_elias_gamma(token_ids, gzip),
+            VarintMode::Huffman => CodecCore::encode_token_ids_huffman(token_ids, gzip),
+            VarintMode::Arithmetic => CodecCore::encode_token_ids_arithmetic(token_ids, gzip),
+            VarintMode::Custom(encoder) => {
+                self.encode_with_custom_varint(token_ids, encoder.as_ref(), gzip)
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl Codec {
+    /// `varint_mode` is `"leb128"` (the default), `"vbyte"`, `"rice"`,
+    /// `"elias_gamma"`, `"huffman"` or `"arithmetic"`. `logits_top_p` defaults
+    /// to 0.9. `use_delta` delta-codes mapped IDs (leb128 only).
+    #[new]
+    #[pyo3(signature = (unk_token = None, varint_mode = "leb128", logits_top_p = None, use_delta = false))]
+    fn py_new(
+        unk_token: Option<i32>,
+        varint_mode: &str,
+        logits_top_p: Option<f32>,
+        use_delta: bool,
+    ) -> PyResult<Self> {
+        Ok(Self::with_config(CodecConfig {
+            unk_token,
+            varint_mode: varint_mode.parse()?,
+            use_delta,
+            pad_byte: 0,
+            logits_top_p,
+            #[cfg(feature = "zstd")]
+            zstd_dict: None,
+        }))
+    }
+
+    pub fn ping(&self) -> PyResult<String> {
+        Ok("pong".to_string())
+    }
+
+    pub fn encode_token_ids(&self, token_ids: Vec<i32>, gzip: bool) -> PyResult<Vec<u8>> {
+        Ok(self.encode(&token_ids, gzip)?)
+    }
+
+    pub fn decode_token_ids(&self, payload: Vec<u8>, gzip: bool) -> PyResult<Vec<i32>> {
+        Ok(CodecCore::decode_token_ids(&payload, gzip)?)
+    }
+
+    /// Encode against a fixed vocabulary, given as `FreqMap` JSON
+    /// (`{"tokens": [...], "frequencies": [...]}`).
+    #[pyo3(name = "encode_with_freq_map")]
+    pub fn py_encode_with_freq_map(
+        &self,
+        token_ids: Vec<i32>,
+        freq_map_json: &str,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        let freq_map = FreqMap::from_json(freq_map_json)?;
+        Ok(self.encode_with_freq_map(&token_ids, &freq_map, gzip)?)
+    }
+
+    /// The `FreqMap` `encode_token_ids` would build, as JSON.
+    #[pyo3(name = "build_freq_map")]
+    pub fn py_build_freq_map(&self, token_ids: Vec<i32>) -> String {
+        self.build_freq_map(&token_ids).to_json()
+    }
+
+    /// Smallest vocabulary (most frequent tokens first) covering at least
+    /// `target_coverage` of `token_ids`.
+    pub fn min_vocab_size(&self, token_ids: Vec<i32>, target_coverage: f64) -> usize {
+        FreqMap::from_token_ids(&token_ids).min_k_for_coverage(target_coverage)
+    }
+
+    /// Fraction of `query_token_ids` covered by the vocabulary of
+    /// `vocab_token_ids`.
+    pub fn vocab_coverage(&self, vocab_token_ids: Vec<i32>, query_token_ids: Vec<i32>) -> f64 {
+        FreqMap::coverage_of_b_by_a(&FreqMap::from_token_ids(&vocab_token_ids), &query_token_ids)
+    }
+
+    /// Encode into an immutable payload that can be shared without copying.
+    pub fn encode_token_ids_shared(&self, token_ids: Vec<i32>, gzip: bool) -> PyResult<SharedPayload> {
+        Ok(SharedPayload::new(self.encode(&token_ids, gzip)?))
+    }
+
+    /// Decode, also returning the header as a dict with `tokens` (original
+    /// token IDs in mapped-ID order), `len` and `version`.
+    pub fn decode_token_ids_with_header(
+        &self,
+        py: Python<'_>,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, PyObject)> {
+        let (tokens, header) = CodecCore::decode_with_header(&payload, gzip)?;
+        let dict = PyDict::new_bound(py);
+        dict.set_item("tokens", &header.tokens)?;
+        dict.set_item("len", header.len)?;
+        dict.set_item("version", header.version)?;
+        Ok((tokens, dict.into()))
+    }
+
+    /// Decode, returning a dict of per-token `tokens`, `mapped_ids`,
+    /// `zigzag_values` and `varint_byte_offsets` (into the decompressed body).
+    #[pyo3(signature = (payload, gzip = false))]
+    pub fn decode_traced(
+        &self,
+        py: Python<'_>,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<PyObject> {
+        let trace = self.decode_with_trace(&payload, gzip)?;
+        let dict = PyDict::new_bound(py);
+        dict.set_item("tokens", trace.tokens)?;
+        dict.set_item("mapped_ids", trace.mapped_ids)?;
+        dict.set_item("zigzag_values", trace.zigzag_values)?;
+        dict.set_item("varint_byte_offsets", trace.varint_positions)?;
+        Ok(dict.into())
+    }
+
+    #[pyo3(name = "explain", signature = (token_ids, gzip = false))]
+    pub fn py_explain(&self, token_ids: Vec<i32>, gzip: bool) -> String {
+        self.explain(&token_ids, gzip)
+    }
+
+    /// Predicted uncompressed payload sizes, without encoding anything.
+    pub fn estimate_sizes(&self, token_ids: Vec<i32>) -> HashMap<String, usize> {
+        let freq = FreqMap::from_token_ids(&token_ids);
+        HashMap::from([
+            ("header_bytes".to_string(), freq.expected_header_size()),
+            (
+                "body_bytes_estimate".to_string(),
+                freq.expected_body_size_estimate(),
+            ),
+            (
+                "total_estimate".to_string(),
+                freq.expected_total_size_estimate(),
+            ),
+        ])
+    }
+
+    #[pyo3(name = "encode_to_file")]
+    pub fn py_encode_to_file(&self, token_ids: Vec<i32>, path: PathBuf, gzip: bool) -> PyResult<()> {
+        Ok(self.encode_to_file(&token_ids, &path, gzip)?)
+    }
+
+    #[pyo3(name = "decode_from_file")]
+    pub fn py_decode_from_file(&self, path: PathBuf, gzip: bool) -> PyResult<Vec<i32>> {
+        Ok(self.decode_from_file(&path, gzip)?)
+    }
+
+    #[pyo3(name = "encode_to_file_mmap")]
+    pub fn py_encode_to_file_mmap(
+        &self,
+        token_ids: Vec<i32>,
+        path: PathBuf,
+        gzip: bool,
+    ) -> PyResult<()> {
+        Ok(self.encode_to_file_mmap(&token_ids, &path, gzip)?)
+    }
+
+    /// Encode causal-LM inputs once; labels (inputs shifted left, `pad_id`
+    /// appended) are rebuilt by `decode_causal_lm_batch`.
+    #[pyo3(name = "encode_causal_lm_batch")]
+    pub fn py_encode_causal_lm_batch(
+        &self,
+        sequences: Vec<Vec<i32>>,
+        pad_id: i32,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_causal_lm_batch(&sequences, pad_id, gzip)?)
+    }
+
+    /// Returns `(input_ids, labels)`.
+    #[pyo3(name = "decode_causal_lm_batch")]
+    pub fn py_decode_causal_lm_batch(
+        &self,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<causal_lm::CausalLmBatch> {
+        Ok(self.decode_causal_lm_batch(&payload, gzip)?)
+    }
+
+    /// Token IDs plus an attention mask of 0s and 1s, one per token.
+    #[pyo3(name = "encode_tokens_and_mask")]
+    pub fn py_encode_tokens_and_mask(
+        &self,
+        token_ids: Vec<i32>,
+        attention_mask: Vec<i32>,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_tokens_and_mask(&token_ids, &attention_mask, gzip)?)
+    }
+
+    /// Returns `(token_ids, attention_mask)`.
+    #[pyo3(name = "decode_tokens_and_mask")]
+    pub fn py_decode_tokens_and_mask(
+        &self,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, Vec<i32>)> {
+        Ok(self.decode_tokens_and_mask(&payload, gzip)?)
+    }
+
+    /// Token IDs below 65536, with a half-size token table.
+    #[pyo3(name = "encode_u16_ids")]
+    pub fn py_encode_u16_ids(&self, token_ids: Vec<u16>, gzip: bool) -> PyResult<Vec<u8>> {
+        Ok(self.encode_u16_ids(&token_ids, gzip)?)
+    }
+
+    #[pyo3(name = "decode_u16_ids")]
+    pub fn py_decode_u16_ids(&self, payload: Vec<u8>, gzip: bool) -> PyResult<Vec<u16>> {
+        Ok(self.decode_u16_ids(&payload, gzip)?)
+    }
+
+    /// Encode into the front of the `bytearray` `out`, returning the number of
+    /// bytes written.
+    #[pyo3(name = "encode_no_alloc")]
+    pub fn py_encode_no_alloc(
+        &self,
+        token_ids: Vec<i32>,
+        gzip: bool,
+        out: &Bound<'_, PyByteArray>,
+    ) -> PyResult<usize> {
+        // SAFETY: no Python code runs while the slice is alive, so the
+        // bytearray cannot be resized or freed under it.
+        let buf = unsafe { out.as_bytes_mut() };
+        Ok(self.encode_no_alloc(&token_ids, gzip, buf)?)
+    }
+
+    /// Upper bound on the uncompressed payload size for `encode_no_alloc`.
+    #[staticmethod]
+    #[pyo3(name = "max_encoded_size")]
+    pub fn py_max_encoded_size(n_tokens: usize, n_unique: usize) -> usize {
+        Self::max_encoded_size(n_tokens, n_unique)
+    }
+
+    /// Estimated nanoseconds to build a frequency map, from costs calibrated
+    /// on first use.
+    #[staticmethod]
+    #[pyo3(name = "estimate_freq_map_build_ns")]
+    pub fn py_estimate_freq_map_build_ns(n_tokens: usize, n_unique_estimate: usize) -> u64 {
+        Self::estimate_freq_map_build_ns(n_tokens, n_unique_estimate)
+    }
+
+    /// 64-bit XXH3 hash of `token_ids`.
+    #[staticmethod]
+    #[pyo3(name = "fingerprint")]
+    pub fn py_fingerprint(token_ids: Vec<i32>) -> u64 {
+        Self::fingerprint(&token_ids)
+    }
+
+    /// Returns `(payload, fingerprint)`.
+    #[pyo3(name = "encode_with_fingerprint")]
+    pub fn py_encode_with_fingerprint(
+        &self,
+        token_ids: Vec<i32>,
+        gzip: bool,
+    ) -> PyResult<(Vec<u8>, u64)> {
+        Ok(self.encode_with_fingerprint(&token_ids, gzip)?)
+    }
+
+    #[pyo3(name = "verify_fingerprint")]
+    pub fn py_verify_fingerprint(
+        &self,
+        payload: Vec<u8>,
+        expected_fp: u64,
+        gzip: bool,
+    ) -> PyResult<bool> {
+        Ok(self.verify_fingerprint(&payload, expected_fp, gzip)?)
+    }
+
+    /// `hint` is `"speed"`, `"balanced"` or `"size"`.
+    #[cfg(feature = "zstd")]
+    #[pyo3(name = "encode_with_hint")]
+    pub fn py_encode_with_hint(&self, token_ids: Vec<i32>, hint: &str) -> PyResult<Vec<u8>> {
+        Ok(self.encode_with_hint(&token_ids, hint.parse()?)?)
+    }
+
+    #[cfg(feature = "zstd")]
+    #[pyo3(name = "decode_with_hint")]
+    pub fn py_decode_with_hint(&self, payload: Vec<u8>) -> PyResult<Vec<i32>> {
+        Ok(self.decode_with_hint(&payload)?)
+    }
+
+    /// Token IDs plus a per-position bias, stored as f16.
+    #[pyo3(name = "encode_with_position_bias")]
+    pub fn py_encode_with_position_bias(
+        &self,
+        token_ids: Vec<i32>,
+        position_bias: Vec<f32>,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_with_position_bias(&token_ids, &position_bias, gzip)?)
+    }
+
+    /// Returns `(token_ids, position_bias)`.
+    #[pyo3(name = "decode_with_position_bias")]
+    pub fn py_decode_with_position_bias(
+        &self,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, Vec<f32>)> {
+        Ok(self.decode_with_position_bias(&payload, gzip)?)
+    }
+
+    /// `codebook_ids` is row-major, `n_codebooks` codes per token.
+    #[pyo3(name = "encode_vq")]
+    pub fn py_encode_vq(
+        &self,
+        codebook_ids: Vec<i32>,
+        n_codebooks: usize,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_vq(&codebook_ids, n_codebooks, gzip)?)
+    }
+
+    /// One list per codebook.
+    #[pyo3(name = "decode_vq")]
+    pub fn py_decode_vq(
+        &self,
+        payload: Vec<u8>,
+        n_codebooks: usize,
+        gzip: bool,
+    ) -> PyResult<Vec<Vec<i32>>> {
+        Ok(self.decode_vq(&payload, n_codebooks, gzip)?)
+    }
+
+    /// Concatenated documents; `segment_lengths` must add up to the token
+    /// count.
+    #[pyo3(name = "encode_with_segments")]
+    pub fn py_encode_with_segments(
+        &self,
+        token_ids: Vec<i32>,
+        segment_lengths: Vec<u32>,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_with_segments(&token_ids, &segment_lengths, gzip)?)
+    }
+
+    /// Returns `(token_ids, segment_lengths)`.
+    #[pyo3(name = "decode_with_segments")]
+    pub fn py_decode_with_segments(
+        &self,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, Vec<u32>)> {
+        Ok(self.decode_with_segments(&payload, gzip)?)
+    }
+
+    /// Standard payload plus one parity byte per 64-byte block.
+    #[pyo3(name = "encode_with_ecc")]
+    pub fn py_encode_with_ecc(&self, token_ids: Vec<i32>, gzip: bool) -> PyResult<Vec<u8>> {
+        Ok(self.encode_with_ecc(&token_ids, gzip)?)
+    }
+
+    /// Returns `(token_ids, block_errors)`: whether each block failed its
+    /// parity check.
+    #[pyo3(name = "decode_with_ecc")]
+    pub fn py_decode_with_ecc(
+        &self,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, Vec<bool>)> {
+        Ok(self.decode_with_ecc(&payload, gzip)?)
+    }
+
+    /// Per-block parity check of an `encode_with_ecc` payload, without
+    /// decoding it.
+    #[pyo3(name = "check_ecc")]
+    pub fn py_check_ecc(&self, payload: Vec<u8>) -> PyResult<Vec<bool>> {
+        Ok(self.check_ecc(&payload)?)
+    }
+
+    /// Payload whose token table lists only the `k` most frequent tokens.
+    #[pyo3(name = "encode_sparse_header")]
+    pub fn py_encode_sparse_header(
+        &self,
+        token_ids: Vec<i32>,
+        k: usize,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_sparse_header(&token_ids, k, gzip)?)
+    }
+
+    /// `encode_sparse_header` with the table size giving the smallest payload.
+    #[pyo3(name = "encode_adaptive")]
+    pub fn py_encode_adaptive(&self, token_ids: Vec<i32>, gzip: bool) -> PyResult<Vec<u8>> {
+        Ok(self.encode_adaptive(&token_ids, gzip)?)
+    }
+
+    #[pyo3(name = "decode_sparse_header")]
+    pub fn py_decode_sparse_header(&self, payload: Vec<u8>, gzip: bool) -> PyResult<Vec<i32>> {
+        Ok(self.decode_sparse_header(&payload, gzip)?)
+    }
+
+    /// Token IDs plus the top-p part of their logits (`vocab_size` per
+    /// token, row-major).
+    #[pyo3(name = "encode_with_logits")]
+    pub fn py_encode_with_logits(
+        &self,
+        token_ids: Vec<i32>,
+        logits: Vec<f32>,
+        vocab_size: usize,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_with_logits(&token_ids, &logits, vocab_size, gzip)?)
+    }
+
+    /// Returns `(token_ids, logits)`, with a list of `(vocab_index, logit)`
+    /// pairs per token, most probable first.
+    #[pyo3(name = "decode_with_logits")]
+    pub fn py_decode_with_logits(
+        &self,
+        payload: Vec<u8>,
+        vocab_size: usize,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, Vec<logits::TopPLogits>)> {
+        Ok(self.decode_with_logits(&payload, vocab_size, gzip)?)
+    }
+
+    /// Standard payload behind the `b"MISO"` magic and an envelope version,
+    /// for message queues.
+    #[staticmethod]
+    #[pyo3(name = "encode_kafka_compatible")]
+    pub fn py_encode_kafka_compatible(token_ids: Vec<i32>, gzip: bool) -> PyResult<Vec<u8>> {
+        Ok(kafka::encode_kafka_compatible(&token_ids, gzip)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_kafka_compatible")]
+    pub fn py_decode_kafka_compatible(payload: Vec<u8>, gzip: bool) -> PyResult<Vec<i32>> {
+        Ok(kafka::decode_kafka_compatible(&payload, gzip)?)
+    }
+
+    /// Whether `payload` starts with the `b"MISO"` magic.
+    #[staticmethod]
+    #[pyo3(name = "is_kafka_compatible")]
+    pub fn py_is_kafka_compatible(payload: Vec<u8>) -> bool {
+        kafka::is_kafka_compatible(&payload)
+    }
+
+    /// Token IDs as a MessagePack `{"tokens": [...], "version": 1}` map, with
+    /// no miso compression.
+    #[staticmethod]
+    #[pyo3(name = "encode_as_msgpack")]
+    pub fn py_encode_as_msgpack(token_ids: Vec<i32>) -> PyResult<Vec<u8>> {
+        Ok(msgpack::encode_as_msgpack(&token_ids)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_from_msgpack")]
+    pub fn py_decode_from_msgpack(payload: Vec<u8>) -> PyResult<Vec<i32>> {
+        Ok(msgpack::decode_from_msgpack(&payload)?)
+    }
+
+    /// Standard payload wrapped in a MessagePack `bin` value.
+    #[staticmethod]
+    #[pyo3(name = "encode_compressed_msgpack")]
+    pub fn py_encode_compressed_msgpack(token_ids: Vec<i32>, gzip: bool) -> PyResult<Vec<u8>> {
+        Ok(msgpack::encode_compressed_msgpack(&token_ids, gzip)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_compressed_msgpack")]
+    pub fn py_decode_compressed_msgpack(payload: Vec<u8>, gzip: bool) -> PyResult<Vec<i32>> {
+        Ok(msgpack::decode_compressed_msgpack(&payload, gzip)?)
+    }
+
+    /// Write `token_ids` as a 1-D int32 `.npy` file for `numpy.load`.
+    #[staticmethod]
+    #[pyo3(name = "encode_as_npy")]
+    pub fn py_encode_as_npy(token_ids: Vec<i32>, path: PathBuf) -> PyResult<()> {
+        Ok(npy::encode_as_npy(&token_ids, &path)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_from_npy")]
+    pub fn py_decode_from_npy(path: PathBuf) -> PyResult<Vec<i32>> {
+        Ok(npy::decode_from_npy(&path)?)
+    }
+
+    /// Write equal-length rows as a 2-D int32 `.npy` file.
+    #[staticmethod]
+    #[pyo3(name = "encode_matrix_as_npy")]
+    pub fn py_encode_matrix_as_npy(rows: Vec<Vec<i32>>, path: PathBuf) -> PyResult<()> {
+        let rows: Vec<&[i32]> = rows.iter().map(Vec::as_slice).collect();
+        Ok(npy::encode_matrix_as_npy(&rows, &path)?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_matrix_from_npy")]
+    pub fn py_decode_matrix_from_npy(path: PathBuf) -> PyResult<Vec<Vec<i32>>> {
+        Ok(npy::decode_matrix_from_npy(&path)?)
+    }
+
+    /// `{"v":1,"d":"<base64 payload>"}`, for JSON APIs.
+    #[staticmethod]
+    #[pyo3(name = "encode_compact_json")]
+    pub fn py_encode_compact_json(token_ids: Vec<i32>) -> String {
+        compact_json::encode_compact_json(&token_ids)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_compact_json")]
+    pub fn py_decode_compact_json(json: &str) -> PyResult<Vec<i32>> {
+        Ok(compact_json::decode_compact_json(json)?)
+    }
+
+    /// A JSON array of `encode_compact_json` objects.
+    #[staticmethod]
+    #[pyo3(name = "encode_compact_json_array")]
+    pub fn py_encode_compact_json_array(sequences: Vec<Vec<i32>>) -> String {
+        let sequences: Vec<&[i32]> = sequences.iter().map(Vec::as_slice).collect();
+        compact_json::encode_compact_json_array(&sequences)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "decode_compact_json_array")]
+    pub fn py_decode_compact_json_array(json: &str) -> PyResult<Vec<Vec<i32>>> {
+        Ok(compact_json::decode_compact_json_array(json)?)
+    }
+
+    /// Standard payload with a Bloom filter over its distinct tokens.
+    #[pyo3(name = "encode_with_bloom")]
+    pub fn py_encode_with_bloom(
+        &self,
+        token_ids: Vec<i32>,
+        false_positive_rate: f64,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_with_bloom(&token_ids, false_positive_rate, gzip)?)
+    }
+
+    /// Whether a `encode_with_bloom` payload may contain `token`, without
+    /// decoding it.
+    #[pyo3(name = "query_bloom")]
+    pub fn py_query_bloom(&self, payload: Vec<u8>, token: i32) -> PyResult<bool> {
+        Ok(self.query_bloom(&payload, token)?)
+    }
+
+    /// Standard payload followed by the SHA-256 of its source text, which
+    /// must be 32 bytes.
+    #[pyo3(name = "encode_with_source")]
+    pub fn py_encode_with_source(
+        &self,
+        token_ids: Vec<i32>,
+        source_sha256: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        let hash: [u8; provenance::SOURCE_HASH_LEN] =
+            source_sha256.try_into().map_err(|hash: Vec<u8>| {
+                errors::CodecError::Internal(format!(
+                    "source hash must be {} bytes, got {}",
+                    provenance::SOURCE_HASH_LEN,
+                    hash.len()
+                ))
+            })?;
+        Ok(self.encode_with_source_hash(&token_ids, &hash, gzip)?)
+    }
+
+    /// The source hash of an `encode_with_source` payload, without decoding
+    /// it.
+    #[staticmethod]
+    #[pyo3(name = "decode_source_hash_only")]
+    pub fn py_decode_source_hash_only(payload: Vec<u8>) -> PyResult<Vec<u8>> {
+        Ok(provenance::decode_source_hash_only(&payload)?.to_vec())
+    }
+
+    /// Whether an `encode_with_source` payload carries `expected_hash`.
+    #[pyo3(name = "verify_source_hash")]
+    pub fn py_verify_source_hash(&self, payload: Vec<u8>, expected_hash: Vec<u8>) -> bool {
+        <[u8; provenance::SOURCE_HASH_LEN]>::try_from(expected_hash)
+            .is_ok_and(|hash| self.verify_source_hash(&payload, &hash))
+    }
+
+    /// Standard payload plus the sorted positions of the masked tokens.
+    #[pyo3(name = "encode_mlm_input")]
+    pub fn py_encode_mlm_input(
+        &self,
+        token_ids: Vec<i32>,
+        masked_positions: Vec<u32>,
+        gzip: bool,
+    ) -> PyResult<Vec<u8>> {
+        Ok(self.encode_mlm_input(&token_ids, &masked_positions, gzip)?)
+    }
+
+    /// Returns `(token_ids, masked_positions)`.
+    #[pyo3(name = "decode_mlm_input")]
+    pub fn py_decode_mlm_input(
+        &self,
+        payload: Vec<u8>,
+        gzip: bool,
+    ) -> PyResult<(Vec<i32>, Vec<u32>)> {
+        Ok(self.decode_mlm_input(&payload, gzip)?)
+    }
+
+    /// Stream the payload into an open file descriptor (which stays open), via
+    /// `os.fdopen`. Returns the number of bytes written.
+    pub fn encode_to_fd(
+        &self,
+        py: Python<'_>,
+        token_ids: Vec<i32>,
+        fd: i32,
+        gzip: bool,
+    ) -> PyResult<usize> {
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("closefd", false)?;
+        let file = py
+            .import_bound("os")?
+            .call_method("fdopen", (fd, "wb"), Some(&kwargs))?;
+
+        let mut writer = PyFileWriter(file);
+        let written = self.encode_streaming(&token_ids, gzip, &mut writer)?;
+        writer.0.call_method0("close")?;
+        Ok(written)
+    }
+
+    /// Encode a batch on all cores, with the GIL released while the work runs.
+    #[cfg(feature = "parallel")]
+    #[pyo3(name = "encode_batch_parallel")]
+    pub fn py_encode_batch_parallel(
+        &self,
+        py: Python<'_>,
+        sequences: Vec<Vec<i32>>,
+        gzip: bool,
+    ) -> PyResult<Vec<Vec<u8>>> {
+        Ok(py.allow_threads(|| self.encode_batch_parallel(sequences, gzip))?)
+    }
+
+    #[cfg(feature = "parallel")]
+    #[pyo3(name = "decode_batch_parallel")]
+    pub fn py_decode_batch_parallel(
+        &self,
+        py: Python<'_>,
+        payloads: Vec<Vec<u8>>,
+        gzip: bool,
+    ) -> PyResult<Vec<Vec<i32>>> {
+        Ok(py.allow_threads(|| self.decode_batch_parallel(payloads, gzip))?)
+    }
+}
+
+/// `io::Write` over a Python binary file object.
+struct PyFileWriter<'py>(Bound<'py, PyAny>);
+
+impl Write for PyFileWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes = PyBytes::new_bound(self.0.py(), buf);
+        self.0
+            .call_method1("write", (bytes,))
+            .and_then(|n| n.extract())
+            .map_err(io::Error::other)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.call_method0("flush").map(drop).map_err(io::Error::other)
+    }
+}
 
-  #[pyclass]
-  pub struct Codec;
-
-  #[pymethods]
-  impl Codec {
-      #[new]
-      pub fn new() -> Self {
-          Codec
-      }
-
-      pub fn ping(&self) -> PyResult<String> {
-          Ok("pong".to_string())
-      }
-
-      pub fn encode_token_ids(&self, _token_ids: Vec<i32>, _gzip: bool) -> PyResult<Vec<u8>> {
-          unimplemented!()
-      }
-
-      pub fn decode_token_ids(&self, _payload: Vec<u8>, _gzip: bool) -> PyResult<Vec<i32>> {
-          unimplemented!()
-      }
-  }
-
-  #[pymodule]
-  fn miso(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
-      m.add_class::<Codec>()?;
-      Ok(())
-  }
\ No newline at end of file
+#[pymodule]
+fn miso(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Codec>()?;
+    m.add_class::<SharedPayload>()?;
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_LOGITS;
+use crate::{quantize, varint, Codec};
+
+/// `CodecConfig::logits_top_p` when unset.
+pub const DEFAULT_TOP_P: f32 = 0.9;
+
+/// One position's kept `(vocab index, logit)` pairs.
+pub type TopPLogits = Vec<(u32, f32)>;
+
+/// Token IDs plus the top-p (nucleus) part of each position's logits, for
+/// speculative decoding.
+///
+/// Per position, logits are ranked by softmax probability and kept until
+/// their probabilities add up to `CodecConfig::logits_top_p` (at least one is
+/// always kept). The `FLAG_LOGITS` extension holds, per position:
+///   [varint kept][kept × (varint vocab index, f16 LE logit)]
+/// with the kept logits most probable first.
+impl Codec {
+    /// `logits` is row-major, `vocab_size` entries per token; errors with
+    /// `CodecError::InvalidPayload` unless it holds exactly that many.
+    pub fn encode_with_logits(
+        &self,
+        token_ids: &[i32],
+        logits: &[f32],
+        vocab_size: usize,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if token_ids.len().checked_mul(vocab_size) != Some(logits.len()) {
+            return Err(CodecError::InvalidPayload);
+        }
+        let top_p = self.logits_top_p()?;
+
+        let mut section = Vec::new();
+        for row in logits.chunks(vocab_size.max(1)) {
+            let kept = top_p_indices(row, top_p);
+            section.extend_from_slice(&varint::encode(&[to_u32(kept.len())?]));
+            for index in kept {
+                section.extend_from_slice(&varint::encode(&[to_u32(index)?]));
+                section.extend_from_slice(&quantize::encode_f16_quantized(&[row[index]]));
+            }
+        }
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_LOGITS, &section)], gzip)
+    }
+
+    /// The tokens and, per token, the kept `(vocab index, logit)` pairs, most
+    /// probable first. Logits come back rounded to the nearest f16.
+    pub fn decode_with_logits(
+        &self,
+        payload: &[u8],
+        vocab_size: usize,
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<TopPLogits>)> {
+        let (tokens, section) = CodecCore::decode_with_extension(payload, FLAG_LOGITS, gzip)?;
+        let mut rest = section.ok_or(CodecError::InvalidPayload)?;
+
+        let mut logits = Vec::with_capacity(tokens.len());
+        for _ in 0..tokens.len() {
+            let kept = read_varint(&mut rest)? as usize;
+            if kept > vocab_size || kept > rest.len() / 3 {
+                return Err(CodecError::InvalidPayload);
+            }
+            let mut row = Vec::with_capacity(kept);
+            for _ in 0..kept {
+                let index = read_varint(&mut rest)?;
+                if index as usize >= vocab_size || rest.len() < 2 {
+                    return Err(CodecError::InvalidPayload);
+                }
+                let (bits, tail) = rest.split_at(2);
+                row.push((
+                    index,
+                    quantize::f16_bits_to_f32(u16::from_le_bytes([bits[0], bits[1]])),
+                ));
+                rest = tail;
+            }
+            logits.push(row);
+        }
+
+        if !rest.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok((tokens, logits))
+    }
+
+    fn logits_top_p(&self) -> Result<f32> {
+        let top_p = self.config.logits_top_p.unwrap_or(DEFAULT_TOP_P);
+        if !(top_p > 0.0 && top_p <= 1.0) {
+            return Err(CodecError::Internal(format!(
+                "top_p must be in (0, 1], got {top_p}"
+            )));
+        }
+        Ok(top_p)
+    }
+}
+
+/// Indices of `row`'s most probable logits, most probable first (lower index
+/// on ties), until their softmax probabilities reach `top_p`.
+fn top_p_indices(row: &[f32], top_p: f32) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..row.len()).collect();
+    order.sort_by(|&a, &b| row[b].total_cmp(&row[a]).then(a.cmp(&b)));
+
+    let Some(&first) = order.first() else {
+        return order;
+    };
+    // Softmax relative to the largest logit, in f64 for stable sums.
+    let max = f64::from(row[first]);
+    let weights: Vec<f64> = order
+        .iter()
+        .map(|&i| (f64::from(row[i]) - max).exp())
+        .collect();
+    let target = f64::from(top_p) * weights.iter().sum::<f64>();
+
+    let mut cumulative = 0.0;
+    let kept = weights
+        .iter()
+        .take_while(|&&w| {
+            let keep = cumulative < target;
+            cumulative += w;
+            keep
+        })
+        .count();
+    order.truncate(kept.max(1));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CodecConfig;
+
+    const VOCAB: usize = 10;
+
+    /// 3 positions over a 10-token vocabulary: one confident, one split
+    /// between two tokens, one flat.
+    fn logits() -> Vec<f32> {
+        let mut logits = vec![0.0; 3 * VOCAB];
+        logits[3] = 8.0;
+        logits[VOCAB + 1] = 4.0;
+        logits[VOCAB + 7] = 4.0;
+        logits[2 * VOCAB..]
+            .copy_from_slice(&[0.5, 0.4, 0.3, 0.2, 0.1, 0.0, -0.1, -0.2, -0.3, -0.4]);
+        logits
+    }
+
+    fn codec(top_p: f32) -> Codec {
+        Codec::with_config(CodecConfig {
+            logits_top_p: Some(top_p),
+            ..CodecConfig::default()
+        })
+    }
+
+    #[test]
+    fn top_p_keeps_the_nucleus() {
+        let codec = codec(0.9);
+        let ids = [3, 7, 0];
+        for gzip in [false, true] {
+            let payload = codec
+                .encode_with_logits(&ids, &logits(), VOCAB, gzip)
+                .unwrap();
+            let (tokens, kept) = codec.decode_with_logits(&payload, VOCAB, gzip).unwrap();
+            assert_eq!(tokens, ids);
+
+            // 8.0 against nine zeros already holds over 97% of the mass.
+            assert_eq!(kept[0], [(3, 8.0)]);
+            // Two tied logits, lower index first, each just under half.
+            assert_eq!(kept[1], [(1, 4.0), (7, 4.0)]);
+            // A flat row needs most of the vocabulary.
+            let indices: Vec<u32> = kept[2].iter().map(|&(i, _)| i).collect();
+            assert_eq!(indices, [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+            for &(index, logit) in &kept[2] {
+                let want = logits()[2 * VOCAB + index as usize];
+                assert!((logit - want).abs() <= 1e-3, "{logit} vs {want}");
+            }
+
+            // Plain decoders skip the extension.
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn top_p_bounds() {
+        let ids = [3, 7, 0];
+        let all = codec(1.0)
+            .encode_with_logits(&ids, &logits(), VOCAB, false)
+            .unwrap();
+        let (_, kept) = codec(1.0).decode_with_logits(&all, VOCAB, false).unwrap();
+        assert!(kept.iter().all(|row| row.len() == VOCAB));
+
+        let tiny = codec(1e-6)
+            .encode_with_logits(&ids, &logits(), VOCAB, false)
+            .unwrap();
+        let (_, kept) = codec(1e-6).decode_with_logits(&tiny, VOCAB, false).unwrap();
+        assert!(kept.iter().all(|row| row.len() == 1));
+
+        for bad in [0.0, 1.5, f32::NAN] {
+            assert!(codec(bad)
+                .encode_with_logits(&ids, &logits(), VOCAB, false)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn default_top_p_is_0_9() {
+        let ids = [3, 7, 0];
+        let default = Codec::new()
+            .encode_with_logits(&ids, &logits(), VOCAB, false)
+            .unwrap();
+        let explicit = codec(DEFAULT_TOP_P)
+            .encode_with_logits(&ids, &logits(), VOCAB, false)
+            .unwrap();
+        assert_eq!(default, explicit);
+    }
+
+    #[test]
+    fn mismatched_shapes_are_rejected() {
+        let codec = Codec::new();
+        let logits = logits();
+        assert!(codec
+            .encode_with_logits(&[1, 2], &logits, VOCAB, false)
+            .is_err());
+        assert!(codec
+            .encode_with_logits(&[1, 2, 3], &logits, 9, false)
+            .is_err());
+        assert!(codec.encode_with_logits(&[], &[], 0, false).is_ok());
+
+        let payload = codec
+            .encode_with_logits(&[3, 7, 0], &logits, VOCAB, false)
+            .unwrap();
+        // Index 8 of the flat row is out of range for a smaller vocabulary.
+        assert!(codec.decode_with_logits(&payload, 8, false).is_err());
+
+        let plain = CodecCore::encode_token_ids(&[1, 2], false).unwrap();
+        assert!(codec.decode_with_logits(&plain, VOCAB, false).is_err());
+    }
+}
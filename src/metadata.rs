@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_METADATA_PRESENT;
+use crate::varint;
+use crate::Codec;
+
+/// Key/value metadata (model name, version, timestamp, ...) stored alongside the
+/// encoded tokens.
+///
+/// The metadata section is appended after the body and announced by
+/// `FLAG_METADATA_PRESENT` in the header. Section layout:
+///   varint : number of pairs
+///   then per pair, keys in ascending order so the bytes are deterministic:
+///     varint key length,   UTF-8 key bytes
+///     varint value length, UTF-8 value bytes
+impl Codec {
+    pub fn encode_with_metadata(
+        &self,
+        token_ids: &[i32],
+        metadata: &HashMap<String, String>,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut out = CodecCore::encode_with_flags(token_ids, FLAG_METADATA_PRESENT, gzip)?;
+        CodecCore::push_section(&mut out, &encode_section(metadata)?)?;
+        Ok(out)
+    }
+
+    /// Decode tokens and metadata. Payloads without a metadata section yield an
+    /// empty map.
+    pub fn decode_with_metadata(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, HashMap<String, String>)> {
+        let parts = CodecCore::split_payload(payload)?;
+        let metadata = match parts.section(FLAG_METADATA_PRESENT) {
+            Some(section) => decode_section(section)?,
+            None => HashMap::new(),
+        };
+
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let tokens = CodecCore::decode_body(&body, &parts.header)?;
+        Ok((tokens, metadata))
+    }
+
+    /// Read only the metadata section; the body is never decompressed or decoded.
+    pub fn decode_metadata_only(payload: &[u8]) -> Result<HashMap<String, String>> {
+        let parts = CodecCore::split_payload(payload)?;
+        match parts.section(FLAG_METADATA_PRESENT) {
+            Some(section) => decode_section(section),
+            None => Ok(HashMap::new()),
+        }
+    }
+}
+
+fn encode_section(metadata: &HashMap<String, String>) -> Result<Vec<u8>> {
+    let mut entries: Vec<(&String, &String)> = metadata.iter().collect();
+    entries.sort();
+
+    let mut out = varint::encode(&[to_u32(entries.len())?]);
+    for (key, value) in entries {
+        write_str(&mut out, key)?;
+        write_str(&mut out, value)?;
+    }
+    Ok(out)
+}
+
+fn decode_section(mut bytes: &[u8]) -> Result<HashMap<String, String>> {
+    let count = read_varint(&mut bytes)? as usize;
+
+    // Every pair needs at least two length bytes; don't trust `count` blindly.
+    let mut metadata = HashMap::with_capacity(count.min(bytes.len() / 2));
+    for _ in 0..count {
+        let key = read_str(&mut bytes)?;
+        let value = read_str(&mut bytes)?;
+        metadata.insert(key, value);
+    }
+
+    if !bytes.is_empty() {
+        return Err(CodecError::InvalidPayload);
+    }
+    Ok(metadata)
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) -> Result<()> {
+    out.extend_from_slice(&varint::encode(&[to_u32(s.len())?]));
+    out.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+fn read_str(bytes: &mut &[u8]) -> Result<String> {
+    let len = read_varint(bytes)? as usize;
+    if len > bytes.len() {
+        return Err(CodecError::InvalidPayload);
+    }
+
+    let (raw, rest) = bytes.split_at(len);
+    *bytes = rest;
+    String::from_utf8(raw.to_vec()).map_err(|_| CodecError::InvalidPayload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_with_metadata() {
+        let codec = Codec::new();
+        let ids = [10, 20, 10, 30, 10];
+        let metadata = meta(&[
+            ("model", "gpt-2"),
+            ("version", "1.4.0"),
+            ("timestamp", "2024-05-01T12:00:00Z"),
+        ]);
+
+        for gzip in [false, true] {
+            let payload = codec.encode_with_metadata(&ids, &metadata, gzip).unwrap();
+            let (tokens, decoded) = codec.decode_with_metadata(&payload, gzip).unwrap();
+            assert_eq!(tokens, ids);
+            assert_eq!(decoded, metadata);
+            assert_eq!(Codec::decode_metadata_only(&payload).unwrap(), metadata);
+
+            // Plain decoding skips the section entirely.
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn empty_metadata() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_with_metadata(&[1, 2, 3], &HashMap::new(), false)
+            .unwrap();
+
+        let (tokens, decoded) = codec.decode_with_metadata(&payload, false).unwrap();
+        assert_eq!(tokens, vec![1, 2, 3]);
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn unicode_keys_and_special_values() {
+        let codec = Codec::new();
+        let metadata = meta(&[
+            ("modèle", "τοκεναιζερ"),
+            ("日本語", "値"),
+            ("emoji 🚀", ""),
+            ("quotes", "\"a\", 'b', \\c\\"),
+            ("control", "line1\nline2\t\0end"),
+        ]);
+
+        let payload = codec.encode_with_metadata(&[7], &metadata, true).unwrap();
+        assert_eq!(Codec::decode_metadata_only(&payload).unwrap(), metadata);
+    }
+
+    #[test]
+    fn payload_without_metadata_yields_empty_map() {
+        let payload = CodecCore::encode_token_ids(&[4, 4, 2], false).unwrap();
+        assert!(Codec::decode_metadata_only(&payload).unwrap().is_empty());
+    }
+
+    #[test]
+    fn encoding_is_deterministic() {
+        let codec = Codec::new();
+        let metadata = meta(&[("b", "2"), ("a", "1"), ("c", "3")]);
+
+        let first = codec.encode_with_metadata(&[1], &metadata, false).unwrap();
+        let second = codec.encode_with_metadata(&[1], &metadata, false).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        // One pair: key = [0xFF] (invalid UTF-8), value = "".
+        let section = [1, 1, 0xFF, 0];
+        assert!(matches!(
+            decode_section(&section),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
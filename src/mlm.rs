@@ -0,0 +1,149 @@
+use crate::codec_core::{read_varint, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_MASKED_POSITIONS;
+use crate::{varint, Codec};
+
+/// Masked language model inputs: token IDs plus the positions whose loss is
+/// computed.
+///
+/// Masked positions are sparse, so instead of a per-token bit mask the
+/// `FLAG_MASKED_POSITIONS` extension holds them as varint deltas: the first
+/// position, then each one minus the one before. Their count is implied by
+/// the extension length.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` unless `masked_positions` is
+    /// strictly ascending and every position is below the token count.
+    pub fn encode_mlm_input(
+        &self,
+        token_ids: &[i32],
+        masked_positions: &[u32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if masked_positions.windows(2).any(|pair| pair[0] >= pair[1])
+            || masked_positions
+                .last()
+                .is_some_and(|&last| last as usize >= token_ids.len())
+        {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut prev = 0;
+        let deltas: Vec<u32> = masked_positions
+            .iter()
+            .map(|&pos| {
+                let delta = pos - prev;
+                prev = pos;
+                delta
+            })
+            .collect();
+        CodecCore::encode_with_extensions(
+            token_ids,
+            &[(FLAG_MASKED_POSITIONS, &varint::encode(&deltas))],
+            gzip,
+        )
+    }
+
+    pub fn decode_mlm_input(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<u32>)> {
+        let (tokens, deltas) =
+            CodecCore::decode_with_extension(payload, FLAG_MASKED_POSITIONS, gzip)?;
+        let mut rest = deltas.ok_or(CodecError::InvalidPayload)?;
+
+        let mut positions: Vec<u32> = Vec::new();
+        while !rest.is_empty() {
+            let delta = read_varint(&mut rest)?;
+            let pos = match positions.last() {
+                None => delta,
+                Some(_) if delta == 0 => return Err(CodecError::InvalidPayload),
+                Some(&prev) => prev.checked_add(delta).ok_or(CodecError::InvalidPayload)?,
+            };
+            if pos as usize >= tokens.len() {
+                return Err(CodecError::InvalidPayload);
+            }
+            positions.push(pos);
+        }
+        Ok((tokens, positions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 512 tokens with 15% of positions masked, chosen by a fixed LCG.
+    fn bert_input() -> (Vec<i32>, Vec<u32>) {
+        let ids: Vec<i32> = (0..512).map(|i| (i * 7919) % 30_522).collect();
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let positions = (0..512u32)
+            .filter(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 33) % 100 < 15
+            })
+            .collect();
+        (ids, positions)
+    }
+
+    #[test]
+    fn mlm_inputs_round_trip() {
+        let codec = Codec::new();
+        let (ids, positions) = bert_input();
+        assert!((60..95).contains(&positions.len()), "{}", positions.len());
+        for (ids, positions) in [
+            (ids, positions),
+            (vec![], vec![]),
+            (vec![4, 5, 6], vec![]),
+            (vec![4, 5, 6], vec![0, 1, 2]),
+        ] {
+            for gzip in [false, true] {
+                let payload = codec.encode_mlm_input(&ids, &positions, gzip).unwrap();
+                assert_eq!(
+                    codec.decode_mlm_input(&payload, gzip).unwrap(),
+                    (ids.clone(), positions.clone())
+                );
+                // Plain decoders skip the extension.
+                assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn positions_take_a_byte_each() {
+        let codec = Codec::new();
+        let (ids, positions) = bert_input();
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+        let payload = codec.encode_mlm_input(&ids, &positions, false).unwrap();
+        // Every gap is under 128. The extension adds its tag, u32 length and
+        // count byte.
+        assert_eq!(payload.len() - plain.len(), positions.len() + 6);
+    }
+
+    #[test]
+    fn invalid_positions_are_rejected() {
+        let codec = Codec::new();
+        for positions in [&[3][..], &[1, 1], &[2, 1], &[0, 5]] {
+            assert!(
+                codec
+                    .encode_mlm_input(&[7, 8, 9], positions, false)
+                    .is_err(),
+                "{positions:?}"
+            );
+        }
+
+        let plain = CodecCore::encode_token_ids(&[7, 8, 9], false).unwrap();
+        assert!(codec.decode_mlm_input(&plain, false).is_err());
+        // A zero delta after the first position, and a position past the end.
+        for deltas in [[0, 0], [1, 2]] {
+            let payload = CodecCore::encode_with_extensions(
+                &[7, 8, 9],
+                &[(FLAG_MASKED_POSITIONS, &varint::encode(&deltas))],
+                false,
+            )
+            .unwrap();
+            assert!(
+                codec.decode_mlm_input(&payload, false).is_err(),
+                "{deltas:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_MODEL_INFO;
+use crate::{varint, Codec};
+
+/// Token IDs tagged with the model that produced them, for auditing.
+///
+/// Stored in the `FLAG_MODEL_INFO` extension:
+///   [varint name length][name, printable ASCII][u32 LE model version]
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` if `model_name` is empty or
+    /// holds anything but printable ASCII (space included).
+    pub fn encode_with_model_info(
+        &self,
+        token_ids: &[i32],
+        model_name: &str,
+        model_version: u32,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if !is_valid_name(model_name.as_bytes()) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut section = varint::encode(&[to_u32(model_name.len())?]);
+        section.extend_from_slice(model_name.as_bytes());
+        section.extend_from_slice(&model_version.to_le_bytes());
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_MODEL_INFO, &section)], gzip)
+    }
+
+    /// `(model_name, model_version)` of a payload, read from its extension
+    /// without decoding (or decompressing) the tokens.
+    pub fn decode_model_info_only(payload: &[u8]) -> Result<(String, u32)> {
+        let parts = CodecCore::split_payload(payload)?;
+        let mut section = parts
+            .extension(FLAG_MODEL_INFO)
+            .ok_or(CodecError::InvalidPayload)?;
+
+        let len = read_varint(&mut section)? as usize;
+        if section.len() != len + 4 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (name, version) = section.split_at(len);
+        if !is_valid_name(name) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let version = u32::from_le_bytes(
+            version
+                .try_into()
+                .expect("slice of length 4 will always convert"),
+        );
+        // Printable ASCII is always valid UTF-8.
+        Ok((String::from_utf8_lossy(name).into_owned(), version))
+    }
+}
+
+fn is_valid_name(name: &[u8]) -> bool {
+    !name.is_empty() && name.iter().all(|&b| (b' '..=b'~').contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_info_round_trip() {
+        let codec = Codec::new();
+        let ids = [101, 2023, 2003, 1037, 3231, 102];
+
+        for name in [
+            "bert-base-uncased",
+            "my model v2",
+            "llama_3.1_8b",
+            "GPT 4o (2024-05)",
+        ] {
+            for gzip in [false, true] {
+                let payload = codec.encode_with_model_info(&ids, name, 7, gzip).unwrap();
+                assert_eq!(
+                    Codec::decode_model_info_only(&payload).unwrap(),
+                    (name.to_string(), 7)
+                );
+                assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_versions_round_trip() {
+        let codec = Codec::new();
+        for version in [0, 1, 20240501, u32::MAX] {
+            let payload = codec
+                .encode_with_model_info(&[1], "m", version, false)
+                .unwrap();
+            assert_eq!(Codec::decode_model_info_only(&payload).unwrap().1, version);
+        }
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let codec = Codec::new();
+        for name in ["", "tab\there", "new\nline", "caf\u{e9}", "\u{7f}"] {
+            assert!(
+                matches!(
+                    codec.encode_with_model_info(&[1, 2], name, 1, false),
+                    Err(CodecError::InvalidPayload)
+                ),
+                "{name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn payloads_without_model_info_are_rejected() {
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(Codec::decode_model_info_only(&plain).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+
+/// `MsgpackPayload::version` written by `encode_as_msgpack`.
+pub const MSGPACK_FORMAT_VERSION: u8 = 1;
+
+/// The MessagePack document `encode_as_msgpack` writes: a map with the
+/// field names as keys, so msgpack libraries in other languages read it as
+/// a plain dict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MsgpackPayload {
+    pub tokens: Vec<i32>,
+    pub version: u8,
+}
+
+/// Token IDs as a MessagePack document, for stacks that already speak
+/// MessagePack. This is a raw interop format: none of the miso pipeline is
+/// applied, so it is no smaller than the IDs themselves.
+pub fn encode_as_msgpack(token_ids: &[i32]) -> Result<Vec<u8>> {
+    let payload = MsgpackPayload {
+        tokens: token_ids.to_vec(),
+        version: MSGPACK_FORMAT_VERSION,
+    };
+    rmp_serde::to_vec_named(&payload).map_err(|err| CodecError::Internal(err.to_string()))
+}
+
+/// Reverse of `encode_as_msgpack`. Errors with `CodecError::InvalidPayload`
+/// if `bytes` isn't such a document, and with `CodecError::UnknownVersion`
+/// for a newer version.
+pub fn decode_from_msgpack(bytes: &[u8]) -> Result<Vec<i32>> {
+    let payload: MsgpackPayload =
+        rmp_serde::from_slice(bytes).map_err(|_| CodecError::InvalidPayload)?;
+    if payload.version != MSGPACK_FORMAT_VERSION {
+        return Err(CodecError::UnknownVersion(payload.version));
+    }
+    Ok(payload.tokens)
+}
+
+/// A standard payload (`encode_token_ids`) wrapped in a single MessagePack
+/// `bin` value, for transports that carry MessagePack only.
+pub fn encode_compressed_msgpack(token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+    let payload = CodecCore::encode_token_ids(token_ids, gzip)?;
+    rmp_serde::to_vec(&ByteBuf::from(payload)).map_err(|err| CodecError::Internal(err.to_string()))
+}
+
+/// Reverse of `encode_compressed_msgpack`.
+pub fn decode_compressed_msgpack(bytes: &[u8], gzip: bool) -> Result<Vec<i32>> {
+    let payload: ByteBuf = rmp_serde::from_slice(bytes).map_err(|_| CodecError::InvalidPayload)?;
+    CodecCore::decode_token_ids(&payload, gzip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_documents_round_trip() {
+        for ids in [
+            vec![],
+            vec![9],
+            (0..500).map(|i| (i * 7919) % 70_000 - 300).collect(),
+        ] {
+            let bytes = encode_as_msgpack(&ids).unwrap();
+            assert_eq!(decode_from_msgpack(&bytes).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn raw_known_layout() {
+        // {"tokens": [9, -1, 300], "version": 1}
+        let mut expected = vec![0x82, 0xA6];
+        expected.extend_from_slice(b"tokens");
+        expected.extend_from_slice(&[0x93, 0x09, 0xFF, 0xCD, 0x01, 0x2C, 0xA7]);
+        expected.extend_from_slice(b"version");
+        expected.push(0x01);
+        assert_eq!(encode_as_msgpack(&[9, -1, 300]).unwrap(), expected);
+    }
+
+    #[test]
+    fn compressed_documents_wrap_standard_payloads() {
+        let ids: Vec<i32> = (0..500).map(|i| (i * 7919) % 3000 - 40).collect();
+        for gzip in [false, true] {
+            let bytes = encode_compressed_msgpack(&ids, gzip).unwrap();
+            let payload = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            // bin 16: marker, u16 BE length, then the payload itself.
+            assert_eq!(bytes[0], 0xC5);
+            assert_eq!(
+                usize::from(u16::from_be_bytes([bytes[1], bytes[2]])),
+                payload.len()
+            );
+            assert_eq!(bytes[3..], payload);
+            assert_eq!(decode_compressed_msgpack(&bytes, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        let bytes = encode_as_msgpack(&[1, 2, 3]).unwrap();
+        assert!(decode_from_msgpack(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_from_msgpack(&[0xC0]).is_err());
+        assert!(decode_compressed_msgpack(&bytes, false).is_err());
+
+        let newer = rmp_serde::to_vec_named(&MsgpackPayload {
+            tokens: vec![1],
+            version: 2,
+        })
+        .unwrap();
+        assert!(matches!(
+            decode_from_msgpack(&newer),
+            Err(CodecError::UnknownVersion(2))
+        ));
+    }
+}
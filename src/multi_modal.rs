@@ -0,0 +1,191 @@
+use crate::attention_mask::{pack_bits, unpack_bits};
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::Header;
+use crate::{varint, Codec};
+
+/// Mixed text and image token streams for vision-language models, where the
+/// two modalities come from unrelated ID ranges.
+///
+/// Each modality gets its own `FreqMap` and header, so a text token's mapped
+/// ID only competes with other text tokens and likewise for image patches.
+/// The modality mask (0 text, 1 image) is packed one bit per token, least
+/// significant bit first.
+///
+/// Layout:
+///   [varint token count][modality mask bits]
+///   [text header][image header]
+///   [varint text body length][text body][image body]
+///
+/// The bodies are gzipped together when `gzip` is set.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` if the mask length differs
+    /// from the token count or the mask holds anything but 0 and 1.
+    pub fn encode_multi_modal(
+        &self,
+        tokens: &[i32],
+        modality_mask: &[u8],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if modality_mask.len() != tokens.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+        let mask = modality_mask
+            .iter()
+            .map(|&m| match m {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(CodecError::InvalidPayload),
+            })
+            .collect::<Result<Vec<bool>>>()?;
+
+        let (mut text, mut image) = (Vec::new(), Vec::new());
+        for (&token, &is_image) in tokens.iter().zip(&mask) {
+            if is_image {
+                image.push(token);
+            } else {
+                text.push(token);
+            }
+        }
+        let text_freq = FreqMap::from_token_ids(&text);
+        let image_freq = FreqMap::from_token_ids(&image);
+
+        let text_body = CodecCore::encode_body(&text, &text_freq)?;
+        let mut body = varint::encode(&[to_u32(text_body.len())?]);
+        body.extend_from_slice(&text_body);
+        body.extend_from_slice(&CodecCore::encode_body(&image, &image_freq)?);
+
+        let mut out = varint::encode(&[to_u32(tokens.len())?]);
+        out.extend_from_slice(&pack_bits(&mask));
+        out.extend_from_slice(&Header::from_freq_map(&text_freq).encode());
+        out.extend_from_slice(&Header::from_freq_map(&image_freq).encode());
+        out.extend_from_slice(&CodecCore::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    /// The tokens and their modality mask.
+    pub fn decode_multi_modal(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<u8>)> {
+        let mut rest = payload;
+        let n = read_varint(&mut rest)? as usize;
+        let mask_len = n.div_ceil(8);
+        if rest.len() < mask_len {
+            return Err(CodecError::InvalidPayload);
+        }
+        let mask = unpack_bits(&rest[..mask_len], n)?;
+        let rest = &rest[mask_len..];
+
+        let (text_header, rest) =
+            Header::decode_prefix(rest).map_err(|_| CodecError::InvalidPayload)?;
+        let (image_header, rest) =
+            Header::decode_prefix(rest).map_err(|_| CodecError::InvalidPayload)?;
+        let body = CodecCore::decompress(rest, gzip)?;
+        let mut rest = &body[..];
+        let text_len = read_varint(&mut rest)? as usize;
+        if rest.len() < text_len {
+            return Err(CodecError::InvalidPayload);
+        }
+        let text = CodecCore::decode_body(&rest[..text_len], &text_header)?;
+        let image = CodecCore::decode_body(&rest[text_len..], &image_header)?;
+
+        let images = mask.iter().filter(|&&m| m).count();
+        if text.len() != n - images || image.len() != images {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (mut text, mut image) = (text.into_iter(), image.into_iter());
+        let tokens = mask
+            .iter()
+            .map(|&m| if m { image.next() } else { text.next() })
+            .collect::<Option<Vec<i32>>>()
+            .ok_or(CodecError::InvalidPayload)?;
+        Ok((tokens, mask.into_iter().map(u8::from).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2000 tokens, half text (60 distinct IDs under 1000) and half image
+    /// patches (60 distinct codebook entries from 50_000), in runs of
+    /// varying length.
+    fn mixed() -> (Vec<i32>, Vec<u8>) {
+        let mut tokens = Vec::new();
+        let mut mask = Vec::new();
+        for i in 0..2000 {
+            let image = (i / 50) % 2 == 1;
+            let j = (i * 7919) % 60;
+            tokens.push(if image { 50_000 + j * 131 } else { j * 13 });
+            mask.push(u8::from(image));
+        }
+        (tokens, mask)
+    }
+
+    #[test]
+    fn multi_modal_round_trips() {
+        let codec = Codec::new();
+        let (tokens, mask) = mixed();
+        for (tokens, mask) in [
+            (tokens, mask),
+            (vec![], vec![]),
+            (vec![5], vec![0]),
+            (vec![50_001, 50_002], vec![1, 1]),
+            (vec![1, 50_000, 1], vec![0, 1, 0]),
+        ] {
+            for gzip in [false, true] {
+                let payload = codec.encode_multi_modal(&tokens, &mask, gzip).unwrap();
+                assert_eq!(
+                    codec.decode_multi_modal(&payload, gzip).unwrap(),
+                    (tokens.clone(), mask.clone())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn modalities_use_their_own_vocabularies() {
+        let codec = Codec::new();
+        let (tokens, mask) = mixed();
+        assert_eq!(mask.iter().filter(|&&m| m == 1).count(), 1000);
+        let payload = codec.encode_multi_modal(&tokens, &mask, false).unwrap();
+
+        let mut rest = &payload[..];
+        read_varint(&mut rest).unwrap();
+        let rest = &rest[2000 / 8..];
+        let (text, rest) = Header::decode_prefix(rest).unwrap();
+        let (image, _) = Header::decode_prefix(rest).unwrap();
+        assert_eq!(text.tokens.len(), 60);
+        assert_eq!(image.tokens.len(), 60);
+        assert!(text.tokens.iter().all(|&t| t < 1000));
+        assert!(image.tokens.iter().all(|&t| t >= 50_000));
+
+        // With 60 entries per table every mapped ID is a one-byte varint; a
+        // shared 120-entry table needs two bytes for half of them, which
+        // outweighs the mask.
+        let shared = CodecCore::encode_token_ids(&tokens, false).unwrap();
+        assert!(
+            payload.len() + 300 < shared.len(),
+            "{} vs {}",
+            payload.len(),
+            shared.len()
+        );
+    }
+
+    #[test]
+    fn invalid_masks_are_rejected() {
+        let codec = Codec::new();
+        assert!(codec.encode_multi_modal(&[1, 2], &[0], false).is_err());
+        assert!(codec.encode_multi_modal(&[1, 2], &[0, 2], false).is_err());
+
+        let payload = codec
+            .encode_multi_modal(&[1, 2, 3], &[0, 1, 0], false)
+            .unwrap();
+        assert!(codec
+            .decode_multi_modal(&payload[..payload.len() - 1], false)
+            .is_err());
+        assert!(codec.decode_multi_modal(&[], false).is_err());
+        let mut flipped = payload.clone();
+        flipped[1] ^= 0b010;
+        assert!(codec.decode_multi_modal(&flipped, false).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::codec_core::{read_varint, CodecCore};
+use crate::compact_json::{decode_compact_json, encode_compact_json};
+use crate::errors::{CodecError, Result};
+use crate::varint;
+
+/// One newline-delimited JSON record: `encode_compact_json` output and `\n`.
+/// Base64 and the object syntax never produce a newline, so the line can be
+/// split naively.
+pub fn encode_ndjson_line(token_ids: &[i32]) -> String {
+    let mut line = encode_compact_json(token_ids);
+    line.push('\n');
+    line
+}
+
+/// Decode every non-empty line of NDJSON written by `encode_ndjson_line`.
+pub fn decode_ndjson_lines(input: &str) -> Result<Vec<Vec<i32>>> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(decode_compact_json)
+        .collect()
+}
+
+/// One LF-delimited binary record:
+///   [varint payload length][standard payload, not gzipped][b'\n']
+///
+/// Payload bytes can themselves be `\n` (a token table entry or varint equal
+/// to 10, say), so readers must frame lines by the length prefix rather than
+/// by splitting on newlines; the terminator is only a check.
+///
+/// # Panics
+///
+/// If `token_ids` is too long for a standard payload (over `u32::MAX`
+/// tokens).
+pub fn encode_nd_binary_line(token_ids: &[i32]) -> Vec<u8> {
+    let payload = CodecCore::encode_token_ids(token_ids, false)
+        .expect("token sequence too long for a standard payload");
+    let len = u32::try_from(payload.len()).expect("payload length exceeds u32");
+    let mut line = varint::encode(&[len]);
+    line.extend_from_slice(&payload);
+    line.push(b'\n');
+    line
+}
+
+/// Decode concatenated `encode_nd_binary_line` records.
+///
+/// Errors with `CodecError::InvalidPayload` if a record is truncated or isn't
+/// followed by its `\n`, which catches input that was split or joined on
+/// newlines by a tool that didn't know about the length prefix.
+pub fn decode_nd_binary_lines(input: &[u8]) -> Result<Vec<Vec<i32>>> {
+    let mut rest = input;
+    let mut lines = Vec::new();
+    while !rest.is_empty() {
+        let len = read_varint(&mut rest)? as usize;
+        if rest.get(len) != Some(&b'\n') {
+            return Err(CodecError::InvalidPayload);
+        }
+        lines.push(CodecCore::decode_token_ids(&rest[..len], false)?);
+        rest = &rest[len + 1..];
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 sequences of varying length, some containing token 10 (`\n`).
+    fn sequences() -> Vec<Vec<i32>> {
+        (0..100)
+            .map(|i| (0..i * 3).map(|j| (j * 7919 + i) % 300).collect())
+            .collect()
+    }
+
+    #[test]
+    fn ndjson_lines_round_trip() {
+        let sequences = sequences();
+        let input: String = sequences
+            .iter()
+            .map(|ids| encode_ndjson_line(ids))
+            .collect();
+        assert_eq!(input.lines().count(), 100);
+        assert!(input.ends_with('\n'));
+        assert_eq!(decode_ndjson_lines(&input).unwrap(), sequences);
+        assert!(decode_ndjson_lines("not json\n").is_err());
+    }
+
+    #[test]
+    fn binary_lines_round_trip() {
+        let sequences = sequences();
+        let input: Vec<u8> = sequences
+            .iter()
+            .flat_map(|ids| encode_nd_binary_line(ids))
+            .collect();
+        // Newlines inside payloads outnumber the terminators.
+        assert!(input.iter().filter(|&&b| b == b'\n').count() > 100);
+        assert_eq!(decode_nd_binary_lines(&input).unwrap(), sequences);
+        assert!(decode_nd_binary_lines(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn binary_lines_layout() {
+        let line = encode_nd_binary_line(&[10]);
+        let payload = CodecCore::encode_token_ids(&[10], false).unwrap();
+        assert_eq!(line[0] as usize, payload.len());
+        assert_eq!(line[1..line.len() - 1], payload);
+        assert_eq!(line.last(), Some(&b'\n'));
+    }
+
+    #[test]
+    fn broken_framing_is_rejected() {
+        let mut input = encode_nd_binary_line(&[1, 2, 3]);
+        input.extend(encode_nd_binary_line(&[4, 5]));
+
+        // Dropping a terminator, truncating, or splitting on a newline.
+        let first_len = encode_nd_binary_line(&[1, 2, 3]).len();
+        let mut no_terminator = input.clone();
+        no_terminator.remove(first_len - 1);
+        assert!(decode_nd_binary_lines(&no_terminator).is_err());
+        assert!(decode_nd_binary_lines(&input[..input.len() - 1]).is_err());
+        assert!(decode_nd_binary_lines(&encode_nd_binary_line(&[10])[..3]).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{FORMAT_VERSION, PREFIX_LEN};
+use crate::{zigzag, Codec};
+
+/// Body bytes staged on the stack before they are written out.
+const STACK_BUF: usize = 4096;
+
+/// Encoding into a caller-provided buffer.
+///
+/// The payload is written straight into `out`: the header from the
+/// `FreqMap`'s token order, the body one varint at a time through a stack
+/// buffer. The `FreqMap` itself (and, with gzip, the compressor state) are
+/// the only allocations. Without gzip the bytes are identical to
+/// `encode_token_ids`; with gzip the compressed stream can differ (it sees the
+/// body in smaller writes) but decodes the same.
+impl Codec {
+    /// Upper bound on the size of an uncompressed standard payload: the header
+    /// prefix, 4 bytes per unique token and at most 5 varint bytes per token.
+    pub fn max_encoded_size(n_tokens: usize, n_unique: usize) -> usize {
+        PREFIX_LEN + n_unique * 4 + n_tokens * 5
+    }
+
+    /// Returns the number of bytes written to the front of `out`.
+    ///
+    /// Errors with `CodecError::PayloadTooLarge` if `out` is smaller than
+    /// `max_encoded_size` (plus gzip's worst-case overhead when `gzip` is
+    /// set), before anything is written.
+    pub fn encode_no_alloc(&self, token_ids: &[i32], gzip: bool, out: &mut [u8]) -> Result<usize> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        let tokens = freq.ordered_tokens();
+
+        let header_len = PREFIX_LEN + tokens.len() * 4;
+        let mut needed = Self::max_encoded_size(token_ids.len(), tokens.len());
+        if gzip {
+            needed += gzip_overhead(token_ids.len() * 5);
+        }
+        if out.len() < needed {
+            return Err(CodecError::PayloadTooLarge {
+                size: needed,
+                target: out.len(),
+            });
+        }
+
+        let len = u32::try_from(tokens.len())
+            .map_err(|_| CodecError::Internal("too many unique tokens".to_string()))?;
+        out[0] = FORMAT_VERSION;
+        out[1] = 0;
+        out[2..PREFIX_LEN].copy_from_slice(&len.to_le_bytes());
+        for (slot, &token) in out[PREFIX_LEN..header_len].chunks_exact_mut(4).zip(tokens) {
+            slot.copy_from_slice(&token.to_le_bytes());
+        }
+
+        let mut rest = &mut out[header_len..];
+        let available = rest.len();
+        if gzip {
+            let mut encoder = GzEncoder::new(&mut rest, Compression::default());
+            write_body(&mut encoder, token_ids, &freq)?;
+            encoder.finish()?;
+        } else {
+            write_body(&mut rest, token_ids, &freq)?;
+        }
+        Ok(header_len + available - rest.len())
+    }
+}
+
+/// Most bytes gzip can add to `raw` bytes of input: zlib's `deflateBound`
+/// plus the 18 bytes of gzip header and trailer.
+fn gzip_overhead(raw: usize) -> usize {
+    (raw >> 12) + (raw >> 14) + (raw >> 25) + 13 + 18
+}
+
+/// `CodecCore::encode_body`, staged through a stack buffer.
+fn write_body<W: Write>(writer: &mut W, token_ids: &[i32], freq: &FreqMap) -> Result<()> {
+    let mut buf = [0u8; STACK_BUF];
+    let mut used = 0;
+
+    for &token in token_ids {
+        let mapped = freq.map_token(token).ok_or_else(|| {
+            CodecError::Internal(format!("token {token} missing from frequency map"))
+        })?;
+        let mut value = zigzag::encode(mapped);
+        if used + 5 > STACK_BUF {
+            writer.write_all(&buf[..used])?;
+            used = 0;
+        }
+        while value >= 0x80 {
+            buf[used] = (value as u8 & 0x7F) | 0x80;
+            used += 1;
+            value >>= 7;
+        }
+        buf[used] = value as u8;
+        used += 1;
+    }
+
+    writer.write_all(&buf[..used])?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec_core::CodecCore;
+
+    fn ids() -> Vec<i32> {
+        (0..5000).map(|i| (i * 7919) % 3001 - 500).collect()
+    }
+
+    #[test]
+    fn output_matches_encode_token_ids() {
+        let codec = Codec::new();
+        for ids in [ids(), vec![], vec![i32::MIN, i32::MAX, 0, i32::MAX]] {
+            for gzip in [false, true] {
+                let mut out = vec![0xAA; 200_000];
+                let written = codec.encode_no_alloc(&ids, gzip, &mut out).unwrap();
+                let payload = &out[..written];
+                if gzip {
+                    assert_eq!(CodecCore::decode_token_ids(payload, true).unwrap(), ids);
+                } else {
+                    assert_eq!(payload, CodecCore::encode_token_ids(&ids, false).unwrap());
+                }
+                // Nothing past the payload is touched.
+                assert!(out[written..].iter().all(|&b| b == 0xAA));
+            }
+        }
+    }
+
+    #[test]
+    fn exact_bound_buffer_is_enough() {
+        let codec = Codec::new();
+        let ids = ids();
+        let unique = FreqMap::from_token_ids(&ids).ordered_tokens().len();
+        let mut out = vec![0; Codec::max_encoded_size(ids.len(), unique)];
+        let written = codec.encode_no_alloc(&ids, false, &mut out).unwrap();
+        assert_eq!(
+            out[..written],
+            CodecCore::encode_token_ids(&ids, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn small_buffers_are_rejected_untouched() {
+        let codec = Codec::new();
+        let ids = [1, 2, 2, 3];
+        let bound = Codec::max_encoded_size(4, 3);
+        assert_eq!(bound, 6 + 12 + 20);
+
+        let mut out = vec![0; bound - 1];
+        assert!(matches!(
+            codec.encode_no_alloc(&ids, false, &mut out),
+            Err(CodecError::PayloadTooLarge { size, target }) if size == bound && target == bound - 1
+        ));
+        assert!(out.iter().all(|&b| b == 0));
+
+        let mut out = vec![0; bound];
+        assert!(codec.encode_no_alloc(&ids, true, &mut out).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::Path;
+
+use crate::errors::{CodecError, Result};
+
+/// Leading bytes of every `.npy` file.
+pub const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// The only dtype read and written: little-endian int32.
+const NPY_DTYPE: &str = "<i4";
+
+/// NumPy pads the header so the data starts on this boundary.
+const NPY_ALIGN: usize = 64;
+
+/// Write `token_ids` as a 1-D `.npy` file (format version 1.0, dtype `<i4`,
+/// shape `(n,)`), loadable with `numpy.load`.
+///
+/// This is plain NumPy output, not a miso payload: nothing is compressed.
+pub fn encode_as_npy(token_ids: &[i32], path: &Path) -> Result<()> {
+    let shape = format!("({},)", token_ids.len());
+    fs::write(path, npy_bytes(&shape, token_ids.iter()))?;
+    Ok(())
+}
+
+/// Write `rows` as a 2-D `.npy` file of shape `(batch, seq_len)` in C order.
+/// Errors with `CodecError::InvalidPayload` unless every row has the same
+/// length.
+pub fn encode_matrix_as_npy(rows: &[&[i32]], path: &Path) -> Result<()> {
+    let seq_len = rows.first().map_or(0, |row| row.len());
+    if rows.iter().any(|row| row.len() != seq_len) {
+        return Err(CodecError::InvalidPayload);
+    }
+    let shape = format!("({}, {})", rows.len(), seq_len);
+    fs::write(
+        path,
+        npy_bytes(&shape, rows.iter().flat_map(|row| row.iter())),
+    )?;
+    Ok(())
+}
+
+/// Read a 1-D `<i4` `.npy` file, such as `encode_as_npy` or
+/// `numpy.save(path, np.asarray(ids, dtype="<i4"))` writes.
+///
+/// Errors with `CodecError::InvalidPayload` for other dtypes or shapes, or a
+/// malformed file.
+pub fn decode_from_npy(path: &Path) -> Result<Vec<i32>> {
+    let bytes = fs::read(path)?;
+    let npy = NpyArray::parse(&bytes)?;
+    if npy.shape.len() != 1 {
+        return Err(CodecError::InvalidPayload);
+    }
+    Ok(npy.values())
+}
+
+/// Read a 2-D `<i4` `.npy` file as its rows. Fortran-ordered files are
+/// transposed back into rows.
+pub fn decode_matrix_from_npy(path: &Path) -> Result<Vec<Vec<i32>>> {
+    let bytes = fs::read(path)?;
+    let npy = NpyArray::parse(&bytes)?;
+    let &[batch, seq_len] = npy.shape.as_slice() else {
+        return Err(CodecError::InvalidPayload);
+    };
+    let values = npy.values();
+    Ok((0..batch)
+        .map(|row| {
+            (0..seq_len)
+                .map(|col| {
+                    if npy.fortran_order {
+                        values[col * batch + row]
+                    } else {
+                        values[row * seq_len + col]
+                    }
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Magic, version 1.0, the header dict padded to `NPY_ALIGN`, and the data.
+fn npy_bytes<'a>(shape: &str, values: impl Iterator<Item = &'a i32>) -> Vec<u8> {
+    let mut header =
+        format!("{{'descr': '{NPY_DTYPE}', 'fortran_order': False, 'shape': {shape}, }}");
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.extend(std::iter::repeat_n(
+        ' ',
+        unpadded.next_multiple_of(NPY_ALIGN) - unpadded,
+    ));
+    header.push('\n');
+
+    let mut out = NPY_MAGIC.to_vec();
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out
+}
+
+/// A parsed `.npy` file holding `<i4` data.
+struct NpyArray<'a> {
+    shape: Vec<usize>,
+    fortran_order: bool,
+    data: &'a [u8],
+}
+
+impl<'a> NpyArray<'a> {
+    /// Accepts format versions 1.x (u16 header length) and 2.x/3.x (u32).
+    fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let rest = bytes
+            .strip_prefix(NPY_MAGIC)
+            .ok_or(CodecError::InvalidPayload)?;
+        let (header_len, rest) = match rest {
+            [1, _, a, b, rest @ ..] => (usize::from(u16::from_le_bytes([*a, *b])), rest),
+            [2 | 3, _, a, b, c, d, rest @ ..] => {
+                let len = u32::from_le_bytes([*a, *b, *c, *d]);
+                (
+                    usize::try_from(len).map_err(|_| CodecError::InvalidPayload)?,
+                    rest,
+                )
+            }
+            _ => return Err(CodecError::InvalidPayload),
+        };
+        if rest.len() < header_len {
+            return Err(CodecError::InvalidPayload);
+        }
+        let (header, data) = rest.split_at(header_len);
+        let header = std::str::from_utf8(header).map_err(|_| CodecError::InvalidPayload)?;
+
+        let descr = dict_value(header, "descr")?;
+        if descr.trim_matches(|c| c == '\'' || c == '"') != NPY_DTYPE {
+            return Err(CodecError::InvalidPayload);
+        }
+        let fortran_order = match dict_value(header, "fortran_order")? {
+            "True" => true,
+            "False" => false,
+            _ => return Err(CodecError::InvalidPayload),
+        };
+        let shape = dict_value(header, "shape")?
+            .strip_prefix('(')
+            .and_then(|shape| shape.strip_suffix(')'))
+            .ok_or(CodecError::InvalidPayload)?
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| dim.parse::<usize>().map_err(|_| CodecError::InvalidPayload))
+            .collect::<Result<Vec<usize>>>()?;
+
+        let len = shape
+            .iter()
+            .try_fold(4usize, |acc, &dim| acc.checked_mul(dim))
+            .ok_or(CodecError::InvalidPayload)?;
+        if data.len() != len {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(Self {
+            shape,
+            fortran_order,
+            data,
+        })
+    }
+
+    /// The data in file order.
+    fn values(&self) -> Vec<i32> {
+        self.data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+}
+
+/// The source text of `key`'s value in the header dict: a quoted string, a
+/// parenthesized tuple, or a bare word.
+fn dict_value<'h>(header: &'h str, key: &str) -> Result<&'h str> {
+    let start = [format!("'{key}'"), format!("\"{key}\"")]
+        .iter()
+        .find_map(|quoted| header.find(quoted.as_str()).map(|at| at + quoted.len()))
+        .ok_or(CodecError::InvalidPayload)?;
+    let value = header[start..]
+        .trim_start()
+        .strip_prefix(':')
+        .ok_or(CodecError::InvalidPayload)?
+        .trim_start();
+
+    let end = match value.chars().next() {
+        Some(quote @ ('\'' | '"')) => value[1..].find(quote).map(|end| end + 2),
+        Some('(') => value.find(')').map(|end| end + 1),
+        _ => value.find([',', '}']),
+    }
+    .ok_or(CodecError::InvalidPayload)?;
+    Ok(value[..end].trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.npy");
+        for ids in [
+            vec![],
+            vec![7],
+            (0..1000).map(|i| (i * 7919) % 50_257 - 3).collect(),
+        ] {
+            encode_as_npy(&ids, &path).unwrap();
+            assert_eq!(decode_from_npy(&path).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn known_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.npy");
+        encode_as_npy(&[1, -2], &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = usize::from(u16::from_le_bytes([bytes[8], bytes[9]]));
+        assert_eq!((10 + header_len) % NPY_ALIGN, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.starts_with("{'descr': '<i4', 'fortran_order': False, 'shape': (2,), }"));
+        assert!(header.ends_with(" \n"));
+        assert_eq!(
+            bytes[10 + header_len..],
+            [1, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn matrices_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("batch.npy");
+        let rows = [vec![1, 2, 3], vec![4, 5, 6]];
+        let refs: Vec<&[i32]> = rows.iter().map(Vec::as_slice).collect();
+        encode_matrix_as_npy(&refs, &path).unwrap();
+        assert_eq!(decode_matrix_from_npy(&path).unwrap(), rows);
+        // A matrix is not a vector.
+        assert!(decode_from_npy(&path).is_err());
+
+        assert!(encode_matrix_as_npy(&[&[1, 2], &[3]], &path).is_err());
+        encode_matrix_as_npy(&[], &path).unwrap();
+        assert!(decode_matrix_from_npy(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fortran_ordered_matrices_are_transposed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("batch.npy");
+        let mut bytes = npy_bytes("(2, 3)", [1, 4, 2, 5, 3, 6].iter());
+        let at = bytes.windows(5).position(|w| w == b"False").unwrap();
+        bytes.splice(at..at + 5, *b"True ");
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(
+            decode_matrix_from_npy(&path).unwrap(),
+            [vec![1, 2, 3], vec![4, 5, 6]]
+        );
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.npy");
+
+        let good = npy_bytes("(2,)", [1, 2].iter());
+        let at = good.windows(3).position(|w| w == b"<i4").unwrap();
+        let mut wrong_dtype = good.clone();
+        wrong_dtype[at + 2] = b'8';
+        let mut not_npy = good.clone();
+        not_npy[1] = b'X';
+
+        for bytes in [
+            wrong_dtype,
+            not_npy,
+            good[..good.len() - 1].to_vec(),
+            good[..20].to_vec(),
+        ] {
+            fs::write(&path, bytes).unwrap();
+            assert!(decode_from_npy(&path).is_err());
+        }
+        assert!(decode_from_npy(&dir.path().join("missing.npy")).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::codec_core::{read_varint, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_OFFSETS_PRESENT;
+use crate::{varint, Codec};
+
+/// Token IDs plus the byte offset of each token in the source text.
+///
+/// The offsets are stored in the `FLAG_OFFSETS_PRESENT` extension as varint
+/// deltas (the first one relative to 0). Offsets must be non-decreasing, so the
+/// deltas are never negative and stay small for ordinary text.
+impl Codec {
+    pub fn encode_with_offsets(
+        &self,
+        token_ids: &[i32],
+        byte_offsets: &[u32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if byte_offsets.len() != token_ids.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut deltas = Vec::with_capacity(byte_offsets.len());
+        let mut prev = 0;
+        for &offset in byte_offsets {
+            deltas.push(offset.checked_sub(prev).ok_or(CodecError::InvalidPayload)?);
+            prev = offset;
+        }
+
+        let section = varint::encode(&deltas);
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_OFFSETS_PRESENT, &section)], gzip)
+    }
+
+    pub fn decode_with_offsets(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<u32>)> {
+        let (tokens, section) =
+            CodecCore::decode_with_extension(payload, FLAG_OFFSETS_PRESENT, gzip)?;
+        let mut section = section.ok_or(CodecError::InvalidPayload)?;
+
+        let mut offsets = Vec::with_capacity(tokens.len());
+        let mut prev: u32 = 0;
+        for _ in 0..tokens.len() {
+            let delta = read_varint(&mut section)?;
+            prev = prev.checked_add(delta).ok_or(CodecError::InvalidPayload)?;
+            offsets.push(prev);
+        }
+
+        if !section.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok((tokens, offsets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "The quick brown fox jumps." split into word and punctuation tokens.
+    const SENTENCE_IDS: [i32; 6] = [464, 2068, 7586, 21831, 18045, 13];
+    const SENTENCE_OFFSETS: [u32; 6] = [0, 4, 10, 16, 20, 25];
+
+    #[test]
+    fn sentence_round_trip() {
+        let codec = Codec::new();
+        for gzip in [false, true] {
+            let payload = codec
+                .encode_with_offsets(&SENTENCE_IDS, &SENTENCE_OFFSETS, gzip)
+                .unwrap();
+            let (tokens, offsets) = codec.decode_with_offsets(&payload, gzip).unwrap();
+            assert_eq!(tokens, SENTENCE_IDS);
+            assert_eq!(offsets, SENTENCE_OFFSETS);
+
+            // Plain decoders skip the offsets and still see the tokens.
+            assert_eq!(
+                CodecCore::decode_token_ids(&payload, gzip).unwrap(),
+                SENTENCE_IDS
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_offsets_are_allowed() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_with_offsets(&[1, 2, 3], &[5, 5, 9], false)
+            .unwrap();
+        let (_, offsets) = codec.decode_with_offsets(&payload, false).unwrap();
+        assert_eq!(offsets, [5, 5, 9]);
+    }
+
+    #[test]
+    fn empty_input_round_trips() {
+        let codec = Codec::new();
+        let payload = codec.encode_with_offsets(&[], &[], false).unwrap();
+        assert_eq!(
+            codec.decode_with_offsets(&payload, false).unwrap(),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn mismatched_lengths_are_rejected() {
+        let codec = Codec::new();
+        assert!(matches!(
+            codec.encode_with_offsets(&SENTENCE_IDS, &SENTENCE_OFFSETS[..5], false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn decreasing_offsets_are_rejected() {
+        let codec = Codec::new();
+        assert!(matches!(
+            codec.encode_with_offsets(&[1, 2, 3], &[0, 8, 4], false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn payload_without_offsets_is_rejected() {
+        let codec = Codec::new();
+        let payload = CodecCore::encode_token_ids(&SENTENCE_IDS, false).unwrap();
+        assert!(codec.decode_with_offsets(&payload, false).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::Header;
+use crate::Codec;
+
+/// Fixed-size payloads: a standard payload right-padded with
+/// `CodecConfig::pad_byte` up to a target size.
+///
+/// No length is stored. With gzip the stream ends itself and the padding is
+/// ignored; without gzip, trailing pad bytes are stripped from the body. A
+/// raw body ending in the pad byte would lose its last value that way, so
+/// `encode_padded` refuses to produce one; bodies never end in a byte of
+/// 0x80 or above, which makes such pad bytes always safe.
+impl Codec {
+    pub fn encode_padded(
+        &self,
+        token_ids: &[i32],
+        target_size: usize,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut out = self.encode(token_ids, gzip)?;
+        if out.len() > target_size {
+            return Err(CodecError::PayloadTooLarge {
+                size: out.len(),
+                target: target_size,
+            });
+        }
+
+        let pad = self.config().pad_byte;
+        if !gzip && !token_ids.is_empty() && out.last() == Some(&pad) {
+            return Err(CodecError::Internal(format!(
+                "body ends in pad byte {pad:#04x}; use gzip or a pad byte of 0x80 or above"
+            )));
+        }
+
+        out.resize(target_size, pad);
+        Ok(out)
+    }
+
+    pub fn decode_padded(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        if gzip {
+            return Ok(CodecCore::decode_with_header(payload, gzip)?.0);
+        }
+
+        let (header, body) =
+            Header::decode_prefix(payload).map_err(|_| CodecError::InvalidPayload)?;
+        let pad = self.config().pad_byte;
+        let padding = body.iter().rev().take_while(|&&b| b == pad).count();
+        let end = header.body_offset() + body.len() - padding;
+        Ok(CodecCore::decode_with_header(&payload[..end], false)?.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CodecConfig;
+
+    fn ids() -> Vec<i32> {
+        // Ends in 5, which is not the most frequent token.
+        vec![1, 1, 1, 2, 3, 1, 4, 5000, 5]
+    }
+
+    #[test]
+    fn payloads_are_exactly_target_size() {
+        let codec = Codec::new();
+        for gzip in [false, true] {
+            for target in [100, 128, 4096] {
+                let payload = codec.encode_padded(&ids(), target, gzip).unwrap();
+                assert_eq!(payload.len(), target);
+                assert_eq!(codec.decode_padded(&payload, gzip).unwrap(), ids());
+            }
+        }
+    }
+
+    #[test]
+    fn exact_fit_needs_no_padding() {
+        let codec = Codec::new();
+        let size = CodecCore::encode_token_ids(&ids(), false).unwrap().len();
+        let payload = codec.encode_padded(&ids(), size, false).unwrap();
+        assert_eq!(payload, CodecCore::encode_token_ids(&ids(), false).unwrap());
+        assert_eq!(codec.decode_padded(&payload, false).unwrap(), ids());
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected() {
+        let codec = Codec::new();
+        assert!(matches!(
+            codec.encode_padded(&ids(), 10, false),
+            Err(CodecError::PayloadTooLarge { target: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn pad_byte_follows_the_config() {
+        // Ends in the most frequent token, whose varint is 0x00.
+        let ids = [7, 7, 3, 7];
+        assert!(Codec::new().encode_padded(&ids, 64, false).is_err());
+
+        let codec = Codec::with_config(CodecConfig {
+            pad_byte: 0xFF,
+            ..CodecConfig::default()
+        });
+        for gzip in [false, true] {
+            let payload = codec.encode_padded(&ids, 64, gzip).unwrap();
+            assert_eq!(payload.len(), 64);
+            assert_eq!(payload[63], 0xFF);
+            assert_eq!(codec.decode_padded(&payload, gzip).unwrap(), ids);
+        }
+
+        let empty = Codec::new().encode_padded(&[], 32, false).unwrap();
+        assert!(Codec::new()
+            .decode_padded(&empty, false)
+            .unwrap()
+            .is_empty());
+    }
+}
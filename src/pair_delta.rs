@@ -0,0 +1,109 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::Codec;
+
+/// Pair-delta payloads for two correlated, equal-length sequences (e.g. encoder
+/// and decoder inputs in seq2seq).
+///
+/// Instead of two independent payloads, `seq_a` is stored as-is and `seq_b` as
+/// the element-wise difference `seq_b[i] - seq_a[i]`. Both streams share one
+/// `FreqMap` built over `seq_a` plus the deltas, so there is a single header and
+/// identical positions collapse into the (very cheap) delta 0.
+///
+/// Layout: a standard payload whose body holds `2 * n` values, the `n` tokens of
+/// `seq_a` followed by the `n` deltas.
+impl Codec {
+    pub fn encode_pair_delta(&self, seq_a: &[i32], seq_b: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        if seq_a.len() != seq_b.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut combined = Vec::with_capacity(seq_a.len() * 2);
+        combined.extend_from_slice(seq_a);
+        combined.extend(seq_a.iter().zip(seq_b).map(|(&a, &b)| b.wrapping_sub(a)));
+
+        CodecCore::encode_token_ids(&combined, gzip)
+    }
+
+    pub fn decode_pair_delta(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<i32>)> {
+        let mut combined = CodecCore::decode_token_ids(payload, gzip)?;
+        if combined.len() % 2 != 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let deltas = combined.split_off(combined.len() / 2);
+        let seq_b = combined
+            .iter()
+            .zip(&deltas)
+            .map(|(&a, &delta)| a.wrapping_add(delta))
+            .collect();
+        Ok((combined, seq_b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: u32, vocab: u32) -> Vec<i32> {
+        (0..len)
+            .map(|i| (i.wrapping_mul(2654435761) % vocab) as i32)
+            .collect()
+    }
+
+    #[test]
+    fn identical_pair_is_much_smaller_than_two_payloads() {
+        let codec = Codec::new();
+        let seq_a = sample(1000, 1000);
+        let seq_b = seq_a.clone();
+
+        let pair = codec.encode_pair_delta(&seq_a, &seq_b, false).unwrap();
+        let separate = CodecCore::encode_token_ids(&seq_a, false).unwrap().len()
+            + CodecCore::encode_token_ids(&seq_b, false).unwrap().len();
+
+        // One header instead of two, and every delta is the single-byte 0.
+        assert!(
+            pair.len() * 3 < separate * 2,
+            "pair {} vs separate {}",
+            pair.len(),
+            separate
+        );
+        assert_eq!(codec.decode_pair_delta(&pair, false).unwrap(), (seq_a, seq_b));
+    }
+
+    #[test]
+    fn round_trip_with_differences() {
+        let codec = Codec::new();
+        let seq_a = sample(300, 50);
+        let mut seq_b = seq_a.clone();
+        seq_b[0] = i32::MAX;
+        seq_b[10] = i32::MIN;
+        seq_b[299] = -7;
+
+        for gzip in [false, true] {
+            let payload = codec.encode_pair_delta(&seq_a, &seq_b, gzip).unwrap();
+            let (a, b) = codec.decode_pair_delta(&payload, gzip).unwrap();
+            assert_eq!(a, seq_a);
+            assert_eq!(b, seq_b);
+        }
+    }
+
+    #[test]
+    fn empty_pair() {
+        let codec = Codec::new();
+        let payload = codec.encode_pair_delta(&[], &[], false).unwrap();
+        assert_eq!(
+            codec.decode_pair_delta(&payload, false).unwrap(),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_lengths() {
+        let codec = Codec::new();
+        assert!(matches!(
+            codec.encode_pair_delta(&[1, 2], &[1], false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_POSITION_BIAS;
+use crate::{quantize, Codec};
+
+/// Token IDs plus a per-position bias (e.g. ALiBi), f16-quantized
+/// (`quantize::encode_f16_quantized`) into the `FLAG_POSITION_BIAS`
+/// extension. The bias count is the extension length over 2, so it need not
+/// match the token count.
+impl Codec {
+    pub fn encode_with_position_bias(
+        &self,
+        token_ids: &[i32],
+        position_bias: &[f32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let bias = quantize::encode_f16_quantized(position_bias);
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_POSITION_BIAS, &bias)], gzip)
+    }
+
+    /// The bias comes back rounded to the nearest f16.
+    pub fn decode_with_position_bias(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<f32>)> {
+        let (tokens, bias) = CodecCore::decode_with_extension(payload, FLAG_POSITION_BIAS, gzip)?;
+        let bias = bias.ok_or(CodecError::InvalidPayload)?;
+        let bias = quantize::decode_f16_quantized(bias).map_err(|_| CodecError::InvalidPayload)?;
+        Ok((tokens, bias))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ALiBi bias for one head: `-slope * distance`, over 512 positions.
+    fn alibi(slope: f32) -> Vec<f32> {
+        (0..512).map(|i| -slope * i as f32).collect()
+    }
+
+    #[test]
+    fn position_bias_round_trips_within_f16_precision() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..512).map(|i| (i * 7919) % 30_000).collect();
+
+        for slope in [1.0 / 2.0, 1.0 / 16.0, 1.0 / 256.0] {
+            let bias = alibi(slope);
+            // Stay within the range where f16 steps are below 0.002.
+            let bias: Vec<f32> = bias.into_iter().filter(|b| b.abs() <= 2.0).collect();
+            for gzip in [false, true] {
+                let payload = codec.encode_with_position_bias(&ids, &bias, gzip).unwrap();
+                let (tokens, decoded) = codec.decode_with_position_bias(&payload, gzip).unwrap();
+
+                assert_eq!(tokens, ids);
+                assert_eq!(decoded.len(), bias.len());
+                for (got, want) in decoded.iter().zip(&bias) {
+                    assert!((got - want).abs() < 0.001, "{got} vs {want}");
+                }
+                assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn bias_is_required_to_decode() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(codec.decode_with_position_bias(&plain, false).is_err());
+
+        let empty = codec
+            .encode_with_position_bias(&[1, 2, 3], &[], false)
+            .unwrap();
+        assert_eq!(
+            codec.decode_with_position_bias(&empty, false).unwrap(),
+            (vec![1, 2, 3], vec![])
+        );
+    }
+}
@@ -0,0 +1,168 @@
+// src/prefix_free.rs
+use anyhow::{bail, Result};
+
+use crate::bits::{BitReader, BitWriter};
+
+/// Fibonacci numbers from F(2) = 1: every one up to `u32::MAX + 1`.
+const FIBONACCI_LEN: usize = 46;
+
+const FIBONACCI: [u64; FIBONACCI_LEN] = {
+    let mut fib = [0u64; FIBONACCI_LEN];
+    fib[0] = 1;
+    fib[1] = 2;
+    let mut i = 2;
+    while i < FIBONACCI_LEN {
+        fib[i] = fib[i - 1] + fib[i - 2];
+        i += 1;
+    }
+    fib
+};
+
+/// Longest codeword: 46 Zeckendorf bits and the terminating one.
+const MAX_CODEWORD_BITS: u32 = FIBONACCI_LEN as u32 + 1;
+
+/// Fibonacci code `values`.
+///
+/// `v` is written as the Zeckendorf representation of `v + 1` (a sum of
+/// non-consecutive Fibonacci numbers), smallest term first, then an extra one
+/// bit. A Zeckendorf representation never holds two adjacent ones, so the
+/// pair `11` only ever appears at the end of a codeword and no codeword is a
+/// prefix of another: a reader can resynchronize at any `11` without
+/// knowing where values start. As in `elias`, bits are packed least
+/// significant bit first, the final byte is zero-padded and the count must be
+/// stored alongside.
+pub fn encode_prefix_free(values: &[u32]) -> Vec<u8> {
+    let mut writer = BitWriter::with_capacity(values.len());
+    for &v in values {
+        let (bits, len) = codeword(v);
+        // `push` takes at most 33 bits at a time.
+        writer.push(bits & 0xFFFF_FFFF, len.min(32));
+        if len > 32 {
+            writer.push(bits >> 32, len - 32);
+        }
+    }
+    writer.finish()
+}
+
+/// Decode `count` values written by `encode_prefix_free`. Errors on a
+/// truncated stream, a codeword out of `u32` range or trailing bytes.
+pub fn decode_prefix_free(bytes: &[u8], count: usize) -> Result<Vec<u32>> {
+    // Every codeword takes at least two bits, which bounds the allocation.
+    if count > bytes.len() * 4 {
+        bail!("fibonacci stream too short for {} values", count);
+    }
+
+    let mut reader = BitReader::new(bytes);
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let window = reader.peek();
+        // The lowest `11` ends the codeword.
+        let last = (window & (window >> 1)).trailing_zeros();
+        if last + 2 > MAX_CODEWORD_BITS {
+            bail!("invalid fibonacci codeword");
+        }
+        let n: u64 = (0..=last as usize)
+            .filter(|&i| window >> i & 1 == 1)
+            .map(|i| FIBONACCI[i])
+            .sum();
+        let Ok(v) = u32::try_from(n - 1) else {
+            bail!("fibonacci codeword out of range");
+        };
+        reader.skip(last + 2)?;
+        out.push(v);
+    }
+
+    if !reader.at_end() {
+        bail!("trailing bytes after fibonacci stream");
+    }
+    Ok(out)
+}
+
+/// The codeword for `v`, LSB first, and its length in bits.
+fn codeword(v: u32) -> (u64, u32) {
+    let mut n = u64::from(v) + 1;
+    let top = FIBONACCI.partition_point(|&f| f <= n) - 1;
+    let mut bits = 1u64 << (top + 1);
+    for i in (0..=top).rev() {
+        if FIBONACCI[i] <= n {
+            bits |= 1 << i;
+            n -= FIBONACCI[i];
+        }
+    }
+    (bits, top as u32 + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn known_layout() {
+        // In stream order: 0 -> 11; 1 -> 011; 3 -> 1011; 11 -> 101011.
+        assert_eq!(codeword(0), (0b11, 2));
+        assert_eq!(codeword(1), (0b110, 3));
+        assert_eq!(codeword(3), (0b1101, 4));
+        assert_eq!(codeword(11), (0b110101, 6));
+        assert_eq!(encode_prefix_free(&[0, 1, 3]), [0b1011_1011, 0b1]);
+        assert_eq!(
+            decode_prefix_free(&[0b1011_1011, 0b1], 3).unwrap(),
+            [0, 1, 3]
+        );
+    }
+
+    #[test]
+    fn extremes_round_trip() {
+        let values = [0, u32::MAX, 1 << 31, 7];
+        assert_eq!(codeword(u32::MAX).1, MAX_CODEWORD_BITS);
+        let enc = encode_prefix_free(&values);
+        assert_eq!(decode_prefix_free(&enc, 4).unwrap(), values);
+    }
+
+    #[test]
+    fn small_values_take_few_bits() {
+        // Unlike LEB128's 8-bit minimum.
+        assert_eq!(encode_prefix_free(&[0; 4]).len(), 1);
+        // 2 + 3 + 4 + 4 + 5 + 5 + 5 + 6 bits.
+        assert_eq!(encode_prefix_free(&[0, 1, 2, 3, 4, 5, 6, 7]).len(), 5);
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        let enc = encode_prefix_free(&[9, 0, 4, 200, 70_000]);
+        assert!(decode_prefix_free(&enc[..enc.len() - 1], 5).is_err());
+        assert!(decode_prefix_free(&[enc.as_slice(), &[0]].concat(), 5).is_err());
+        assert!(decode_prefix_free(&[], 1).is_err());
+        // No terminator within the longest codeword.
+        assert!(decode_prefix_free(&[0b0101_0101; 8], 1).is_err());
+        // 46 Zeckendorf bits whose sum exceeds u32::MAX + 1.
+        let too_big: u64 = 0b11 << 45 | 1 << 43;
+        assert!(decode_prefix_free(&too_big.to_le_bytes(), 1).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(vals in proptest::collection::vec(any::<u32>(), 0..512)) {
+            let enc = encode_prefix_free(&vals);
+            prop_assert_eq!(decode_prefix_free(&enc, vals.len()).unwrap(), vals);
+        }
+
+        #[test]
+        fn no_codeword_is_a_prefix_of_another(a in any::<u32>(), b in any::<u32>()) {
+            prop_assume!(a != b);
+            let ((short, short_len), (long, _)) = {
+                let (ca, cb) = (codeword(a), codeword(b));
+                if ca.1 <= cb.1 { (ca, cb) } else { (cb, ca) }
+            };
+            prop_assert_ne!(long & ((1 << short_len) - 1), short);
+        }
+
+        #[test]
+        fn codewords_end_in_their_only_11(v in any::<u32>()) {
+            let (bits, len) = codeword(v);
+            prop_assert_eq!((bits & (bits >> 1)).trailing_zeros(), len - 2);
+            prop_assert_eq!(bits >> len, 0);
+        }
+    }
+}
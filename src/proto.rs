@@ -0,0 +1,77 @@
+//! Protocol Buffer interchange for `FreqMap` and `Header`.
+//!
+//! The message types are generated by `prost-build` (see `build.rs`) from
+//! `proto/miso.proto`.
+
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::Header;
+
+include!(concat!(env!("OUT_DIR"), "/miso.rs"));
+
+impl FreqMap {
+    /// Tokens and their counts, in mapped-ID order.
+    pub fn to_proto(&self) -> FreqMapProto {
+        FreqMapProto {
+            tokens: self.ordered_tokens().to_vec(),
+            frequencies: self.counts().iter().map(|&count| count as u64).collect(),
+        }
+    }
+
+    /// Rebuild a map through `FreqMap::from_frequencies`, so mapped IDs are
+    /// re-derived from the counts rather than taken from the message order.
+    pub fn from_proto(proto: FreqMapProto) -> Result<Self> {
+        FreqMap::from_frequencies(&proto.tokens, &proto.frequencies)
+    }
+}
+
+impl Header {
+    pub fn to_proto(&self) -> HeaderProto {
+        HeaderProto {
+            version: self.version.into(),
+            flags: self.flags.into(),
+            tokens: self.tokens.clone(),
+        }
+    }
+
+    /// Errors with `CodecError::InvalidPayload` if version or flags do not fit
+    /// in a byte.
+    pub fn from_proto(proto: HeaderProto) -> Result<Self> {
+        let version = u8::try_from(proto.version).map_err(|_| CodecError::InvalidPayload)?;
+        let flags = u8::try_from(proto.flags).map_err(|_| CodecError::InvalidPayload)?;
+        Ok(Header::new(version, flags, proto.tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_proto_round_trip() {
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(&[8, -1, 8, 300]));
+        assert_eq!(Header::from_proto(header.to_proto()).unwrap(), header);
+    }
+
+    #[test]
+    fn header_proto_rejects_wide_fields() {
+        let proto = HeaderProto {
+            version: 256,
+            flags: 0,
+            tokens: vec![],
+        };
+        assert!(Header::from_proto(proto).is_err());
+    }
+
+    #[test]
+    fn freq_map_proto_rejects_mismatched_lengths() {
+        let proto = FreqMapProto {
+            tokens: vec![1, 2],
+            frequencies: vec![3],
+        };
+        assert!(matches!(
+            FreqMap::from_proto(proto),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
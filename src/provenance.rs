@@ -0,0 +1,129 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::{Header, FLAG_SOURCE_HASH, FORMAT_VERSION};
+use crate::Codec;
+
+/// Length of the source hash trailer (a SHA-256 digest).
+pub const SOURCE_HASH_LEN: usize = 32;
+
+/// Payloads that record which text they were tokenized from: a standard
+/// payload in the `FLAG_SOURCE_HASH` mode, followed by the source's hash:
+///   [header][body][32-byte source hash]
+///
+/// The codec doesn't hash anything itself; callers pass the digest of the
+/// source text (SHA-256 by convention).
+impl Codec {
+    pub fn encode_with_source_hash(
+        &self,
+        token_ids: &[i32],
+        source_hash: &[u8; SOURCE_HASH_LEN],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut out = CodecCore::encode_with_flags(token_ids, FLAG_SOURCE_HASH, gzip)?;
+        out.extend_from_slice(source_hash);
+        Ok(out)
+    }
+
+    pub fn decode_with_source_hash(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, [u8; SOURCE_HASH_LEN])> {
+        let hash = decode_source_hash_only(payload)?;
+        let payload = &payload[..payload.len() - SOURCE_HASH_LEN];
+        let (header, _) = Header::decode_prefix(payload).map_err(|_| CodecError::InvalidPayload)?;
+        let body = CodecCore::decompress(&payload[header.body_offset()..], gzip)?;
+        Ok((CodecCore::decode_body(&body, &header)?, hash))
+    }
+
+    /// Whether the payload carries `expected_hash`. False for payloads
+    /// without a source hash; the tokens themselves are not checked.
+    pub fn verify_source_hash(
+        &self,
+        payload: &[u8],
+        expected_hash: &[u8; SOURCE_HASH_LEN],
+    ) -> bool {
+        decode_source_hash_only(payload).is_ok_and(|hash| hash == *expected_hash)
+    }
+}
+
+/// The source hash of an `encode_with_source_hash` payload, from its version
+/// and flags bytes and its last 32 bytes only.
+///
+/// Errors with `CodecError::InvalidPayload` for payloads without one.
+pub fn decode_source_hash_only(payload: &[u8]) -> Result<[u8; SOURCE_HASH_LEN]> {
+    match payload {
+        [FORMAT_VERSION, FLAG_SOURCE_HASH, ..] if payload.len() >= 2 + SOURCE_HASH_LEN => {}
+        _ => return Err(CodecError::InvalidPayload),
+    }
+    Ok(payload[payload.len() - SOURCE_HASH_LEN..]
+        .try_into()
+        .expect("slice of length 32 will always convert"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(seed: u8) -> [u8; SOURCE_HASH_LEN] {
+        std::array::from_fn(|i| seed.wrapping_mul(31).wrapping_add(i as u8))
+    }
+
+    #[test]
+    fn source_hashed_payloads_round_trip() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..300).map(|i| (i * 7919) % 1000).collect();
+        for ids in [ids, vec![], vec![7]] {
+            for gzip in [false, true] {
+                let payload = codec.encode_with_source_hash(&ids, &hash(1), gzip).unwrap();
+                assert_eq!(payload[1], FLAG_SOURCE_HASH);
+                assert_eq!(payload[payload.len() - SOURCE_HASH_LEN..], hash(1));
+                assert_eq!(decode_source_hash_only(&payload).unwrap(), hash(1));
+                assert_eq!(
+                    codec.decode_with_source_hash(&payload, gzip).unwrap(),
+                    (ids.clone(), hash(1))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn different_sources_are_distinguishable() {
+        let codec = Codec::new();
+        // Two texts that happen to tokenize identically.
+        let a = codec
+            .encode_with_source_hash(&[5, 6, 7], &hash(1), false)
+            .unwrap();
+        let b = codec
+            .encode_with_source_hash(&[5, 6, 7], &hash(2), false)
+            .unwrap();
+        assert_ne!(a, b);
+        assert_ne!(
+            decode_source_hash_only(&a).unwrap(),
+            decode_source_hash_only(&b).unwrap()
+        );
+        assert_eq!(
+            codec.decode_with_source_hash(&a, false).unwrap().0,
+            codec.decode_with_source_hash(&b, false).unwrap().0
+        );
+    }
+
+    #[test]
+    fn verify_rejects_wrong_hashes() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_with_source_hash(&[5, 6, 7], &hash(1), true)
+            .unwrap();
+        assert!(codec.verify_source_hash(&payload, &hash(1)));
+        assert!(!codec.verify_source_hash(&payload, &hash(2)));
+        let mut flipped = hash(1);
+        flipped[31] ^= 1;
+        assert!(!codec.verify_source_hash(&payload, &flipped));
+
+        let plain = CodecCore::encode_token_ids(&[5, 6, 7], false).unwrap();
+        assert!(!codec.verify_source_hash(&plain, &hash(1)));
+        assert!(decode_source_hash_only(&plain).is_err());
+        assert!(codec.decode_with_source_hash(&plain, false).is_err());
+        assert!(decode_source_hash_only(&payload[..20]).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+// src/quantize.rs
+use anyhow::{bail, Result};
+
+/// IEEE 754 half-precision bits nearest to `value` (ties to even).
+///
+/// Values beyond the f16 range become infinities, values too small for its
+/// subnormals become signed zeros, and NaNs stay NaNs.
+pub fn f32_to_f16_bits(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xFF) as i32;
+    let mant = x & 0x7F_FFFF;
+
+    if exp == 0xFF {
+        let payload = if mant != 0 {
+            0x200 | (mant >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7C00 | payload;
+    }
+
+    let e = exp - 127 + 15;
+    if e >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        // Subnormal: shift the mantissa, implicit bit included, into place.
+        let shift = (14 - e) as u32;
+        return sign | round_shift(mant | 0x80_0000, shift) as u16;
+    }
+
+    // A carry out of the mantissa correctly bumps the exponent (up to
+    // infinity).
+    sign | round_shift(((e as u32) << 23) | mant, 13) as u16
+}
+
+/// `value >> shift`, rounded to nearest with ties to even.
+#[inline]
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let truncated = value >> shift;
+    let rest = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if rest > halfway || (rest == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+/// The f32 equal to the half-precision value `bits`.
+pub fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exp = u32::from((bits >> 10) & 0x1F);
+    let mant = u32::from(bits & 0x3FF);
+
+    match exp {
+        0 => {
+            // Zero or subnormal: mant * 2^-24.
+            let magnitude = mant as f32 / 16_777_216.0;
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (mant << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (mant << 13)),
+    }
+}
+
+/// Every value of `values` as f16, 2 bytes LE each.
+pub fn encode_f16_quantized(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|&v| f32_to_f16_bits(v).to_le_bytes())
+        .collect()
+}
+
+/// Reverse of `encode_f16_quantized`.
+pub fn decode_f16_quantized(bytes: &[u8]) -> Result<Vec<f32>> {
+    if !bytes.len().is_multiple_of(2) {
+        bail!("f16 data has odd length {}", bytes.len());
+    }
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|b| f16_bits_to_f32(u16::from_le_bytes([b[0], b[1]])))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_f16_survives_a_round_trip() {
+        for bits in 0..=u16::MAX {
+            let value = f16_bits_to_f32(bits);
+            if value.is_nan() {
+                assert!(f16_bits_to_f32(f32_to_f16_bits(value)).is_nan());
+            } else {
+                assert_eq!(f32_to_f16_bits(value), bits, "{bits:#06x}");
+            }
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        assert_eq!(f32_to_f16_bits(1.0), 0x3C00);
+        assert_eq!(f32_to_f16_bits(-2.0), 0xC000);
+        assert_eq!(f32_to_f16_bits(65504.0), 0x7BFF);
+        assert_eq!(f32_to_f16_bits(1e6), 0x7C00);
+        assert_eq!(f32_to_f16_bits(-1e-10), 0x8000);
+        assert_eq!(f32_to_f16_bits(f32::NEG_INFINITY), 0xFC00);
+        // Smallest subnormal, and a tie that rounds to even (down to zero).
+        assert_eq!(f32_to_f16_bits(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16_bits(2f32.powi(-25)), 0x0000);
+        // 1 + 2^-11 is halfway between 1 and the next f16; ties go to even.
+        assert_eq!(f32_to_f16_bits(1.0 + 2f32.powi(-11)), 0x3C00);
+        assert_eq!(f32_to_f16_bits(1.0 + 3.0 * 2f32.powi(-11)), 0x3C02);
+    }
+
+    #[test]
+    fn quantization_error_is_bounded() {
+        for i in 0..10_000 {
+            let value = (i as f32 - 5000.0) * 0.0137;
+            let back = f16_bits_to_f32(f32_to_f16_bits(value));
+            assert!(
+                (back - value).abs() <= value.abs() / 1024.0 + 1e-7,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn slices_round_trip() {
+        let values = [0.0, 0.5, -0.25, 1.0];
+        let bytes = encode_f16_quantized(&values);
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(decode_f16_quantized(&bytes).unwrap(), values);
+        assert!(decode_f16_quantized(&bytes[..3]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::attention_mask::{pack_bits, unpack_bits};
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::{FLAG_RELEVANCE_SCORES, FLAG_RETRIEVED_MASK};
+use crate::{quantize, Codec};
+
+/// Retrieval-augmented inputs: token IDs, which of them were retrieved, and a
+/// relevance score per token.
+///
+/// The mask is bit-packed (as the attention mask is) into the
+/// `FLAG_RETRIEVED_MASK` extension; the scores are f16-quantized
+/// (`quantize::encode_f16_quantized`) into the `FLAG_RELEVANCE_SCORES`
+/// extension. Both lengths are implied by the token count.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` unless the mask and scores
+    /// hold one entry per token.
+    pub fn encode_retrieval_input(
+        &self,
+        token_ids: &[i32],
+        retrieved_mask: &[bool],
+        scores: &[f32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if retrieved_mask.len() != token_ids.len() || scores.len() != token_ids.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mask = pack_bits(retrieved_mask);
+        let scores = quantize::encode_f16_quantized(scores);
+        CodecCore::encode_with_extensions(
+            token_ids,
+            &[
+                (FLAG_RETRIEVED_MASK, &mask),
+                (FLAG_RELEVANCE_SCORES, &scores),
+            ],
+            gzip,
+        )
+    }
+
+    /// Scores come back rounded to the nearest f16.
+    pub fn decode_retrieval_input(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<(Vec<i32>, Vec<bool>, Vec<f32>)> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let tokens = CodecCore::decode_body(&body, &parts.header)?;
+
+        let mask = parts
+            .extension(FLAG_RETRIEVED_MASK)
+            .ok_or(CodecError::InvalidPayload)?;
+        let mask = unpack_bits(mask, tokens.len())?;
+
+        let scores = parts
+            .extension(FLAG_RELEVANCE_SCORES)
+            .filter(|scores| scores.len() == 2 * tokens.len())
+            .ok_or(CodecError::InvalidPayload)?;
+        let scores =
+            quantize::decode_f16_quantized(scores).map_err(|_| CodecError::InvalidPayload)?;
+
+        Ok((tokens, mask, scores))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 512 tokens, of which the 64 at positions 100..164 were retrieved.
+    fn input() -> (Vec<i32>, Vec<bool>, Vec<f32>) {
+        let ids = (0..512).map(|i| (i * 7919) % 30_000).collect();
+        let mask = (0..512).map(|i| (100..164).contains(&i)).collect();
+        let scores = (0..512)
+            .map(|i| {
+                if (100..164).contains(&i) {
+                    0.5 + i as f32 / 1024.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        (ids, mask, scores)
+    }
+
+    #[test]
+    fn retrieval_input_round_trips() {
+        let codec = Codec::new();
+        let (ids, mask, scores) = input();
+
+        for gzip in [false, true] {
+            let payload = codec
+                .encode_retrieval_input(&ids, &mask, &scores, gzip)
+                .unwrap();
+            let (tokens, decoded_mask, decoded_scores) =
+                codec.decode_retrieval_input(&payload, gzip).unwrap();
+
+            assert_eq!(tokens, ids);
+            assert_eq!(decoded_mask, mask);
+            assert_eq!(decoded_mask.iter().filter(|&&m| m).count(), 64);
+            for (got, want) in decoded_scores.iter().zip(&scores) {
+                assert!((got - want).abs() <= want.abs() / 1024.0, "{got} vs {want}");
+            }
+
+            // Plain decoders skip both extensions.
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn mismatched_lengths_are_rejected() {
+        let codec = Codec::new();
+        let (ids, mask, scores) = input();
+        assert!(codec
+            .encode_retrieval_input(&ids, &mask[1..], &scores, false)
+            .is_err());
+        assert!(codec
+            .encode_retrieval_input(&ids, &mask, &scores[1..], false)
+            .is_err());
+
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+        assert!(codec.decode_retrieval_input(&plain, false).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+// src/rice.rs
+use anyhow::{bail, Result};
+
+use crate::bits::{BitReader, BitWriter};
+
+/// Quotients of this size or more are not written in unary: instead `ESCAPE`
+/// one bits are followed by the raw 32-bit value, which bounds the cost of an
+/// outlier.
+const ESCAPE: u32 = 32;
+
+/// Rice-code `values` with parameter `m`, rounded down to a power of two
+/// `2^k` (`k = floor(log2(m))`; `m = 0` is treated as 1).
+///
+/// Each value `v` is written as the quotient `v >> k` in unary (that many one
+/// bits, then a zero bit) followed by the low `k` bits of `v`. Bits are packed
+/// least significant bit first; the final byte is zero-padded.
+///
+/// Rice coding is optimal for geometrically distributed values, which is
+/// roughly what frequency-ranked IDs look like.
+pub fn encode_rice(values: &[u32], m: u32) -> Vec<u8> {
+    let k = exponent(m);
+    let mut writer = BitWriter::with_capacity(values.len());
+
+    for &v in values {
+        let q = v >> k;
+        if q >= ESCAPE {
+            writer.push(u64::from(u32::MAX), ESCAPE);
+            writer.push(u64::from(v), 32);
+        } else {
+            // q ones, then the terminating zero.
+            writer.push((1u64 << q) - 1, q + 1);
+            writer.push(u64::from(v) & ((1u64 << k) - 1), k);
+        }
+    }
+
+    writer.finish()
+}
+
+/// Decode `count` values written by `encode_rice` with the same `m`.
+/// Errors on a truncated stream or trailing bytes.
+pub fn decode_rice(bytes: &[u8], m: u32, count: usize) -> Result<Vec<u32>> {
+    let k = exponent(m);
+    // Every value takes at least one bit, which bounds the allocation.
+    if count > bytes.len() * 8 {
+        bail!("rice stream too short for {} values", count);
+    }
+
+    let mut reader = BitReader::new(bytes);
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let q = (!reader.peek()).trailing_zeros().min(ESCAPE);
+        if q == ESCAPE {
+            reader.skip(ESCAPE)?;
+            out.push(reader.read(32)?);
+        } else {
+            reader.skip(q + 1)?;
+            out.push((q << k) | reader.read(k)?);
+        }
+    }
+
+    if !reader.at_end() {
+        bail!("trailing bytes after rice stream");
+    }
+    Ok(out)
+}
+
+/// Rice parameter for `values`, estimated from their mean as the power of two
+/// nearest below `mean * ln 2` (the optimum for a geometric distribution).
+pub fn optimal_rice_parameter(values: &[u32]) -> u32 {
+    if values.is_empty() {
+        return 1;
+    }
+    let mean = values.iter().map(|&v| f64::from(v)).sum::<f64>() / values.len() as f64;
+    let target = mean * std::f64::consts::LN_2;
+    if target < 2.0 {
+        return 1;
+    }
+    1 << (target.log2().floor() as u32).min(31)
+}
+
+/// `k` such that `2^k` is `m` rounded down to a power of two.
+#[inline]
+fn exponent(m: u32) -> u32 {
+    m.max(1).ilog2()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn known_layout() {
+        // k = 2: 5 -> q 1, r 01 -> bits 1,0 then 1,0 (LSB first) = 0b0101;
+        //        2 -> q 0, r 10 -> bits 0 then 0,1 = 0b100 << 4.
+        assert_eq!(encode_rice(&[5, 2], 4), [0b0100_0101]);
+        assert_eq!(decode_rice(&[0b0100_0101], 4, 2).unwrap(), [5, 2]);
+    }
+
+    #[test]
+    fn m_is_rounded_down_to_a_power_of_two() {
+        let values = [0, 3, 17, 1000];
+        assert_eq!(encode_rice(&values, 7), encode_rice(&values, 4));
+        assert_eq!(encode_rice(&values, 0), encode_rice(&values, 1));
+        assert_eq!(decode_rice(&encode_rice(&values, 7), 5, 4).unwrap(), values);
+    }
+
+    #[test]
+    fn outliers_are_escaped() {
+        let values = [u32::MAX, 0, 1 << 20];
+        let enc = encode_rice(&values, 1);
+        // Each outlier costs 32 + 32 bits instead of ~2^32 unary bits.
+        assert!(enc.len() <= 17);
+        assert_eq!(decode_rice(&enc, 1, 3).unwrap(), values);
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        let enc = encode_rice(&[9, 1, 4, 200], 2);
+        assert!(decode_rice(&enc[..enc.len() - 1], 2, 4).is_err());
+        assert!(decode_rice(&[enc.as_slice(), &[0]].concat(), 2, 4).is_err());
+        assert!(decode_rice(&[], 2, 1).is_err());
+        assert!(decode_rice(&[0xFF], 1, 1).is_err());
+    }
+
+    #[test]
+    fn optimal_parameter_tracks_the_mean() {
+        assert_eq!(optimal_rice_parameter(&[]), 1);
+        assert_eq!(optimal_rice_parameter(&[0, 1, 0, 2]), 1);
+        // mean 9 (geometric with p = 0.1): 9 * ln 2 = 6.2 -> 4.
+        assert_eq!(optimal_rice_parameter(&[9; 10]), 4);
+        assert_eq!(optimal_rice_parameter(&[u32::MAX; 3]), 1 << 31);
+    }
+
+    #[test]
+    fn beats_leb128_on_geometric_values() {
+        // Inverse-CDF samples of a geometric distribution with p = 0.1.
+        let values: Vec<u32> = (1..10_000)
+            .map(|i| ((f64::from(i) / 10_000.0).ln() / 0.9f64.ln()) as u32)
+            .collect();
+        let m = optimal_rice_parameter(&values);
+        let rice = encode_rice(&values, m).len();
+        let leb128 = crate::varint::encode(&values).len();
+        assert!(rice * 100 <= leb128 * 85, "rice {rice} vs leb128 {leb128}");
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(
+            vals in proptest::collection::vec(prop_oneof![8 => 0u32..64, 1 => any::<u32>()], 0..512),
+            m in 0u32..100_000,
+        ) {
+            let enc = encode_rice(&vals, m);
+            prop_assert_eq!(decode_rice(&enc, m, vals.len()).unwrap(), vals);
+        }
+    }
+}
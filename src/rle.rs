@@ -0,0 +1,110 @@
+// src/rle.rs
+use anyhow::{bail, Result};
+
+use crate::{varint, zigzag};
+
+/// Encode `values` as runs: a LEB128 run count, then one
+/// `(zigzag value, run length)` LEB128 pair per run.
+///
+/// No frequency remapping is done, so this suits sequences that are already
+/// small integers with long runs (e.g. token type IDs `[0, 0, ..., 1, 1]`).
+pub fn encode_rle_only(values: &[i32]) -> Vec<u8> {
+    let mut runs: Vec<(i32, u32)> = Vec::new();
+    for &v in values {
+        match runs.last_mut() {
+            Some((value, len)) if *value == v && *len < u32::MAX => *len += 1,
+            _ => runs.push((v, 1)),
+        }
+    }
+
+    let mut words = Vec::with_capacity(1 + 2 * runs.len());
+    words.push(runs.len() as u32);
+    for (value, len) in runs {
+        words.push(zigzag::encode(value));
+        words.push(len);
+    }
+    varint::encode(&words)
+}
+
+/// Decode `encode_rle_only` output.
+/// Errors on a truncated stream, trailing bytes, or an empty run.
+pub fn decode_rle_only(bytes: &[u8]) -> Result<Vec<i32>> {
+    let (count, mut pos) = varint::decode_one(bytes)?;
+
+    // Every run takes at least two bytes, which bounds the allocation.
+    let mut runs = Vec::with_capacity((count as usize).min(bytes.len() / 2));
+    let mut total: usize = 0;
+    for _ in 0..count {
+        let (value, used) = varint::decode_one(&bytes[pos..])?;
+        pos += used;
+        let (len, used) = varint::decode_one(&bytes[pos..])?;
+        pos += used;
+        if len == 0 {
+            bail!("empty run in rle stream");
+        }
+        total = total
+            .checked_add(len as usize)
+            .ok_or_else(|| anyhow::anyhow!("rle stream too long"))?;
+        runs.push((zigzag::decode(value), len as usize));
+    }
+    if pos != bytes.len() {
+        bail!("{} trailing bytes after rle stream", bytes.len() - pos);
+    }
+
+    let mut out = Vec::with_capacity(total);
+    for (value, len) in runs {
+        out.extend(std::iter::repeat_n(value, len));
+    }
+    Ok(out)
+}
+
+/// Number of runs `encode_rle_only` would write for `values`.
+pub fn run_count(values: &[i32]) -> usize {
+    if values.is_empty() {
+        return 0;
+    }
+    1 + values.windows(2).filter(|w| w[0] != w[1]).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn thousand_zeros_are_three_bytes() {
+        let zeros = vec![0; 1000];
+        let enc = encode_rle_only(&zeros);
+        // One run: count 1, value 0, length 1000 (two bytes).
+        assert_eq!(enc, [1, 0, 0xE8, 0x07]);
+        assert_eq!(decode_rle_only(&enc).unwrap(), zeros);
+    }
+
+    #[test]
+    fn zeros_then_ones() {
+        let values: Vec<i32> = [vec![0; 500], vec![1; 500]].concat();
+        let enc = encode_rle_only(&values);
+        assert_eq!(enc, [2, 0, 0xF4, 0x03, 2, 0xF4, 0x03]);
+        assert_eq!(decode_rle_only(&enc).unwrap(), values);
+        assert_eq!(run_count(&values), 2);
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        assert!(decode_rle_only(&[]).is_err());
+        assert!(decode_rle_only(&[1, 0]).is_err());
+        assert!(decode_rle_only(&[1, 0, 0]).is_err());
+        assert!(decode_rle_only(&[1, 0, 1, 9]).is_err());
+        assert!(decode_rle_only(&[0]).unwrap().is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(vals in proptest::collection::vec(-3i32..3, 0..512)) {
+            let enc = encode_rle_only(&vals);
+            prop_assert_eq!(decode_rle_only(&enc).unwrap(), vals.clone());
+            prop_assert_eq!(varint::decode_one(&enc).unwrap().0 as usize, run_count(&vals));
+        }
+    }
+}
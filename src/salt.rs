@@ -0,0 +1,103 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::PREFIX_LEN;
+use crate::Codec;
+
+/// Salted payloads, for applications sharing one storage namespace.
+///
+/// The header bytes are XORed with a keystream derived from `salt`, so a
+/// payload only parses when decoded with the salt it was written with; the
+/// body is left as is. This is **not** cryptographic protection: the tokens
+/// are still visible in the body and the scrambling is trivially reversible
+/// by anyone who knows it is there. It only prevents one application from
+/// accidentally decoding another's payloads.
+///
+/// Keystream block `i` (32 bytes) is `blake3::keyed_hash(blake3(salt), i)`.
+impl Codec {
+    pub fn encode_with_salt(&self, token_ids: &[i32], salt: &[u8], gzip: bool) -> Result<Vec<u8>> {
+        let mut payload = CodecCore::encode_token_ids(token_ids, gzip)?;
+        let header_len = PREFIX_LEN + 4 * token_count(&payload)?;
+        scramble(&mut payload[..header_len], salt);
+        Ok(payload)
+    }
+
+    pub fn decode_with_salt(&self, payload: &[u8], salt: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        if payload.len() < PREFIX_LEN {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        // The keystream is positional, so unscramble the fixed prefix first to
+        // learn how long the rest of the header is.
+        let mut prefix = [0u8; PREFIX_LEN];
+        prefix.copy_from_slice(&payload[..PREFIX_LEN]);
+        scramble(&mut prefix, salt);
+
+        let header_len = token_count(&prefix)?
+            .checked_mul(4)
+            .and_then(|tokens| tokens.checked_add(PREFIX_LEN))
+            .filter(|&len| len <= payload.len())
+            .ok_or(CodecError::InvalidPayload)?;
+
+        let mut plain = payload.to_vec();
+        scramble(&mut plain[..header_len], salt);
+        CodecCore::decode_token_ids(&plain, gzip)
+    }
+}
+
+/// The header's token count, read from the u32 after version and flags.
+fn token_count(prefix: &[u8]) -> Result<usize> {
+    let len: [u8; 4] = prefix
+        .get(2..PREFIX_LEN)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(CodecError::InvalidPayload)?;
+    Ok(u32::from_le_bytes(len) as usize)
+}
+
+/// XOR `bytes` with the salt's keystream (its own inverse).
+fn scramble(bytes: &mut [u8], salt: &[u8]) {
+    let key = blake3::hash(salt);
+    for (block, chunk) in bytes.chunks_mut(blake3::OUT_LEN).enumerate() {
+        let stream = blake3::keyed_hash(key.as_bytes(), &(block as u64).to_le_bytes());
+        for (byte, key_byte) in chunk.iter_mut().zip(stream.as_bytes()) {
+            *byte ^= key_byte;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_only_with_the_same_salt() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..300).map(|i| (i * 13) % 97).collect();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_with_salt(&ids, b"app-a", gzip).unwrap();
+            assert_eq!(
+                codec.decode_with_salt(&payload, b"app-a", gzip).unwrap(),
+                ids
+            );
+            assert!(codec.decode_with_salt(&payload, b"app-b", gzip).is_err());
+            assert!(CodecCore::decode_token_ids(&payload, gzip).is_err());
+        }
+    }
+
+    #[test]
+    fn only_the_header_is_scrambled() {
+        let codec = Codec::new();
+        let ids = [4, 4, 8];
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+        let salted = codec.encode_with_salt(&ids, b"ns", false).unwrap();
+
+        let header_len = PREFIX_LEN + 2 * 4;
+        assert_ne!(salted[..header_len], plain[..header_len]);
+        assert_eq!(salted[header_len..], plain[header_len..]);
+    }
+
+    #[test]
+    fn rejects_short_payloads() {
+        assert!(Codec::new().decode_with_salt(&[1, 2], b"x", false).is_err());
+    }
+}
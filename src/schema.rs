@@ -0,0 +1,134 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_SCHEMA_PRESENT;
+use crate::varint;
+use crate::Codec;
+
+/// Self-describing tensor shapes (e.g. `batch_size x seq_len`), so the decoder
+/// can rebuild the tensor without out-of-band shape information.
+///
+/// The schema section is appended after the body and announced by
+/// `FLAG_SCHEMA_PRESENT` in the header. Section layout:
+///   varint : rank (number of dimensions)
+///   varint : size of each dimension, outermost first
+impl Codec {
+    /// Fails with `CodecError::InvalidPayload` unless the shape's element count
+    /// equals `token_ids.len()`.
+    pub fn encode_with_schema(
+        &self,
+        token_ids: &[i32],
+        shape: &[u32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let elements = shape
+            .iter()
+            .try_fold(1u32, |acc, &dim| acc.checked_mul(dim))
+            .ok_or(CodecError::InvalidPayload)?;
+        if elements != to_u32(token_ids.len())? {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut section = varint::encode(&[to_u32(shape.len())?]);
+        section.extend_from_slice(&varint::encode(shape));
+
+        let mut out = CodecCore::encode_with_flags(token_ids, FLAG_SCHEMA_PRESENT, gzip)?;
+        CodecCore::push_section(&mut out, &section)?;
+        Ok(out)
+    }
+
+    /// Decode tokens and shape. Payloads without a schema section are reported
+    /// as one-dimensional.
+    pub fn decode_with_schema(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<u32>)> {
+        let parts = CodecCore::split_payload(payload)?;
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let tokens = CodecCore::decode_body(&body, &parts.header)?;
+
+        let shape = match parts.section(FLAG_SCHEMA_PRESENT) {
+            Some(section) => decode_section(section)?,
+            None => vec![to_u32(tokens.len())?],
+        };
+        Ok((tokens, shape))
+    }
+}
+
+fn decode_section(mut bytes: &[u8]) -> Result<Vec<u32>> {
+    let rank = read_varint(&mut bytes)? as usize;
+
+    let mut shape = Vec::with_capacity(rank.min(bytes.len()));
+    for _ in 0..rank {
+        shape.push(read_varint(&mut bytes)?);
+    }
+
+    if !bytes.is_empty() {
+        return Err(CodecError::InvalidPayload);
+    }
+    Ok(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn round_trip(shape: &[u32]) {
+        let codec = Codec::new();
+        let len: u32 = shape.iter().product();
+        let ids: Vec<i32> = (0..len as i32).map(|i| i % 13).collect();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_with_schema(&ids, shape, gzip).unwrap();
+            let (tokens, decoded) = codec.decode_with_schema(&payload, gzip).unwrap();
+            assert_eq!(tokens, ids);
+            assert_eq!(decoded, shape);
+
+            // Plain decoding skips the section entirely.
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn one_dimensional() {
+        round_trip(&[17]);
+    }
+
+    #[test]
+    fn two_dimensional() {
+        round_trip(&[4, 128]);
+    }
+
+    #[test]
+    fn three_dimensional() {
+        round_trip(&[2, 3, 300]);
+    }
+
+    #[test]
+    fn rejects_shape_mismatch() {
+        let codec = Codec::new();
+        assert!(matches!(
+            codec.encode_with_schema(&[1, 2, 3], &[2, 2], false),
+            Err(CodecError::InvalidPayload)
+        ));
+        assert!(codec
+            .encode_with_schema(&[1], &[u32::MAX, u32::MAX], false)
+            .is_err());
+    }
+
+    #[test]
+    fn payload_without_schema_is_one_dimensional() {
+        let codec = Codec::new();
+        let payload = CodecCore::encode_token_ids(&[4, 4, 2], false).unwrap();
+        assert_eq!(
+            codec.decode_with_schema(&payload, false).unwrap().1,
+            vec![3]
+        );
+
+        let payload = codec
+            .encode_with_metadata(&[4, 4], &HashMap::new(), false)
+            .unwrap();
+        assert_eq!(
+            codec.decode_with_schema(&payload, false).unwrap().1,
+            vec![2]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::FLAG_SEGMENTS;
+use crate::{varint, Codec};
+
+/// Concatenated documents with their boundaries: the length of every segment,
+/// varint encoded, in the `FLAG_SEGMENTS` extension.
+impl Codec {
+    /// Errors with `CodecError::InvalidPayload` unless the segment lengths add
+    /// up to the token count.
+    pub fn encode_with_segments(
+        &self,
+        token_ids: &[i32],
+        segment_lengths: &[u32],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if !covers(segment_lengths, token_ids.len()) {
+            return Err(CodecError::InvalidPayload);
+        }
+        let segments = varint::encode(segment_lengths);
+        CodecCore::encode_with_extensions(token_ids, &[(FLAG_SEGMENTS, &segments)], gzip)
+    }
+
+    pub fn decode_with_segments(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, Vec<u32>)> {
+        let (tokens, segments) = CodecCore::decode_with_extension(payload, FLAG_SEGMENTS, gzip)?;
+        let segments = segments.ok_or(CodecError::InvalidPayload)?;
+        let lengths = varint::decode(segments).map_err(|_| CodecError::InvalidPayload)?;
+        if !covers(&lengths, tokens.len()) {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok((tokens, lengths))
+    }
+}
+
+fn covers(segment_lengths: &[u32], n_tokens: usize) -> bool {
+    segment_lengths
+        .iter()
+        .map(|&len| u64::from(len))
+        .sum::<u64>()
+        == n_tokens as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 5 documents of different lengths, each ending in an EOS token (0).
+    fn batch() -> (Vec<i32>, Vec<u32>) {
+        let lengths = [17u32, 250, 3, 1, 96];
+        let mut ids = Vec::new();
+        for (doc, &len) in lengths.iter().enumerate() {
+            ids.extend((1..len as i32).map(|i| (i * 7919 + doc as i32) % 5000 + 1));
+            ids.push(0);
+        }
+        (ids, lengths.to_vec())
+    }
+
+    #[test]
+    fn segments_round_trip() {
+        let codec = Codec::new();
+        let (ids, lengths) = batch();
+        for gzip in [false, true] {
+            let payload = codec.encode_with_segments(&ids, &lengths, gzip).unwrap();
+            assert_eq!(
+                codec.decode_with_segments(&payload, gzip).unwrap(),
+                (ids.clone(), lengths.clone())
+            );
+            assert_eq!(CodecCore::decode_token_ids(&payload, gzip).unwrap(), ids);
+        }
+
+        // Segment boundaries line up with the EOS tokens.
+        let mut end = 0;
+        for len in lengths {
+            end += len as usize;
+            assert_eq!(ids[end - 1], 0);
+        }
+    }
+
+    #[test]
+    fn lengths_must_cover_the_tokens() {
+        let codec = Codec::new();
+        let (ids, mut lengths) = batch();
+        lengths[2] += 1;
+        assert!(matches!(
+            codec.encode_with_segments(&ids, &lengths, false),
+            Err(CodecError::InvalidPayload)
+        ));
+        assert!(codec
+            .encode_with_segments(&[1, 2], &[u32::MAX, 3], false)
+            .is_err());
+
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+        assert!(codec.decode_with_segments(&plain, false).is_err());
+
+        let empty = codec.encode_with_segments(&[], &[], false).unwrap();
+        assert_eq!(
+            codec.decode_with_segments(&empty, false).unwrap(),
+            (vec![], vec![])
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::codec_core::CodecCore;
+use crate::config::{CodecConfig, VarintMode};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_MODE_MASK, FLAG_SAME_HEADER, FORMAT_VERSION, PREFIX_LEN};
+use crate::Codec;
+
+/// Stateful codec for a stream of payloads, where consecutive payloads often
+/// share one vocabulary ordering.
+///
+/// When a payload's header would repeat the previous one, it is replaced by a
+/// 4-byte reference:
+///
+///   [version u8][flags u8 = FLAG_SAME_HEADER][u32 LE token_table_crc32][body]
+///
+/// Encoder and decoder must see the same payloads in the same order, so that
+/// their cached headers stay in step; a reference that does not match the
+/// decoder's cache is rejected with `CodecError::InvalidPayload`.
+#[derive(Debug, Clone, Default)]
+pub struct SessionCodec {
+    last_header: Option<Header>,
+}
+
+impl SessionCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn encode(&mut self, token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        let body = CodecCore::compress(CodecCore::encode_body(token_ids, &freq)?, gzip)?;
+
+        let reuse = self
+            .last_header
+            .as_ref()
+            .filter(|last| last.tokens == freq.ordered_tokens());
+        let mut out = match reuse {
+            Some(last) => {
+                let mut out = Vec::with_capacity(PREFIX_LEN + body.len());
+                out.push(FORMAT_VERSION);
+                out.push(FLAG_SAME_HEADER);
+                out.extend_from_slice(&last.token_table_crc32().to_le_bytes());
+                out
+            }
+            None => {
+                let header = Header::from_freq_map(&freq);
+                let out = header.encode();
+                self.last_header = Some(header);
+                out
+            }
+        };
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    pub fn decode(&mut self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let is_same_header = payload.len() >= PREFIX_LEN
+            && payload[0] == FORMAT_VERSION
+            && payload[1] & FLAG_MODE_MASK == FLAG_SAME_HEADER;
+        if !is_same_header {
+            let (tokens, header) = CodecCore::decode_with_header(payload, gzip)?;
+            self.last_header = Some(header);
+            return Ok(tokens);
+        }
+
+        let hash_bytes: [u8; 4] = payload[2..PREFIX_LEN]
+            .try_into()
+            .expect("slice of length 4 will always convert");
+        let header = self
+            .last_header
+            .as_ref()
+            .filter(|last| last.token_table_crc32() == u32::from_le_bytes(hash_bytes))
+            .ok_or(CodecError::InvalidPayload)?;
+
+        let body = CodecCore::decompress(&payload[PREFIX_LEN..], gzip)?;
+        CodecCore::decode_body(&body, header)
+    }
+}
+
+/// Token-at-a-time encoder for autoregressive generation.
+///
+/// Tokens accumulate as they are generated. `current_payload` encodes the
+/// whole sequence so far; `delta_payload` encodes only the tokens pushed
+/// since the previous `delta_payload`, as a payload of its own, for
+/// streaming to a `SessionDecoder`. Both use `Codec::encode` with the
+/// session's config and no gzip.
+#[derive(Debug, Clone, Default)]
+pub struct SessionEncoder {
+    codec: Codec,
+    tokens: Vec<i32>,
+    /// How many of `tokens` earlier delta payloads covered.
+    sent: usize,
+}
+
+impl SessionEncoder {
+    pub fn new(config: CodecConfig) -> Self {
+        Self {
+            codec: Codec::with_config(config),
+            tokens: Vec::new(),
+            sent: 0,
+        }
+    }
+
+    pub fn push_token(&mut self, token: i32) {
+        self.tokens.push(token);
+    }
+
+    pub fn push_tokens(&mut self, tokens: &[i32]) {
+        self.tokens.extend_from_slice(tokens);
+    }
+
+    /// Every token pushed so far.
+    pub fn tokens(&self) -> &[i32] {
+        &self.tokens
+    }
+
+    /// The whole sequence, identical to `Codec::encode` of `tokens()`.
+    pub fn current_payload(&self) -> Result<Vec<u8>> {
+        self.codec.encode(&self.tokens, false)
+    }
+
+    /// The tokens pushed since the last call (an empty payload if none).
+    pub fn delta_payload(&mut self) -> Result<Vec<u8>> {
+        let payload = self.codec.encode(&self.tokens[self.sent..], false)?;
+        self.sent = self.tokens.len();
+        Ok(payload)
+    }
+}
+
+/// Rebuilds a `SessionEncoder`'s sequence from its delta payloads, which must
+/// arrive in order.
+#[derive(Debug, Clone, Default)]
+pub struct SessionDecoder {
+    codec: Codec,
+    tokens: Vec<i32>,
+}
+
+impl SessionDecoder {
+    /// `config` must match the encoder's when it uses
+    /// `VarintMode::Custom`, since those payloads need the same encoder to be
+    /// read back; every other mode is recorded in the payloads themselves.
+    pub fn new(config: CodecConfig) -> Self {
+        Self {
+            codec: Codec::with_config(config),
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Append the tokens of `payload` and return them. On error the sequence
+    /// is left unchanged.
+    pub fn push_delta(&mut self, payload: &[u8]) -> Result<&[i32]> {
+        let tokens = match &self.codec.config().varint_mode {
+            VarintMode::Custom(encoder) => {
+                self.codec
+                    .decode_with_custom_varint(payload, encoder.as_ref(), false)?
+            }
+            _ => CodecCore::decode_token_ids(payload, false)?,
+        };
+        let start = self.tokens.len();
+        self.tokens.extend(tokens);
+        Ok(&self.tokens[start..])
+    }
+
+    /// Every token decoded so far.
+    pub fn tokens(&self) -> &[i32] {
+        &self.tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 rotations of one sequence: same counts, so the same vocabulary order.
+    fn sequences() -> Vec<Vec<i32>> {
+        let base: Vec<i32> = (0..200).map(|i| (i * 7919) % 97 + 1000).collect();
+        (0..10)
+            .map(|r| {
+                let mut seq = base.clone();
+                seq.rotate_left(r * 13);
+                seq
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repeated_vocabularies_send_the_header_once() {
+        let mut encoder = SessionCodec::new();
+        let mut decoder = SessionCodec::new();
+
+        for gzip in [false, true] {
+            for (i, seq) in sequences().iter().enumerate() {
+                let payload = encoder.encode(seq, gzip).unwrap();
+                let standard = CodecCore::encode_token_ids(seq, gzip).unwrap();
+                if i == 0 && !gzip {
+                    assert_eq!(payload, standard);
+                } else {
+                    assert_eq!(payload[1], FLAG_SAME_HEADER);
+                    assert!(payload.len() + 300 < standard.len());
+                }
+                assert_eq!(decoder.decode(&payload, gzip).unwrap(), *seq);
+            }
+        }
+    }
+
+    #[test]
+    fn vocabulary_changes_resend_the_header() {
+        let mut encoder = SessionCodec::new();
+        let mut decoder = SessionCodec::new();
+
+        for seq in [vec![1, 1, 2], vec![2, 1, 1], vec![5, 6], vec![6, 5]] {
+            let payload = encoder.encode(&seq, false).unwrap();
+            assert_eq!(decoder.decode(&payload, false).unwrap(), seq);
+        }
+
+        // [1, 1, 2] and [2, 1, 1] share a header; so do [5, 6] and [6, 5].
+        let mut fresh = SessionCodec::new();
+        fresh.encode(&[1, 1, 2], false).unwrap();
+        assert_eq!(
+            fresh.encode(&[2, 1, 1], false).unwrap()[1],
+            FLAG_SAME_HEADER
+        );
+        assert_eq!(fresh.encode(&[5, 6], false).unwrap()[1], 0);
+    }
+
+    #[test]
+    fn references_need_the_matching_cache() {
+        let mut encoder = SessionCodec::new();
+        encoder.encode(&[1, 2, 3], false).unwrap();
+        let reference = encoder.encode(&[3, 2, 1], false).unwrap();
+
+        // A decoder that never saw the first payload.
+        assert!(matches!(
+            SessionCodec::new().decode(&reference, false),
+            Err(CodecError::InvalidPayload)
+        ));
+
+        // A decoder whose cache holds a different vocabulary.
+        let mut other = SessionCodec::new();
+        other
+            .decode(
+                &CodecCore::encode_token_ids(&[7, 8, 9], false).unwrap(),
+                false,
+            )
+            .unwrap();
+        assert!(other.decode(&reference, false).is_err());
+    }
+
+    /// 100 generation steps after a 20-token prompt, emitting one token per
+    /// step (and two on every tenth).
+    fn generate(config: CodecConfig) -> (SessionEncoder, Vec<Vec<u8>>, Vec<i32>) {
+        let from_scratch = Codec::with_config(config.clone());
+        let mut encoder = SessionEncoder::new(config);
+        let prompt: Vec<i32> = (0..20).map(|i| (i * 7919) % 50_000).collect();
+        encoder.push_tokens(&prompt);
+        let mut sequence = prompt;
+        let mut deltas = vec![encoder.delta_payload().unwrap()];
+
+        for step in 0..100 {
+            let token = (step * 131 + 7) % 300;
+            encoder.push_token(token);
+            sequence.push(token);
+            if step % 10 == 9 {
+                encoder.push_token(50_256);
+                sequence.push(50_256);
+            }
+            deltas.push(encoder.delta_payload().unwrap());
+            assert_eq!(
+                encoder.current_payload().unwrap(),
+                from_scratch.encode(&sequence, false).unwrap()
+            );
+        }
+        (encoder, deltas, sequence)
+    }
+
+    #[test]
+    fn current_payload_matches_a_full_encode() {
+        let (encoder, _, sequence) = generate(CodecConfig::default());
+        assert_eq!(encoder.tokens(), sequence);
+        assert_eq!(sequence.len(), 130);
+        assert_eq!(
+            encoder.current_payload().unwrap(),
+            CodecCore::encode_token_ids(&sequence, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn decoder_reassembles_delta_payloads() {
+        let (_, deltas, sequence) = generate(CodecConfig::default());
+        let mut decoder = SessionDecoder::new(CodecConfig::default());
+        let mut decoded_at_step = Vec::new();
+        for delta in &deltas {
+            decoded_at_step.push(decoder.push_delta(delta).unwrap().len());
+        }
+        assert_eq!(decoder.tokens(), sequence);
+        assert_eq!(decoded_at_step[0], 20);
+        assert_eq!(decoded_at_step[1], 1);
+        assert_eq!(decoded_at_step[10], 2);
+    }
+
+    #[test]
+    fn delta_payloads_cover_only_new_tokens() {
+        let mut encoder = SessionEncoder::new(CodecConfig::default());
+        encoder.push_tokens(&[1, 2, 3]);
+        assert_eq!(
+            encoder.delta_payload().unwrap(),
+            CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap()
+        );
+        // Nothing new yet.
+        assert_eq!(
+            encoder.delta_payload().unwrap(),
+            CodecCore::encode_token_ids(&[], false).unwrap()
+        );
+        encoder.push_token(4);
+        assert_eq!(
+            encoder.delta_payload().unwrap(),
+            CodecCore::encode_token_ids(&[4], false).unwrap()
+        );
+        // current_payload doesn't advance the delta position.
+        encoder.push_token(5);
+        encoder.current_payload().unwrap();
+        assert_eq!(
+            encoder.delta_payload().unwrap(),
+            CodecCore::encode_token_ids(&[5], false).unwrap()
+        );
+    }
+
+    #[test]
+    fn sessions_follow_the_varint_mode() {
+        let config = CodecConfig {
+            varint_mode: VarintMode::VByte,
+            ..CodecConfig::default()
+        };
+        let (_, deltas, sequence) = generate(config.clone());
+        // Standard decoding reads the mode from each payload.
+        let mut decoder = SessionDecoder::new(CodecConfig::default());
+        for delta in &deltas {
+            decoder.push_delta(delta).unwrap();
+        }
+        assert_eq!(decoder.tokens(), sequence);
+
+        let custom = CodecConfig {
+            varint_mode: VarintMode::Custom(std::sync::Arc::new(
+                crate::custom_varint::FixedU32Encoder,
+            )),
+            ..CodecConfig::default()
+        };
+        let mut encoder = SessionEncoder::new(custom.clone());
+        encoder.push_tokens(&[9, 8, 7]);
+        let delta = encoder.delta_payload().unwrap();
+        let mut decoder = SessionDecoder::new(custom);
+        assert_eq!(decoder.push_delta(&delta).unwrap(), [9, 8, 7]);
+
+        // A bad payload leaves the sequence alone.
+        assert!(decoder.push_delta(&delta[..3]).is_err());
+        assert_eq!(decoder.tokens(), [9, 8, 7]);
+    }
+}
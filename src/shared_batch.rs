@@ -0,0 +1,144 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::varint;
+use crate::Codec;
+
+/// Batches compressed as a whole, so gzip can exploit redundancy across
+/// sequences instead of restarting its window for every small payload.
+///
+/// Layout:
+///   varint  : number of sequences N
+///   N varint: byte size of each sequence's chunk
+///   body    : the N chunks back to back (gzipped as one stream when `gzip`)
+///
+/// A chunk is an uncompressed standalone `encode_token_ids` payload, i.e. each
+/// sequence keeps its own mini-header.
+impl Codec {
+    pub fn encode_compressed_batch_shared(
+        &self,
+        sequences: &[&[i32]],
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut sizes = Vec::with_capacity(sequences.len());
+        let mut body = Vec::new();
+        for seq in sequences {
+            let chunk = CodecCore::encode_token_ids(seq, false)?;
+            sizes.push(to_u32(chunk.len())?);
+            body.extend_from_slice(&chunk);
+        }
+
+        let mut out = varint::encode(&[to_u32(sequences.len())?]);
+        out.extend_from_slice(&varint::encode(&sizes));
+        out.extend_from_slice(&CodecCore::compress(body, gzip)?);
+        Ok(out)
+    }
+
+    pub fn decode_compressed_batch_shared(
+        &self,
+        payload: &[u8],
+        gzip: bool,
+    ) -> Result<Vec<Vec<i32>>> {
+        let mut rest = payload;
+        let count = read_varint(&mut rest)? as usize;
+
+        // Every size takes at least one byte, which bounds the allocation.
+        let mut sizes = Vec::with_capacity(count.min(rest.len()));
+        for _ in 0..count {
+            sizes.push(read_varint(&mut rest)? as usize);
+        }
+
+        let body = CodecCore::decompress(rest, gzip)?;
+        let mut chunks = &body[..];
+        let mut sequences = Vec::with_capacity(count);
+        for size in sizes {
+            if size > chunks.len() {
+                return Err(CodecError::InvalidPayload);
+            }
+            let (chunk, tail) = chunks.split_at(size);
+            sequences.push(CodecCore::decode_token_ids(chunk, false)?);
+            chunks = tail;
+        }
+
+        if !chunks.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(sequences)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch() -> Vec<Vec<i32>> {
+        (0..200u32)
+            .map(|i| {
+                (0..50u32)
+                    .map(|j| ((j * 31 + i % 7) % 40) as i32 + 1000)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let codec = Codec::new();
+        let seqs = batch();
+        let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+
+        for gzip in [false, true] {
+            let payload = codec.encode_compressed_batch_shared(&refs, gzip).unwrap();
+            assert_eq!(
+                codec
+                    .decode_compressed_batch_shared(&payload, gzip)
+                    .unwrap(),
+                seqs
+            );
+        }
+    }
+
+    #[test]
+    fn shared_gzip_beats_individual_gzip() {
+        let codec = Codec::new();
+        let seqs = batch();
+        let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+
+        let shared = codec.encode_compressed_batch_shared(&refs, true).unwrap();
+        let individual: usize = refs
+            .iter()
+            .map(|seq| CodecCore::encode_token_ids(seq, true).unwrap().len())
+            .sum();
+        assert!(
+            shared.len() < individual,
+            "{} vs {individual}",
+            shared.len()
+        );
+    }
+
+    #[test]
+    fn empty_batch_and_empty_sequences() {
+        let codec = Codec::new();
+        for seqs in [vec![], vec![vec![], vec![3, 3], vec![]]] {
+            let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+            let payload = codec.encode_compressed_batch_shared(&refs, true).unwrap();
+            assert_eq!(
+                codec
+                    .decode_compressed_batch_shared(&payload, true)
+                    .unwrap(),
+                seqs
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_past_the_body() {
+        let codec = Codec::new();
+        let mut payload = codec
+            .encode_compressed_batch_shared(&[&[1, 2, 3]], false)
+            .unwrap();
+        payload[1] += 1;
+        assert!(codec
+            .decode_compressed_batch_shared(&payload, false)
+            .is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::codec_core::CodecCore;
+use crate::errors::Result;
+
+/// Immutable encoded payload shared by reference.
+///
+/// Cloning only bumps the `Arc` count, so one payload can be handed to many
+/// workers (or Python objects) without copying it, and none of them can
+/// mutate the bytes the others see.
+#[pyclass(frozen)]
+#[derive(Debug, Clone)]
+pub struct SharedPayload {
+    bytes: Arc<Vec<u8>>,
+}
+
+impl SharedPayload {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes: Arc::new(bytes),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn decode(&self, gzip: bool) -> Result<Vec<i32>> {
+        CodecCore::decode_token_ids(&self.bytes, gzip)
+    }
+}
+
+#[pymethods]
+impl SharedPayload {
+    fn __len__(&self) -> usize {
+        self.len()
+    }
+
+    fn __bytes__<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.bytes)
+    }
+
+    #[pyo3(name = "decode")]
+    fn py_decode(&self, gzip: bool) -> PyResult<Vec<i32>> {
+        Ok(self.decode(gzip)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn clones_share_one_buffer() {
+        let payload = SharedPayload::new(CodecCore::encode_token_ids(&[1, 2, 1], false).unwrap());
+        let clone = payload.clone();
+
+        assert!(std::ptr::eq(payload.as_bytes(), clone.as_bytes()));
+        assert_eq!(Arc::strong_count(&payload.bytes), 2);
+        assert_eq!(clone.len(), payload.len());
+    }
+
+    #[test]
+    fn concurrent_decodes_from_many_clones() {
+        let ids: Vec<i32> = (0..2000).map(|i| (i * 31) % 257).collect();
+        let payload = SharedPayload::new(CodecCore::encode_token_ids(&ids, true).unwrap());
+
+        let clones: Vec<SharedPayload> = (0..1000).map(|_| payload.clone()).collect();
+        assert_eq!(Arc::strong_count(&payload.bytes), 1001);
+
+        let results: Vec<Vec<i32>> = thread::scope(|scope| {
+            let handles: Vec<_> = clones
+                .chunks(125)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|clone| clone.decode(true).unwrap())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(results.len(), 1000);
+        assert!(results.iter().all(|decoded| *decoded == ids));
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::{
+    Header, FLAG_FIXED_WIDTH, FLAG_RLE_ONLY, FLAG_SORTED_PERMUTATION, FORMAT_VERSION,
+};
+use crate::{rle, Codec};
+
+/// `encode_smart` only considers run-length coding for sequences with fewer
+/// distinct values than this...
+const RLE_MAX_DISTINCT: usize = 16;
+
+/// ...and whose average run is at least this long.
+const RLE_MIN_AVERAGE_RUN: usize = 4;
+
+/// Layout choice per input, plus a decoder that follows the header's mode.
+///
+/// RLE payloads carry the `FLAG_RLE_ONLY` mode and an empty token table
+/// (gzip covers the body only):
+///   [header][rle::encode_rle_only output]
+impl Codec {
+    /// Run-length coding for long runs of few distinct values, `encode`
+    /// otherwise. Decode the result with `decode_auto`.
+    pub fn encode_smart(&self, token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        if !prefers_rle(token_ids) {
+            return self.encode(token_ids, gzip);
+        }
+
+        let header = Header::new(FORMAT_VERSION, FLAG_RLE_ONLY, Vec::new());
+        let mut out = header.encode();
+        out.extend_from_slice(&CodecCore::compress(rle::encode_rle_only(token_ids), gzip)?);
+        Ok(out)
+    }
+
+    /// Decode any payload whose layout is fully described by its header:
+    /// standard, vbyte, fixed-width, sorted-permutation and RLE payloads.
+    pub fn decode_auto(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        match parts.header.mode() {
+            FLAG_RLE_ONLY => {
+                let body = CodecCore::decompress(parts.body, gzip)?;
+                rle::decode_rle_only(&body).map_err(|_| CodecError::InvalidPayload)
+            }
+            FLAG_FIXED_WIDTH => {
+                let &bits = parts.body.first().ok_or(CodecError::InvalidPayload)?;
+                self.decode_fixed_width(payload, bits)
+            }
+            FLAG_SORTED_PERMUTATION => self.decode_sorted_with_permutation(payload, gzip),
+            _ => Ok(CodecCore::decode_with_header(payload, gzip)?.0),
+        }
+    }
+}
+
+fn prefers_rle(token_ids: &[i32]) -> bool {
+    let runs = rle::run_count(token_ids);
+    if runs == 0 || token_ids.len() / runs < RLE_MIN_AVERAGE_RUN {
+        return false;
+    }
+
+    let mut distinct = HashSet::new();
+    for &t in token_ids {
+        distinct.insert(t);
+        if distinct.len() >= RLE_MAX_DISTINCT {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_of_few_values_use_rle() {
+        let codec = Codec::new();
+        let zeros = vec![0; 1000];
+        let halves: Vec<i32> = [vec![0; 500], vec![1; 500]].concat();
+
+        for gzip in [false, true] {
+            for ids in [&zeros, &halves] {
+                let payload = codec.encode_smart(ids, gzip).unwrap();
+                assert_eq!(payload[1], FLAG_RLE_ONLY);
+                assert_eq!(codec.decode_auto(&payload, gzip).unwrap(), *ids);
+            }
+        }
+
+        let payload = codec.encode_smart(&halves, false).unwrap();
+        assert!(payload.len() < 16, "{} bytes", payload.len());
+        assert!(payload.len() < CodecCore::encode_token_ids(&halves, false).unwrap().len());
+    }
+
+    #[test]
+    fn other_sequences_use_the_standard_layout() {
+        let codec = Codec::new();
+        let alternating: Vec<i32> = (0..1000).map(|i| i % 2).collect();
+        let many_values: Vec<i32> = (0..1000).map(|i| i / 50).collect();
+
+        for ids in [alternating, many_values, vec![]] {
+            let payload = codec.encode_smart(&ids, true).unwrap();
+            assert_eq!(payload, codec.encode(&ids, true).unwrap());
+            assert_eq!(codec.decode_auto(&payload, true).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn decode_auto_follows_the_mode() {
+        let codec = Codec::new();
+        let ids = [5, 9, 5, 5, -2, 9];
+
+        let fixed = codec.encode_fixed_width(&ids, 8).unwrap();
+        assert_eq!(codec.decode_auto(&fixed, false).unwrap(), ids);
+
+        let sorted = codec.encode_sorted_with_permutation(&ids, true).unwrap();
+        assert_eq!(codec.decode_auto(&sorted, true).unwrap(), ids);
+
+        let vbyte = CodecCore::encode_token_ids_vbyte(&ids, false).unwrap();
+        assert_eq!(codec.decode_auto(&vbyte, false).unwrap(), ids);
+
+        let bert = codec
+            .encode_bert_inputs(&ids, &[0; 6], &[0, 1, 2, 3, 4, 5], false)
+            .unwrap();
+        assert!(codec.decode_auto(&bert, false).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::header::{Header, FLAG_SORTED_PERMUTATION, FORMAT_VERSION};
+use crate::{varint, zigzag, Codec};
+
+/// Sorted-plus-permutation payloads: the tokens in sorted order, delta coded,
+/// followed by the permutation that restores the original order.
+///
+/// Sorted deltas are tiny (mostly 0 for repeated tokens), so the first half
+/// compresses very well; the permutation costs about `log2(n)` bits per token
+/// and pays off for long sequences with many repeats.
+///
+/// Layout (header carries the `FLAG_SORTED_PERMUTATION` mode and no token
+/// table; gzip covers the body only):
+///   [header][varint n][n varint deltas][n varint source indices]
+///
+/// The first delta is the zigzagged first sorted token; the others are
+/// `sorted[i] - sorted[i - 1]` as a wrapping `u32`, which is never "negative".
+/// Source index `i` is the position in the input of the `i`-th sorted token.
+impl Codec {
+    pub fn encode_sorted_with_permutation(&self, token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let n = to_u32(token_ids.len())?;
+        // Stable, so equal tokens keep ascending source indices.
+        let mut permutation: Vec<u32> = (0..n).collect();
+        permutation.sort_by_key(|&i| token_ids[i as usize]);
+
+        let mut values = Vec::with_capacity(1 + 2 * token_ids.len());
+        values.push(n);
+        let mut prev = None;
+        for &i in &permutation {
+            let token = token_ids[i as usize];
+            values.push(match prev {
+                None => zigzag::encode(token),
+                Some(prev) => token.wrapping_sub(prev) as u32,
+            });
+            prev = Some(token);
+        }
+        values.extend_from_slice(&permutation);
+
+        let header = Header::new(FORMAT_VERSION, FLAG_SORTED_PERMUTATION, Vec::new());
+        let mut out = header.encode();
+        out.extend_from_slice(&CodecCore::compress(varint::encode(&values), gzip)?);
+        Ok(out)
+    }
+
+    pub fn decode_sorted_with_permutation(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_SORTED_PERMUTATION {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let mut rest: &[u8] = &body;
+
+        let n = read_varint(&mut rest)? as usize;
+        // Every token needs at least two bytes, which bounds the allocation.
+        if n > rest.len() / 2 {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let mut sorted = Vec::with_capacity(n);
+        let mut prev = None;
+        for _ in 0..n {
+            let delta = read_varint(&mut rest)?;
+            let token = match prev {
+                None => zigzag::decode(delta),
+                Some(prev) => i32::wrapping_add(prev, delta as i32),
+            };
+            sorted.push(token);
+            prev = Some(token);
+        }
+
+        let mut tokens = vec![None; n];
+        for token in sorted {
+            let index = read_varint(&mut rest)? as usize;
+            let slot = tokens.get_mut(index).ok_or(CodecError::InvalidPayload)?;
+            if slot.replace(token).is_some() {
+                return Err(CodecError::InvalidPayload);
+            }
+        }
+        if !rest.is_empty() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        // n distinct in-range indices fill every slot.
+        Ok(tokens.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(ids: &[i32]) {
+        let codec = Codec::new();
+        for gzip in [false, true] {
+            let payload = codec.encode_sorted_with_permutation(ids, gzip).unwrap();
+            assert_eq!(payload[1], FLAG_SORTED_PERMUTATION);
+            assert_eq!(
+                codec
+                    .decode_sorted_with_permutation(&payload, gzip)
+                    .unwrap(),
+                ids
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&[]);
+        round_trip(&[42]);
+        round_trip(&[5, 3, 5, 1, 3, 5]);
+        round_trip(&[i32::MAX, i32::MIN, 0, -1, i32::MAX]);
+        round_trip(
+            &(0..2000)
+                .map(|i| (i * 7919) % 503 - 250)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn sorted_deltas_are_small() {
+        let codec = Codec::new();
+        let ids = [900, 100, 900, 100, 500];
+        let payload = codec.encode_sorted_with_permutation(&ids, false).unwrap();
+
+        let body = &payload[Header::new(FORMAT_VERSION, 0, Vec::new()).encode().len()..];
+        let values = varint::decode(body).unwrap();
+        assert_eq!(values[0], 5);
+        // Sorted: 100, 100, 500, 900, 900 from positions 1, 3, 4, 0, 2.
+        assert_eq!(values[1..6], [zigzag::encode(100), 0, 400, 400, 0]);
+        assert_eq!(values[6..], [1, 3, 4, 0, 2]);
+    }
+
+    #[test]
+    fn invalid_permutations_are_rejected() {
+        let codec = Codec::new();
+        let mut payload = codec
+            .encode_sorted_with_permutation(&[7, 8], false)
+            .unwrap();
+        let last = payload.len() - 1;
+
+        // Duplicate index.
+        payload[last] = payload[last - 1];
+        assert!(codec
+            .decode_sorted_with_permutation(&payload, false)
+            .is_err());
+        // Out-of-range index.
+        payload[last] = 2;
+        assert!(codec
+            .decode_sorted_with_permutation(&payload, false)
+            .is_err());
+        // Truncated.
+        assert!(codec
+            .decode_sorted_with_permutation(&payload[..last], false)
+            .is_err());
+    }
+
+    #[test]
+    fn other_modes_are_rejected() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&[1, 2], false).unwrap();
+        assert!(codec.decode_sorted_with_permutation(&plain, false).is_err());
+
+        let payload = codec
+            .encode_sorted_with_permutation(&[1, 2], false)
+            .unwrap();
+        assert!(CodecCore::decode_token_ids(&payload, false).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::codec_core::{to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::varint;
+use crate::Codec;
+
+/// Sequence-of-sequences ("sos") payloads: many ragged sequences in one buffer,
+/// each independently decodable by index.
+///
+/// Layout:
+///   varint  : number of sequences N
+///   N varint: byte offset of each sequence's start within the body
+///   body    : the N sequences, each a standalone `encode_token_ids` payload
+///
+/// Every sequence gets its own `FreqMap`/`Header` (and its own gzip stream when
+/// `gzip` is set), so decoding sequence `idx` never touches the others.
+impl Codec {
+    pub fn encode_sos(&self, sequences: &[&[i32]], gzip: bool) -> Result<Vec<u8>> {
+        let mut offsets = Vec::with_capacity(sequences.len());
+        let mut body = Vec::new();
+
+        for seq in sequences {
+            offsets.push(to_u32(body.len())?);
+            body.extend_from_slice(&CodecCore::encode_token_ids(seq, gzip)?);
+        }
+
+        let mut out = varint::encode(&[to_u32(sequences.len())?]);
+        out.extend_from_slice(&varint::encode(&offsets));
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    pub fn decode_sos_at(&self, payload: &[u8], idx: usize, gzip: bool) -> Result<Vec<i32>> {
+        let (count, mut pos) =
+            varint::decode_one(payload).map_err(|_| CodecError::InvalidPayload)?;
+        let count = count as usize;
+
+        if idx >= count {
+            return Err(CodecError::IndexOutOfRange {
+                index: idx,
+                len: count,
+            });
+        }
+
+        // The table has to be walked in full to find where the body starts.
+        // Each offset takes at least a byte, which bounds the allocation.
+        let mut offsets = Vec::with_capacity(count.min(payload.len() - pos));
+        for _ in 0..count {
+            let (offset, used) =
+                varint::decode_one(&payload[pos..]).map_err(|_| CodecError::InvalidPayload)?;
+            offsets.push(offset as usize);
+            pos += used;
+        }
+
+        let body = &payload[pos..];
+        let start = offsets[idx];
+        let end = offsets.get(idx + 1).copied().unwrap_or(body.len());
+        if start > end || end > body.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        CodecCore::decode_token_ids(&body[start..end], gzip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20 sequences with lengths spread over 1..200 and varied vocabularies.
+    fn ragged_sequences() -> Vec<Vec<i32>> {
+        (0..20u32)
+            .map(|i| {
+                let len = 1 + (i * 199) / 19;
+                (0..len)
+                    .map(|j| ((j.wrapping_mul(2654435761) ^ i) % (10 + i * 7)) as i32)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_sequence_decodes_independently() {
+        let codec = Codec::new();
+        let seqs = ragged_sequences();
+        let refs: Vec<&[i32]> = seqs.iter().map(Vec::as_slice).collect();
+
+        assert_eq!(seqs.first().unwrap().len(), 1);
+        assert_eq!(seqs.last().unwrap().len(), 200);
+
+        for gzip in [false, true] {
+            let payload = codec.encode_sos(&refs, gzip).unwrap();
+            for (idx, seq) in seqs.iter().enumerate() {
+                assert_eq!(&codec.decode_sos_at(&payload, idx, gzip).unwrap(), seq);
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_index_is_an_error() {
+        let codec = Codec::new();
+        let payload = codec.encode_sos(&[&[1, 2, 3], &[4]], false).unwrap();
+
+        assert!(matches!(
+            codec.decode_sos_at(&payload, 2, false),
+            Err(CodecError::IndexOutOfRange { index: 2, len: 2 })
+        ));
+    }
+
+    #[test]
+    fn empty_sequences_are_addressable() {
+        let codec = Codec::new();
+        let payload = codec.encode_sos(&[&[], &[7, 7], &[]], false).unwrap();
+
+        assert!(codec.decode_sos_at(&payload, 0, false).unwrap().is_empty());
+        assert_eq!(codec.decode_sos_at(&payload, 1, false).unwrap(), vec![7, 7]);
+        assert!(codec.decode_sos_at(&payload, 2, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn huge_sequence_count_is_rejected() {
+        // Claims u32::MAX sequences with no offset table behind it.
+        let payload = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+        assert!(matches!(
+            Codec::new().decode_sos_at(&payload, 0, false),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_SPARSE_HEADER, FORMAT_VERSION, PREFIX_LEN};
+use crate::{varint, zigzag, Codec};
+
+/// Table sizes `encode_adaptive` chooses from.
+pub const ADAPTIVE_K_CANDIDATES: [usize; 5] = [16, 32, 64, 128, 256];
+
+/// Sparse-header payloads for very large vocabularies, where listing every
+/// unique token would dominate the payload.
+///
+/// The token table holds only the `k` most frequent tokens (its length is
+/// `k`); every other token is written as itself. Each body value is a u64
+/// varint: `v < k` is the table's `v`-th token, `v >= k` is the token whose
+/// zigzag code is `v - k`.
+///
+/// Layout (header carries the `FLAG_SPARSE_HEADER` mode; gzip covers the
+/// body only):
+///   [header, top-k token table][varint u64 per token]
+impl Codec {
+    /// `k` is capped at the number of unique tokens.
+    pub fn encode_sparse_header(&self, token_ids: &[i32], k: usize, gzip: bool) -> Result<Vec<u8>> {
+        encode_sparse(token_ids, &FreqMap::from_token_ids(token_ids), k, gzip)
+    }
+
+    /// `encode_sparse_header` with the `optimal_k` for `token_ids`.
+    pub fn encode_adaptive(&self, token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        let freq = FreqMap::from_token_ids(token_ids);
+        encode_sparse(token_ids, &freq, Self::optimal_k(&freq), gzip)
+    }
+
+    /// The `ADAPTIVE_K_CANDIDATES` entry giving the smallest uncompressed
+    /// sparse payload for the input `freq_map` was built from (the smallest
+    /// such `k` on ties). Sizes are exact, computed from the counts alone.
+    pub fn optimal_k(freq_map: &FreqMap) -> usize {
+        ADAPTIVE_K_CANDIDATES
+            .into_iter()
+            .min_by_key(|&k| (sparse_size(freq_map, k), k))
+            .expect("candidates are not empty")
+    }
+
+    pub fn decode_sparse_header(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != FLAG_SPARSE_HEADER {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+        let top = &parts.header.tokens;
+        let k = top.len() as u64;
+
+        varint::decode_u64(&body)
+            .map_err(|_| CodecError::InvalidPayload)?
+            .into_iter()
+            .map(|v| {
+                if v < k {
+                    Ok(top[v as usize])
+                } else {
+                    u32::try_from(v - k)
+                        .map(zigzag::decode)
+                        .map_err(|_| CodecError::InvalidPayload)
+                }
+            })
+            .collect()
+    }
+}
+
+fn encode_sparse(token_ids: &[i32], freq: &FreqMap, k: usize, gzip: bool) -> Result<Vec<u8>> {
+    let top = &freq.ordered_tokens()[..k.min(freq.ordered_tokens().len())];
+    let k = top.len() as u64;
+
+    let values: Vec<u64> = token_ids
+        .iter()
+        .map(|&token| match freq.map_token(token) {
+            Some(mapped) if (mapped as u64) < k => mapped as u64,
+            _ => k + u64::from(zigzag::encode(token)),
+        })
+        .collect();
+
+    let header = Header::new(FORMAT_VERSION, FLAG_SPARSE_HEADER, top.to_vec());
+    let mut out = header.encode();
+    out.extend_from_slice(&CodecCore::compress(varint::encode_u64(&values), gzip)?);
+    Ok(out)
+}
+
+/// Exact uncompressed size of the sparse payload with table size `k` for the
+/// input `freq` was built from.
+fn sparse_size(freq: &FreqMap, k: usize) -> usize {
+    let k = k.min(freq.ordered_tokens().len());
+    let body: usize = freq
+        .ordered_tokens()
+        .iter()
+        .zip(freq.counts())
+        .enumerate()
+        .map(|(mapped, (&token, &count))| {
+            let value = if mapped < k {
+                mapped as u64
+            } else {
+                k as u64 + u64::from(zigzag::encode(token))
+            };
+            count * varint_len_u64(value)
+        })
+        .sum();
+    PREFIX_LEN + 4 * k + body
+}
+
+/// LEB128 length of `value`, as written by `varint::encode_u64`.
+fn varint_len_u64(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n_unique` distinct tokens, the first 16 of which repeat often.
+    fn ids(n_unique: i32) -> Vec<i32> {
+        let mut ids: Vec<i32> = (0..n_unique).map(|i| i * 3 - 1000).collect();
+        ids.extend((0..20_000).map(|i| (i % 16) * 3 - 1000));
+        ids
+    }
+
+    fn header_len(payload: &[u8]) -> usize {
+        let (header, _) = Header::decode_prefix(payload).unwrap();
+        header.encode().len()
+    }
+
+    #[test]
+    fn sparse_header_round_trips() {
+        let codec = Codec::new();
+        for ids in [ids(150_000), vec![], vec![i32::MIN, i32::MAX, i32::MIN, 0]] {
+            for k in [0, 1, 256] {
+                for gzip in [false, true] {
+                    let payload = codec.encode_sparse_header(&ids, k, gzip).unwrap();
+                    assert_eq!(payload[1], FLAG_SPARSE_HEADER);
+                    assert_eq!(codec.decode_sparse_header(&payload, gzip).unwrap(), ids);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn header_size_depends_on_k_not_the_vocabulary() {
+        let codec = Codec::new();
+        for n_unique in [1000, 10_000, 150_000] {
+            let ids = ids(n_unique);
+            let sparse = codec.encode_sparse_header(&ids, 256, false).unwrap();
+            assert_eq!(header_len(&sparse), PREFIX_LEN + 256 * 4);
+
+            let standard = CodecCore::encode_token_ids(&ids, false).unwrap();
+            assert_eq!(header_len(&standard), PREFIX_LEN + n_unique as usize * 4);
+        }
+
+        let ids = ids(150_000);
+        let sparse = codec.encode_sparse_header(&ids, 256, false).unwrap();
+        let standard = CodecCore::encode_token_ids(&ids, false).unwrap();
+        assert!(sparse.len() < standard.len() / 2);
+    }
+
+    #[test]
+    fn k_is_capped_at_the_vocabulary() {
+        let codec = Codec::new();
+        let payload = codec.encode_sparse_header(&[4, 4, 9], 256, false).unwrap();
+        assert_eq!(header_len(&payload), PREFIX_LEN + 2 * 4);
+        // Both tokens come from the table.
+        assert_eq!(&payload[header_len(&payload)..], [0, 0, 1]);
+    }
+
+    #[test]
+    fn other_payloads_are_rejected() {
+        let codec = Codec::new();
+        let plain = CodecCore::encode_token_ids(&[1, 2], false).unwrap();
+        assert!(codec.decode_sparse_header(&plain, false).is_err());
+
+        // A value past k whose zigzag code doesn't fit in a u32.
+        let mut wide = Header::new(FORMAT_VERSION, FLAG_SPARSE_HEADER, vec![]).encode();
+        wide.extend_from_slice(&varint::encode_u64(&[1 << 32]));
+        assert!(codec.decode_sparse_header(&wide, false).is_err());
+    }
+
+    /// Zipf-like tokens from a vocabulary of `vocab` IDs.
+    fn zipf(vocab: u32, n: usize) -> Vec<i32> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..n)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                (f64::from(vocab).powf(u * u) as i32).min(vocab as i32 - 1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn size_estimates_are_exact() {
+        let codec = Codec::new();
+        let ids = zipf(100_000, 20_000);
+        let freq = FreqMap::from_token_ids(&ids);
+        for k in [0, 1, 16, 127, 128, 256, 1 << 20] {
+            let payload = codec.encode_sparse_header(&ids, k, false).unwrap();
+            assert_eq!(sparse_size(&freq, k), payload.len(), "k = {k}");
+        }
+        assert_eq!(varint_len_u64(0), 1);
+        assert_eq!(varint_len_u64(u64::MAX), 10);
+    }
+
+    #[test]
+    fn adaptive_k_matches_the_best_fixed_k() {
+        let codec = Codec::new();
+        for vocab in [50, 1000, 30_000, 200_000] {
+            let ids = zipf(vocab, 20_000);
+            let adaptive = codec.encode_adaptive(&ids, false).unwrap();
+            assert_eq!(codec.decode_sparse_header(&adaptive, false).unwrap(), ids);
+
+            let best = ADAPTIVE_K_CANDIDATES
+                .iter()
+                .map(|&k| codec.encode_sparse_header(&ids, k, false).unwrap().len())
+                .min()
+                .unwrap();
+            assert!(adaptive.len() * 100 <= best * 105, "vocab {vocab}");
+
+            let standard = CodecCore::encode_token_ids(&ids, false).unwrap();
+            if vocab >= 30_000 {
+                assert!(adaptive.len() < standard.len(), "vocab {vocab}");
+            }
+        }
+    }
+
+    #[test]
+    fn optimal_k_prefers_small_tables_for_few_frequent_tokens() {
+        // 16 tokens carry almost everything; the rest appear once each.
+        let mut ids: Vec<i32> = (0..5000).map(|i| 100_000 + i).collect();
+        ids.extend((0..50_000).map(|i| i % 16));
+        let freq = FreqMap::from_token_ids(&ids);
+        assert_eq!(Codec::optimal_k(&freq), 16);
+    }
+}
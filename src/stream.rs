@@ -0,0 +1,316 @@
+use std::io::{self, Read, Write};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::Header;
+use crate::{varint, zigzag, Codec};
+
+/// Tokens remapped and varint-encoded per write in `encode_streaming`.
+const STREAM_CHUNK_TOKENS: usize = 64 * 1024;
+
+/// Cursor over a LEB128 byte stream, yielding one value at a time.
+#[derive(Debug, Clone)]
+pub struct VarintReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> VarintReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Bytes not consumed yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    /// Read the next value, or `None` at the end of the stream.
+    pub fn read(&mut self) -> Option<Result<u32>> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(match varint::decode_one(self.remaining()) {
+            Ok((value, used)) => {
+                self.pos += used;
+                Ok(value)
+            }
+            Err(_) => {
+                // A broken stream stays broken; stop here instead of resyncing.
+                self.pos = self.bytes.len();
+                Err(CodecError::InvalidPayload)
+            }
+        })
+    }
+
+    /// Skip the next `n` values; errors if fewer than `n` remain.
+    pub fn skip_n(&mut self, n: usize) -> Result<()> {
+        let used = varint::skip_n(self.remaining(), n).map_err(|_| CodecError::InvalidPayload)?;
+        self.pos += used;
+        Ok(())
+    }
+}
+
+/// Lazily decodes a varint body: zigzag decode and reverse-map lookup happen
+/// one token at a time as the iterator is advanced.
+///
+/// Iteration stops after the first error.
+#[derive(Debug, Clone)]
+pub struct TokenIterator<'a> {
+    reader: VarintReader<'a>,
+    header: &'a Header,
+}
+
+impl TokenIterator<'_> {
+    /// Exact number of tokens left, or `None` if the remaining bytes end in a
+    /// truncated varint (so the count cannot be known).
+    ///
+    /// Every value ends in exactly one byte with the MSB clear, so this is a
+    /// byte scan rather than a decode.
+    pub fn count_remaining(&self) -> Option<usize> {
+        let rest = self.reader.remaining();
+        if rest.last().is_some_and(|&b| b & 0x80 != 0) {
+            return None;
+        }
+        Some(rest.iter().filter(|&&b| b & 0x80 == 0).count())
+    }
+
+    /// Skip up to `n` tokens without decoding them, returning how many were
+    /// skipped (fewer than `n` only at the end of the body).
+    pub fn skip_n(&mut self, n: usize) -> Result<usize> {
+        let n = match self.count_remaining() {
+            Some(left) => n.min(left),
+            None => n,
+        };
+        self.reader.skip_n(n)?;
+        Ok(n)
+    }
+}
+
+impl Iterator for TokenIterator<'_> {
+    type Item = Result<i32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = match self.reader.read()? {
+            Ok(value) => value,
+            Err(err) => return Some(Err(err)),
+        };
+
+        let mapped = zigzag::decode(value);
+        let token = usize::try_from(mapped)
+            .ok()
+            .and_then(|idx| self.header.tokens.get(idx).copied());
+        if token.is_none() {
+            self.reader.pos = self.reader.bytes.len();
+        }
+        Some(token.ok_or(CodecError::InvalidPayload))
+    }
+}
+
+impl Codec {
+    /// Write the payload for `token_ids` straight to `writer`, returning the
+    /// number of bytes written.
+    ///
+    /// The header is written first, then the body in chunks of
+    /// `STREAM_CHUNK_TOKENS` (through a `GzEncoder` wrapping `writer` when
+    /// `gzip` is set), so the full payload never exists in memory. The bytes
+    /// are identical to `Codec::encode`; configs with another body encoding
+    /// are encoded with it up front and then written in one go.
+    pub fn encode_streaming<W: Write>(
+        &self,
+        token_ids: &[i32],
+        gzip: bool,
+        writer: &mut W,
+    ) -> Result<usize> {
+        if !self.config.is_standard_body() {
+            let payload = self.encode(token_ids, gzip)?;
+            writer.write_all(&payload)?;
+            writer.flush()?;
+            return Ok(payload.len());
+        }
+
+        let freq = FreqMap::from_token_ids(token_ids);
+        let mut counter = CountingWriter {
+            inner: writer,
+            written: 0,
+        };
+        counter.write_all(&Header::from_freq_map(&freq).encode())?;
+
+        if gzip {
+            let mut encoder = GzEncoder::new(&mut counter, Compression::default());
+            write_body(&mut encoder, token_ids, &freq)?;
+            encoder.finish()?;
+        } else {
+            write_body(&mut counter, token_ids, &freq)?;
+        }
+
+        counter.flush()?;
+        Ok(counter.written)
+    }
+
+    /// Read a whole payload from `reader` and decode it.
+    pub fn decode_streaming<R: Read>(&self, reader: &mut R, gzip: bool) -> Result<Vec<i32>> {
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+        CodecCore::decode_token_ids(&payload, gzip)
+    }
+
+    /// Iterate over the tokens of an uncompressed varint body (the `body` of
+    /// `CodecCore::split_payload`) without materializing them.
+    pub fn decode_lazy<'a>(&self, payload: &'a [u8], header: &'a Header) -> TokenIterator<'a> {
+        TokenIterator {
+            reader: VarintReader::new(payload),
+            header,
+        }
+    }
+}
+
+fn write_body<W: Write>(writer: &mut W, token_ids: &[i32], freq: &FreqMap) -> Result<()> {
+    for chunk in token_ids.chunks(STREAM_CHUNK_TOKENS) {
+        writer.write_all(&CodecCore::encode_body(chunk, freq)?)?;
+    }
+    Ok(())
+}
+
+/// Pass-through writer that counts the bytes reaching `inner`.
+struct CountingWriter<'a, W> {
+    inner: &'a mut W,
+    written: usize,
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::config::{CodecConfig, VarintMode};
+
+    fn sample() -> Vec<i32> {
+        (0..500).map(|i| (i * i) % 211 - 100).collect()
+    }
+
+    #[test]
+    fn lazy_decode_matches_eager_decode() {
+        let ids = sample();
+        let payload = CodecCore::encode_token_ids(&ids, false).unwrap();
+        let parts = CodecCore::split_payload(&payload).unwrap();
+
+        let lazy: Vec<i32> = Codec::new()
+            .decode_lazy(parts.body, &parts.header)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(lazy, ids);
+    }
+
+    #[test]
+    fn partial_consumption_and_skipping() {
+        let ids = sample();
+        let payload = CodecCore::encode_token_ids(&ids, false).unwrap();
+        let parts = CodecCore::split_payload(&payload).unwrap();
+        let codec = Codec::new();
+        let mut iter = codec.decode_lazy(parts.body, &parts.header);
+
+        let head: Vec<i32> = iter.by_ref().take(10).map(|t| t.unwrap()).collect();
+        assert_eq!(head, ids[..10]);
+        assert_eq!(iter.count_remaining(), Some(ids.len() - 10));
+
+        assert_eq!(iter.skip_n(100).unwrap(), 100);
+        assert_eq!(iter.next().unwrap().unwrap(), ids[110]);
+
+        // Skipping past the end stops at the end.
+        assert_eq!(iter.skip_n(10_000).unwrap(), ids.len() - 111);
+        assert_eq!(iter.count_remaining(), Some(0));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn stops_after_bad_mapped_id() {
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(&[]));
+        let body = varint::encode(&[zigzag::encode(0), zigzag::encode(0)]);
+        let codec = Codec::new();
+        let mut iter = codec.decode_lazy(&body, &header);
+
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn truncated_body_has_unknown_count() {
+        let header = Header::from_freq_map(&FreqMap::from_token_ids(&[1]));
+        let codec = Codec::new();
+        let iter = codec.decode_lazy(&[0x00, 0x80], &header);
+        assert_eq!(iter.count_remaining(), None);
+    }
+
+    #[test]
+    fn streaming_output_matches_encode_token_ids() {
+        let codec = Codec::new();
+        // Spans several chunks.
+        let ids: Vec<i32> = (0..200_000).map(|i| (i * 7919) % 40_000 - 100).collect();
+
+        for gzip in [false, true] {
+            let mut cursor = Cursor::new(Vec::new());
+            let written = codec.encode_streaming(&ids, gzip, &mut cursor).unwrap();
+
+            let expected = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            assert_eq!(written, expected.len());
+            assert_eq!(cursor.get_ref(), &expected);
+
+            cursor.set_position(0);
+            assert_eq!(codec.decode_streaming(&mut cursor, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn streaming_follows_the_config() {
+        let ids: Vec<i32> = (0..1000).map(|i| (i * 37) % 101).collect();
+        for config in [
+            CodecConfig {
+                varint_mode: VarintMode::VByte,
+                ..CodecConfig::default()
+            },
+            CodecConfig {
+                use_delta: true,
+                ..CodecConfig::default()
+            },
+        ] {
+            let codec = Codec::with_config(config);
+            let mut out = Vec::new();
+            let written = codec.encode_streaming(&ids, true, &mut out).unwrap();
+            assert_eq!(written, out.len());
+            assert_eq!(out, codec.encode(&ids, true).unwrap());
+        }
+    }
+
+    #[test]
+    fn streaming_empty_input() {
+        let codec = Codec::new();
+        let mut out = Vec::new();
+        let written = codec.encode_streaming(&[], true, &mut out).unwrap();
+        assert_eq!(written, out.len());
+        assert!(codec
+            .decode_streaming(&mut out.as_slice(), true)
+            .unwrap()
+            .is_empty());
+    }
+}
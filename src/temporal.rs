@@ -0,0 +1,182 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::{Header, FLAG_TIMESTAMP, FORMAT_VERSION};
+use crate::Codec;
+
+/// Length of the timestamp trailer.
+pub const TIMESTAMP_LEN: usize = 8;
+
+/// Timestamped payloads for time-ordered archives: a standard payload in the
+/// `FLAG_TIMESTAMP` mode, followed by nanoseconds as a u64 LE:
+///   [header][body][u64 LE timestamp_ns]
+///
+/// The timestamp comes last so that `decode_timestamp_only` can read it
+/// without touching the rest.
+impl Codec {
+    pub fn encode_with_timestamp(
+        &self,
+        token_ids: &[i32],
+        timestamp_ns: u64,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let mut out = CodecCore::encode_with_flags(token_ids, FLAG_TIMESTAMP, gzip)?;
+        out.extend_from_slice(&timestamp_ns.to_le_bytes());
+        Ok(out)
+    }
+
+    pub fn decode_with_timestamp(&self, payload: &[u8], gzip: bool) -> Result<(Vec<i32>, u64)> {
+        let timestamp = decode_timestamp_only(payload)?;
+        let payload = &payload[..payload.len() - TIMESTAMP_LEN];
+        let (header, _) = Header::decode_prefix(payload).map_err(|_| CodecError::InvalidPayload)?;
+        let body = CodecCore::decompress(&payload[header.body_offset()..], gzip)?;
+        Ok((CodecCore::decode_body(&body, &header)?, timestamp))
+    }
+}
+
+/// The timestamp of an `encode_with_timestamp` payload, from its version and
+/// flags bytes and its last 8 bytes only.
+///
+/// Errors with `CodecError::InvalidPayload` for payloads without one.
+pub fn decode_timestamp_only(payload: &[u8]) -> Result<u64> {
+    match payload {
+        [FORMAT_VERSION, FLAG_TIMESTAMP, ..] if payload.len() >= 2 + TIMESTAMP_LEN => {}
+        _ => return Err(CodecError::InvalidPayload),
+    }
+    let trailer: [u8; TIMESTAMP_LEN] = payload[payload.len() - TIMESTAMP_LEN..]
+        .try_into()
+        .expect("slice of length 8 will always convert");
+    Ok(u64::from_le_bytes(trailer))
+}
+
+/// Timestamps and archive offsets of a sequence of payloads, for finding the
+/// ones in a time range without decoding any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemporalIndex {
+    /// (timestamp_ns, payload index), sorted.
+    by_time: Vec<(u64, usize)>,
+    /// Byte offset of each payload in the concatenation of all of them.
+    offsets: Vec<usize>,
+}
+
+impl TemporalIndex {
+    /// Index `payloads`, laid out back to back in that order. Payloads
+    /// without a timestamp get an offset but are never in range.
+    pub fn build(payloads: &[Vec<u8>]) -> Self {
+        let mut by_time = Vec::with_capacity(payloads.len());
+        let mut offsets = Vec::with_capacity(payloads.len());
+        let mut offset = 0;
+        for (index, payload) in payloads.iter().enumerate() {
+            if let Ok(timestamp) = decode_timestamp_only(payload) {
+                by_time.push((timestamp, index));
+            }
+            offsets.push(offset);
+            offset += payload.len();
+        }
+        by_time.sort_unstable();
+        Self { by_time, offsets }
+    }
+
+    /// Indices, ascending, of the payloads with `start_ns <= timestamp <
+    /// end_ns`.
+    pub fn payloads_in_range(&self, start_ns: u64, end_ns: u64) -> Vec<usize> {
+        let from = self.by_time.partition_point(|&(t, _)| t < start_ns);
+        let to = self.by_time.partition_point(|&(t, _)| t < end_ns);
+        let mut indices: Vec<usize> = self.by_time[from..to.max(from)]
+            .iter()
+            .map(|&(_, index)| index)
+            .collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// Byte offset of payload `index` in the archive.
+    pub fn offset(&self, index: usize) -> Option<usize> {
+        self.offsets.get(index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamped_payloads_round_trip() {
+        let codec = Codec::new();
+        let ids: Vec<i32> = (0..300).map(|i| (i * 7919) % 1000).collect();
+        for ids in [ids, vec![], vec![7]] {
+            for gzip in [false, true] {
+                let payload = codec
+                    .encode_with_timestamp(&ids, 1_700_000_000_123_456_789, gzip)
+                    .unwrap();
+                assert_eq!(payload[1], FLAG_TIMESTAMP);
+                assert_eq!(
+                    decode_timestamp_only(&payload).unwrap(),
+                    1_700_000_000_123_456_789
+                );
+                assert_eq!(
+                    codec.decode_with_timestamp(&payload, gzip).unwrap(),
+                    (ids.clone(), 1_700_000_000_123_456_789)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn timestamp_is_the_last_8_bytes() {
+        let codec = Codec::new();
+        let payload = codec
+            .encode_with_timestamp(&[9], u64::MAX - 1, false)
+            .unwrap();
+        assert_eq!(payload[payload.len() - 8..], (u64::MAX - 1).to_le_bytes());
+
+        let plain = CodecCore::encode_token_ids(&[9], false).unwrap();
+        assert!(decode_timestamp_only(&plain).is_err());
+        assert!(codec.decode_with_timestamp(&plain, false).is_err());
+        assert!(decode_timestamp_only(&payload[..9]).is_err());
+    }
+
+    #[test]
+    fn index_finds_payloads_in_range() {
+        let codec = Codec::new();
+        // 1000 payloads, one per millisecond, but shuffled out of time order.
+        let payloads: Vec<Vec<u8>> = (0..1000u64)
+            .map(|i| {
+                let t = (i * 7919) % 1000 * 1_000_000;
+                codec
+                    .encode_with_timestamp(&[i as i32, 1, 2], t, false)
+                    .unwrap()
+            })
+            .collect();
+        let index = TemporalIndex::build(&payloads);
+
+        let found = index.payloads_in_range(100_000_000, 200_000_000);
+        assert_eq!(found.len(), 100);
+        for &i in &found {
+            let t = decode_timestamp_only(&payloads[i]).unwrap();
+            assert!((100_000_000..200_000_000).contains(&t));
+        }
+        assert!(found.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(index.payloads_in_range(0, u64::MAX).len(), 1000);
+        assert!(index.payloads_in_range(5, 5).is_empty());
+        assert!(index.payloads_in_range(10, 0).is_empty());
+        assert!(index.payloads_in_range(1_000_000_000, u64::MAX).is_empty());
+    }
+
+    #[test]
+    fn index_records_archive_offsets() {
+        let codec = Codec::new();
+        let payloads = vec![
+            codec.encode_with_timestamp(&[1, 2, 3], 30, false).unwrap(),
+            CodecCore::encode_token_ids(&[4], false).unwrap(),
+            codec.encode_with_timestamp(&[5], 10, false).unwrap(),
+        ];
+        let index = TemporalIndex::build(&payloads);
+        assert_eq!(index.offset(0), Some(0));
+        assert_eq!(index.offset(1), Some(payloads[0].len()));
+        assert_eq!(index.offset(2), Some(payloads[0].len() + payloads[1].len()));
+        assert_eq!(index.offset(3), None);
+        // The untimestamped payload is never in range.
+        assert_eq!(index.payloads_in_range(0, 100), [0, 2]);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::Codec;
+
+/// Payload type discriminators for multiplexed byte streams.
+pub const PAYLOAD_TYPE_TOKEN_IDS: u8 = 0x01;
+pub const PAYLOAD_TYPE_SCORES: u8 = 0x02;
+pub const PAYLOAD_TYPE_MASK: u8 = 0x03;
+
+/// Typed payloads: a single discriminator byte in front of a standard payload,
+/// so several payload kinds can share one byte stream.
+///
+/// Layout: `[payload_type u8][standard payload]`
+impl Codec {
+    pub fn encode_with_prefix(
+        &self,
+        token_ids: &[i32],
+        payload_type: u8,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        let encoded = CodecCore::encode_token_ids(token_ids, gzip)?;
+
+        let mut out = Vec::with_capacity(1 + encoded.len());
+        out.push(payload_type);
+        out.extend_from_slice(&encoded);
+        Ok(out)
+    }
+
+    /// Split a typed payload into its type byte and the remaining bytes.
+    pub fn decode_typed(payload: &[u8]) -> Result<(u8, &[u8])> {
+        payload
+            .split_first()
+            .map(|(&payload_type, rest)| (payload_type, rest))
+            .ok_or(CodecError::InvalidPayload)
+    }
+}
+
+/// Wraps `Codec` and adds/strips the type prefix automatically.
+#[derive(Debug, Clone)]
+pub struct TypedCodec {
+    codec: Codec,
+    payload_type: u8,
+}
+
+impl TypedCodec {
+    /// A codec that tags everything it encodes with `payload_type`.
+    pub fn new(payload_type: u8) -> Self {
+        Self {
+            codec: Codec::new(),
+            payload_type,
+        }
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    pub fn encode(&self, token_ids: &[i32], gzip: bool) -> Result<Vec<u8>> {
+        self.codec.encode_with_prefix(token_ids, self.payload_type, gzip)
+    }
+
+    /// Decode a typed payload, failing with `CodecError::UnexpectedPayloadType`
+    /// if its type byte is not `expected_type`.
+    pub fn decode(&self, payload: Vec<u8>, expected_type: u8, gzip: bool) -> Result<Vec<i32>> {
+        let (found, rest) = Codec::decode_typed(&payload)?;
+        if found != expected_type {
+            return Err(CodecError::UnexpectedPayloadType {
+                expected: expected_type,
+                found,
+            });
+        }
+        CodecCore::decode_token_ids(rest, gzip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_is_a_single_leading_byte() {
+        let codec = Codec::new();
+        let ids = [5, 6, 5];
+
+        let typed = codec.encode_with_prefix(&ids, PAYLOAD_TYPE_SCORES, false).unwrap();
+        let plain = CodecCore::encode_token_ids(&ids, false).unwrap();
+
+        let (payload_type, rest) = Codec::decode_typed(&typed).unwrap();
+        assert_eq!(payload_type, PAYLOAD_TYPE_SCORES);
+        assert_eq!(rest, plain.as_slice());
+    }
+
+    #[test]
+    fn typed_codec_round_trip() {
+        let typed = TypedCodec::new(PAYLOAD_TYPE_TOKEN_IDS);
+        let ids: Vec<i32> = (0..64).map(|i| i % 5).collect();
+
+        for gzip in [false, true] {
+            let payload = typed.encode(&ids, gzip).unwrap();
+            assert_eq!(payload[0], PAYLOAD_TYPE_TOKEN_IDS);
+            let decoded = typed.decode(payload, PAYLOAD_TYPE_TOKEN_IDS, gzip).unwrap();
+            assert_eq!(decoded, ids);
+        }
+    }
+
+    #[test]
+    fn typed_codec_rejects_wrong_type() {
+        let mask = TypedCodec::new(PAYLOAD_TYPE_MASK);
+        let payload = mask.encode(&[0, 1, 1], false).unwrap();
+
+        assert!(matches!(
+            mask.decode(payload, PAYLOAD_TYPE_TOKEN_IDS, false),
+            Err(CodecError::UnexpectedPayloadType {
+                expected: PAYLOAD_TYPE_TOKEN_IDS,
+                found: PAYLOAD_TYPE_MASK,
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_typed_rejects_empty_input() {
+        assert!(Codec::decode_typed(&[]).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::codec_core::{to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::freq_map::FreqMap;
+use crate::header::{Header, FLAG_MODE_MASK, FLAG_U16_TOKENS, FORMAT_VERSION, PREFIX_LEN};
+use crate::Codec;
+
+/// Token IDs from vocabularies of at most 65536 tokens, with a token table
+/// of u16s instead of i32s:
+///
+///   [version u8][flags u8 = FLAG_U16_TOKENS][u32 LE len][len × u16 LE][body]
+///
+/// The body is the standard LEB128 body.
+impl Codec {
+    pub fn encode_u16_ids(&self, token_ids: &[u16], gzip: bool) -> Result<Vec<u8>> {
+        let ids: Vec<i32> = token_ids.iter().map(|&t| i32::from(t)).collect();
+        let freq = FreqMap::from_token_ids(&ids);
+        let tokens = freq.ordered_tokens();
+
+        let mut out = Vec::with_capacity(PREFIX_LEN + 2 * tokens.len() + ids.len());
+        out.push(FORMAT_VERSION);
+        out.push(FLAG_U16_TOKENS);
+        out.extend_from_slice(&to_u32(tokens.len())?.to_le_bytes());
+        for &t in tokens {
+            out.extend_from_slice(&(t as u16).to_le_bytes());
+        }
+        out.extend(CodecCore::compress(
+            CodecCore::encode_body(&ids, &freq)?,
+            gzip,
+        )?);
+        Ok(out)
+    }
+
+    pub fn decode_u16_ids(&self, payload: &[u8], gzip: bool) -> Result<Vec<u16>> {
+        if payload.len() < PREFIX_LEN
+            || payload[0] != FORMAT_VERSION
+            || payload[1] & FLAG_MODE_MASK != FLAG_U16_TOKENS
+        {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let len = u32::from_le_bytes(payload[2..PREFIX_LEN].try_into().unwrap()) as usize;
+        let table_end = len
+            .checked_mul(2)
+            .and_then(|size| size.checked_add(PREFIX_LEN))
+            .filter(|&end| end <= payload.len())
+            .ok_or(CodecError::InvalidPayload)?;
+        let tokens = payload[PREFIX_LEN..table_end]
+            .chunks_exact(2)
+            .map(|b| i32::from(u16::from_le_bytes([b[0], b[1]])))
+            .collect();
+        let header = Header::new(FORMAT_VERSION, FLAG_U16_TOKENS, tokens);
+
+        let body = CodecCore::decompress(&payload[table_end..], gzip)?;
+        let ids = CodecCore::decode_body(&body, &header)?;
+        // Every token came from the u16 table.
+        Ok(ids.into_iter().map(|t| t as u16).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids() -> Vec<u16> {
+        (0..2000).map(|i| ((i * 7919) % 50_257) as u16).collect()
+    }
+
+    #[test]
+    fn u16_ids_round_trip() {
+        let codec = Codec::new();
+        for ids in [ids(), vec![], vec![0, u16::MAX, u16::MAX, 1]] {
+            for gzip in [false, true] {
+                let payload = codec.encode_u16_ids(&ids, gzip).unwrap();
+                assert_eq!(payload[1], FLAG_U16_TOKENS);
+                assert_eq!(codec.decode_u16_ids(&payload, gzip).unwrap(), ids);
+            }
+        }
+    }
+
+    #[test]
+    fn token_table_is_half_the_size() {
+        let codec = Codec::new();
+        let wide: Vec<i32> = ids().iter().map(|&t| i32::from(t)).collect();
+        let standard = CodecCore::encode_token_ids(&wide, false).unwrap();
+        let compact = codec.encode_u16_ids(&ids(), false).unwrap();
+
+        let standard_header = Header::decode_prefix(&standard).unwrap().0.body_offset();
+        let table = standard_header - PREFIX_LEN;
+        assert_eq!(compact.len(), standard.len() - table / 2);
+        // Same body.
+        assert_eq!(
+            compact[PREFIX_LEN + table / 2..],
+            standard[standard_header..]
+        );
+    }
+
+    #[test]
+    fn other_payloads_are_rejected() {
+        let codec = Codec::new();
+        let standard = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        assert!(codec.decode_u16_ids(&standard, false).is_err());
+
+        let compact = codec.encode_u16_ids(&[1, 2, 3], false).unwrap();
+        assert!(codec
+            .decode_u16_ids(&compact[..PREFIX_LEN + 3], false)
+            .is_err());
+    }
+}
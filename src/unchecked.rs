@@ -0,0 +1,135 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::Header;
+use crate::{varint, zigzag, Codec};
+
+/// Decoding without per-token checks, for payloads that were already validated
+/// at ingest time.
+impl Codec {
+    /// Decode a standard payload without bounds checks on mapped IDs and
+    /// without varint overflow checks.
+    ///
+    /// The header and gzip stream are still parsed normally, and this panics
+    /// if either is malformed; only the per-token work in the body is
+    /// unchecked.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that:
+    /// - `payload` was produced by `encode_token_ids` (or an equivalent
+    ///   standard-layout encoder) with the same `gzip` setting;
+    /// - the header is intact, so its token table has an entry for every
+    ///   mapped ID in the body;
+    /// - no byte of the payload was modified since it was encoded.
+    ///
+    /// Violating any of these is undefined behaviour: a corrupted body can
+    /// index past the end of the token table.
+    pub unsafe fn decode_token_ids_unchecked(&self, payload: &[u8], gzip: bool) -> Vec<i32> {
+        let parts = CodecCore::split_payload(payload).expect("payload header is malformed");
+        let body = CodecCore::decompress(parts.body, gzip).expect("payload body is malformed");
+
+        let mut out = Vec::with_capacity(body.len());
+        let mut value: u32 = 0;
+        let mut shift: u32 = 0;
+        for &byte in body.iter() {
+            value |= u32::from(byte & 0x7F).wrapping_shl(shift);
+            if byte & 0x80 == 0 {
+                let mapped = zigzag::decode(value) as usize;
+                out.push(*parts.header.tokens.get_unchecked(mapped));
+                value = 0;
+                shift = 0;
+            } else {
+                shift += 7;
+            }
+        }
+        out
+    }
+
+    /// Checked decode that validates the whole body up front and then looks
+    /// tokens up without per-token bounds checks.
+    ///
+    /// Malformed payloads are rejected with `CodecError::InvalidPayload`, just
+    /// like `decode_token_ids`.
+    pub fn decode_token_ids_fast(&self, payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let parts = CodecCore::split_payload(payload)?;
+        if parts.header.mode() != 0 {
+            return Err(CodecError::InvalidPayload);
+        }
+        let body = CodecCore::decompress(parts.body, gzip)?;
+
+        let values = varint::decode(&body).map_err(|_| CodecError::InvalidPayload)?;
+        let mapped = zigzag::decode_slice(&values);
+        if !all_in_range(&mapped, &parts.header) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        // SAFETY: every mapped ID was just checked to index into the table.
+        Ok(mapped
+            .iter()
+            .map(|&m| unsafe { *parts.header.tokens.get_unchecked(m as usize) })
+            .collect())
+    }
+}
+
+/// Whether every mapped ID is a valid index into the header's token table.
+///
+/// Comparing as `u32` folds the negative check into the upper bound.
+fn all_in_range(mapped: &[i32], header: &Header) -> bool {
+    let len = header.tokens.len();
+    mapped.iter().all(|&m| (m as u32 as usize) < len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_ids() -> Vec<i32> {
+        (0..5_000).map(|i| (i * 7919) % 3001 - 1500).collect()
+    }
+
+    #[test]
+    fn unchecked_matches_checked_decode() {
+        let codec = Codec::new();
+        let ids = sample_ids();
+
+        for gzip in [false, true] {
+            let payload = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            // SAFETY: the payload was just produced by `encode_token_ids`.
+            let decoded = unsafe { codec.decode_token_ids_unchecked(&payload, gzip) };
+            assert_eq!(decoded, ids);
+        }
+    }
+
+    #[test]
+    fn fast_matches_checked_decode() {
+        let codec = Codec::new();
+        for ids in [sample_ids(), vec![], vec![i32::MIN, i32::MAX, 0]] {
+            let payload = CodecCore::encode_token_ids(&ids, false).unwrap();
+            assert_eq!(codec.decode_token_ids_fast(&payload, false).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn fast_rejects_out_of_range_mapped_ids() {
+        let codec = Codec::new();
+        let mut payload = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        // Mapped ID 3 (zigzag 6) has no entry in a three-token table.
+        *payload.last_mut().unwrap() = 6;
+        assert!(matches!(
+            codec.decode_token_ids_fast(&payload, false),
+            Err(CodecError::InvalidPayload)
+        ));
+
+        // Mapped ID -1 (zigzag 1) is never valid.
+        *payload.last_mut().unwrap() = 1;
+        assert!(codec.decode_token_ids_fast(&payload, false).is_err());
+    }
+
+    #[test]
+    fn fast_rejects_truncated_body() {
+        let codec = Codec::new();
+        let mut payload = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        *payload.last_mut().unwrap() |= 0x80;
+        assert!(codec.decode_token_ids_fast(&payload, false).is_err());
+    }
+}
@@ -1,6 +1,8 @@
 // src/varint.rs
 use anyhow::{bail, Result};
 
+use crate::zigzag;
+
 /// Encode a slice of u32s into LEB128 (little-endian base-128) bytes.
 /// Each value uses 1..=5 bytes.
 pub fn encode(values: &[u32]) -> Vec<u8> {
@@ -19,6 +21,14 @@ pub fn encode(values: &[u32]) -> Vec<u8> {
     out
 }
 
+/// Number of bytes `encode` uses for `value` (1..=5).
+#[inline]
+pub fn encoded_len(value: u32) -> usize {
+    // One byte per started group of 7 significant bits; zero still takes a byte.
+    let bits = 32 - value.leading_zeros() as usize;
+    bits.div_ceil(7).max(1)
+}
+
 /// Decode a LEB128 byte stream back into u32s.
 /// Errors on truncated final value or shift overflow.
 pub fn decode(bytes: &[u8]) -> Result<Vec<u32>> {
@@ -55,8 +65,412 @@ pub fn decode(bytes: &[u8]) -> Result<Vec<u32>> {
     Ok(out)
 }
 
+/// High bit of every byte in a `u64`: a word is eight single-byte varints
+/// exactly when none of these bits is set.
+const CONTINUATION_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// Same output as `encode`, but emits runs of eight values below 128 as a
+/// single 8-byte write (SWAR: SIMD within a register).
+///
+/// Frequency-ranked mapped IDs are mostly small, so most chunks take the fast
+/// path; chunks holding a larger value fall back to the scalar loop.
+pub fn encode_batch_swar(values: &[u32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * 5);
+
+    let mut chunks = values.chunks_exact(8);
+    for chunk in chunks.by_ref() {
+        if chunk.iter().fold(0, |acc, &v| acc | v) < 0x80 {
+            let bytes: [u8; 8] = std::array::from_fn(|i| chunk[i] as u8);
+            out.extend_from_slice(&bytes);
+        } else {
+            out.extend_from_slice(&encode(chunk));
+        }
+    }
+    out.extend_from_slice(&encode(chunks.remainder()));
+
+    out
+}
+
+/// Same result as `decode`, but consumes runs of eight single-byte varints
+/// (no continuation bit set anywhere in a `u64` word) at once.
+pub fn decode_batch_swar(bytes: &[u8]) -> Result<Vec<u32>> {
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut acc: u32 = 0;
+    let mut shift: u32 = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        // Only safe between values: a word of terminal bytes mid-value would
+        // finish the pending value with its first byte.
+        if shift == 0 {
+            if let Some(word) = bytes.get(i..i + 8) {
+                let word: [u8; 8] = word.try_into().expect("slice has 8 bytes");
+                if u64::from_le_bytes(word) & CONTINUATION_BITS == 0 {
+                    out.extend(word.iter().map(|&b| u32::from(b)));
+                    i += 8;
+                    continue;
+                }
+            }
+        }
+
+        let b = bytes[i];
+        if shift >= 32 {
+            bail!("varint overflow while decoding u32");
+        }
+        acc |= ((b & 0x7F) as u32) << shift;
+        if (b & 0x80) == 0 {
+            out.push(acc);
+            acc = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+        i += 1;
+    }
+
+    if shift != 0 {
+        bail!("incomplete varint at end of stream");
+    }
+
+    Ok(out)
+}
+
+/// Incremental `decode` for input that arrives in pieces, such as reads from
+/// a socket. A value split across chunks is carried over to the next `feed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Decoder {
+    /// Bits of the value in progress.
+    buf: u32,
+    /// Bits of it decoded so far; 0 between values.
+    shift: u32,
+}
+
+impl Decoder {
+    /// A decoder waiting for the first byte of a value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The values completed by `chunk`. On overflow the error is returned and
+    /// the decoder starts over; use `feed_into` to keep the values completed
+    /// before the bad byte.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<u32>> {
+        let mut out = Vec::new();
+        self.feed_into(chunk, &mut out)?;
+        Ok(out)
+    }
+
+    /// Append the values completed by `chunk` to `out`. On overflow decoding
+    /// stops at the offending byte: the values completed before it stay in
+    /// `out`, the error is returned and the decoder starts over.
+    pub fn feed_into(&mut self, chunk: &[u8], out: &mut Vec<u32>) -> Result<()> {
+        out.reserve(chunk.len());
+        for &b in chunk {
+            if self.shift >= 32 {
+                *self = Self::new();
+                bail!("varint overflow while decoding u32");
+            }
+            self.buf |= ((b & 0x7F) as u32) << self.shift;
+
+            if (b & 0x80) == 0 {
+                out.push(self.buf);
+                *self = Self::new();
+            } else {
+                self.shift += 7;
+            }
+        }
+        Ok(())
+    }
+
+    /// Errors if the input ended partway through a value.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.shift != 0 {
+            *self = Self::new();
+            bail!("incomplete varint at end of stream");
+        }
+        Ok(())
+    }
+}
+
+/// Decode a single LEB128 value from the front of `bytes`.
+/// Returns the value together with the number of bytes it occupied, so callers
+/// can walk a buffer that mixes varints with other data.
+pub fn decode_one(bytes: &[u8]) -> Result<(u32, usize)> {
+    let mut acc: u32 = 0;
+    let mut shift: u32 = 0;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        if shift >= 32 {
+            bail!("varint overflow while decoding u32");
+        }
+        acc |= ((b & 0x7F) as u32) << shift;
+
+        if (b & 0x80) == 0 {
+            return Ok((acc, i + 1));
+        }
+        shift += 7;
+    }
+
+    bail!("incomplete varint at end of stream");
+}
+
+/// Decode exactly the first `n` values of `bytes`, returning them and the
+/// bytes after them, e.g. the next frame. Errors, naming how many complete
+/// values there were, if the stream holds fewer than `n`.
+pub fn decode_n(bytes: &[u8], n: usize) -> Result<(Vec<u32>, &[u8])> {
+    // Every value takes at least a byte, which bounds the allocation.
+    let mut out = Vec::with_capacity(n.min(bytes.len()));
+    let mut rest = bytes;
+    while out.len() < n {
+        match decode_one(rest) {
+            Ok((value, used)) => {
+                out.push(value);
+                rest = &rest[used..];
+            }
+            Err(_) if rest.iter().all(|&b| b & 0x80 != 0) => {
+                bail!("expected {} varints: stream holds only {}", n, out.len());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((out, rest))
+}
+
+/// Skip over the first `n` values in `bytes` without decoding them.
+/// Returns the number of bytes those values occupy; errors if the stream holds
+/// fewer than `n` complete values.
+pub fn skip_n(bytes: &[u8], n: usize) -> Result<usize> {
+    if n == 0 {
+        return Ok(0);
+    }
+
+    // Only terminal bytes (MSB clear) matter: the n-th one ends the n-th value.
+    let mut seen = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if (b & 0x80) == 0 {
+            seen += 1;
+            if seen == n {
+                return Ok(i + 1);
+            }
+        }
+    }
+
+    bail!("cannot skip {} varints: stream holds only {}", n, seen);
+}
+
+/// `encode` for u64s; each value uses 1..=10 bytes.
+pub fn encode_u64(values: &[u64]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * 10);
+    for &v in values {
+        push_u64(&mut out, v);
+    }
+    out
+}
+
+/// Number of bytes `encode_u64` uses for `value` (1..=10).
+#[inline]
+pub fn encoded_len_u64(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    bits.div_ceil(7).max(1)
+}
+
+/// Reverse of `encode_u64`. Errors on a truncated final value or a value
+/// wider than 64 bits.
+pub fn decode_u64(bytes: &[u8]) -> Result<Vec<u64>> {
+    let mut out = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (value, used) = decode_one_u64(rest)?;
+        out.push(value);
+        rest = &rest[used..];
+    }
+    Ok(out)
+}
+
+/// Signed LEB128 (SLEB128, as in DWARF and WebAssembly) for i32s: 7 bits per
+/// byte in two's complement, ending once the remaining bits are all copies of
+/// the last byte's bit 6. Each value uses 1..=5 bytes, never more than
+/// `encode` of its zigzag.
+pub fn encode_signed(values: &[i32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * 5);
+    for &value in values {
+        let mut v = value;
+        loop {
+            let byte = (v & 0x7F) as u8;
+            // Arithmetic shift, so negative values fill with ones.
+            v >>= 7;
+            let done = (v == 0 && byte & 0x40 == 0) || (v == -1 && byte & 0x40 != 0);
+            if done {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+    out
+}
+
+/// Reverse of `encode_signed`. Errors on a truncated final value or one that
+/// doesn't fit in an i32.
+pub fn decode_signed(bytes: &[u8]) -> Result<Vec<i32>> {
+    let mut out = Vec::new();
+
+    let mut acc: u32 = 0;
+    let mut shift: u32 = 0;
+
+    for &b in bytes {
+        if shift >= 32 {
+            bail!("varint overflow while decoding i32");
+        }
+        // The fifth byte holds bits 28..=31; its top three bits must repeat
+        // bit 31.
+        if shift == 28 && b & 0x80 == 0 && !matches!(b & 0x78, 0x00 | 0x78) {
+            bail!("varint overflow while decoding i32");
+        }
+        acc |= ((b & 0x7F) as u32) << shift;
+        shift += 7;
+
+        if (b & 0x80) == 0 {
+            if shift < 32 && b & 0x40 != 0 {
+                acc |= u32::MAX << shift;
+            }
+            out.push(acc as i32);
+            acc = 0;
+            shift = 0;
+        }
+    }
+
+    if shift != 0 {
+        bail!("incomplete varint at end of stream");
+    }
+
+    Ok(out)
+}
+
+/// `encode` for u16s, without widening them first; each value uses 1..=3
+/// bytes. The bytes are the same as `encode` of the widened values.
+pub fn encode_u16(values: &[u16]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * 3);
+    for &v in values {
+        if v < 0x80 {
+            out.push(v as u8);
+        } else if v < 0x4000 {
+            out.extend_from_slice(&[(v as u8) | 0x80, (v >> 7) as u8]);
+        } else {
+            out.extend_from_slice(&[(v as u8) | 0x80, (v >> 7) as u8 | 0x80, (v >> 14) as u8]);
+        }
+    }
+    out
+}
+
+/// Reverse of `encode_u16`. Errors on a truncated final value or a value
+/// above `u16::MAX`.
+pub fn decode_u16(bytes: &[u8]) -> Result<Vec<u16>> {
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut acc: u32 = 0;
+    let mut shift: u32 = 0;
+
+    for &b in bytes {
+        // u16s end by the third byte.
+        if shift > 14 {
+            bail!("varint overflow while decoding u16");
+        }
+        acc |= ((b & 0x7F) as u32) << shift;
+
+        if (b & 0x80) == 0 {
+            let Ok(value) = u16::try_from(acc) else {
+                bail!("varint overflow while decoding u16");
+            };
+            out.push(value);
+            acc = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+
+    if shift != 0 {
+        bail!("incomplete varint at end of stream");
+    }
+
+    Ok(out)
+}
+
+/// Zigzag + LEB128 for i64s in one pass; same bytes as
+/// `encode_u64` over `zigzag::encode_i64` of every value, without the
+/// intermediate `Vec<u64>`.
+pub fn encode_zigzag_i64(values: &[i64]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * 10);
+    for &v in values {
+        push_u64(&mut out, zigzag::encode_i64(v));
+    }
+    out
+}
+
+/// Reverse of `encode_zigzag_i64`.
+pub fn decode_zigzag_i64(bytes: &[u8]) -> Result<Vec<i64>> {
+    let mut out = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (value, used) = decode_one_u64(rest)?;
+        out.push(zigzag::decode_i64(value));
+        rest = &rest[used..];
+    }
+    Ok(out)
+}
+
+/// The `encode_zigzag_i64` bytes of a single value, produced lazily so they
+/// can be written out without allocating.
+pub fn encode_one_zigzag_i64(value: i64) -> impl Iterator<Item = u8> {
+    let mut rest = Some(zigzag::encode_i64(value));
+    std::iter::from_fn(move || {
+        let v = rest?;
+        if v >= 0x80 {
+            rest = Some(v >> 7);
+            Some(((v & 0x7F) as u8) | 0x80)
+        } else {
+            rest = None;
+            Some(v as u8)
+        }
+    })
+}
+
+#[inline]
+fn push_u64(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(((v & 0x7F) as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// `decode_one` for u64 values.
+fn decode_one_u64(bytes: &[u8]) -> Result<(u64, usize)> {
+    let mut acc: u64 = 0;
+    let mut shift: u32 = 0;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        // The tenth byte holds only bit 63.
+        if shift >= 64 || (shift == 63 && b & 0x7E != 0) {
+            bail!("varint overflow while decoding u64");
+        }
+        acc |= ((b & 0x7F) as u64) << shift;
+
+        if (b & 0x80) == 0 {
+            return Ok((acc, i + 1));
+        }
+        shift += 7;
+    }
+
+    bail!("incomplete varint at end of stream");
+}
+
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -97,14 +511,333 @@ mod tests {
         assert!(err.contains("incomplete varint"));
     }
 
+    #[test]
+    fn decode_one_reports_consumed_bytes() {
+        // 300 -> [0xAC, 0x02], followed by unrelated trailing bytes.
+        let bytes = [0xAC, 0x02, 0xFF, 0x00];
+        assert_eq!(decode_one(&bytes).unwrap(), (300, 2));
+        assert!(decode_one(&[0x80]).is_err());
+        assert!(decode_one(&[]).is_err());
+    }
+
+    #[test]
+    fn encoded_len_matches_encode() {
+        for value in [0, 1, 127, 128, 16383, 16384, 1 << 21, 1 << 28, u32::MAX] {
+            assert_eq!(encoded_len(value), encode(&[value]).len(), "{value}");
+        }
+        for value in [0, 127, 128, 1 << 35, 1 << 63, u64::MAX] {
+            assert_eq!(encoded_len_u64(value), encode_u64(&[value]).len(), "{value}");
+        }
+    }
+
+    #[test]
+    fn skip_n_lands_on_value_boundaries() {
+        let enc = encode(&[1, 300, 16384, 5]);
+        assert_eq!(skip_n(&enc, 0).unwrap(), 0);
+        assert_eq!(skip_n(&enc, 2).unwrap(), 3);
+        assert_eq!(decode_one(&enc[skip_n(&enc, 2).unwrap()..]).unwrap().0, 16384);
+        assert_eq!(skip_n(&enc, 4).unwrap(), enc.len());
+        assert!(skip_n(&enc, 5).is_err());
+    }
+
     #[test]
     fn many_values_roundtrip() {
         let mut vals = Vec::new();
         for i in 0..50_000u32 {
-            vals.push((i * 2654435761u32) ^ 0xDEADBEEF); // pseudo-randomish spread
+            vals.push(i.wrapping_mul(2654435761u32) ^ 0xDEADBEEF); // pseudo-randomish spread
         }
         let enc = encode(&vals);
         let dec = decode(&enc).unwrap();
         assert_eq!(dec, vals);
     }
+
+    #[test]
+    fn swar_matches_scalar_on_mixed_runs() {
+        // Long small runs, a large value mid-chunk, and a short tail.
+        let mut vals: Vec<u32> = (0..37).map(|i| i % 128).collect();
+        vals.insert(13, 300);
+        vals.push(u32::MAX);
+        vals.extend([1, 2, 3]);
+
+        let enc = encode_batch_swar(&vals);
+        assert_eq!(enc, encode(&vals));
+        assert_eq!(decode_batch_swar(&enc).unwrap(), vals);
+    }
+
+    #[test]
+    fn swar_decode_rejects_what_decode_rejects() {
+        let err = decode_batch_swar(&[0, 0, 0, 0, 0, 0, 0, 0, 0x80])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("incomplete varint"));
+        assert!(decode_batch_swar(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]).is_err());
+    }
+
+    #[test]
+    fn zigzag_i64_extremes_round_trip() {
+        let cases = [
+            i64::MIN,
+            i64::MAX,
+            0,
+            -1,
+            1,
+            i32::MIN as i64,
+            u32::MAX as i64,
+        ];
+        let enc = encode_zigzag_i64(&cases);
+        assert_eq!(decode_zigzag_i64(&enc).unwrap(), cases);
+
+        // i64::MIN zigzags to u64::MAX: nine full bytes and a final 0x01.
+        let min: Vec<u8> = encode_one_zigzag_i64(i64::MIN).collect();
+        assert_eq!(min, [[0xFF; 9].as_slice(), &[0x01]].concat());
+        assert_eq!(encode_one_zigzag_i64(0).collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn zigzag_i64_million_random_values() {
+        // splitmix64, so the sample is the same on every run.
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let values: Vec<i64> = (0..1_000_000)
+            .map(|_| {
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                // Vary the magnitude so every encoded length shows up.
+                ((z ^ (z >> 31)) as i64) >> (z % 64)
+            })
+            .collect();
+
+        let enc = encode_zigzag_i64(&values);
+        let two_step: Vec<u64> = values.iter().map(|&v| zigzag::encode_i64(v)).collect();
+        assert_eq!(enc, encode_u64(&two_step));
+        assert_eq!(
+            enc,
+            values
+                .iter()
+                .flat_map(|&v| encode_one_zigzag_i64(v))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(decode_zigzag_i64(&enc).unwrap(), values);
+        assert_eq!(decode_u64(&enc).unwrap(), two_step);
+    }
+
+    #[test]
+    fn u64_boundaries() {
+        let max = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        assert_eq!(encode_u64(&[u64::MAX]), max);
+        assert_eq!(decode_u64(&max).unwrap(), [u64::MAX]);
+
+        let pairs: &[(u64, &[u8])] = &[
+            (0, &[0x00]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (u32::MAX as u64, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+            (1 << 32, &[0x80, 0x80, 0x80, 0x80, 0x10]),
+            (1 << 63, &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]),
+        ];
+        for &(value, bytes) in pairs {
+            assert_eq!(encode_u64(&[value]), bytes, "{value}");
+            assert_eq!(decode_u64(bytes).unwrap(), [value]);
+        }
+    }
+
+    #[test]
+    fn u64_decode_rejects_malformed_streams() {
+        assert!(decode_u64(&[0x80]).is_err());
+        assert!(decode_zigzag_i64(&[0xFF; 11]).is_err());
+        // Bits past 63 in the tenth byte.
+        let mut too_wide = [0xFF; 10];
+        too_wide[9] = 0x02;
+        assert!(decode_u64(&too_wide).is_err());
+    }
+
+    #[test]
+    fn u16_boundaries() {
+        let pairs: &[(u16, &[u8])] = &[
+            (0, &[0x00]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (0x3FFF, &[0xFF, 0x7F]),
+            (0x4000, &[0x80, 0x80, 0x01]),
+            (u16::MAX, &[0xFF, 0xFF, 0x03]),
+        ];
+        for &(value, bytes) in pairs {
+            assert_eq!(encode_u16(&[value]), bytes, "{value}");
+            assert_eq!(encode_u16(&[value]), encode(&[u32::from(value)]));
+            assert_eq!(decode_u16(bytes).unwrap(), [value]);
+        }
+    }
+
+    #[test]
+    fn u16_never_takes_more_than_3_bytes() {
+        for value in 0..=u16::MAX {
+            let len = encode_u16(&[value]).len();
+            assert!(len <= 3, "{value} took {len} bytes");
+        }
+    }
+
+    #[test]
+    fn u16_decode_rejects_malformed_streams() {
+        // 0x10000, one past u16::MAX.
+        assert!(decode_u16(&[0x80, 0x80, 0x04]).is_err());
+        // A fourth byte.
+        assert!(decode_u16(&[0x80, 0x80, 0x80, 0x00]).is_err());
+        assert!(decode_u16(&[0x80]).is_err());
+        assert!(decode_u16(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_n_returns_the_remainder() {
+        let mut bytes = encode(&[0, 127, 128, 300, u32::MAX]);
+        bytes.extend_from_slice(&[0x1F, 0x8B, 0x08]);
+
+        let (values, rest) = decode_n(&bytes, 3).unwrap();
+        assert_eq!(values, [0, 127, 128]);
+        assert_eq!(rest, &bytes[4..]);
+
+        let (values, rest) = decode_n(&bytes, 5).unwrap();
+        assert_eq!(values, [0, 127, 128, 300, u32::MAX]);
+        assert_eq!(rest, [0x1F, 0x8B, 0x08]);
+
+        let (values, rest) = decode_n(&bytes, 0).unwrap();
+        assert!(values.is_empty());
+        assert_eq!(rest, bytes);
+    }
+
+    #[test]
+    fn decode_n_names_how_many_were_found() {
+        let bytes = encode(&[1, 2, 300]);
+        let err = decode_n(&bytes, 5).unwrap_err();
+        assert!(err.to_string().contains("only 3"), "{err}");
+        // A truncated third value doesn't count.
+        let err = decode_n(&bytes[..3], 3).unwrap_err();
+        assert!(err.to_string().contains("only 2"), "{err}");
+        assert!(decode_n(&[0xFF; 6], 1).is_err());
+    }
+
+    #[test]
+    fn decoder_takes_single_byte_chunks() {
+        let values = [0, 127, 128, 300, u32::MAX];
+        let mut decoder = Decoder::new();
+        let mut decoded = Vec::new();
+        for chunk in encode(&values).chunks(1) {
+            decoded.extend(decoder.feed(chunk).unwrap());
+        }
+        decoder.finish().unwrap();
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn decoder_matches_decode_for_any_split() {
+        let values: Vec<u32> = (0..200).map(|i| i * 0x0101_0101 + i).collect();
+        let enc = encode(&values);
+        for split in [2, 3, 5, 64, enc.len()] {
+            let mut decoder = Decoder::new();
+            let mut decoded = Vec::new();
+            for chunk in enc.chunks(split) {
+                decoder.feed_into(chunk, &mut decoded).unwrap();
+            }
+            decoder.finish().unwrap();
+            assert_eq!(decoded, values, "chunks of {split}");
+        }
+    }
+
+    #[test]
+    fn decoder_reports_partial_and_overlong_values() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.feed(&[0x05, 0xAC]).unwrap(), [5]);
+        assert!(decoder.finish().is_err());
+        // finish starts over.
+        assert_eq!(decoder.feed(&[0x01]).unwrap(), [1]);
+        decoder.finish().unwrap();
+
+        assert!(decoder.feed(&[0xFF; 6]).is_err());
+        assert_eq!(decoder, Decoder::new());
+    }
+
+    #[test]
+    fn decoder_keeps_values_completed_before_overflow() {
+        let mut decoder = Decoder::new();
+        let mut decoded = Vec::new();
+        let chunk = [1, 2, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(decoder.feed_into(&chunk, &mut decoded).is_err());
+        assert_eq!(decoded, [1, 2]);
+        assert_eq!(decoder, Decoder::new());
+    }
+
+    #[test]
+    fn signed_known_encodings() {
+        let pairs: &[(i32, &[u8])] = &[
+            (0, &[0x00]),
+            (-1, &[0x7F]),
+            (63, &[0x3F]),
+            (64, &[0xC0, 0x00]),
+            (-64, &[0x40]),
+            (-65, &[0xBF, 0x7F]),
+            (128, &[0x80, 0x01]),
+            (-128, &[0x80, 0x7F]),
+            (i32::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0x07]),
+            (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x78]),
+        ];
+        for &(value, bytes) in pairs {
+            assert_eq!(encode_signed(&[value]), bytes, "{value}");
+            assert_eq!(decode_signed(bytes).unwrap(), [value]);
+        }
+    }
+
+    #[test]
+    fn signed_decode_rejects_malformed_streams() {
+        assert!(decode_signed(&[0x80]).is_err());
+        // Six bytes, and fifth bytes whose top bits don't repeat bit 31.
+        assert!(decode_signed(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]).is_err());
+        assert!(decode_signed(&[0x80, 0x80, 0x80, 0x80, 0x10]).is_err());
+        assert!(decode_signed(&[0x80, 0x80, 0x80, 0x80, 0x70]).is_err());
+        assert!(decode_signed(&[]).unwrap().is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn signed_round_trip(vals in proptest::collection::vec(any::<i32>(), 0..512)) {
+            prop_assert_eq!(decode_signed(&encode_signed(&vals)).unwrap(), vals);
+        }
+
+        #[test]
+        fn signed_is_no_larger_than_zigzag_for_negatives(v in i32::MIN..0) {
+            let sleb = encode_signed(&[v]).len();
+            prop_assert!(sleb <= encode(&[zigzag::encode(v)]).len());
+        }
+
+        #[test]
+        fn u64_round_trip(vals in proptest::collection::vec(any::<u64>(), 0..512)) {
+            let enc = encode_u64(&vals);
+            prop_assert!(enc.len() <= vals.len() * 10);
+            prop_assert_eq!(decode_u64(&enc).unwrap(), vals);
+        }
+
+        #[test]
+        fn u16_round_trip(vals in proptest::collection::vec(any::<u16>(), 0..512)) {
+            let enc = encode_u16(&vals);
+            let widened: Vec<u32> = vals.iter().map(|&v| u32::from(v)).collect();
+            prop_assert_eq!(&enc, &encode(&widened));
+            prop_assert_eq!(decode_u16(&enc).unwrap(), vals);
+        }
+
+        #[test]
+        fn swar_round_trip(vals in proptest::collection::vec(
+            prop_oneof![4 => 0u32..128, 1 => any::<u32>()],
+            0..512,
+        )) {
+            let enc = encode_batch_swar(&vals);
+            prop_assert_eq!(&enc, &encode(&vals));
+            prop_assert_eq!(decode_batch_swar(&enc).unwrap(), vals);
+        }
+
+        #[test]
+        fn swar_decode_agrees_on_arbitrary_bytes(
+            bytes in proptest::collection::vec(any::<u8>(), 0..256),
+        ) {
+            prop_assert_eq!(decode_batch_swar(&bytes).ok(), decode(&bytes).ok());
+        }
+    }
 }
@@ -0,0 +1,146 @@
+// src/vbyte.rs
+use anyhow::{bail, Result};
+
+use crate::varint;
+
+/// Values per group; one selector byte describes all of them.
+const GROUP: usize = 4;
+
+/// Value used to fill the last group up to `GROUP` values.
+const PAD: u32 = 0;
+
+/// Encode u32s as Group VarInt: a LEB128 value count, then groups of four
+/// values, each group led by a selector byte.
+///
+/// Bits `2i..2i+2` of the selector hold `byte_len - 1` for the group's `i`-th
+/// value, which follows as 1..=4 little-endian bytes. Decoders learn all four
+/// lengths from one byte instead of testing a continuation bit per byte.
+///
+/// The last group is padded with zeros; the count prefix tells decoders where
+/// the real values end.
+pub fn encode_vbyte(values: &[u32]) -> Vec<u8> {
+    let mut out = varint::encode(&[values.len() as u32]);
+    out.reserve(values.len().div_ceil(GROUP) * (1 + GROUP * 4));
+
+    for group in values.chunks(GROUP) {
+        let mut padded = [PAD; GROUP];
+        padded[..group.len()].copy_from_slice(group);
+
+        let selector_pos = out.len();
+        out.push(0);
+        let mut selector = 0u8;
+        for (i, value) in padded.iter().enumerate() {
+            let len = byte_len(*value);
+            selector |= ((len - 1) as u8) << (2 * i);
+            out.extend_from_slice(&value.to_le_bytes()[..len]);
+        }
+        out[selector_pos] = selector;
+    }
+
+    out
+}
+
+/// Decode `encode_vbyte` output.
+/// Errors on a truncated stream, trailing bytes, or a count too large for the
+/// data.
+pub fn decode_vbyte(bytes: &[u8]) -> Result<Vec<u32>> {
+    let (count, used) = varint::decode_one(bytes)?;
+    let count = count as usize;
+    let mut rest = &bytes[used..];
+
+    // Every group takes at least five bytes, which bounds the allocation.
+    let groups = count.div_ceil(GROUP);
+    if groups > rest.len() / 5 {
+        bail!("vbyte stream too short for {} values", count);
+    }
+
+    let mut out = Vec::with_capacity(groups * GROUP);
+    for _ in 0..groups {
+        let Some((&selector, tail)) = rest.split_first() else {
+            bail!("incomplete vbyte group at end of stream");
+        };
+        rest = tail;
+
+        for i in 0..GROUP {
+            let len = usize::from((selector >> (2 * i)) & 0b11) + 1;
+            if rest.len() < len {
+                bail!("incomplete vbyte group at end of stream");
+            }
+            let mut le = [0u8; 4];
+            le[..len].copy_from_slice(&rest[..len]);
+            out.push(u32::from_le_bytes(le));
+            rest = &rest[len..];
+        }
+    }
+
+    if !rest.is_empty() {
+        bail!("{} trailing bytes after vbyte stream", rest.len());
+    }
+    out.truncate(count);
+    Ok(out)
+}
+
+/// Bytes needed for `value` in little-endian form (1..=4).
+#[inline]
+fn byte_len(value: u32) -> usize {
+    let bits = 32 - value.leading_zeros() as usize;
+    bits.div_ceil(8).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn known_layout() {
+        // Count 5, then [1, 300, 70000, u32::MAX] and [7, pad, pad, pad].
+        let enc = encode_vbyte(&[1, 300, 70_000, u32::MAX, 7]);
+        assert_eq!(
+            enc,
+            [
+                5,
+                0b11_10_01_00,
+                1,
+                0x2C,
+                0x01,
+                0x70,
+                0x11,
+                0x01,
+                0xFF,
+                0xFF,
+                0xFF,
+                0xFF,
+                0,
+                7,
+                0,
+                0,
+                0,
+            ]
+        );
+        assert_eq!(decode_vbyte(&enc).unwrap(), [1, 300, 70_000, u32::MAX, 7]);
+    }
+
+    #[test]
+    fn empty_round_trip() {
+        assert_eq!(encode_vbyte(&[]), [0]);
+        assert!(decode_vbyte(&[0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        let enc = encode_vbyte(&[1, 2, 3, 4, 5]);
+        assert!(decode_vbyte(&enc[..enc.len() - 1]).is_err());
+        assert!(decode_vbyte(&[enc.as_slice(), &[0]].concat()).is_err());
+        assert!(decode_vbyte(&[9, 0, 1, 2, 3, 4]).is_err());
+        assert!(decode_vbyte(&[]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(vals in proptest::collection::vec(any::<u32>(), 0..512)) {
+            prop_assert_eq!(decode_vbyte(&encode_vbyte(&vals)).unwrap(), vals);
+        }
+    }
+}
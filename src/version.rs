@@ -0,0 +1,182 @@
+use crate::codec_core::CodecCore;
+use crate::errors::{CodecError, Result};
+use crate::header::{self, Header, FORMAT_VERSION};
+use crate::Codec;
+
+/// Whether this build can decode a payload, and if not, what it lacks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadCompatibility {
+    pub is_compatible: bool,
+    /// Features the payload needs that this build does not support; a too-new
+    /// format version is reported as `format_version_<n>`.
+    pub missing_features: Vec<String>,
+}
+
+/// Reading and upgrading payloads written by older format versions.
+///
+/// Version-0 payloads have no version byte at all (they start straight with the
+/// header's u32 token count), so they cannot be recognised by their first byte.
+/// `decode_any_version` therefore dispatches on the first byte when it names a
+/// known version and falls back to parsing the input as version 0 otherwise.
+impl Codec {
+    /// Rewrite an uncompressed version-0 payload as version 1.
+    ///
+    /// The token table and varint body are carried over byte for byte; only the
+    /// version and (empty) flags bytes are added in front. Gzipped version-0
+    /// payloads wrapped the header too, so they must be inflated first.
+    pub fn migrate_v0_to_v1(payload: &[u8]) -> Result<Vec<u8>> {
+        let (mut header, body) =
+            Header::decode_prefix_v0(payload).map_err(|_| CodecError::InvalidPayload)?;
+        // Make sure the body really is a valid v0 body before we bless it.
+        CodecCore::decode_body(body, &header)?;
+
+        header.version = FORMAT_VERSION;
+        let mut out = header.encode();
+        out.extend_from_slice(body);
+        Ok(out)
+    }
+
+    /// Decode a payload written by any version in `VERSION_HISTORY`.
+    ///
+    /// Fails with `CodecError::UnknownVersion` when the leading byte names a
+    /// newer version and the payload is not a valid version-0 payload either.
+    pub fn decode_any_version(payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let first = *payload.first().ok_or(CodecError::InvalidPayload)?;
+
+        if first == FORMAT_VERSION {
+            if let Ok(tokens) = CodecCore::decode_token_ids(payload, gzip) {
+                return Ok(tokens);
+            }
+        }
+
+        match Self::decode_v0(payload, gzip) {
+            Ok(tokens) => Ok(tokens),
+            Err(_) if first > FORMAT_VERSION => Err(CodecError::UnknownVersion(first)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Check a (version-1 or newer) payload's version and flags against what
+    /// this build supports, without decoding it.
+    pub fn check_compatibility(payload: &[u8]) -> Result<PayloadCompatibility> {
+        let [version, flags, ..] = *payload else {
+            return Err(CodecError::InvalidPayload);
+        };
+
+        let mut missing_features = Vec::new();
+        if version > FORMAT_VERSION {
+            missing_features.push(format!("format_version_{version}"));
+        }
+        missing_features.extend(header::unsupported_features(flags));
+
+        Ok(PayloadCompatibility {
+            is_compatible: missing_features.is_empty(),
+            missing_features,
+        })
+    }
+
+    fn decode_v0(payload: &[u8], gzip: bool) -> Result<Vec<i32>> {
+        let raw = CodecCore::decompress(payload, gzip)?;
+        let (header, body) =
+            Header::decode_prefix_v0(&raw).map_err(|_| CodecError::InvalidPayload)?;
+        CodecCore::decode_body(body, &header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::freq_map::FreqMap;
+    use crate::header::{FLAG_MODE_MASK, MODE_NAMES};
+
+    /// What a version-0 codec wrote: header table + body, gzip over everything.
+    fn encode_v0(ids: &[i32], gzip: bool) -> Vec<u8> {
+        let freq = FreqMap::from_token_ids(ids);
+        let mut out = Header::from_freq_map(&freq).encode()[2..].to_vec();
+        out.extend_from_slice(&CodecCore::encode_body(ids, &freq).unwrap());
+        CodecCore::compress(out, gzip).unwrap()
+    }
+
+    #[test]
+    fn migrated_v0_payload_decodes_as_v1() {
+        let ids = [12, 7, 12, 12, 300, 7, -4];
+        let v0 = encode_v0(&ids, false);
+
+        let v1 = Codec::migrate_v0_to_v1(&v0).unwrap();
+        assert_eq!(v1[0], FORMAT_VERSION);
+        assert_eq!(v1, CodecCore::encode_token_ids(&ids, false).unwrap());
+        assert_eq!(CodecCore::decode_token_ids(&v1, false).unwrap(), ids);
+    }
+
+    #[test]
+    fn decode_any_version_reads_both_versions() {
+        let ids: Vec<i32> = (0..200).map(|i| (i * 31) % 17).collect();
+
+        for gzip in [false, true] {
+            let v0 = encode_v0(&ids, gzip);
+            let v1 = CodecCore::encode_token_ids(&ids, gzip).unwrap();
+            assert_eq!(Codec::decode_any_version(&v0, gzip).unwrap(), ids);
+            assert_eq!(Codec::decode_any_version(&v1, gzip).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn decode_any_version_handles_v0_with_one_token() {
+        // A v0 payload with one unique token starts with byte 1, like a v1 header.
+        let v0 = encode_v0(&[5, 5, 5], false);
+        assert_eq!(v0[0], 1);
+        assert_eq!(Codec::decode_any_version(&v0, false).unwrap(), vec![5, 5, 5]);
+    }
+
+    #[test]
+    fn decode_any_version_rejects_future_version() {
+        let mut payload = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        payload[0] = 2;
+
+        assert!(matches!(
+            Codec::decode_any_version(&payload, false),
+            Err(CodecError::UnknownVersion(2))
+        ));
+    }
+
+    #[test]
+    fn migrate_rejects_garbage() {
+        assert!(Codec::migrate_v0_to_v1(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn check_compatibility_accepts_current_payloads() {
+        let plain = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        let compat = Codec::check_compatibility(&plain).unwrap();
+        assert!(compat.is_compatible);
+        assert!(compat.missing_features.is_empty());
+
+        let metadata = [("k".to_string(), "v".to_string())].into_iter().collect();
+        let with_metadata = Codec::new()
+            .encode_with_metadata(&[1, 2, 3], &metadata, false)
+            .unwrap();
+        assert!(Codec::check_compatibility(&with_metadata).unwrap().is_compatible);
+    }
+
+    #[test]
+    fn check_compatibility_reports_what_is_missing() {
+        let mut payload = CodecCore::encode_token_ids(&[1, 2, 3], false).unwrap();
+        let unknown = (1..=FLAG_MODE_MASK)
+            .find(|mode| !MODE_NAMES.iter().any(|(code, _)| code == mode))
+            .unwrap();
+        payload[0] = FORMAT_VERSION + 1;
+        payload[1] |= unknown;
+
+        let compat = Codec::check_compatibility(&payload).unwrap();
+        assert!(!compat.is_compatible);
+        assert_eq!(
+            compat.missing_features,
+            vec![
+                format!("format_version_{}", FORMAT_VERSION + 1),
+                format!("unknown_mode_{unknown:#04x}"),
+            ]
+        );
+
+        assert!(Codec::check_compatibility(&[1]).is_err());
+    }
+}
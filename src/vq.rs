@@ -0,0 +1,145 @@
+use crate::codec_core::{read_varint, to_u32, CodecCore};
+use crate::errors::{CodecError, Result};
+use crate::{varint, Codec};
+
+/// Vector-quantized tokens: `n_codebooks` codes per position, each codebook
+/// with its own ID distribution.
+///
+/// Input is row-major (`n_tokens` rows of `n_codebooks` codes). Each
+/// codebook's column becomes a standard payload with its own `FreqMap` and
+/// header, and the payloads are concatenated:
+///   n_codebooks varint: byte length of each codebook's payload
+///   then              : the payloads, in codebook order
+impl Codec {
+    /// Fails with `CodecError::InvalidPayload` unless `n_codebooks` is
+    /// non-zero and divides the number of codes.
+    pub fn encode_vq(
+        &self,
+        codebook_ids: &[i32],
+        n_codebooks: usize,
+        gzip: bool,
+    ) -> Result<Vec<u8>> {
+        if n_codebooks == 0 || !codebook_ids.len().is_multiple_of(n_codebooks) {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let encoded = (0..n_codebooks)
+            .map(|c| {
+                let column: Vec<i32> = codebook_ids
+                    .iter()
+                    .skip(c)
+                    .step_by(n_codebooks)
+                    .copied()
+                    .collect();
+                CodecCore::encode_token_ids(&column, gzip)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let lengths = encoded
+            .iter()
+            .map(|bytes| to_u32(bytes.len()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut out = varint::encode(&lengths);
+        for bytes in &encoded {
+            out.extend_from_slice(bytes);
+        }
+        Ok(out)
+    }
+
+    /// Inverse of `encode_vq`, returning one row per codebook (shape
+    /// `(n_codebooks, n_tokens)`).
+    pub fn decode_vq(
+        &self,
+        payload: &[u8],
+        n_codebooks: usize,
+        gzip: bool,
+    ) -> Result<Vec<Vec<i32>>> {
+        let mut rest = payload;
+        let mut lengths = Vec::with_capacity(n_codebooks.min(payload.len()));
+        for _ in 0..n_codebooks {
+            lengths.push(read_varint(&mut rest)? as usize);
+        }
+        if lengths.iter().sum::<usize>() != rest.len() {
+            return Err(CodecError::InvalidPayload);
+        }
+
+        let codebooks = lengths
+            .iter()
+            .map(|&len| {
+                let (bytes, tail) = rest.split_at(len);
+                rest = tail;
+                CodecCore::decode_token_ids(bytes, gzip)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if codebooks
+            .windows(2)
+            .any(|pair| pair[0].len() != pair[1].len())
+        {
+            return Err(CodecError::InvalidPayload);
+        }
+        Ok(codebooks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 512 positions of 4 codebooks, each drawing from its own 1024 codes
+    /// with a different skew.
+    fn codes() -> Vec<i32> {
+        (0..512)
+            .flat_map(|t: i32| (0..4).map(move |c| ((t * 7919) % 1024) / (c + 1) + c * 2000))
+            .collect()
+    }
+
+    #[test]
+    fn codebooks_round_trip_as_rows() {
+        let codec = Codec::new();
+        let codes = codes();
+        for gzip in [false, true] {
+            let payload = codec.encode_vq(&codes, 4, gzip).unwrap();
+            let rows = codec.decode_vq(&payload, 4, gzip).unwrap();
+
+            assert_eq!(rows.len(), 4);
+            for (c, row) in rows.iter().enumerate() {
+                assert_eq!(row.len(), 512);
+                let column: Vec<i32> = codes.iter().skip(c).step_by(4).copied().collect();
+                assert_eq!(*row, column);
+            }
+        }
+    }
+
+    #[test]
+    fn codebooks_get_their_own_headers() {
+        let codec = Codec::new();
+        let payload = codec.encode_vq(&codes(), 4, false).unwrap();
+        let mut rest = payload.as_slice();
+        let first = read_varint(&mut rest).unwrap() as usize;
+        for _ in 1..4 {
+            read_varint(&mut rest).unwrap();
+        }
+        // Codebook 0 holds codes below 1024 only; the others are offset.
+        let first_header = crate::header::Header::decode_prefix(&rest[..first])
+            .unwrap()
+            .0;
+        assert!(first_header.tokens.iter().all(|&t| t < 1024));
+    }
+
+    #[test]
+    fn bad_shapes_are_rejected() {
+        let codec = Codec::new();
+        assert!(codec.encode_vq(&[1, 2, 3], 2, false).is_err());
+        assert!(codec.encode_vq(&[1, 2], 0, false).is_err());
+
+        let payload = codec.encode_vq(&[1, 2, 3, 4], 2, false).unwrap();
+        assert!(codec.decode_vq(&payload, 3, false).is_err());
+        assert!(codec
+            .decode_vq(&payload[..payload.len() - 1], 2, false)
+            .is_err());
+        assert_eq!(
+            codec.decode_vq(&payload, 2, false).unwrap(),
+            [vec![1, 3], vec![2, 4]]
+        );
+    }
+}
@@ -17,8 +17,63 @@ pub fn decode(value: u32) -> i32 {
     ((value >> 1) as i32) ^ (-((value & 1) as i32))
 }
 
+/// `encode` for 64 bit integers, e.g. token IDs beyond the i32 range or
+/// signed token hashes. Same mapping: 0->0, -1->1, 1->2, -2->3, 2->4, ...
+#[inline]
+pub fn encode_i64(value: i64) -> u64 {
+    // Protobuf's sint64: (n << 1) ^ (n >> 63)
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Reverse of `encode_i64`.
+#[inline]
+pub fn decode_i64(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ (-((value & 1) as i64))
+}
+
+/// Zigzag-encode every element of `values` into a new Vec.
+pub fn encode_slice(values: &[i32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(values.len());
+    encode_slice_into(values, &mut out);
+    out
+}
+
+/// Reverse of `encode_slice`.
+pub fn decode_slice(values: &[u32]) -> Vec<i32> {
+    let mut out = Vec::with_capacity(values.len());
+    decode_slice_into(values, &mut out);
+    out
+}
+
+/// Like `encode_slice`, but appends to `dst`, which allocates nothing once
+/// `dst` has the capacity.
+///
+/// A plain `map` over the slice with no early exits, which LLVM auto-vectorizes.
+pub fn encode_slice_into(src: &[i32], dst: &mut Vec<u32>) {
+    dst.extend(src.iter().map(|&v| encode(v)));
+}
+
+/// Like `decode_slice`, but appends to `dst`.
+pub fn decode_slice_into(src: &[u32], dst: &mut Vec<i32>) {
+    dst.extend(src.iter().map(|&v| decode(v)));
+}
+
+/// Like `encode_slice`, but reuses `dst` (cleared first) to avoid allocating.
+pub fn encode_slice_inplace(src: &[i32], dst: &mut Vec<u32>) {
+    dst.clear();
+    encode_slice_into(src, dst);
+}
+
+/// Like `decode_slice`, but reuses `dst` (cleared first) to avoid allocating.
+pub fn decode_slice_inplace(src: &[u32], dst: &mut Vec<i32>) {
+    dst.clear();
+    decode_slice_into(src, dst);
+}
+
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -47,4 +102,93 @@ mod tests {
         assert_eq!(decode(encode(i32::MIN)), i32::MIN);
         assert_eq!(decode(encode(i32::MAX)), i32::MAX);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn i64_round_trip_small_values() {
+        let cases = [-2, -1, 0, 1, 2, 17, -17, 123456, -123456, 1 << 40, -(1 << 40)];
+        for &x in &cases {
+            let z = encode_i64(x);
+            assert_eq!(decode_i64(z), x, "failed round-trip for {x} -> {z}");
+        }
+    }
+
+    #[test]
+    fn i64_exact_known_mappings() {
+        assert_eq!(encode_i64(0), 0);
+        assert_eq!(encode_i64(-1), 1);
+        assert_eq!(encode_i64(1), 2);
+        assert_eq!(encode_i64(-2), 3);
+        assert_eq!(encode_i64(2), 4);
+    }
+
+    #[test]
+    fn i64_matches_i32_and_covers_extremes() {
+        for x in [-2, -1, 0, 1, 2, i32::MIN, i32::MAX] {
+            assert_eq!(encode_i64(x as i64), encode(x) as u64);
+        }
+        assert_eq!(encode_i64(i64::MIN), u64::MAX);
+        assert_eq!(decode_i64(encode_i64(i64::MIN)), i64::MIN);
+        assert_eq!(decode_i64(encode_i64(i64::MAX)), i64::MAX);
+    }
+
+    #[test]
+    fn slices_match_scalar_functions() {
+        let values = [0, -1, 1, i32::MIN, i32::MAX, 300, -300];
+        let encoded = encode_slice(&values);
+        assert_eq!(encoded, values.map(encode));
+        assert_eq!(decode_slice(&encoded), values);
+    }
+
+    #[test]
+    fn inplace_variants_overwrite_the_buffer() {
+        let mut enc = vec![99; 10];
+        encode_slice_inplace(&[1, -1], &mut enc);
+        assert_eq!(enc, vec![2, 1]);
+
+        let mut dec = vec![7; 1];
+        decode_slice_inplace(&enc, &mut dec);
+        assert_eq!(dec, vec![1, -1]);
+    }
+
+    #[test]
+    fn into_variants_append() {
+        let mut enc = vec![99];
+        encode_slice_into(&[1, -1], &mut enc);
+        encode_slice_into(&[2], &mut enc);
+        assert_eq!(enc, vec![99, 2, 1, 4]);
+
+        let mut dec = Vec::with_capacity(8);
+        let capacity = dec.capacity();
+        decode_slice_into(&enc[1..], &mut dec);
+        decode_slice_into(&[3], &mut dec);
+        assert_eq!(dec, vec![1, -1, 2, -2]);
+        assert_eq!(dec.capacity(), capacity);
+    }
+
+    proptest! {
+        #[test]
+        fn into_matches_slice(
+            prefix in proptest::collection::vec(any::<u32>(), 0..8),
+            xs in proptest::collection::vec(any::<i32>(), 0..512),
+        ) {
+            let mut enc = prefix.clone();
+            encode_slice_into(&xs, &mut enc);
+            prop_assert_eq!(&enc[..prefix.len()], &prefix[..]);
+            prop_assert_eq!(&enc[prefix.len()..], &encode_slice(&xs)[..]);
+
+            let mut dec = vec![7];
+            decode_slice_into(&enc[prefix.len()..], &mut dec);
+            prop_assert_eq!(&dec[1..], &xs[..]);
+        }
+
+        #[test]
+        fn slice_round_trip(xs in proptest::collection::vec(any::<i32>(), 0..512)) {
+            prop_assert_eq!(decode_slice(&encode_slice(&xs)), xs);
+        }
+
+        #[test]
+        fn i64_round_trip(x in any::<i64>()) {
+            prop_assert_eq!(decode_i64(encode_i64(x)), x);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use crate::codec_core::CodecCore;
+use crate::config::CodecConfig;
+use crate::errors::{CodecError, Result};
+use crate::header::Header;
+use crate::Codec;
+
+/// Compression level used with the dictionary.
+const LEVEL: i32 = zstd::DEFAULT_COMPRESSION_LEVEL;
+
+/// Train a zstd dictionary of at most `dict_size` bytes on sample payloads.
+///
+/// Small payloads compress poorly on their own because zstd has no history
+/// to match against; a dictionary trained on similar payloads supplies one.
+pub fn train_zstd_dict(samples: &[Vec<u8>], dict_size: usize) -> Result<Vec<u8>> {
+    Ok(zstd::dict::from_samples(samples, dict_size)?)
+}
+
+/// Payloads whose body is a zstd frame compressed against a shared
+/// dictionary. The header stays uncompressed, as with gzip.
+///
+/// Encoder and decoder must hold the same dictionary; it is not recorded in
+/// the payload.
+impl Codec {
+    pub fn with_zstd_dict(dict: Vec<u8>) -> Self {
+        Self::with_config(CodecConfig {
+            zstd_dict: Some(Arc::from(dict)),
+            ..CodecConfig::default()
+        })
+    }
+
+    pub fn encode_token_ids_zstd_dict(&self, token_ids: &[i32]) -> Result<Vec<u8>> {
+        let plain = self.encode(token_ids, false)?;
+        let (header, body) =
+            Header::decode_prefix(&plain).map_err(|e| CodecError::Internal(e.to_string()))?;
+
+        let mut compressor = zstd::bulk::Compressor::with_dictionary(LEVEL, self.zstd_dict()?)?;
+        let body = compressor.compress(body)?;
+
+        let mut out = plain[..header.body_offset()].to_vec();
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    pub fn decode_token_ids_zstd_dict(&self, payload: &[u8]) -> Result<Vec<i32>> {
+        let (header, body) =
+            Header::decode_prefix(payload).map_err(|_| CodecError::InvalidPayload)?;
+        let capacity = zstd::zstd_safe::get_frame_content_size(body)
+            .ok()
+            .flatten()
+            .and_then(|size| usize::try_from(size).ok())
+            .ok_or(CodecError::InvalidPayload)?;
+
+        let mut decompressor = zstd::bulk::Decompressor::with_dictionary(self.zstd_dict()?)?;
+        let body = decompressor
+            .decompress(body, capacity)
+            .map_err(|_| CodecError::InvalidPayload)?;
+
+        let mut plain = payload[..header.body_offset()].to_vec();
+        plain.extend_from_slice(&body);
+        Ok(CodecCore::decode_with_header(&plain, false)?.0)
+    }
+
+    fn zstd_dict(&self) -> Result<&[u8]> {
+        self.config()
+            .zstd_dict
+            .as_deref()
+            .ok_or_else(|| CodecError::Internal("codec has no zstd dictionary".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Short sequences drawn from one skewed vocabulary.
+    fn sequence(seed: u32) -> Vec<i32> {
+        (0..64u32)
+            .map(|i| {
+                let r = (i ^ seed).wrapping_mul(2654435761) >> 20;
+                (r % 40 + (r % 7) * (r % 3) * 100) as i32
+            })
+            .collect()
+    }
+
+    /// Codec with a dictionary trained on 100 sample payloads.
+    fn trained_codec() -> Codec {
+        let samples: Vec<Vec<u8>> = (0..100)
+            .map(|s| CodecCore::encode_token_ids(&sequence(s), false).unwrap())
+            .collect();
+        Codec::with_zstd_dict(train_zstd_dict(&samples, 4096).unwrap())
+    }
+
+    fn standard_zstd_len(ids: &[i32]) -> usize {
+        let plain = CodecCore::encode_token_ids(ids, false).unwrap();
+        let (header, body) = Header::decode_prefix(&plain).unwrap();
+        header.body_offset() + zstd::bulk::compress(body, LEVEL).unwrap().len()
+    }
+
+    #[test]
+    fn trained_dict_beats_standard_zstd() {
+        let codec = trained_codec();
+
+        let tests: Vec<Vec<i32>> = (1000..1050).map(sequence).collect();
+        let mut with_dict = 0;
+        let mut standard = 0;
+        for ids in &tests {
+            let payload = codec.encode_token_ids_zstd_dict(ids).unwrap();
+            assert_eq!(codec.decode_token_ids_zstd_dict(&payload).unwrap(), *ids);
+            with_dict += payload.len();
+            standard += standard_zstd_len(ids);
+        }
+
+        let (with_dict, standard) = (with_dict / tests.len(), standard / tests.len());
+        assert!(
+            with_dict < standard,
+            "average {with_dict} bytes with dict vs {standard} without"
+        );
+    }
+
+    #[test]
+    fn requires_a_dictionary() {
+        assert!(matches!(
+            Codec::new().encode_token_ids_zstd_dict(&[1, 2, 3]),
+            Err(CodecError::Internal(_))
+        ));
+    }
+
+    #[test]
+    fn non_zstd_body_is_rejected() {
+        let codec = trained_codec();
+        let plain = CodecCore::encode_token_ids(&sequence(7), false).unwrap();
+        assert!(matches!(
+            codec.decode_token_ids_zstd_dict(&plain),
+            Err(CodecError::InvalidPayload)
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+//! Protobuf interchange, end to end through the wire encoding.
+#![cfg(feature = "prost")]
+
+use miso::freq_map::FreqMap;
+use miso::header::Header;
+use miso::proto::{FreqMapProto, HeaderProto};
+use prost::Message;
+
+#[test]
+fn freq_map_survives_protobuf_bytes() {
+    let ids: Vec<i32> = (0..2000).map(|i| (i * 7919) % 503 - 250).collect();
+    let fm = FreqMap::from_token_ids(&ids);
+
+    let bytes = fm.to_proto().encode_to_vec();
+    let decoded = FreqMap::from_proto(FreqMapProto::decode(bytes.as_slice()).unwrap()).unwrap();
+
+    assert_eq!(decoded, fm);
+    assert_eq!(decoded.ordered_tokens(), fm.ordered_tokens());
+    assert_eq!(decoded.counts(), fm.counts());
+}
+
+#[test]
+fn header_survives_protobuf_bytes() {
+    let header = Header::from_freq_map(&FreqMap::from_token_ids(&[5, 5, i32::MIN, i32::MAX]));
+
+    let bytes = header.to_proto().encode_to_vec();
+    let decoded = Header::from_proto(HeaderProto::decode(bytes.as_slice()).unwrap()).unwrap();
+
+    assert_eq!(decoded, header);
+}
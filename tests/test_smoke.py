@@ -4,3 +4,419 @@ def test_ping():
     c = Codec()
     assert c.ping() == 'pong'
 
+
+def test_explain():
+    c = Codec()
+    report = c.explain([3, 7, 3, 3, 9])
+    assert "Input:" in report
+    assert "Header:" in report
+    assert "Varint" in report
+
+
+def test_file_round_trip():
+    import os
+    import tempfile
+
+    c = Codec()
+    ids = [5, 1, 5, 5, 9, -3]
+    with tempfile.TemporaryDirectory() as tmp:
+        path = os.path.join(tmp, "tokens.miso")
+        c.encode_to_file(ids, path, True)
+        assert c.decode_from_file(path, True) == ids
+
+        c.encode_to_file_mmap(ids, path, False)
+        with open(path, "rb") as f:
+            assert list(f.read()) == c.encode_token_ids(ids, False)
+
+
+def test_estimate_sizes():
+    c = Codec()
+    ids = [3, 3, 3, 7, 9, 9]
+    sizes = c.estimate_sizes(ids)
+    assert sizes["total_estimate"] == len(c.encode_token_ids(ids, False))
+    assert sizes["header_bytes"] + sizes["body_bytes_estimate"] == sizes["total_estimate"]
+
+
+def test_empty_and_single_token_inputs():
+    c = Codec()
+    for ids in ([], [0], [-2**31], [2**31 - 1], [77, 77]):
+        for gzip in (False, True):
+            assert c.decode_token_ids(c.encode_token_ids(ids, gzip), gzip) == ids
+
+
+def test_encode_to_fd():
+    import tempfile
+
+    c = Codec()
+    ids = list(range(50)) * 3
+    with tempfile.TemporaryFile() as f:
+        written = c.encode_to_fd(ids, f.fileno(), True)
+        f.seek(0)
+        data = f.read()
+    assert written == len(data)
+    assert list(data) == c.encode_token_ids(ids, True)
+
+    vbyte = Codec(varint_mode="vbyte")
+    with tempfile.TemporaryFile() as f:
+        written = vbyte.encode_to_fd(ids, f.fileno(), False)
+        f.seek(0)
+        data = f.read()
+    assert written == len(data)
+    assert list(data) == vbyte.encode_token_ids(ids, False)
+    assert Codec().decode_token_ids(data, False) == ids
+
+
+def test_causal_lm_batch():
+    c = Codec()
+    sequences = [[10, 11, 12, 13], [20, 21], [10, 12]]
+    payload = c.encode_causal_lm_batch(sequences, 0, True)
+    inputs, labels = c.decode_causal_lm_batch(payload, True)
+    assert inputs == sequences
+    assert labels == [[11, 12, 13, 0], [21, 0], [12, 0]]
+
+
+def test_decode_token_ids_with_header():
+    c = Codec()
+    ids = [8, -2, 8, 30, 8, -2]
+    tokens, header = c.decode_token_ids_with_header(c.encode_token_ids(ids, True), True)
+    assert tokens == ids
+    assert header == {"tokens": [8, -2, 30], "len": 3, "version": 1}
+
+
+def test_shared_payload():
+    from concurrent.futures import ThreadPoolExecutor
+
+    c = Codec()
+    ids = list(range(100)) * 5
+    shared = c.encode_token_ids_shared(ids, True)
+    assert len(shared) == len(c.encode_token_ids(ids, True))
+    assert list(bytes(shared)) == c.encode_token_ids(ids, True)
+
+    vbyte = Codec(varint_mode="vbyte")
+    shared_vbyte = vbyte.encode_token_ids_shared(ids, False)
+    assert list(bytes(shared_vbyte)) == vbyte.encode_token_ids(ids, False)
+
+    with ThreadPoolExecutor(max_workers=4) as pool:
+        results = list(pool.map(lambda p: p.decode(True), [shared] * 1000))
+    assert all(r == ids for r in results)
+
+
+def test_encode_with_freq_map():
+    corpus = list(range(50)) * 3 + [999]
+    vocab = Codec().build_freq_map(corpus)
+
+    ids = [3, 7, 3, 49]
+    payload = Codec().encode_with_freq_map(ids, vocab, True)
+    assert Codec().decode_token_ids(payload, True) == ids
+
+    payload = Codec(unk_token=999).encode_with_freq_map([3, 12345], vocab, False)
+    assert Codec().decode_token_ids(payload, False) == [3, 999]
+    try:
+        Codec().encode_with_freq_map([12345], vocab, False)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("unknown token should be rejected")
+
+
+def test_vbyte_varint_mode():
+    ids = [4, 4, 9, 1, 300, 4]
+    payload = Codec(varint_mode="vbyte").encode_token_ids(ids, True)
+    assert payload != Codec().encode_token_ids(ids, True)
+    assert Codec().decode_token_ids(payload, True) == ids
+
+
+def test_huffman_varint_mode():
+    ids = [4, 4, 9, 1, 300, 4, 4, 9]
+    for gzip in (False, True):
+        payload = Codec(varint_mode="huffman").encode_token_ids(ids, gzip)
+        assert Codec().decode_token_ids(payload, gzip) == ids
+
+
+def test_arithmetic_varint_mode():
+    ids = [4] * 50 + [9, 1, 300]
+    for gzip in (False, True):
+        payload = Codec(varint_mode="arithmetic").encode_token_ids(ids, gzip)
+        assert Codec().decode_token_ids(payload, gzip) == ids
+    plain = Codec(varint_mode="arithmetic").encode_token_ids(ids, False)
+    assert len(plain) < len(Codec().encode_token_ids(ids, False))
+
+
+def test_vocab_coverage():
+    c = Codec()
+    assert c.vocab_coverage([1, 2, 3, 4], [1, 2, 3, 4]) == 1.0
+    assert c.vocab_coverage([1, 2, 3, 4], [3, 4, 5, 6]) == 0.5
+    assert c.vocab_coverage([1, 2], [7, 8]) == 0.0
+
+
+def test_tokens_and_mask():
+    c = Codec()
+    ids = [(i * 7919) % 3000 + 100 for i in range(118)] + [0] * 10
+    mask = [1] * 118 + [0] * 10
+    for gzip in (False, True):
+        payload = c.encode_tokens_and_mask(ids, mask, gzip)
+        assert c.decode_tokens_and_mask(payload, gzip) == (ids, mask)
+        assert c.decode_token_ids(payload, gzip) == ids
+    try:
+        c.encode_tokens_and_mask(ids, mask[:-1], False)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("length mismatch should be rejected")
+
+
+def test_decode_traced():
+    c = Codec()
+    ids = [3, 7, 3, 3, 500]
+    trace = c.decode_traced(c.encode_token_ids(ids, True), True)
+    assert trace["tokens"] == ids
+    assert trace["mapped_ids"] == [0, 1, 0, 0, 2]
+    assert trace["zigzag_values"] == [0, 2, 0, 0, 4]
+    assert trace["varint_byte_offsets"] == [0, 1, 2, 3, 4]
+
+
+def test_encode_no_alloc():
+    c = Codec()
+    ids = [(i * 7919) % 3001 for i in range(1000)]
+    out = bytearray(Codec.max_encoded_size(len(ids), len(set(ids))) + 64)
+    for gzip in (False, True):
+        n = c.encode_no_alloc(ids, gzip, out)
+        assert c.decode_token_ids(bytes(out[:n]), gzip) == ids
+    n = c.encode_no_alloc(ids, False, out)
+    assert bytes(out[:n]) == bytes(c.encode_token_ids(ids, False))
+    try:
+        c.encode_no_alloc(ids, False, bytearray(10))
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("small buffer should be rejected")
+
+
+def test_fingerprint():
+    c = Codec()
+    ids = [3, 1, 4, 1, 5]
+    payload, fp = c.encode_with_fingerprint(ids, True)
+    assert fp == Codec.fingerprint(ids)
+    assert fp != Codec.fingerprint(ids[::-1])
+    assert c.verify_fingerprint(payload, fp, True)
+    assert not c.verify_fingerprint(payload, fp ^ 1, True)
+
+
+def test_position_bias():
+    c = Codec()
+    ids = list(range(100))
+    bias = [-i / 64 for i in range(100)]
+    for gzip in (False, True):
+        tokens, decoded = c.decode_with_position_bias(
+            c.encode_with_position_bias(ids, bias, gzip), gzip
+        )
+        assert tokens == ids
+        assert all(abs(a - b) < 1e-3 for a, b in zip(decoded, bias))
+
+
+def test_vq():
+    c = Codec()
+    rows = [[(t * 31) % 1024 for t in range(64)], [(t * 7) % 512 for t in range(64)]]
+    flat = [code for pair in zip(*rows) for code in pair]
+    for gzip in (False, True):
+        assert c.decode_vq(c.encode_vq(flat, 2, gzip), 2, gzip) == rows
+
+
+def test_segments():
+    c = Codec()
+    docs = [[5, 6, 0], [7, 0], [8, 8, 8, 9, 0]]
+    ids = [t for doc in docs for t in doc]
+    lengths = [len(doc) for doc in docs]
+    for gzip in (False, True):
+        payload = c.encode_with_segments(ids, lengths, gzip)
+        assert c.decode_with_segments(payload, gzip) == (ids, lengths)
+    try:
+        c.encode_with_segments(ids, [1, 2], False)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("lengths must add up to the token count")
+
+
+def test_ecc():
+    c = Codec()
+    ids = [(i * 7919) % 3000 for i in range(500)]
+    payload = c.encode_with_ecc(ids, False)
+    tokens, errors = c.decode_with_ecc(payload, False)
+    assert tokens == ids and not any(errors)
+    corrupt = bytearray(payload)
+    corrupt[70] ^= 0x01
+    errors = c.check_ecc(bytes(corrupt))
+    assert errors[1] and sum(errors) == 1
+
+
+def test_estimate_freq_map_build_ns():
+    small = Codec.estimate_freq_map_build_ns(1000, 100)
+    assert small > 0
+    assert Codec.estimate_freq_map_build_ns(100_000, 100) > small
+
+
+def test_sparse_header():
+    c = Codec()
+    ids = list(range(5000)) + [7, 8, 9] * 100
+    for gzip in (False, True):
+        payload = c.encode_sparse_header(ids, 256, gzip)
+        assert c.decode_sparse_header(payload, gzip) == ids
+    assert len(c.encode_sparse_header(ids, 256, False)) < len(c.encode_token_ids(ids, False))
+    adaptive = c.encode_adaptive(ids, False)
+    assert c.decode_sparse_header(adaptive, False) == ids
+
+
+def test_logits():
+    ids = [3, 7]
+    logits = [0.0] * 20
+    logits[3] = 8.0
+    logits[10 + 1] = logits[10 + 7] = 4.0
+    for c in (Codec(), Codec(logits_top_p=0.9)):
+        payload = c.encode_with_logits(ids, logits, 10, False)
+        tokens, kept = c.decode_with_logits(payload, 10, False)
+        assert tokens == ids
+        assert kept == [[(3, 8.0)], [(1, 4.0), (7, 4.0)]]
+    payload = Codec(logits_top_p=1.0).encode_with_logits(ids, logits, 10, False)
+    assert all(len(row) == 10 for row in c.decode_with_logits(payload, 10, False)[1])
+
+
+def test_kafka_compatible():
+    ids = [5, 9, 5, 300]
+    for gzip in (False, True):
+        payload = Codec.encode_kafka_compatible(ids, gzip)
+        assert bytes(payload[:4]) == b"MISO"
+        assert Codec.is_kafka_compatible(payload)
+        assert Codec.decode_kafka_compatible(payload, gzip) == ids
+    assert not Codec.is_kafka_compatible(bytes(16))
+
+
+def test_msgpack_interop():
+    ids = [5, 9, -1, 300]
+    raw = Codec.encode_as_msgpack(ids)
+    assert Codec.decode_from_msgpack(raw) == ids
+    wrapped = Codec.encode_compressed_msgpack(ids, True)
+    assert Codec.decode_compressed_msgpack(wrapped, True) == ids
+
+    try:
+        import msgpack
+    except ImportError:
+        return
+    assert msgpack.unpackb(bytes(raw)) == {"tokens": ids, "version": 1}
+    plain = Codec.encode_compressed_msgpack(ids, False)
+    assert msgpack.unpackb(bytes(plain)) == bytes(Codec().encode_token_ids(ids, False))
+
+
+def test_npy_files():
+    import os
+    import tempfile
+
+    ids = [5, 9, -1, 50000]
+    rows = [[1, 2, 3], [4, 5, 6]]
+    with tempfile.TemporaryDirectory() as d:
+        vector = os.path.join(d, "ids.npy")
+        matrix = os.path.join(d, "batch.npy")
+        Codec.encode_as_npy(ids, vector)
+        Codec.encode_matrix_as_npy(rows, matrix)
+        with open(vector, "rb") as f:
+            assert f.read(6) == b"\x93NUMPY"
+        assert Codec.decode_from_npy(vector) == ids
+        assert Codec.decode_matrix_from_npy(matrix) == rows
+
+        try:
+            import numpy as np
+        except ImportError:
+            return
+        assert np.load(vector).tolist() == ids
+        assert np.load(matrix).tolist() == rows
+        np.save(vector, np.asarray(ids, dtype="<i4"))
+        assert Codec.decode_from_npy(vector) == ids
+
+
+def test_compact_json():
+    import json
+
+    ids = [5, 9, -1, 50000, 5, 5]
+    encoded = Codec.encode_compact_json(ids)
+    assert json.loads(encoded)["v"] == 1
+    assert Codec.decode_compact_json(encoded) == ids
+
+    batch = Codec.encode_compact_json_array([ids, []])
+    assert len(json.loads(batch)) == 2
+    assert Codec.decode_compact_json_array(batch) == [ids, []]
+
+
+def test_bloom_filter():
+    c = Codec()
+    ids = [(i * 7919) % 50000 for i in range(500)]
+    payload = c.encode_with_bloom(ids, 0.01, True)
+    assert c.decode_token_ids(payload, True) == ids
+    assert all(c.query_bloom(payload, t) for t in ids)
+    hits = sum(c.query_bloom(payload, 100000 + t) for t in range(2000))
+    assert hits < 100
+
+
+def test_source_hash():
+    import hashlib
+
+    c = Codec()
+    digest = hashlib.sha256(b"the quick brown fox").digest()
+    payload = c.encode_with_source([464, 2068, 7586], digest, True)
+    assert bytes(payload[-32:]) == digest
+    assert bytes(Codec.decode_source_hash_only(payload)) == digest
+    assert c.verify_source_hash(payload, digest)
+    assert not c.verify_source_hash(payload, hashlib.sha256(b"other").digest())
+    assert not c.verify_source_hash(payload, b"short")
+    try:
+        c.encode_with_source([1], b"short", False)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("expected ValueError")
+
+
+def test_mlm_input():
+    c = Codec()
+    ids = [(i * 7919) % 30522 for i in range(512)]
+    positions = list(range(3, 512, 7))
+    for gzip in (False, True):
+        payload = c.encode_mlm_input(ids, positions, gzip)
+        assert c.decode_mlm_input(payload, gzip) == (ids, positions)
+    try:
+        c.encode_mlm_input(ids, [5, 4], False)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("expected ValueError")
+
+
+def test_use_delta():
+    ids = [k for r in range(10) for k in range(100 - r)]
+    c = Codec(use_delta=True)
+    for gzip in (False, True):
+        payload = c.encode_token_ids(ids, gzip)
+        assert Codec().decode_token_ids(payload, gzip) == ids
+    assert len(c.encode_token_ids(ids, False)) < len(Codec().encode_token_ids(ids, False))
+    try:
+        Codec(varint_mode="rice", use_delta=True).encode_token_ids(ids, False)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError("expected ValueError")
+
+
+def test_u16_ids():
+    c = Codec()
+    ids = [(i * 7919) % 50257 for i in range(500)]
+    for gzip in (False, True):
+        payload = c.encode_u16_ids(ids, gzip)
+        assert c.decode_u16_ids(payload, gzip) == ids
+    assert len(c.encode_u16_ids(ids, False)) < len(c.encode_token_ids(ids, False))
+
+
+def test_min_vocab_size():
+    c = Codec()
+    ids = [1, 1, 1, 1, 2, 2, 3, 4]
+    assert c.min_vocab_size(ids, 0.5) == 1
+    assert c.min_vocab_size(ids, 0.75) == 2
+    assert c.min_vocab_size(ids, 1.0) == 4